@@ -0,0 +1,99 @@
+use crate::{
+    config::{CreateAction, Database, Execution},
+    executor::Executor,
+    library::QueryLibrary,
+    record::{CsvSerializer, RecordSerializer},
+    shell,
+    tempdir::TempDir,
+};
+use anyhow::{anyhow, Result};
+use datafusion::{arrow::util::pretty, prelude::ExecutionContext};
+use std::{fs, path::Path};
+
+/// 分析结果注册的 database 名称
+pub static DB_NAME: &str = "repo";
+
+/// 摘要报告中依次执行的内置命名查询
+static SUMMARY_QUERIES: [&str; 4] = [
+    "top_authors",
+    "top_languages",
+    "commits_per_hour",
+    "gitmoji_usage",
+];
+
+/// 摘要报告的概览语句
+fn overview_sql(db: &str) -> String {
+    format!(
+        "SELECT repo_name, count(DISTINCT hash) AS commits, count(DISTINCT author_email) AS authors, \
+        first_commit_date(datetime) AS first_commit, last_commit_date(datetime) AS last_commit, \
+        most_active_weekday(datetime) AS most_active_weekday FROM '{}.commit' GROUP BY repo_name",
+        db
+    )
+}
+
+/// 摘要报告的语句列表（标题，语句）
+fn summary_statements(db: &str) -> Result<Vec<(String, String)>> {
+    let library = QueryLibrary::load(None)?;
+    let mut statements = vec![(String::from("overview"), overview_sql(db))];
+    for name in SUMMARY_QUERIES {
+        for stmt in library.statements(name, db)? {
+            statements.push((name.to_string(), stmt));
+        }
+    }
+    Ok(statements)
+}
+
+async fn summary(mut ctx: ExecutionContext) -> Result<()> {
+    for (title, stmt) in summary_statements(DB_NAME)? {
+        let batches = ctx.sql(&stmt).await?.collect().await?;
+        println!("\n{}:", title);
+        pretty::print_batches(&batches)?;
+    }
+    Ok(())
+}
+
+/// 不依赖配置文件分析单个本地仓库，`gitv analyze [path]`
+///
+/// 数据写入临时目录并注册为 `repo` database，随后进入 shell 或者输出摘要报告，退出时删除临时目录
+pub async fn analyze(path: &str, print_summary: bool) -> Result<()> {
+    if !Path::new(path).is_dir() {
+        return Err(anyhow!("Repo path '{}' is not a directory", path));
+    }
+
+    let dir = TempDir::new("analyze")?;
+    analyze_into(path, dir.path_str(), print_summary).await
+}
+
+async fn analyze_into(path: &str, dir: &str, print_summary: bool) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    let config = CreateAction {
+        databases: vec![Database::from_lines(dir, dir, path)],
+        ..Default::default()
+    };
+    CsvSerializer::serialize(config).await?;
+
+    let executions = vec![Execution {
+        db_name: DB_NAME.to_string(),
+        dir: dir.to_string(),
+    }];
+    let ctx = Executor::create_context(executions.clone(), None, None).await?;
+    match print_summary {
+        true => summary(ctx).await,
+        false => shell::console_loop(ctx, &executions, None).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_statements() {
+        let statements = summary_statements("repo").unwrap();
+        assert_eq!(statements[0].0, "overview");
+        assert!(statements.len() > SUMMARY_QUERIES.len());
+        assert!(statements
+            .iter()
+            .all(|(_, stmt)| stmt.contains("'repo.") && !stmt.contains("{db}")));
+    }
+}
@@ -0,0 +1,307 @@
+use crate::{config, http};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs};
+use tokio::sync::Mutex;
+
+static KEYRING_PREFIX: &str = "keyring:";
+static KEYRING_USER: &str = "gitv";
+
+/// 解析配置中的凭证，`keyring:<service>[/<user>]` 格式的凭证从系统钥匙串中读取，user 默认为 gitv
+pub fn resolve_secret(value: &str) -> Result<String> {
+    match value.strip_prefix(KEYRING_PREFIX) {
+        Some(entry) => {
+            let (service, user) = keyring_entry(entry);
+            read_keyring(service, user)
+        }
+        None => Ok(value.to_string()),
+    }
+}
+
+/// 将凭证写入系统钥匙串，entry 格式为 `<service>[/<user>]`，可省略 `keyring:` 前缀
+pub fn store_secret(entry: &str, secret: &str) -> Result<()> {
+    let entry = entry.strip_prefix(KEYRING_PREFIX).unwrap_or(entry);
+    let (service, user) = keyring_entry(entry);
+    write_keyring(service, user, secret)
+}
+
+/// git 命令使用的 https 凭证，由 credential helper 通过环境变量读取，不会写入 remote 地址
+#[derive(Clone, Default)]
+pub struct GitCredential {
+    pub username: String,
+    pub token: String,
+}
+
+impl fmt::Debug for GitCredential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GitCredential")
+            .field("username", &self.username)
+            .field("token", &"***")
+            .finish()
+    }
+}
+
+/// 查找 host 匹配 https 地址的凭证，已包含凭证或者非 https 的地址返回 None
+pub fn clone_credential(
+    remote: &str,
+    credentials: &[config::CloneCredential],
+) -> Result<Option<GitCredential>> {
+    let rest = match remote.strip_prefix("https://") {
+        Some(rest) => rest,
+        None => return Ok(None),
+    };
+    let host = rest.split('/').next().unwrap_or_default();
+    let credential = match credentials.iter().find(|c| c.host == host) {
+        Some(c) if !host.contains('@') => c,
+        _ => return Ok(None),
+    };
+
+    Ok(Some(GitCredential {
+        username: credential
+            .username
+            .clone()
+            .unwrap_or_else(|| String::from("x-access-token")),
+        token: resolve_secret(&credential.token)?,
+    }))
+}
+
+fn keyring_entry(entry: &str) -> (&str, &str) {
+    entry.split_once('/').unwrap_or((entry, KEYRING_USER))
+}
+
+#[cfg(feature = "keychain")]
+fn read_keyring(service: &str, user: &str) -> Result<String> {
+    keyring::Entry::new(service, user)
+        .get_password()
+        .map_err(|e| anyhow!("Failed to read '{}/{}' from keyring: {}", service, user, e))
+}
+
+#[cfg(feature = "keychain")]
+fn write_keyring(service: &str, user: &str, secret: &str) -> Result<()> {
+    keyring::Entry::new(service, user)
+        .set_password(secret)
+        .map_err(|e| anyhow!("Failed to write '{}/{}' to keyring: {}", service, user, e))
+}
+
+#[cfg(not(feature = "keychain"))]
+fn read_keyring(service: &str, user: &str) -> Result<String> {
+    Err(anyhow!(
+        "Failed to read '{}/{}' from keyring: gitv is built without the `keychain` feature",
+        service,
+        user
+    ))
+}
+
+#[cfg(not(feature = "keychain"))]
+fn write_keyring(service: &str, user: &str, _secret: &str) -> Result<()> {
+    Err(anyhow!(
+        "Failed to write '{}/{}' to keyring: gitv is built without the `keychain` feature",
+        service,
+        user
+    ))
+}
+
+/// Github 请求凭证，支持 Personal Access Token 以及 Github App 两种方式
+pub enum GithubToken {
+    Personal(String),
+    App(GithubAppAuth),
+}
+
+impl GithubToken {
+    /// 配置了 app 时优先使用 Github App 验证，account 为 app 安装所在的组织或者用户
+    pub fn try_new(
+        token: &str,
+        app: Option<config::GithubApp>,
+        account: GithubAccount,
+    ) -> Result<Self> {
+        match app {
+            Some(app) => Ok(GithubToken::App(GithubAppAuth::try_new(app, account)?)),
+            None if !token.is_empty() => Self::personal(token),
+            None => Err(anyhow!("Either token or app should be configured")),
+        }
+    }
+
+    /// Personal Access Token，支持从系统钥匙串中读取
+    pub fn personal(token: &str) -> Result<Self> {
+        Ok(GithubToken::Personal(resolve_secret(token)?))
+    }
+
+    pub async fn token(&self) -> Result<String> {
+        match self {
+            GithubToken::Personal(token) => Ok(token.clone()),
+            GithubToken::App(app) => app.token().await,
+        }
+    }
+}
+
+/// Github App 安装所在的账户
+pub enum GithubAccount {
+    Org(String),
+    User(String),
+}
+
+#[derive(Debug, Serialize)]
+struct Claims {
+    iat: i64,
+    exp: i64,
+    iss: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct InstallationResponse {
+    id: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccessTokenResponse {
+    token: String,
+    expires_at: String,
+}
+
+/// Github App 验证：使用 App 私钥签发 JWT，再换取有效期为 1 小时的 installation token
+///
+/// installation token 会被缓存，过期前 5 分钟自动刷新
+pub struct GithubAppAuth {
+    app_id: u64,
+    key: EncodingKey,
+    installation_id: Option<u64>,
+    account: GithubAccount,
+    cached: Mutex<Option<(String, DateTime<Utc>)>>,
+}
+
+impl GithubAppAuth {
+    fn try_new(config: config::GithubApp, account: GithubAccount) -> Result<Self> {
+        let pem = fs::read(&config.private_key_path).map_err(|e| {
+            anyhow!(
+                "Read github app private key '{}' error: {}",
+                config.private_key_path,
+                e
+            )
+        })?;
+        Ok(Self {
+            app_id: config.app_id,
+            key: EncodingKey::from_rsa_pem(&pem)?,
+            installation_id: config.installation_id,
+            account,
+            cached: Mutex::new(None),
+        })
+    }
+
+    fn jwt(&self) -> Result<String> {
+        let now = Utc::now();
+        // 签发时间往前调整 60s 以兼容时钟偏差，JWT 最长有效期为 10 分钟
+        let claims = Claims {
+            iat: (now - Duration::seconds(60)).timestamp(),
+            exp: (now + Duration::minutes(9)).timestamp(),
+            iss: self.app_id.to_string(),
+        };
+        Ok(jsonwebtoken::encode(
+            &Header::new(Algorithm::RS256),
+            &claims,
+            &self.key,
+        )?)
+    }
+
+    async fn installation_id(&self, jwt: &str) -> Result<u64> {
+        if let Some(id) = self.installation_id {
+            return Ok(id);
+        }
+
+        let url = match &self.account {
+            GithubAccount::Org(org) => format!("https://api.github.com/orgs/{}/installation", org),
+            GithubAccount::User(user) => {
+                format!("https://api.github.com/users/{}/installation", user)
+            }
+        };
+        let response = http::client()?
+            .get(&url)
+            .bearer_auth(jwt)
+            .header("Accept", "application/vnd.github.v3+json")
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<InstallationResponse>()
+            .await?;
+        Ok(response.id)
+    }
+
+    async fn token(&self) -> Result<String> {
+        let mut cached = self.cached.lock().await;
+        if let Some((token, expires_at)) = cached.as_ref() {
+            if *expires_at - Duration::minutes(5) > Utc::now() {
+                return Ok(token.clone());
+            }
+        }
+
+        let jwt = self.jwt()?;
+        let id = self.installation_id(&jwt).await?;
+        let response = http::client()?
+            .post(format!(
+                "https://api.github.com/app/installations/{}/access_tokens",
+                id
+            ))
+            .bearer_auth(&jwt)
+            .header("Accept", "application/vnd.github.v3+json")
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<AccessTokenResponse>()
+            .await?;
+
+        let expires_at = DateTime::parse_from_rfc3339(&response.expires_at)?.with_timezone(&Utc);
+        info!(
+            "[github]: installation token refreshed, expires at {}",
+            expires_at.to_rfc3339()
+        );
+        *cached = Some((response.token.clone(), expires_at));
+        Ok(response.token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_secret() {
+        assert_eq!(resolve_secret("ghp_xxx").unwrap(), "ghp_xxx");
+        assert_eq!(keyring_entry("gitv-github"), ("gitv-github", "gitv"));
+        assert_eq!(keyring_entry("gitv-github/bot"), ("gitv-github", "bot"));
+    }
+
+    #[test]
+    fn test_clone_credential() {
+        let credentials = vec![config::CloneCredential {
+            host: "github.com".to_string(),
+            username: None,
+            token: "ghp_xxx".to_string(),
+        }];
+        let credential =
+            clone_credential("https://github.com/chenjiandongx/gitv.git", &credentials)
+                .unwrap()
+                .unwrap();
+        assert_eq!(credential.username, "x-access-token");
+        assert_eq!(credential.token, "ghp_xxx");
+        assert!(!format!("{:?}", credential).contains("ghp_xxx"));
+
+        assert!(
+            clone_credential("git@github.com:chenjiandongx/gitv.git", &credentials)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            clone_credential("https://gitee.com/chenjiandongx/gitv.git", &credentials)
+                .unwrap()
+                .is_none()
+        );
+        assert!(clone_credential(
+            "https://bot@github.com/chenjiandongx/gitv.git",
+            &credentials
+        )
+        .unwrap()
+        .is_none());
+    }
+}
@@ -0,0 +1,224 @@
+use crate::{
+    config::{Config, CreateAction, Execution, FetchAction},
+    executor::Executor,
+    library::QueryLibrary,
+    render,
+};
+use std::{collections::HashSet, path::Path};
+
+/// 检查配置中的所有 action，返回发现的问题，每个问题以所属的 action 开头
+///
+/// 只检查文件、目录以及引用关系，不会 clone 仓库、请求远程 API 或者执行查询
+pub async fn check(config: &Config) -> Vec<String> {
+    let mut problems = vec![];
+    if let Some(create) = &config.create {
+        check_create(create, &mut problems);
+    }
+    if let Some(fetch) = &config.fetch {
+        check_fetch(fetch, &mut problems);
+    }
+    if let Some(shell) = &config.shell {
+        check_executions("shell", &shell.executions, &mut problems);
+        if let Err(e) = QueryLibrary::load(shell.query_library.as_deref()) {
+            problems.push(format!("shell: {}", e));
+        }
+    }
+    if let Some(render) = &config.render {
+        check_executions("render", &render.executions, &mut problems);
+        let ctx = Executor::create_context(
+            render.executions.clone(),
+            render.periods.clone(),
+            render.display.timezone.clone(),
+        )
+        .await;
+        match ctx {
+            Ok(ctx) => problems.extend(
+                render::check(ctx, render.clone())
+                    .await
+                    .into_iter()
+                    .map(|p| format!("render: {}", p)),
+            ),
+            Err(e) => problems.push(format!("render: create executor context error: {}", e)),
+        }
+    }
+    problems
+}
+
+fn check_create(config: &CreateAction, problems: &mut Vec<String>) {
+    if config.databases.is_empty() {
+        problems.push(String::from("create: no databases configured"));
+    }
+    for file in config.author_mapping_files.iter().flatten() {
+        if !Path::new(file).exists() {
+            problems.push(format!(
+                "create: author mappings file '{}' does not exist",
+                file
+            ));
+        }
+    }
+    if let Some(binary) = &config.git_binary {
+        if binary.contains(std::path::MAIN_SEPARATOR) && !Path::new(binary).exists() {
+            problems.push(format!("create: gitBinary '{}' does not exist", binary));
+        }
+    }
+
+    for (index, database) in config.databases.iter().enumerate() {
+        let missing = database
+            .files
+            .iter()
+            .flatten()
+            .filter(|file| !Path::new(file).exists())
+            .collect::<Vec<_>>();
+        for file in missing.iter() {
+            problems.push(format!(
+                "create: databases[{}] repos file '{}' does not exist, run `gitv --fetch` first or fix the path",
+                index, file
+            ));
+        }
+        if !missing.is_empty() {
+            continue;
+        }
+        match database.load() {
+            Ok(repos) if repos.is_empty() => {
+                problems.push(format!("create: databases[{}] has no repos", index))
+            }
+            Ok(_) => {}
+            Err(e) => problems.push(format!("create: databases[{}] {}", index, e)),
+        }
+    }
+}
+
+fn check_fetch(config: &FetchAction, problems: &mut Vec<String>) {
+    let mut destinations = vec![];
+    let mut keys = vec![];
+    for c in config.github_authenticated.iter().flatten() {
+        destinations.push(&c.destination);
+    }
+    for c in config.github_user.iter().flatten() {
+        destinations.push(&c.destination);
+        keys.extend(c.app.iter().map(|app| &app.private_key_path));
+    }
+    for c in config.github_org.iter().flatten() {
+        destinations.push(&c.destination);
+        keys.extend(c.app.iter().map(|app| &app.private_key_path));
+    }
+    for c in config.bitbucket_workspace.iter().flatten() {
+        destinations.push(&c.destination);
+    }
+    for c in config.gitea.iter().flatten() {
+        destinations.push(&c.destination);
+    }
+
+    for destination in destinations {
+        let dir = Path::new(destination)
+            .parent()
+            .unwrap_or_else(|| Path::new(""));
+        if !dir.as_os_str().is_empty() && !dir.is_dir() {
+            problems.push(format!(
+                "fetch: directory of destination '{}' does not exist",
+                destination
+            ));
+        }
+    }
+    for key in keys {
+        if !Path::new(key).exists() {
+            problems.push(format!(
+                "fetch: github app privateKeyPath '{}' does not exist",
+                key
+            ));
+        }
+    }
+}
+
+fn check_executions(action: &str, executions: &[Execution], problems: &mut Vec<String>) {
+    if executions.is_empty() {
+        problems.push(format!("{}: no executions configured", action));
+    }
+    let mut names = HashSet::new();
+    for execution in executions {
+        if !names.insert(&execution.db_name) {
+            problems.push(format!(
+                "{}: duplicate dbName '{}'",
+                action, execution.db_name
+            ));
+        }
+        if !Path::new(&execution.dir).is_dir() {
+            problems.push(format!(
+                "{}: dir '{}' of database '{}' does not exist, run `gitv --create` first or fix the path",
+                action, execution.dir, execution.db_name
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{Database, GithubApp, GithubOrg},
+        tempdir::TempDir,
+    };
+
+    #[test]
+    fn test_check_sections() {
+        let dir = TempDir::new("check").unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+        std::fs::write(path("repos.yaml"), "[]").unwrap();
+
+        let mut problems = vec![];
+        let create = CreateAction {
+            databases: vec![
+                Database {
+                    dir: path("db"),
+                    files: Some(vec![path("missing.yaml")]),
+                    ..Default::default()
+                },
+                Database {
+                    dir: path("db"),
+                    files: Some(vec![path("repos.yaml")]),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        check_create(&create, &mut problems);
+
+        let fetch = FetchAction {
+            github_org: Some(vec![GithubOrg {
+                destination: path("missing/repos.yaml"),
+                app: Some(GithubApp {
+                    private_key_path: path("key.pem"),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        check_fetch(&fetch, &mut problems);
+
+        let execution = |db_name: &str, dir: String| Execution {
+            db_name: db_name.to_string(),
+            dir,
+        };
+        let executions = vec![
+            execution("db", dir.to_str().unwrap().to_string()),
+            execution("db", path("missing")),
+        ];
+        check_executions("shell", &executions, &mut problems);
+        check_executions("render", &[], &mut problems);
+
+        let expected = [
+            "create: databases[0] repos file",
+            "create: databases[1] has no repos",
+            "fetch: directory of destination",
+            "fetch: github app privateKeyPath",
+            "shell: duplicate dbName 'db'",
+            "shell: dir",
+            "render: no executions configured",
+        ];
+        assert_eq!(problems.len(), expected.len(), "{:?}", problems);
+        for (problem, expected) in problems.iter().zip(expected.iter()) {
+            assert!(problem.starts_with(expected), "{}", problem);
+        }
+    }
+}
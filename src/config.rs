@@ -1,43 +1,435 @@
-use anyhow::Result;
-use serde::{Deserialize, Serialize};
+use crate::{
+    library::QueryLibrary,
+    model::{Author, PathFilter, Repository},
+};
+use anyhow::{anyhow, Result};
+use log::warn;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_yaml::Value;
-use std::{collections::HashMap, fs::File};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    fs::{self, File},
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+};
 
 #[derive(Debug, Clone, Default, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateAction {
     pub disable_pull: Option<bool>,
+    /// 检测到浅克隆仓库时自动拉取完整历史
+    pub unshallow: Option<bool>,
+    pub remote_mismatch: Option<RemoteMismatch>,
+    /// 多用户共享的 mirror 缓存目录，clone 时通过 alternates 复用缓存中的对象
+    pub cache_dir: Option<String>,
+    /// clone 私有仓库时按 host 注入到 https 地址中的凭证
+    pub clone_credentials: Option<Vec<CloneCredential>>,
+    /// 每个 database 同时 clone/pull 以及分析的仓库数量上限
+    pub concurrency: Option<usize>,
+    /// git 可执行文件的路径，默认为 PATH 中的 `git`
+    pub git_binary: Option<String>,
+    /// 每个 git 命令前添加的全局参数，如 `["-c", "http.proxy=http://127.0.0.1:7890"]`
+    pub git_global_args: Option<Vec<String>>,
+    pub runs_log: Option<String>,
+    /// 数据文件的 csv 格式，读取时会根据表头自动识别分隔符
+    pub csv: Option<CsvOptions>,
+    pub wait_lock: Option<bool>,
+    /// 分析前交互式选择每个 database 中需要分析的仓库
+    pub select: Option<bool>,
+    /// 连续失败多少次后在后续运行中跳过该仓库，0 表示不跳过
+    pub skip_after_failures: Option<u32>,
+    /// 重新尝试被跳过的仓库
+    pub retry_failed: Option<bool>,
+    pub delta_report: Option<bool>,
+    pub status_thresholds: Option<StatusThresholds>,
+    pub churn_months: Option<i64>,
+    pub pattern_thresholds: Option<PatternThresholds>,
+    /// 生成 first_contribution 表，记录每个作者在仓库中的首次提交
+    pub first_contribution: Option<bool>,
+    /// 分析时包含 merge 提交，commit 表中以 is_merge 标记
+    pub include_merges: Option<bool>,
+    /// 为 `Co-authored-by` trailer 中的每个共同作者额外生成一条 commit 记录
+    pub co_authors: Option<bool>,
+    /// 统计代码增删行数时忽略只有空白字符或者空行的改动
+    pub ignore_whitespace: Option<bool>,
+    pub snapshot_interval: Option<SnapshotInterval>,
+    /// 提交数超过阈值的超大仓库只分析部分采样的提交
+    pub sampling: Option<Sampling>,
+    /// 相邻历史快照之间语言占比的变化不小于该值时视为语言迁移，写入 migrations 表
+    pub migration_threshold: Option<f64>,
+    pub stargazers: Option<Stargazers>,
+    pub fork_dedup: Option<ForkDedup>,
+    /// 在 authorMappings 之前对作者邮箱进行归一化
+    pub normalize_authors: Option<NormalizeAuthors>,
     pub author_mappings: Option<Vec<AuthorMapping>>,
+    /// 作者映射文件，内容格式与 authorMappings 相同，规则追加在 authorMappings 之后
+    pub author_mapping_files: Option<Vec<String>>,
     pub databases: Vec<Database>,
 }
 
-#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+impl CreateAction {
+    /// authorMappings 以及 authorMappingFiles 中的所有映射规则，按顺序匹配
+    pub fn author_mappings(&self) -> Result<Vec<AuthorMapping>> {
+        let mut mappings = self.author_mappings.clone().unwrap_or_default();
+        for file in self.author_mapping_files.iter().flatten() {
+            let content = fs::read_to_string(file)
+                .map_err(|e| anyhow!("Read author mappings '{}' error: {}", file, e))?;
+            let file_mappings: Vec<AuthorMapping> = serde_yaml::from_str(&content)
+                .map_err(|e| anyhow!("Parse author mappings '{}' error: {}", file, e))?;
+            mappings.extend(file_mappings);
+        }
+        Ok(mappings)
+    }
+}
+
+/// 上游仓库与 fork 仓库中相同 hash 提交的去重策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ForkDedup {
+    /// 保留 database 中第一个出现该提交的仓库
+    KeepFirst,
+    /// 优先保留非 fork 仓库，均为 fork 时退化为 keep-first
+    KeepUpstream,
+    /// 保留所有记录，仅在 duplicate 表中标记
+    TagBoth,
+}
+
+/// 数据文件的 csv 格式，如 Excel 需要 BOM 才能正确识别 UTF-8 编码
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CsvOptions {
+    pub bom: Option<bool>,
+    pub delimiter: Option<char>,
+    pub quote_style: Option<CsvQuoteStyle>,
+}
+
+/// csv 字段的引号策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CsvQuoteStyle {
+    /// 只在字段包含分隔符、引号或者换行时添加引号
+    Necessary,
+    /// 所有字段都添加引号
+    Always,
+    /// 非数字字段添加引号
+    NonNumeric,
+}
+
+impl CsvOptions {
+    /// 创建 csv writer，开启 bom 时先写入 UTF-8 BOM
+    pub fn writer<W: Write>(&self, mut w: W) -> Result<csv::Writer<W>> {
+        if self.bom.unwrap_or(false) {
+            w.write_all(b"\xEF\xBB\xBF")?;
+        }
+        let quote_style = match self.quote_style.unwrap_or(CsvQuoteStyle::Necessary) {
+            CsvQuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+            CsvQuoteStyle::Always => csv::QuoteStyle::Always,
+            CsvQuoteStyle::NonNumeric => csv::QuoteStyle::NonNumeric,
+        };
+        Ok(csv::WriterBuilder::new()
+            .delimiter(self.delimiter()?)
+            .quote_style(quote_style)
+            .from_writer(w))
+    }
+
+    fn delimiter(&self) -> Result<u8> {
+        match self.delimiter.unwrap_or(',') {
+            c if c.is_ascii() && c != '"' && c != '\n' => Ok(c as u8),
+            c => Err(anyhow!("Invalid csv delimiter: {:?}", c)),
+        }
+    }
+}
+
+/// https 地址的 clone 凭证，username 默认为 `x-access-token`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloneCredential {
+    pub host: String,
+    pub username: Option<String>,
+    pub token: String,
+}
+
+/// 拉取 Github 仓库的 star 时间线，写入 star 表
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stargazers {
+    pub token: String,
+    pub max_retries: Option<u32>,
+}
+
+/// 历史快照的统计周期
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SnapshotInterval {
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+/// 本地仓库的 `remote.origin.url` 与配置的 remote 不一致时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RemoteMismatch {
+    /// 报错并跳过该仓库
+    Error,
+    /// 删除本地目录后重新 clone
+    Reclone,
+    /// 忽略不一致，继续 pull
+    Ignore,
+}
+
+impl Default for RemoteMismatch {
+    fn default() -> Self {
+        RemoteMismatch::Error
+    }
+}
+
+/// 仓库建议状态的判定阈值，单位均为天
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusThresholds {
+    pub active_days: Option<i64>,
+    pub maintenance_days: Option<i64>,
+    pub stale_days: Option<i64>,
+    pub trend_days: Option<i64>,
+}
+
+impl StatusThresholds {
+    pub fn active_days(&self) -> i64 {
+        self.active_days.unwrap_or(30)
+    }
+
+    pub fn maintenance_days(&self) -> i64 {
+        self.maintenance_days.unwrap_or(180)
+    }
+
+    pub fn stale_days(&self) -> i64 {
+        self.stale_days.unwrap_or(365)
+    }
+
+    pub fn trend_days(&self) -> i64 {
+        self.trend_days.unwrap_or(90)
+    }
+}
+
+/// 作者工作模式的判定阈值，均为提交数的占比（0~1）
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatternThresholds {
+    pub night_ratio: Option<f64>,
+    pub early_ratio: Option<f64>,
+    pub weekend_ratio: Option<f64>,
+}
+
+impl PatternThresholds {
+    pub fn night_ratio(&self) -> f64 {
+        self.night_ratio.unwrap_or(0.3)
+    }
+
+    pub fn early_ratio(&self) -> f64 {
+        self.early_ratio.unwrap_or(0.3)
+    }
+
+    pub fn weekend_ratio(&self) -> f64 {
+        self.weekend_ratio.unwrap_or(0.4)
+    }
+}
+
+/// 超大仓库的采样策略，只需要大致趋势时避免单个仓库拖慢整个分析过程
+///
+/// 提交数超过 maxCommits 的仓库先按 since 过滤提交，再每 every 个提交保留一个
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sampling {
+    pub max_commits: usize,
+    /// 每 N 个提交保留一个（从最新的提交开始）
+    pub every: Option<usize>,
+    /// 只分析该时间之后的提交，格式同 `git log --since`，如 `2021-01-01`、`1 year ago`
+    pub since: Option<String>,
+}
+
+impl Sampling {
+    /// 每 every 个提交保留一个
+    pub fn sample(&self, hashs: Vec<String>) -> Vec<String> {
+        let every = self.every.unwrap_or(1).max(1);
+        hashs.into_iter().step_by(every).collect()
+    }
+}
+
+/// 作者归一化配置，`true` 表示开启所有归一化选项
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum NormalizeAuthors {
+    Enabled(bool),
+    Options(NormalizeOptions),
+}
+
+impl NormalizeAuthors {
+    pub fn options(&self) -> Option<NormalizeOptions> {
+        match self {
+            NormalizeAuthors::Enabled(true) => Some(NormalizeOptions::default()),
+            NormalizeAuthors::Enabled(false) => None,
+            NormalizeAuthors::Options(opts) => Some(*opts),
+        }
+    }
+}
+
+/// 作者归一化选项，未配置的选项默认开启
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NormalizeOptions {
+    /// 邮箱转为小写
+    pub lowercase: Option<bool>,
+    /// 去除邮箱的 `+tag` 后缀，如 `me+github@x.com` -> `me@x.com`
+    pub strip_plus: Option<bool>,
+    /// 去除名称以及邮箱的首尾空白
+    pub trim: Option<bool>,
+}
+
+impl NormalizeOptions {
+    pub fn lowercase(&self) -> bool {
+        self.lowercase.unwrap_or(true)
+    }
+
+    pub fn strip_plus(&self) -> bool {
+        self.strip_plus.unwrap_or(true)
+    }
+
+    pub fn trim(&self) -> bool {
+        self.trim.unwrap_or(true)
+    }
+}
+
+/// 作者身份解析：先按 NormalizeOptions 归一化，再应用 authorMappings
+///
+/// 同时统计原始、归一化后以及映射后的身份数量，clone 后共享同一份统计数据
+#[derive(Debug, Clone, Default)]
+pub struct AuthorResolver {
+    normalize: Option<NormalizeOptions>,
+    mappings: Vec<AuthorMapping>,
+    identities: Arc<Mutex<[HashSet<Author>; 3]>>,
+}
+
+/// 作者身份合并统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IdentityStats {
+    /// 原始身份数量
+    pub raw: usize,
+    /// 归一化后的身份数量
+    pub normalized: usize,
+    /// 应用 authorMappings 后的身份数量
+    pub resolved: usize,
+}
+
+impl AuthorResolver {
+    pub fn new(normalize: Option<NormalizeOptions>, mappings: Vec<AuthorMapping>) -> Self {
+        Self {
+            normalize,
+            mappings,
+            ..Default::default()
+        }
+    }
+
+    pub fn resolve(&self, author: &Author) -> Author {
+        let normalized = match &self.normalize {
+            Some(opts) => author.normalize(opts),
+            None => author.clone(),
+        };
+        let resolved = self
+            .mappings
+            .iter()
+            .find(|mapping| mapping.source.matches(&normalized))
+            .map(|mapping| mapping.destination.clone())
+            .unwrap_or_else(|| normalized.clone());
+
+        let mut identities = self.identities.lock().unwrap();
+        identities[0].insert(author.clone());
+        identities[1].insert(normalized);
+        identities[2].insert(resolved.clone());
+        resolved
+    }
+
+    pub fn stats(&self) -> IdentityStats {
+        let identities = self.identities.lock().unwrap();
+        IdentityStats {
+            raw: identities[0].len(),
+            normalized: identities[1].len(),
+            resolved: identities[2].len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct AuthorMapping {
-    pub source: Author,
+    pub source: AuthorMatcher,
     pub destination: Author,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
-pub struct Repository {
-    pub name: String,
-    pub branch: Option<String>,
-    pub remote: Option<String>,
-    pub path: String,
-    pub forks_count: Option<usize>,
-    pub stargazers_count: Option<usize>,
+/// 作者匹配规则，未配置的字段匹配任意值
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthorMatcher {
+    pub name: Option<Pattern>,
+    pub email: Option<Pattern>,
 }
 
-#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq, Hash)]
-pub struct Author {
-    pub name: String,
-    pub email: String,
+impl AuthorMatcher {
+    pub fn matches(&self, author: &Author) -> bool {
+        let matches = |pattern: &Option<Pattern>, s: &str| match pattern {
+            Some(pattern) => pattern.matches(s),
+            None => true,
+        };
+        matches(&self.name, &author.name) && matches(&self.email, &author.email)
+    }
+}
+
+/// 字符串匹配模式
+///
+/// - `regex:` 前缀：正则表达式，如 `regex:^bot-\d+$`
+/// - 包含 `*` 或 `?`：glob 通配，如 `*@old-company.com`
+/// - 其他：精确匹配
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Pattern {
+    Exact(String),
+    Regex(regex::Regex),
 }
 
-impl Author {
-    pub fn domain(&self) -> String {
-        let email = self.email.clone();
-        let fields = email.splitn(2, '@').collect::<Vec<&str>>();
-        fields.last().unwrap_or(&"").to_string()
+impl Pattern {
+    pub fn matches(&self, s: &str) -> bool {
+        match self {
+            Pattern::Exact(p) => p == s,
+            Pattern::Regex(re) => re.is_match(s),
+        }
+    }
+
+    fn glob_to_regex(glob: &str) -> String {
+        let mut re = String::from("^");
+        for c in glob.chars() {
+            match c {
+                '*' => re.push_str(".*"),
+                '?' => re.push('.'),
+                c => re.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        re.push('$');
+        re
+    }
+}
+
+impl TryFrom<String> for Pattern {
+    type Error = regex::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        if let Some(re) = s.strip_prefix("regex:") {
+            return Ok(Pattern::Regex(regex::Regex::new(re)?));
+        }
+        if s.contains('*') || s.contains('?') {
+            return Ok(Pattern::Regex(regex::Regex::new(&Self::glob_to_regex(&s))?));
+        }
+        Ok(Pattern::Exact(s))
     }
 }
 
@@ -47,9 +439,59 @@ pub struct Database {
     pub dir: String,
     pub files: Option<Vec<String>>,
     pub repos: Option<Vec<Repository>>,
+    pub exclude_paths: Option<Vec<String>>,
 }
 
 impl Database {
+    /// 由换行分隔的本地路径或者远程地址构建临时 database，远程仓库将 clone 到 clone_dir 下
+    pub fn from_lines(dir: &str, clone_dir: &str, lines: &str) -> Database {
+        let repos = lines
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| Self::adhoc_repo(clone_dir, line))
+            .collect();
+
+        Database {
+            dir: dir.to_string(),
+            repos: Some(repos),
+            ..Default::default()
+        }
+    }
+
+    fn adhoc_repo(clone_dir: &str, line: &str) -> Repository {
+        let remote = line.contains("://") || line.starts_with("git@");
+        if !remote {
+            let path = fs::canonicalize(line).unwrap_or_else(|_| line.into());
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| line.to_string());
+            return Repository {
+                name,
+                path: path.to_string_lossy().to_string(),
+                ..Default::default()
+            };
+        }
+
+        // https://github.com/owner/repo.git 以及 git@github.com:owner/repo.git 均取 owner/repo 作为仓库名
+        let url = line.trim_end_matches('/').trim_end_matches(".git");
+        let segments = url
+            .rsplit(|c| c == '/' || c == ':')
+            .take(2)
+            .collect::<Vec<_>>();
+        let name = segments.into_iter().rev().collect::<Vec<_>>().join("/");
+        Repository {
+            path: Path::new(clone_dir)
+                .join(&name)
+                .to_string_lossy()
+                .to_string(),
+            name,
+            remote: Some(line.to_string()),
+            ..Default::default()
+        }
+    }
+
     pub fn load(&self) -> Result<Vec<Repository>> {
         let mut repos = vec![];
         if self.repos.is_some() {
@@ -63,6 +505,11 @@ impl Database {
                 repos.extend(r);
             }
         }
+
+        let filter = PathFilter::try_new(&self.exclude_paths.clone().unwrap_or_default())?;
+        for repo in repos.iter_mut() {
+            repo.exclude_paths = filter.clone();
+        }
         Ok(repos)
     }
 }
@@ -70,9 +517,33 @@ impl Database {
 #[derive(Debug, Clone, Default, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FetchAction {
+    pub max_retries: Option<u32>,
+    /// 生成的仓库列表使用 ssh 地址作为 remote
+    pub ssh: Option<bool>,
+    pub api: Option<GithubApiKind>,
+    /// 请求使用的 User-Agent，默认为 `rust/reqwest`
+    pub user_agent: Option<String>,
     pub github_authenticated: Option<Vec<GithubAuthenticated>>,
     pub github_user: Option<Vec<GithubUser>>,
     pub github_org: Option<Vec<GithubOrg>>,
+    pub bitbucket_workspace: Option<Vec<BitbucketWorkspace>>,
+    pub gitea: Option<Vec<Gitea>>,
+}
+
+/// Github 仓库列表的拉取方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GithubApiKind {
+    /// REST API，每页 100 个仓库
+    Rest,
+    /// GraphQL API，单次请求只返回需要的字段，仓库数量较多时更快
+    Graphql,
+}
+
+impl Default for GithubApiKind {
+    fn default() -> Self {
+        GithubApiKind::Rest
+    }
 }
 
 #[derive(Debug, Clone, Default, Deserialize)]
@@ -85,6 +556,17 @@ pub struct GithubAuthenticated {
     pub exclude_repos: Option<Vec<String>>,
     pub visibility: Option<String>,
     pub affiliation: Option<String>,
+    /// 同时拉取仓库的 issues 以及 pull requests，写入该目录下的 issue.csv 以及 pull_request.csv
+    pub issues_dir: Option<String>,
+}
+
+/// Github App 验证配置，设置后使用 installation token 替代 Personal Access Token
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GithubApp {
+    pub app_id: u64,
+    pub private_key_path: String,
+    pub installation_id: Option<u64>,
 }
 
 #[derive(Debug, Clone, Default, Deserialize)]
@@ -92,9 +574,12 @@ pub struct GithubAuthenticated {
 pub struct GithubUser {
     pub clone_dir: String,
     pub destination: String,
+    #[serde(default)]
     pub token: String,
+    pub app: Option<GithubApp>,
     pub username: String,
     pub exclude_repos: Option<Vec<String>>,
+    pub issues_dir: Option<String>,
     #[serde(rename(serialize = "type", deserialize = "type"))]
     pub typ: String,
 }
@@ -104,16 +589,57 @@ pub struct GithubUser {
 pub struct GithubOrg {
     pub clone_dir: String,
     pub destination: String,
+    #[serde(default)]
     pub token: String,
+    pub app: Option<GithubApp>,
     pub org: String,
     pub exclude_repos: Option<Vec<String>>,
+    pub issues_dir: Option<String>,
+    /// 拉取组织成员的资料并在该路径生成作者映射文件，可以在 create 的 authorMappingFiles 中引用
+    pub member_mappings: Option<String>,
     #[serde(rename(serialize = "type", deserialize = "type"))]
     pub typ: String,
 }
 
 #[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BitbucketWorkspace {
+    pub clone_dir: String,
+    pub destination: String,
+    pub workspace: String,
+    pub username: String,
+    pub app_password: String,
+    pub exclude_repos: Option<Vec<String>>,
+}
+
+/// Gitea 等自建代码托管服务，org 与 user 均未设置时拉取 token 所属用户的仓库
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Gitea {
+    pub clone_dir: String,
+    pub destination: String,
+    pub base_url: String,
+    pub token: String,
+    pub org: Option<String>,
+    pub user: Option<String>,
+    pub exclude_repos: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ShellAction {
     pub executions: Vec<Execution>,
+    pub periods: Option<Vec<Period>>,
+    /// 命名查询库文件，shell 中通过 `\use <name>` 执行
+    pub query_library: Option<String>,
+}
+
+/// period() 函数的时段划分，从 `start` 小时开始直到下一个时段的起始小时
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Period {
+    pub start: u32,
+    pub label: String,
+    pub emoji: Option<String>,
 }
 
 #[derive(Debug, Clone, Default, Deserialize)]
@@ -124,8 +650,12 @@ pub struct Execution {
 }
 
 #[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct RenderAction {
     pub executions: Vec<Execution>,
+    pub periods: Option<Vec<Period>>,
+    /// 命名查询库文件，与内置查询合并后供 queries 中的 `use` 引用
+    pub query_library: Option<String>,
     pub display: Display,
     pub colors: Option<HashMap<String, Vec<Value>>>,
     pub functions: Option<HashMap<String, Value>>,
@@ -137,9 +667,65 @@ pub struct Display {
     pub destination: String,
     pub render_mode: String,
     pub dependency: Option<Dependency>,
+    pub accessibility: Option<Accessibility>,
+    /// svg 模式下额外生成 index.html 图表索引页，按缩略尺寸展示原始的 svg 图表，不生成 png 缩略图
+    pub gallery: Option<bool>,
+    /// 将所有图表、数据以及 js 依赖内联到单个 html 文件中
+    pub bundle: Option<bool>,
+    /// 浮点数保留的小数位数，图表可单独覆盖
+    pub precision: Option<u32>,
+    /// 每个图表文件生成后依次执行的命令
+    pub post_processors: Option<Vec<PostProcessor>>,
+    /// 时间函数的默认时区，IANA 时区名称或者固定的时区偏移，`author` 表示提交者的本地时间
+    pub timezone: Option<String>,
     pub queries: Vec<Query>,
 }
 
+/// 图表文件的后处理命令，如 html 压缩、图片优化或者截图工具，不经过 shell 直接执行
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PostProcessor {
+    pub command: String,
+    /// 命令参数，`{file}` 替换为生成的文件路径，未包含 `{file}` 时文件路径作为最后一个参数
+    pub args: Option<Vec<String>>,
+    /// 只处理这些扩展名的文件，如 html、svg，默认处理所有文件
+    pub extensions: Option<Vec<String>>,
+}
+
+impl PostProcessor {
+    pub fn matches(&self, path: &Path) -> bool {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        match &self.extensions {
+            Some(extensions) => extensions
+                .iter()
+                .any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(ext)),
+            None => true,
+        }
+    }
+
+    pub fn args(&self, file: &str) -> Vec<String> {
+        let mut args = self.args.clone().unwrap_or_default();
+        if !args.iter().any(|arg| arg.contains("{file}")) {
+            args.push(String::from("{file}"));
+        }
+        args.iter().map(|arg| arg.replace("{file}", file)).collect()
+    }
+}
+
+/// 图表页面的无障碍选项
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Accessibility {
+    /// 为图表 canvas 添加 role 以及 aria-label 属性
+    pub aria_label: Option<bool>,
+    /// 在图表下方输出查询结果的数据表格
+    pub data_table: Option<bool>,
+    /// 所有颜色变量统一使用高对比度的 HighContrast 调色板
+    pub high_contrast: Option<bool>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Dependency {
     chartjs: String,
@@ -184,9 +770,65 @@ impl Default for Dependency {
 }
 
 #[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Query {
+    #[serde(default)]
     pub statements: Vec<String>,
+    /// 使用查询库中的命名查询作为 statements
+    #[serde(rename = "use")]
+    pub use_query: Option<String>,
+    /// 替换命名查询中的 `{db}`，默认为第一个 execution 的 dbName
+    pub db: Option<String>,
+    /// 对每条语句的结果排序，如 `code DESC`
+    pub order_by: Option<String>,
+    /// 限制每条语句返回的行数
+    pub limit: Option<usize>,
     pub chart: Option<ChartConfig>,
+    /// 每条语句在配置文件中的行号（从 1 开始），由 load_configs 填充
+    #[serde(skip)]
+    pub lines: Vec<Option<usize>>,
+    /// 查询所在的配置文件，只在加载多个配置文件时填充
+    #[serde(skip)]
+    pub file: Option<String>,
+}
+
+impl Query {
+    /// 描述语句所在的查询、图表以及配置文件行号，用于错误信息
+    pub fn location(&self, index: usize, stmt: usize) -> String {
+        let mut s = format!("query #{}", index + 1);
+        if let Some(chart) = &self.chart {
+            s.push_str(&format!(" (chart '{}')", chart.name));
+        }
+        s.push_str(&format!(" statement #{}", stmt + 1));
+        match (&self.file, self.lines.get(stmt)) {
+            (Some(file), Some(Some(line))) => s.push_str(&format!(" at {}:{}", file, line)),
+            (None, Some(Some(line))) => s.push_str(&format!(" at line {}", line)),
+            _ => {}
+        }
+        s
+    }
+
+    /// 配置了 orderBy/limit 时将语句包装为子查询，非 SQL 用户无需改写语句即可排序截断
+    pub fn sqls(&self) -> Vec<String> {
+        if self.order_by.is_none() && self.limit.is_none() {
+            return self.statements.clone();
+        }
+
+        self.statements
+            .iter()
+            .map(|sql| {
+                let mut wrapped =
+                    format!("SELECT * FROM ({}) AS t", sql.trim().trim_end_matches(';'));
+                if let Some(order_by) = &self.order_by {
+                    wrapped.push_str(&format!(" ORDER BY {}", order_by));
+                }
+                if let Some(limit) = self.limit {
+                    wrapped.push_str(&format!(" LIMIT {}", limit));
+                }
+                wrapped
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, Default, Deserialize)]
@@ -196,20 +838,615 @@ pub struct ChartConfig {
     pub width: String,
     pub height: String,
     pub name: String,
+    /// 图表描述，开启 ariaLabel 时作为 aria-label 属性
+    pub description: Option<String>,
+    /// 覆盖 display.precision
+    pub precision: Option<u32>,
     pub options: Option<Value>,
     pub data: Value,
 }
 
+/// fetch、render 以及 self-update 发送 http 请求时使用的代理
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Proxy {
+    /// 代理地址，未设置时使用 `HTTPS_PROXY`/`HTTP_PROXY` 环境变量
+    pub url: Option<String>,
+    /// 直连的 host 列表，未设置时使用 `NO_PROXY` 环境变量
+    pub no_proxy: Option<Vec<String>>,
+}
+
 #[derive(Debug, Clone, Default, Deserialize)]
 pub struct Config {
+    pub proxy: Option<Proxy>,
     pub create: Option<CreateAction>,
     pub fetch: Option<FetchAction>,
     pub shell: Option<ShellAction>,
     pub render: Option<RenderAction>,
 }
 
-pub fn load_config(c: &str) -> Result<Config> {
-    let f = File::open(c)?;
-    let config: Config = serde_yaml::from_reader(f)?;
+/// 展开配置路径，目录按文件名顺序展开为其中的 `.yaml`/`.yml` 文件（不递归）
+pub fn config_files(paths: &[String]) -> Result<Vec<String>> {
+    let mut files = vec![];
+    for path in paths {
+        if !Path::new(path).is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        let mut entries = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|p| {
+                p.is_file()
+                    && matches!(
+                        p.extension().and_then(|ext| ext.to_str()),
+                        Some("yaml") | Some("yml")
+                    )
+            })
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return Err(anyhow!("No config files found in directory '{}'", path));
+        }
+        entries.sort();
+        files.extend(entries.iter().map(|p| p.to_string_lossy().to_string()));
+    }
+    Ok(files)
+}
+
+/// 读取单个配置文件，展开环境变量并迁移旧版本的配置结构
+fn load_value(file: &str) -> Result<(String, Value)> {
+    let content =
+        fs::read_to_string(file).map_err(|e| anyhow!("Read config '{}' error: {}", file, e))?;
+    let mut value: Value = serde_yaml::from_str(&content)
+        .map_err(|e| anyhow!("Parse config '{}' error: {}", file, e))?;
+    expand_env(&mut value, &|name| env::var(name).ok())
+        .map_err(|e| anyhow!("Invalid config '{}': {}", file, e))?;
+    for hint in migrate(&mut value) {
+        warn!("Config '{}': {}", file, hint);
+    }
+    Ok((content, value))
+}
+
+/// 将 other 合并到 base 中：对象按字段递归合并，列表依次拼接，其他类型的值由 other 覆盖
+pub(crate) fn merge_value(base: &mut Value, other: Value) {
+    match (base, other) {
+        (Value::Mapping(base), Value::Mapping(other)) => {
+            for (k, v) in other {
+                match base.get_mut(&k) {
+                    Some(existing) => merge_value(existing, v),
+                    None => {
+                        base.insert(k, v);
+                    }
+                }
+            }
+        }
+        (Value::Sequence(base), Value::Sequence(other)) => base.extend(other),
+        (base, Value::Null) if !base.is_null() => {}
+        (base, other) => *base = other,
+    }
+}
+
+/// 加载并合并多个配置文件或者目录，便于按团队拆分配置
+///
+/// 按顺序合并：列表（如 databases、executions、githubOrg、queries）依次拼接，其他字段以后面的文件为准
+pub fn load_configs(paths: &[String]) -> Result<Config> {
+    let files = config_files(paths)?;
+    let mut merged = Value::Null;
+    let mut contents = vec![];
+    for file in files.iter() {
+        let (content, value) = load_value(file)?;
+        let queries = lookup(&value, "render.display.queries")
+            .and_then(|v| v.as_sequence())
+            .map(|v| v.len())
+            .unwrap_or_default();
+        contents.push((file, content, queries));
+        merge_value(&mut merged, value);
+    }
+
+    let name = files.join(", ");
+    let mut config: Config = serde_yaml::from_value(merged.clone()).map_err(|e| {
+        let located = locate_error(&merged).unwrap_or_else(|| e.to_string());
+        anyhow!("Invalid config '{}': {}", name, located)
+    })?;
+    if let Some(render) = config.render.as_mut() {
+        let library = QueryLibrary::load(render.query_library.as_deref())?;
+        let db = render
+            .executions
+            .first()
+            .map(|e| e.db_name.clone())
+            .unwrap_or_default();
+        library.resolve(&mut render.display.queries, &db)?;
+
+        let mut offset = 0;
+        for (file, content, n) in contents {
+            let end = (offset + n).min(render.display.queries.len());
+            let queries = &mut render.display.queries[offset..end];
+            locate_statements(&content, queries);
+            if files.len() > 1 {
+                for query in queries.iter_mut() {
+                    query.file = Some(file.clone());
+                }
+            }
+            offset = end;
+        }
+    }
     Ok(config)
 }
+
+/// 展开配置中所有字符串值里的 `${ENV_VAR}` 环境变量引用，引用的变量未设置时返回错误
+///
+/// 变量名只能由大写字母、数字以及下划线组成，以免与图表中的 `${column}`、`${Blues}` 等变量冲突
+pub(crate) fn expand_env(value: &mut Value, env: &dyn Fn(&str) -> Option<String>) -> Result<()> {
+    let mut missing = vec![];
+    expand_value(value, "", env, &mut missing);
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "environment variable {} is not set",
+        missing.join(", ")
+    ))
+}
+
+fn expand_value(
+    value: &mut Value,
+    path: &str,
+    env: &dyn Fn(&str) -> Option<String>,
+    missing: &mut Vec<String>,
+) {
+    match value {
+        Value::String(s) => {
+            *s = expand_str(s, |name| {
+                let v = env(name);
+                if v.is_none() {
+                    missing.push(format!("'{}' (referenced by '{}')", name, path));
+                }
+                v
+            })
+        }
+        Value::Sequence(items) => {
+            for (idx, item) in items.iter_mut().enumerate() {
+                expand_value(item, &format!("{}[{}]", path, idx), env, missing);
+            }
+        }
+        Value::Mapping(mapping) => {
+            for (k, v) in mapping.iter_mut() {
+                let key = k.as_str().unwrap_or_default();
+                let path = match path.is_empty() {
+                    true => key.to_string(),
+                    false => format!("{}.{}", path, key),
+                };
+                expand_value(v, &path, env, missing);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_env_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// 替换字符串中的 `${ENV_VAR}`，未设置的变量保持原样
+fn expand_str(s: &str, mut lookup: impl FnMut(&str) -> Option<String>) -> String {
+    let mut expanded = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find('}') {
+            Some(end) if is_env_name(&after[..end]) => {
+                let name = &after[..end];
+                match lookup(name) {
+                    Some(v) => expanded.push_str(&v),
+                    None => expanded.push_str(&rest[start..start + end + 3]),
+                }
+                rest = &after[end + 1..];
+            }
+            _ => {
+                expanded.push_str("${");
+                rest = after;
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// fetch 中的数据源，旧版本的配置为单个对象，当前版本为列表
+static FETCH_SOURCES: [&str; 5] = [
+    "githubAuthenticated",
+    "githubUser",
+    "githubOrg",
+    "bitbucketWorkspace",
+    "gitea",
+];
+
+/// 配置中的列表字段，写成对象时反序列化会报 `invalid type: map, expected a sequence`
+static LIST_PATHS: [&str; 4] = [
+    "create.databases",
+    "shell.executions",
+    "render.executions",
+    "render.display.queries",
+];
+
+/// 将旧版本的配置结构迁移为当前结构，返回每处迁移的提示
+///
+/// 目前只有 fetch 数据源由单个对象改为列表，两种写法均可以加载
+pub(crate) fn migrate(value: &mut Value) -> Vec<String> {
+    let mut hints = vec![];
+    let fetch = match value.get_mut("fetch").and_then(|v| v.as_mapping_mut()) {
+        Some(fetch) => fetch,
+        None => return hints,
+    };
+    for source in FETCH_SOURCES {
+        let section = match fetch.get_mut(&Value::from(source)) {
+            Some(section) if section.is_mapping() => section,
+            _ => continue,
+        };
+        *section = Value::Sequence(vec![section.clone()]);
+        hints.push(format!(
+            "'fetch.{}' is a single object, which is deprecated, change it to a list by prefixing the first field with '- '",
+            source
+        ));
+    }
+    hints
+}
+
+fn error_of<T: DeserializeOwned>(value: &Value) -> Option<String> {
+    serde_yaml::from_value::<T>(value.clone())
+        .err()
+        .map(|e| e.to_string())
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |v, key| v.get(key))
+}
+
+fn items_error<T: DeserializeOwned>(value: &Value, path: &str) -> Option<String> {
+    let items = lookup(value, path)?.as_sequence()?;
+    items
+        .iter()
+        .enumerate()
+        .find_map(|(idx, item)| error_of::<T>(item).map(|e| format!("'{}[{}]': {}", path, idx, e)))
+}
+
+/// serde_yaml 的错误信息不包含字段路径，逐层反序列化配置的各个部分来定位出错的位置
+fn locate_error(value: &Value) -> Option<String> {
+    let paths = FETCH_SOURCES
+        .iter()
+        .map(|source| format!("fetch.{}", source))
+        .chain(LIST_PATHS.iter().map(|path| path.to_string()));
+    for path in paths {
+        if let Some(Value::Mapping(_)) = lookup(value, &path) {
+            return Some(format!(
+                "'{}': invalid type: map, expected a sequence, this field is a list, prefix each item with '- '",
+                path
+            ));
+        }
+    }
+
+    items_error::<GithubAuthenticated>(value, "fetch.githubAuthenticated")
+        .or_else(|| items_error::<GithubUser>(value, "fetch.githubUser"))
+        .or_else(|| items_error::<GithubOrg>(value, "fetch.githubOrg"))
+        .or_else(|| items_error::<BitbucketWorkspace>(value, "fetch.bitbucketWorkspace"))
+        .or_else(|| items_error::<Gitea>(value, "fetch.gitea"))
+        .or_else(|| items_error::<Database>(value, "create.databases"))
+        .or_else(|| items_error::<Execution>(value, "shell.executions"))
+        .or_else(|| items_error::<Execution>(value, "render.executions"))
+        .or_else(|| items_error::<Query>(value, "render.display.queries"))
+        .or_else(|| {
+            let sections = [
+                ("proxy", error_of::<Proxy> as fn(&Value) -> Option<String>),
+                ("create", error_of::<CreateAction>),
+                ("fetch", error_of::<FetchAction>),
+                ("shell", error_of::<ShellAction>),
+                ("render", error_of::<RenderAction>),
+            ];
+            sections.iter().find_map(|(name, check)| {
+                let section = value.get(name)?;
+                check(section).map(|e| format!("'{}': {}", name, e))
+            })
+        })
+}
+
+/// serde_yaml 不提供字段位置信息，按顺序在配置文本中查找每条语句的首行来确定行号
+///
+/// 每次匹配后从下一行继续查找，首行相同的语句依次对应各自的行号；查找使用的是展开 `${VAR}` 之后的语句，
+/// 首行包含环境变量的语句以及 useQuery 引用的语句在原始文本中找不到，行号为 None
+pub(crate) fn locate_statements(content: &str, queries: &mut [Query]) {
+    let lines = content.lines().collect::<Vec<_>>();
+    let mut cursor = 0;
+    for query in queries.iter_mut() {
+        query.lines = query
+            .statements
+            .iter()
+            .map(|stmt| {
+                let first = stmt.lines().next().unwrap_or_default().trim();
+                if first.is_empty() {
+                    return None;
+                }
+                let pos = lines[cursor..].iter().position(|l| l.contains(first))?;
+                cursor += pos + 1;
+                Some(cursor)
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    #[test]
+    fn test_expand_env() {
+        let vars = HashMap::from([("GITHUB_TOKEN", "ghp_xxx"), ("DB_DIR", "./db")]);
+        let env = |name: &str| vars.get(name).map(|v| v.to_string());
+
+        let mut value: Value = serde_yaml::from_str(
+            r#"
+fetch:
+  githubOrg:
+    - destination: "${DB_DIR}/repos-${org}.yaml"
+      token: "${GITHUB_TOKEN}"
+render:
+  display:
+    queries:
+      - chart:
+          labels: "${repo_name}"
+          backgroundColor: "${Blues}"
+          formatter: "${ unclosed"
+"#,
+        )
+        .unwrap();
+        expand_env(&mut value, &env).unwrap();
+        let org = &value["fetch"]["githubOrg"][0];
+        assert_eq!(org["destination"].as_str(), Some("./db/repos-${org}.yaml"));
+        assert_eq!(org["token"].as_str(), Some("ghp_xxx"));
+        let chart = &value["render"]["display"]["queries"][0]["chart"];
+        assert_eq!(chart["labels"].as_str(), Some("${repo_name}"));
+        assert_eq!(chart["backgroundColor"].as_str(), Some("${Blues}"));
+        assert_eq!(chart["formatter"].as_str(), Some("${ unclosed"));
+
+        let mut value: Value =
+            serde_yaml::from_str("gitea:\n  - token: \"${GITEA_TOKEN}\"\n").unwrap();
+        let e = expand_env(&mut value, &env).unwrap_err().to_string();
+        assert_eq!(
+            e,
+            "environment variable 'GITEA_TOKEN' (referenced by 'gitea[0].token') is not set"
+        );
+    }
+
+    #[test]
+    fn test_load_configs() {
+        let dir = TempDir::new("configs").unwrap();
+        let teams = dir.join("teams");
+        fs::create_dir_all(&teams).unwrap();
+        let base = dir.join("gitv.yaml");
+        fs::write(
+            &base,
+            r#"
+create:
+  concurrency: 4
+  databases:
+    - dir: "./db/base"
+render:
+  executions:
+    - dbName: "base"
+      dir: "./db/base"
+  display:
+    destination: "./static"
+    renderMode: "html"
+    queries:
+      - statements:
+          - "SELECT 1;"
+"#,
+        )
+        .unwrap();
+        fs::write(
+            teams.join("b.yaml"),
+            "create:\n  concurrency: 8\n  databases:\n    - dir: \"./db/b\"\n",
+        )
+        .unwrap();
+        fs::write(
+            teams.join("a.yml"),
+            "render:\n  display:\n    queries:\n      - statements:\n          - \"SELECT 2;\"\n",
+        )
+        .unwrap();
+        fs::write(teams.join("README.md"), "not a config").unwrap();
+
+        let paths = vec![
+            base.to_str().unwrap().to_string(),
+            teams.to_str().unwrap().to_string(),
+        ];
+        let files = config_files(&paths).unwrap();
+        assert_eq!(files.len(), 3);
+        assert!(files[1].ends_with("a.yml") && files[2].ends_with("b.yaml"));
+
+        let config = load_configs(&paths).unwrap();
+        let create = config.create.unwrap();
+        assert_eq!(create.concurrency, Some(8));
+        let dirs = create
+            .databases
+            .iter()
+            .map(|d| d.dir.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(dirs, vec!["./db/base", "./db/b"]);
+
+        let render = config.render.unwrap();
+        assert_eq!(render.display.destination, "./static");
+        let queries = render.display.queries;
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[0].file.as_deref(), Some(files[0].as_str()));
+        assert_eq!(queries[0].lines, vec![Some(15)]);
+        assert_eq!(queries[1].file.as_deref(), Some(files[1].as_str()));
+        assert_eq!(queries[1].lines, vec![Some(5)]);
+        assert!(queries[1]
+            .location(1, 0)
+            .ends_with(&format!("at {}:5", files[1])));
+
+        let config = load_configs(&paths[..1]).unwrap();
+        let query = &config.render.unwrap().display.queries[0];
+        assert_eq!(query.file, None);
+        assert!(query.location(0, 0).ends_with("at line 15"));
+
+        let empty = dir.join("empty");
+        fs::create_dir_all(&empty).unwrap();
+        assert!(config_files(&[empty.to_str().unwrap().to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_migrate() {
+        let mut value: Value = serde_yaml::from_str(
+            r#"
+fetch:
+  githubOrg:
+    cloneDir: "./db"
+    destination: "./db/repos.yaml"
+    token: "token"
+    org: "gitv"
+    type: "all"
+  gitea:
+    - cloneDir: "./db"
+      destination: "./db/gitea.yaml"
+      baseUrl: "https://gitea.example.com"
+      token: "token"
+"#,
+        )
+        .unwrap();
+        let hints = migrate(&mut value);
+        assert_eq!(hints.len(), 1);
+        assert!(hints[0].starts_with("'fetch.githubOrg' is a single object"));
+
+        let config: Config = serde_yaml::from_value(value.clone()).unwrap();
+        let fetch = config.fetch.unwrap();
+        assert_eq!(fetch.github_org.unwrap()[0].org, "gitv");
+        assert_eq!(fetch.gitea.unwrap().len(), 1);
+        assert!(migrate(&mut value).is_empty());
+    }
+
+    #[test]
+    fn test_post_processor() {
+        let processor = PostProcessor {
+            command: String::from("html-minifier"),
+            args: Some(vec![
+                String::from("-o"),
+                String::from("{file}"),
+                String::from("{file}"),
+            ]),
+            extensions: Some(vec![String::from(".HTML")]),
+        };
+        assert!(processor.matches(Path::new("./static/gitv.html")));
+        assert!(!processor.matches(Path::new("./static/gitv.svg")));
+        assert_eq!(processor.args("a.html"), vec!["-o", "a.html", "a.html"]);
+
+        let processor = PostProcessor {
+            command: String::from("svgo"),
+            args: Some(vec![String::from("--multipass")]),
+            extensions: None,
+        };
+        assert!(processor.matches(Path::new("./static/index")));
+        assert_eq!(processor.args("a.svg"), vec!["--multipass", "a.svg"]);
+    }
+
+    #[test]
+    fn test_locate_error() {
+        let locate = |content: &str| {
+            let value: Value = serde_yaml::from_str(content).unwrap();
+            assert!(serde_yaml::from_value::<Config>(value.clone()).is_err());
+            locate_error(&value).unwrap()
+        };
+
+        let e = locate("render:\n  executions:\n    dbName: gitv\n    dir: ./db\n");
+        assert!(e.starts_with("'render.executions': invalid type: map, expected a sequence"));
+
+        let e = locate("fetch:\n  githubUser:\n    - cloneDir: ./db\n      destination: ./db/repos.yaml\n      token: t\n      username: a\n      type: owner\n    - cloneDir: ./db\n");
+        assert!(
+            e.starts_with("'fetch.githubUser[1]': missing field"),
+            "{}",
+            e
+        );
+
+        let e = locate("shell:\n  executions: []\n  periods: 1\n");
+        assert!(e.starts_with("'shell': invalid type"), "{}", e);
+    }
+
+    #[test]
+    fn test_sampling() {
+        let hashs = (0..7).map(|i| format!("h{}", i)).collect::<Vec<_>>();
+        let sampling = |every: Option<usize>| Sampling {
+            max_commits: 1,
+            every,
+            since: None,
+        };
+        assert_eq!(sampling(None).sample(hashs.clone()), hashs);
+        assert_eq!(sampling(Some(0)).sample(hashs.clone()), hashs);
+        assert_eq!(sampling(Some(1)).sample(hashs.clone()), hashs);
+        assert_eq!(
+            sampling(Some(3)).sample(hashs.clone()),
+            vec!["h0", "h3", "h6"]
+        );
+        assert_eq!(sampling(Some(10)).sample(hashs), vec!["h0"]);
+        assert!(sampling(Some(2)).sample(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_query_sqls() {
+        let mut query = Query {
+            statements: vec![
+                "SELECT language, code FROM 'gitv.snapshot'".to_string(),
+                " SELECT author, count(1) AS n FROM 'gitv.commit' GROUP BY author; \n".to_string(),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(query.sqls(), query.statements);
+
+        query.order_by = Some("code DESC".to_string());
+        query.limit = Some(10);
+        assert_eq!(
+            query.sqls(),
+            vec![
+                "SELECT * FROM (SELECT language, code FROM 'gitv.snapshot') AS t ORDER BY code DESC LIMIT 10",
+                "SELECT * FROM (SELECT author, count(1) AS n FROM 'gitv.commit' GROUP BY author) AS t ORDER BY code DESC LIMIT 10",
+            ]
+        );
+
+        query.order_by = None;
+        assert_eq!(
+            query.sqls()[1],
+            "SELECT * FROM (SELECT author, count(1) AS n FROM 'gitv.commit' GROUP BY author) AS t LIMIT 10"
+        );
+    }
+
+    #[test]
+    fn test_database_from_lines() {
+        let dir = TempDir::new("from_lines").unwrap();
+        let local = dir.join("gitv");
+        fs::create_dir_all(&local).unwrap();
+
+        let lines = format!(
+            "# repos\nhttps://github.com/o/r1.git\n\n  git@github.com:o/r2.git\nhttps://gitea.local/o/r3/\n{}\n",
+            local.to_string_lossy()
+        );
+        let db = Database::from_lines("./db", "./db/repos", &lines);
+        assert_eq!(db.dir, "./db");
+
+        let repos = db.repos.unwrap();
+        let names = repos.iter().map(|r| r.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["o/r1", "o/r2", "o/r3", "gitv"]);
+        assert_eq!(repos[0].path, "./db/repos/o/r1");
+        assert_eq!(repos[1].remote.as_deref(), Some("git@github.com:o/r2.git"));
+        assert_eq!(
+            repos[2].remote.as_deref(),
+            Some("https://gitea.local/o/r3/")
+        );
+
+        let canonical = fs::canonicalize(&local).unwrap();
+        assert_eq!(repos[3].path, canonical.to_string_lossy());
+        assert!(repos[3].remote.is_none());
+    }
+}
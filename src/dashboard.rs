@@ -0,0 +1,355 @@
+use crate::config::Execution;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use datafusion::{
+    arrow::{record_batch::RecordBatch, util::display::array_value_to_string},
+    prelude::ExecutionContext,
+};
+use std::{cmp::Reverse, collections::BTreeMap};
+
+/// 仪表盘统计的周数
+const WEEKS: usize = 12;
+/// 展示的作者以及最近提交的数量
+const LIMIT: usize = 10;
+
+/// 终端仪表盘数据：每个仓库近 12 周的提交趋势、提交最多的作者以及最近的提交
+#[derive(Debug, Default)]
+pub struct Dashboard {
+    /// 仓库名称以及每周的提交数（按时间升序）
+    pub sparklines: Vec<(String, Vec<u64>)>,
+    /// 作者名称以及提交数
+    pub authors: Vec<(String, u64)>,
+    /// 最近的提交：日期、仓库名称、作者名称以及提交标题
+    pub recent: Vec<[String; 4]>,
+}
+
+impl Dashboard {
+    /// 查询 executions 中所有 database 的 commit 表生成仪表盘数据
+    pub async fn load(ctx: &mut ExecutionContext, executions: &[Execution]) -> Result<Self> {
+        if executions.is_empty() {
+            return Err(anyhow!("No executions configured"));
+        }
+        let commits = executions
+            .iter()
+            .map(|e| {
+                format!(
+                    "SELECT repo_name, hash, datetime, author_name, subject FROM '{}.commit'",
+                    e.db_name
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" UNION ALL ");
+
+        let sql = format!(
+            "SELECT DISTINCT repo_name, hash, datetime FROM ({}) AS t",
+            commits
+        );
+        let rows = query(ctx, &sql).await?;
+        let sparklines = weekly_commits(&rows, Utc::now(), WEEKS);
+
+        let sql = format!(
+            "SELECT author_name, count(DISTINCT hash) AS commits FROM ({}) AS t \
+            GROUP BY author_name ORDER BY commits DESC LIMIT {}",
+            commits, LIMIT
+        );
+        let authors = query(ctx, &sql)
+            .await?
+            .into_iter()
+            .map(|row| (row[0].clone(), row[1].parse().unwrap_or_default()))
+            .collect();
+
+        let sql = format!(
+            "SELECT datetime, repo_name, author_name, subject FROM ({}) AS t \
+            ORDER BY datetime DESC LIMIT {}",
+            commits, LIMIT
+        );
+        let recent = query(ctx, &sql)
+            .await?
+            .into_iter()
+            .map(|row| {
+                let date = row[0].get(..10).unwrap_or(&row[0]).to_string();
+                [date, row[1].clone(), row[2].clone(), row[3].clone()]
+            })
+            .collect();
+
+        Ok(Self {
+            sparklines,
+            authors,
+            recent,
+        })
+    }
+}
+
+/// 执行查询并将结果转换为字符串的行
+async fn query(ctx: &mut ExecutionContext, sql: &str) -> Result<Vec<Vec<String>>> {
+    let batches: Vec<RecordBatch> = ctx.sql(sql).await?.collect().await?;
+    let mut rows = vec![];
+    for batch in batches {
+        for row in 0..batch.num_rows() {
+            let mut values = vec![];
+            for column in batch.columns() {
+                values.push(array_value_to_string(column, row)?);
+            }
+            rows.push(values);
+        }
+    }
+    Ok(rows)
+}
+
+/// 统计每个仓库最近 weeks 周内每周的提交数，rows 为 (repo_name, hash, datetime)
+fn weekly_commits(
+    rows: &[Vec<String>],
+    now: DateTime<Utc>,
+    weeks: usize,
+) -> Vec<(String, Vec<u64>)> {
+    const WEEK: i64 = 3600 * 24 * 7;
+    let mut counts: BTreeMap<String, Vec<u64>> = BTreeMap::new();
+    for row in rows {
+        let timestamp = match DateTime::parse_from_rfc3339(&row[2]) {
+            Ok(t) => t.timestamp(),
+            Err(_) => continue,
+        };
+        let counts = counts
+            .entry(row[0].clone())
+            .or_insert_with(|| vec![0; weeks]);
+        let ago = (now.timestamp() - timestamp).div_euclid(WEEK);
+        if (0..weeks as i64).contains(&ago) {
+            counts[weeks - 1 - ago as usize] += 1;
+        }
+    }
+
+    let mut sparklines = counts.into_iter().collect::<Vec<_>>();
+    sparklines.sort_by_key(|(_, counts)| Reverse(counts.iter().sum::<u64>()));
+    sparklines
+}
+
+/// 未使用 `tui` feature 编译时以纯文本的形式输出仪表盘
+#[cfg(not(feature = "tui"))]
+pub async fn show(mut ctx: ExecutionContext, executions: &[Execution]) -> Result<()> {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let dashboard = Dashboard::load(&mut ctx, executions).await?;
+
+    println!("Commits per week (last {} weeks)", WEEKS);
+    for (repo, counts) in dashboard.sparklines.iter() {
+        let max = counts.iter().copied().max().unwrap_or_default().max(1);
+        let line = counts
+            .iter()
+            .map(|&c| match c {
+                0 => ' ',
+                c => BARS[((c * 8 - 1) / max).min(7) as usize],
+            })
+            .collect::<String>();
+        println!("  {} {} ({})", line, repo, counts.iter().sum::<u64>());
+    }
+
+    println!("\nTop authors");
+    for (name, commits) in dashboard.authors.iter() {
+        println!("  {:>6} {}", commits, name);
+    }
+
+    println!("\nRecent activity");
+    for [date, repo, author, subject] in dashboard.recent.iter() {
+        println!("  {} {} {}: {}", date, repo, author, subject);
+    }
+    Ok(())
+}
+
+/// 在终端中展示仪表盘，按 q 或 Esc 退出
+#[cfg(feature = "tui")]
+pub use tui::show;
+
+#[cfg(feature = "tui")]
+mod tui {
+    use super::*;
+    use crossterm::{
+        event::{self, Event, KeyCode, KeyEventKind},
+        execute,
+        terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    };
+    use ratatui::{
+        backend::{Backend, CrosstermBackend},
+        layout::{Constraint, Direction, Layout, Rect},
+        widgets::{Block, Borders, Cell, Row, Sparkline, Table},
+        Frame, Terminal,
+    };
+    use std::io;
+
+    pub async fn show(mut ctx: ExecutionContext, executions: &[Execution]) -> Result<()> {
+        let dashboard = Dashboard::load(&mut ctx, executions).await?;
+
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+        let result = run(&mut terminal, &dashboard);
+
+        disable_raw_mode()?;
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+        terminal.show_cursor()?;
+        result
+    }
+
+    fn run<B: Backend>(terminal: &mut Terminal<B>, dashboard: &Dashboard) -> Result<()> {
+        loop {
+            terminal.draw(|f| draw(f, dashboard))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press
+                    && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn draw<B: Backend>(f: &mut Frame<B>, dashboard: &Dashboard) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(f.size());
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(rows[0]);
+
+        draw_sparklines(f, dashboard, columns[0]);
+
+        let authors = dashboard
+            .authors
+            .iter()
+            .map(|(name, commits)| Row::new(vec![name.clone(), commits.to_string()]))
+            .collect::<Vec<_>>();
+        let authors = Table::new(authors)
+            .header(Row::new(vec!["author", "commits"]))
+            .block(Block::default().borders(Borders::ALL).title("Top authors"))
+            .widths(&[Constraint::Percentage(75), Constraint::Percentage(25)]);
+        f.render_widget(authors, columns[1]);
+
+        let recent = dashboard
+            .recent
+            .iter()
+            .map(|row| Row::new(row.iter().map(|v| Cell::from(v.as_str()))))
+            .collect::<Vec<_>>();
+        let recent = Table::new(recent)
+            .header(Row::new(vec!["date", "repo", "author", "subject"]))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Recent activity (q: quit)"),
+            )
+            .widths(&[
+                Constraint::Length(10),
+                Constraint::Percentage(25),
+                Constraint::Percentage(20),
+                Constraint::Percentage(55),
+            ]);
+        f.render_widget(recent, rows[1]);
+    }
+
+    /// 每个仓库一行，左侧为仓库名称以及总提交数，右侧为每周提交数的 sparkline
+    fn draw_sparklines<B: Backend>(f: &mut Frame<B>, dashboard: &Dashboard, area: Rect) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Commits per week (last {} weeks)", WEEKS));
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let height = inner.height as usize;
+        let repos = &dashboard.sparklines[..dashboard.sparklines.len().min(height)];
+        let lines = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Length(1); repos.len()])
+            .split(inner);
+        for ((repo, counts), line) in repos.iter().zip(lines.iter()) {
+            let parts = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(20), Constraint::Length(WEEKS as u16)])
+                .split(*line);
+            let total = counts.iter().sum::<u64>();
+            let label = format!("{} ({})", repo, total);
+            f.render_widget(Block::default().title(label), parts[0]);
+            f.render_widget(Sparkline::default().data(counts), parts[1]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{executor::Executor, tempdir::TempDir};
+    use chrono::TimeZone;
+    use std::fs;
+
+    #[test]
+    fn test_weekly_commits() {
+        let row = |repo: &str, datetime: &str| {
+            vec![repo.to_string(), String::new(), datetime.to_string()]
+        };
+        let rows = vec![
+            row("a", "2022-03-01T08:00:00+08:00"),
+            row("a", "2022-02-27T00:00:00+00:00"),
+            row("a", "2021-01-01T00:00:00+00:00"),
+            row("b", "2022-02-20T00:00:00+00:00"),
+            row("b", "2022-02-21T00:00:00+00:00"),
+            row("b", "2022-02-22T00:00:00+00:00"),
+            row("b", "invalid"),
+        ];
+        let now = Utc.ymd(2022, 3, 1).and_hms(12, 0, 0);
+        let sparklines = weekly_commits(&rows, now, 3);
+        assert_eq!(
+            sparklines,
+            vec![
+                (String::from("b"), vec![0, 3, 0]),
+                (String::from("a"), vec![0, 0, 2]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load() {
+        let dir = TempDir::new("dashboard").unwrap();
+        fs::write(
+            dir.join("commit.csv"),
+            "repo_name,hash,datetime,author_name,subject\n\
+            a/x,h1,2022-03-01T10:00:00+08:00,alice,init\n\
+            a/x,h2,2022-03-02T10:00:00+08:00,bob,fix parser\n\
+            a/y,h3,2022-03-03T10:00:00+08:00,alice,add docs\n",
+        )
+        .unwrap();
+        let executions = vec![Execution {
+            db_name: String::from("db"),
+            dir: dir.path_str().to_string(),
+        }];
+        let mut ctx = Executor::create_context(executions.clone(), None, None)
+            .await
+            .unwrap();
+
+        let dashboard = Dashboard::load(&mut ctx, &executions).await.unwrap();
+        let mut repos = dashboard
+            .sparklines
+            .iter()
+            .map(|(repo, counts)| {
+                assert_eq!(counts.len(), WEEKS);
+                repo.as_str()
+            })
+            .collect::<Vec<_>>();
+        repos.sort_unstable();
+        assert_eq!(repos, vec!["a/x", "a/y"]);
+        assert_eq!(
+            dashboard.authors,
+            vec![(String::from("alice"), 2), (String::from("bob"), 1)]
+        );
+        assert_eq!(
+            dashboard.recent[0],
+            [
+                String::from("2022-03-03"),
+                String::from("a/y"),
+                String::from("alice"),
+                String::from("add docs")
+            ]
+        );
+        assert_eq!(dashboard.recent.len(), 3);
+        assert!(Dashboard::load(&mut ctx, &[]).await.is_err());
+    }
+}
@@ -0,0 +1,277 @@
+use crate::{
+    config::{CsvOptions, ForkDedup},
+    model::Repository,
+    record::{self, RecordChange, RecordCommit, RecordDuplicate, RecordEnrichment},
+};
+use anyhow::{anyhow, Result};
+use log::info;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// 去重后被移除的记录数
+#[derive(Debug, Default)]
+pub struct DedupStats {
+    pub commits: usize,
+    pub changes: usize,
+    pub enrichments: usize,
+}
+
+/// 同一个 database 中包含上游仓库以及 fork 仓库时，相同 hash 的提交会被重复统计
+///
+/// Deduplicator 在所有仓库分析完成后，按照 hash 对 `commit.csv.tmp`、`change.csv.tmp` 以及 `enrichment.csv.tmp` 去重
+pub struct Deduplicator {
+    policy: ForkDedup,
+    /// repo_name => (是否为 fork 仓库, 在 database 中的顺序)
+    ranks: HashMap<String, (bool, usize)>,
+}
+
+impl Deduplicator {
+    pub fn new(policy: ForkDedup, repos: &[Repository]) -> Self {
+        let ranks = repos
+            .iter()
+            .enumerate()
+            .map(|(i, repo)| (repo.name.clone(), (repo.fork.unwrap_or(false), i)))
+            .collect();
+        Self { policy, ranks }
+    }
+
+    fn rank(&self, repo_name: &str) -> (bool, usize) {
+        let (fork, index) = self
+            .ranks
+            .get(repo_name)
+            .cloned()
+            .unwrap_or((false, usize::MAX));
+        match self.policy {
+            ForkDedup::KeepUpstream => (fork, index),
+            ForkDedup::KeepFirst | ForkDedup::TagBoth => (false, index),
+        }
+    }
+
+    fn table_path(dir: &str, name: String) -> PathBuf {
+        Path::new(dir).join(format!("{}.csv.tmp", name))
+    }
+
+    fn column(headers: &csv::StringRecord, name: &str) -> Result<usize> {
+        headers
+            .iter()
+            .position(|h| h == name)
+            .ok_or_else(|| anyhow!("Column '{}' not found", name))
+    }
+
+    /// 计算每个重复 hash 保留的仓库，返回 hash => (保留仓库, 所有包含该 hash 的仓库)
+    fn canonicals(&self, commits: &Path) -> Result<HashMap<String, (String, Vec<String>)>> {
+        let mut repos: HashMap<String, Vec<String>> = HashMap::new();
+        if fs::metadata(commits)?.len() == 0 {
+            return Ok(HashMap::new());
+        }
+
+        let mut rdr = record::csv_reader(commits)?;
+        let headers = rdr.headers()?.clone();
+        let (repo_idx, hash_idx) = (
+            Self::column(&headers, "repo_name")?,
+            Self::column(&headers, "hash")?,
+        );
+        for row in rdr.records() {
+            let row = row?;
+            let entry = repos.entry(row[hash_idx].to_string()).or_default();
+            if !entry.iter().any(|r| r == &row[repo_idx]) {
+                entry.push(row[repo_idx].to_string());
+            }
+        }
+
+        let mut canonicals = HashMap::new();
+        for (hash, mut names) in repos.into_iter().filter(|(_, v)| v.len() > 1) {
+            names.sort_by_key(|name| self.rank(name));
+            canonicals.insert(hash, (names[0].clone(), names));
+        }
+        Ok(canonicals)
+    }
+
+    /// 重写临时数据文件，移除非保留仓库中的重复记录，返回移除的行数
+    fn rewrite(
+        path: &Path,
+        canonicals: &HashMap<String, (String, Vec<String>)>,
+        csv: &CsvOptions,
+    ) -> Result<usize> {
+        if fs::metadata(path)?.len() == 0 {
+            return Ok(0);
+        }
+
+        let dedup = path.with_extension("tmp.dedup");
+        let mut rdr = record::csv_reader(path)?;
+        let headers = rdr.headers()?.clone();
+        let (repo_idx, hash_idx) = (
+            Self::column(&headers, "repo_name")?,
+            Self::column(&headers, "hash")?,
+        );
+
+        let mut wtr = csv.writer(fs::File::create(&dedup)?)?;
+        wtr.write_record(&headers)?;
+        let mut removed = 0;
+        for row in rdr.records() {
+            let row = row?;
+            if let Some((canonical, _)) = canonicals.get(&row[hash_idx]) {
+                if canonical != &row[repo_idx] {
+                    removed += 1;
+                    continue;
+                }
+            }
+            wtr.write_record(&row)?;
+        }
+        wtr.flush()?;
+        fs::rename(&dedup, path)?;
+        Ok(removed)
+    }
+
+    /// 执行去重，返回重复提交的明细记录以及移除的行数
+    pub fn apply(&self, dir: &str, csv: &CsvOptions) -> Result<(Vec<RecordDuplicate>, DedupStats)> {
+        let commit_path = Self::table_path(dir, RecordCommit::name());
+        let canonicals = self.canonicals(&commit_path)?;
+
+        let mut records = vec![];
+        let mut hashs = canonicals.keys().collect::<Vec<_>>();
+        hashs.sort();
+        for hash in hashs {
+            let (canonical, repos) = &canonicals[hash];
+            for repo in repos {
+                records.push(RecordDuplicate {
+                    repo_name: repo.clone(),
+                    hash: hash.clone(),
+                    canonical: canonical.clone(),
+                    kept: self.policy == ForkDedup::TagBoth || repo == canonical,
+                });
+            }
+        }
+
+        let mut stats = DedupStats::default();
+        if self.policy != ForkDedup::TagBoth && !canonicals.is_empty() {
+            stats.commits = Self::rewrite(&commit_path, &canonicals, csv)?;
+            stats.changes = Self::rewrite(
+                &Self::table_path(dir, RecordChange::name()),
+                &canonicals,
+                csv,
+            )?;
+            stats.enrichments = Self::rewrite(
+                &Self::table_path(dir, RecordEnrichment::name()),
+                &canonicals,
+                csv,
+            )?;
+        }
+
+        let repos = records
+            .iter()
+            .map(|r| r.repo_name.as_str())
+            .collect::<HashSet<_>>();
+        info!(
+            "[dedup] '{}' => {} duplicated commits across {} repos, removed {} commit rows and {} change rows",
+            dir,
+            canonicals.len(),
+            repos.len(),
+            stats.commits,
+            stats.changes,
+        );
+        Ok((records, stats))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    fn repo(name: &str, fork: bool) -> Repository {
+        Repository {
+            name: name.to_string(),
+            fork: Some(fork),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_dedup_apply() {
+        let dir = TempDir::new("dedup").unwrap();
+        let dir = dir.path_str();
+
+        let mut wtr =
+            csv::Writer::from_path(Deduplicator::table_path(dir, RecordCommit::name())).unwrap();
+        for (repo_name, hash) in [("me/a", "h1"), ("org/a", "h1"), ("org/a", "h2")] {
+            wtr.serialize(RecordCommit {
+                repo_name: repo_name.to_string(),
+                hash: hash.to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        }
+        wtr.flush().unwrap();
+        fs::File::create(Deduplicator::table_path(dir, RecordChange::name())).unwrap();
+        fs::File::create(Deduplicator::table_path(dir, RecordEnrichment::name())).unwrap();
+
+        let repos = vec![repo("me/a", true), repo("org/a", false)];
+        let (records, stats) = Deduplicator::new(ForkDedup::KeepUpstream, &repos)
+            .apply(dir, &CsvOptions::default())
+            .unwrap();
+        assert_eq!(stats.commits, 1);
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|r| r.canonical == "org/a"));
+
+        let rows = csv::Reader::from_path(Deduplicator::table_path(dir, RecordCommit::name()))
+            .unwrap()
+            .records()
+            .map(|r| r.unwrap()[0].to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(rows, vec!["org/a", "org/a"]);
+    }
+
+    #[test]
+    fn test_dedup_apply_csv_options() {
+        let dir = TempDir::new("dedup-csv").unwrap();
+        let dir = dir.path_str();
+
+        let csv = CsvOptions {
+            bom: Some(true),
+            delimiter: Some(';'),
+            ..Default::default()
+        };
+        let path = Deduplicator::table_path(dir, RecordCommit::name());
+        let mut wtr = csv.writer(fs::File::create(&path).unwrap()).unwrap();
+        for (repo_name, hash) in [("me/a", "h1"), ("org/a", "h1")] {
+            wtr.serialize(RecordCommit {
+                repo_name: repo_name.to_string(),
+                hash: hash.to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        }
+        wtr.flush().unwrap();
+        fs::File::create(Deduplicator::table_path(dir, RecordChange::name())).unwrap();
+        fs::File::create(Deduplicator::table_path(dir, RecordEnrichment::name())).unwrap();
+
+        let repos = vec![repo("me/a", true), repo("org/a", false)];
+        let (_, stats) = Deduplicator::new(ForkDedup::KeepUpstream, &repos)
+            .apply(dir, &csv)
+            .unwrap();
+        assert_eq!(stats.commits, 1);
+
+        let content = fs::read(&path).unwrap();
+        assert!(content.starts_with(b"\xEF\xBB\xBFrepo_name;"));
+        assert_eq!(record::csv_delimiter(&path).unwrap(), b';');
+        let rows = record::csv_reader(&path)
+            .unwrap()
+            .records()
+            .map(|r| r.unwrap()[0].to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(rows, vec!["org/a"]);
+    }
+
+    #[test]
+    fn test_dedup_rank() {
+        let repos = vec![repo("me/a", true), repo("org/a", false)];
+        let d = Deduplicator::new(ForkDedup::KeepFirst, &repos);
+        assert!(d.rank("me/a") < d.rank("org/a"));
+        let d = Deduplicator::new(ForkDedup::KeepUpstream, &repos);
+        assert!(d.rank("org/a") < d.rank("me/a"));
+    }
+}
@@ -0,0 +1,259 @@
+use crate::record::{self, RecordActive, RecordCommit};
+use anyhow::Result;
+use chrono::Local;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Deserialize)]
+struct CommitRow {
+    repo_name: String,
+    hash: String,
+    author_name: String,
+    author_email: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActiveRow {
+    repo_name: String,
+    forks: usize,
+    stars: usize,
+}
+
+static DELTA_REPORT: &str = "delta_report.md";
+
+fn read_rows<T: DeserializeOwned>(p: &Path) -> Result<Vec<T>> {
+    let mut rows = vec![];
+    for row in record::csv_reader(p)?.deserialize() {
+        rows.push(row?);
+    }
+    Ok(rows)
+}
+
+/// 对比上一次运行的数据文件（`${name}.csv`）以及本次新生成的数据文件（`${name}.csv.tmp`）
+/// 生成 markdown 格式的变更报告
+pub struct DeltaReport {
+    /// 仓库新增提交数
+    commits: BTreeMap<String, usize>,
+    /// 仓库新增贡献者
+    contributors: BTreeMap<String, Vec<(String, String)>>,
+    /// 仓库 stars/forks 变化
+    actives: BTreeMap<String, (usize, i64, usize, i64)>,
+}
+
+impl DeltaReport {
+    fn table_path(dir: &str, name: String, tmp: bool) -> PathBuf {
+        if tmp {
+            Path::new(dir).join(format!("{}.csv.tmp", name))
+        } else {
+            Path::new(dir).join(format!("{}.csv", name))
+        }
+    }
+
+    /// 上一次运行的数据不存在时返回 None
+    pub fn try_new(dir: &str) -> Result<Option<DeltaReport>> {
+        let prev_commit = Self::table_path(dir, RecordCommit::name(), false);
+        if !prev_commit.exists() {
+            return Ok(None);
+        }
+
+        let prev: Vec<CommitRow> = read_rows(&prev_commit)?;
+        let curr: Vec<CommitRow> = read_rows(&Self::table_path(dir, RecordCommit::name(), true))?;
+
+        let hashs: HashSet<(&str, &str)> = prev
+            .iter()
+            .map(|c| (c.repo_name.as_str(), c.hash.as_str()))
+            .collect();
+        let authors: HashSet<(&str, &str)> = prev
+            .iter()
+            .map(|c| (c.repo_name.as_str(), c.author_email.as_str()))
+            .collect();
+
+        let mut commits = BTreeMap::new();
+        let mut contributors: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+        let mut seen = HashSet::new();
+        for c in curr.iter() {
+            if hashs.contains(&(c.repo_name.as_str(), c.hash.as_str())) {
+                continue;
+            }
+            *commits.entry(c.repo_name.clone()).or_insert(0) += 1;
+
+            let key = (c.repo_name.as_str(), c.author_email.as_str());
+            if !authors.contains(&key) && seen.insert(key) {
+                contributors
+                    .entry(c.repo_name.clone())
+                    .or_default()
+                    .push((c.author_name.clone(), c.author_email.clone()));
+            }
+        }
+
+        let mut actives = BTreeMap::new();
+        let prev_active = Self::table_path(dir, RecordActive::name(), false);
+        let curr_active = Self::table_path(dir, RecordActive::name(), true);
+        if prev_active.exists() && curr_active.exists() {
+            let prev: HashMap<String, ActiveRow> = read_rows::<ActiveRow>(&prev_active)?
+                .into_iter()
+                .map(|a| (a.repo_name.clone(), a))
+                .collect();
+            for a in read_rows::<ActiveRow>(&curr_active)? {
+                let (stars, forks) = match prev.get(&a.repo_name) {
+                    Some(p) => (p.stars as i64, p.forks as i64),
+                    None => (0, 0),
+                };
+                let (ds, df) = (a.stars as i64 - stars, a.forks as i64 - forks);
+                if ds != 0 || df != 0 {
+                    actives.insert(a.repo_name, (a.stars, ds, a.forks, df));
+                }
+            }
+        }
+
+        Ok(Some(Self {
+            commits,
+            contributors,
+            actives,
+        }))
+    }
+
+    fn render(&self) -> String {
+        let mut lines = vec![
+            String::from("# Delta Report"),
+            String::new(),
+            format!("Generated at {}", Local::now().to_rfc3339()),
+            String::new(),
+            String::from("## New Commits"),
+            String::new(),
+        ];
+        if self.commits.is_empty() {
+            lines.push(String::from("No new commits."));
+        } else {
+            lines.push(String::from("| repo | commits |"));
+            lines.push(String::from("| ---- | ------- |"));
+            for (repo, n) in self.commits.iter() {
+                lines.push(format!("| {} | {} |", repo, n));
+            }
+        }
+
+        lines.extend([
+            String::new(),
+            String::from("## New Contributors"),
+            String::new(),
+        ]);
+        if self.contributors.is_empty() {
+            lines.push(String::from("No new contributors."));
+        } else {
+            lines.push(String::from("| repo | author | email |"));
+            lines.push(String::from("| ---- | ------ | ----- |"));
+            for (repo, authors) in self.contributors.iter() {
+                for (name, email) in authors {
+                    lines.push(format!("| {} | {} | {} |", repo, name, email));
+                }
+            }
+        }
+
+        lines.extend([
+            String::new(),
+            String::from("## Stars Changes"),
+            String::new(),
+        ]);
+        if self.actives.is_empty() {
+            lines.push(String::from("No stars/forks changes."));
+        } else {
+            lines.push(String::from("| repo | stars | delta | forks | delta |"));
+            lines.push(String::from("| ---- | ----- | ----- | ----- | ----- |"));
+            for (repo, (stars, ds, forks, df)) in self.actives.iter() {
+                lines.push(format!(
+                    "| {} | {} | {:+} | {} | {:+} |",
+                    repo, stars, ds, forks, df
+                ));
+            }
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    pub fn write(&self, dir: &str) -> Result<()> {
+        let mut f = File::create(Path::new(dir).join(DELTA_REPORT))?;
+        f.write_all(self.render().as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+    use std::fs;
+
+    static COMMIT_HEADER: &str = "repo_name,hash,author_name,author_email\n";
+    static ACTIVE_HEADER: &str = "repo_name,forks,stars\n";
+
+    fn write_table(dir: &Path, name: String, tmp: bool, header: &str, rows: &[&str]) {
+        let path = DeltaReport::table_path(dir.to_str().unwrap(), name, tmp);
+        fs::write(path, format!("{}{}\n", header, rows.join("\n"))).unwrap();
+    }
+
+    #[test]
+    fn test_delta_report() {
+        let dir = TempDir::new("delta").unwrap();
+        let path = dir.path_str();
+
+        let commits = ["a,h1,alice,alice@x", "b,h2,bob,bob@x"];
+        let actives = ["a,1,10", "b,0,5"];
+        write_table(&dir, RecordCommit::name(), true, COMMIT_HEADER, &commits);
+        write_table(&dir, RecordActive::name(), true, ACTIVE_HEADER, &actives);
+        assert!(DeltaReport::try_new(path).unwrap().is_none());
+
+        write_table(&dir, RecordCommit::name(), false, COMMIT_HEADER, &commits);
+        write_table(&dir, RecordActive::name(), false, ACTIVE_HEADER, &actives);
+        let report = DeltaReport::try_new(path).unwrap().unwrap();
+        assert!(report.commits.is_empty());
+        assert!(report.contributors.is_empty());
+        assert!(report.actives.is_empty());
+        let content = report.render();
+        assert!(content.contains("No new commits."));
+        assert!(content.contains("No new contributors."));
+        assert!(content.contains("No stars/forks changes."));
+
+        // 新增仓库 c，移除仓库 b
+        write_table(
+            &dir,
+            RecordCommit::name(),
+            true,
+            COMMIT_HEADER,
+            &[
+                "a,h1,alice,alice@x",
+                "a,h3,carol,carol@x",
+                "a,h4,carol,carol@x",
+                "c,h5,dave,dave@x",
+            ],
+        );
+        write_table(
+            &dir,
+            RecordActive::name(),
+            true,
+            ACTIVE_HEADER,
+            &["a,1,12", "c,0,3"],
+        );
+        let report = DeltaReport::try_new(path).unwrap().unwrap();
+        assert_eq!(
+            report.commits.into_iter().collect::<Vec<_>>(),
+            vec![(String::from("a"), 2), (String::from("c"), 1)]
+        );
+        assert_eq!(
+            report.contributors.get("a").unwrap(),
+            &vec![(String::from("carol"), String::from("carol@x"))]
+        );
+        assert_eq!(
+            report.contributors.get("c").unwrap(),
+            &vec![(String::from("dave"), String::from("dave@x"))]
+        );
+        assert_eq!(report.actives.get("a"), Some(&(12, 2, 1, 0)));
+        assert_eq!(report.actives.get("c"), Some(&(3, 3, 0, 0)));
+        assert!(!report.contributors.contains_key("b"));
+        assert!(!report.actives.contains_key("b"));
+    }
+}
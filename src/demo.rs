@@ -0,0 +1,262 @@
+use crate::{
+    gitmoji,
+    record::{RecordActive, RecordChange, RecordCommit, RecordSnapshot, RecordTag},
+};
+use anyhow::Result;
+use chrono::{Duration, FixedOffset, Local};
+use rand::prelude::*;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+static CONTENT_DEMO: &str = include_str!("../static/demo.yaml");
+
+/// 演示数据的随机种子，保证每次生成的数据一致
+static SEED: u64 = 20220320;
+
+/// 演示数据覆盖的天数，截止到昨天
+static DAYS: i64 = 365;
+
+static REPOS: [(&str, usize); 3] = [
+    ("demo/api-server", 320),
+    ("demo/web-console", 220),
+    ("demo/cli", 120),
+];
+
+static AUTHORS: [(&str, &str); 6] = [
+    ("alice", "alice@example.com"),
+    ("bob", "bob@example.com"),
+    ("carol", "carol@example.org"),
+    ("dave", "dave@example.org"),
+    ("erin", "erin@users.noreply.github.com"),
+    ("frank", "frank@example.com"),
+];
+
+static EXTS: [&str; 6] = ["rs", "go", "ts", "py", "md", "yaml"];
+
+static SUBJECTS: [&str; 8] = [
+    ":memo: Fix typo in README",
+    ":bug: Add retry for flaky requests",
+    ":recycle: Refactor config loading",
+    "⬆️ Bump dependencies",
+    "Improve error messages",
+    "✅ Add unit tests",
+    ":sparkles: Support dark mode",
+    "Update CI workflow",
+];
+
+/// 演示数据，所有表共用同一组虚构的提交
+#[derive(Debug, Default)]
+struct Dataset {
+    commits: Vec<RecordCommit>,
+    changes: Vec<RecordChange>,
+    tags: Vec<RecordTag>,
+    snapshots: Vec<RecordSnapshot>,
+    actives: Vec<RecordActive>,
+}
+
+impl Dataset {
+    fn generate(today: chrono::DateTime<FixedOffset>) -> Self {
+        let mut rng = StdRng::seed_from_u64(SEED);
+        let midnight = today.date().and_hms(0, 0, 0);
+        let mut dataset = Dataset::default();
+        for (repo_name, total) in REPOS {
+            // 作者的提交数呈长尾分布，第一位作者最活跃
+            let weights = (1..=AUTHORS.len())
+                .map(|i| 1.0 / i as f64)
+                .collect::<Vec<_>>();
+            let mut datetimes = (0..total)
+                .map(|_| {
+                    let day = rng.gen_range(1..=DAYS);
+                    // 大部分提交集中在工作时间
+                    let hour = match rng.gen_bool(0.8) {
+                        true => rng.gen_range(9..19),
+                        false => rng.gen_range(0..24),
+                    };
+                    midnight - Duration::days(day)
+                        + Duration::hours(hour)
+                        + Duration::minutes(rng.gen_range(0..60))
+                })
+                .collect::<Vec<_>>();
+            datetimes.sort();
+
+            let mut lines: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+            for (idx, datetime) in datetimes.iter().enumerate() {
+                let author_idx = weighted_index(&mut rng, &weights);
+                let (author_name, author_email) = AUTHORS[author_idx];
+                let hash = Sha256::digest(format!("{}{}", repo_name, idx).as_bytes())
+                    .iter()
+                    .take(20)
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>();
+                let subject = SUBJECTS.choose(&mut rng).unwrap_or(&SUBJECTS[0]);
+                let commit = RecordCommit {
+                    repo_name: repo_name.to_string(),
+                    hash: hash.clone(),
+                    branch: String::from("main"),
+                    datetime: datetime.to_rfc3339(),
+                    author_name: author_name.to_string(),
+                    author_email: author_email.to_string(),
+                    author_domain: author_email
+                        .split('@')
+                        .last()
+                        .unwrap_or_default()
+                        .to_string(),
+                    subject: subject.to_string(),
+                    emoji: gitmoji::leading_emoji(subject).unwrap_or_default(),
+                    ..Default::default()
+                };
+
+                let amount = rng.gen_range(1..=3);
+                for ext in EXTS.choose_multiple(&mut rng, amount) {
+                    let insertion = rng.gen_range(1..200);
+                    let deletion = rng.gen_range(0..insertion);
+                    let entry = lines.entry(*ext).or_default();
+                    entry.0 += insertion;
+                    entry.1 += deletion;
+                    dataset.changes.push(RecordChange {
+                        repo_name: commit.repo_name.clone(),
+                        hash: hash.clone(),
+                        branch: commit.branch.clone(),
+                        datetime: commit.datetime.clone(),
+                        author_name: commit.author_name.clone(),
+                        author_email: commit.author_email.clone(),
+                        author_domain: commit.author_domain.clone(),
+                        ext: ext.to_string(),
+                        insertion,
+                        deletion,
+                    });
+                }
+
+                // 每 40 个提交发布一个版本
+                if (idx + 1) % 40 == 0 {
+                    dataset.tags.push(RecordTag {
+                        repo_name: commit.repo_name.clone(),
+                        branch: commit.branch.clone(),
+                        datetime: commit.datetime.clone(),
+                        tag: format!("v0.{}.0", (idx + 1) / 40),
+                        tagger_name: commit.author_name.clone(),
+                        tag_datetime: commit.datetime.clone(),
+                        is_annotated: true,
+                    });
+                }
+                dataset.commits.push(commit);
+            }
+
+            let last = datetimes.last().map(|d| d.to_rfc3339()).unwrap_or_default();
+            for (ext, (insertions, deletions)) in lines {
+                let code = insertions - deletions;
+                dataset.snapshots.push(RecordSnapshot {
+                    repo_name: repo_name.to_string(),
+                    branch: String::from("main"),
+                    datetime: last.clone(),
+                    ext: ext.to_string(),
+                    code,
+                    comments: code / 6,
+                    blanks: code / 8,
+                    insertions,
+                    deletions,
+                });
+            }
+            dataset.actives.push(RecordActive {
+                repo_name: repo_name.to_string(),
+                forks: total / 10 + rng.gen_range(0..20),
+                stars: total + rng.gen_range(0..500),
+            });
+        }
+        dataset
+    }
+}
+
+fn weighted_index(rng: &mut StdRng, weights: &[f64]) -> usize {
+    let mut n = rng.gen_range(0.0..weights.iter().sum::<f64>());
+    for (idx, weight) in weights.iter().enumerate() {
+        if n < *weight {
+            return idx;
+        }
+        n -= weight;
+    }
+    weights.len() - 1
+}
+
+fn write_records<T: Serialize>(dir: &Path, name: &str, records: &[T]) -> Result<()> {
+    let mut wtr = csv::Writer::from_path(dir.join(format!("{}.csv", name)))?;
+    for record in records {
+        wtr.serialize(record)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// 在 dir 下生成演示数据库 `db/` 以及演示配置 `gitv.yaml`，返回配置文件路径
+pub fn generate(dir: &str) -> Result<PathBuf> {
+    let db = Path::new(dir).join("db");
+    fs::create_dir_all(&db)?;
+
+    let dataset = Dataset::generate(Local::now().into());
+    write_records(&db, &RecordCommit::name(), &dataset.commits)?;
+    write_records(&db, &RecordChange::name(), &dataset.changes)?;
+    write_records(&db, &RecordTag::name(), &dataset.tags)?;
+    write_records(&db, &RecordSnapshot::name(), &dataset.snapshots)?;
+    write_records(&db, &RecordActive::name(), &dataset.actives)?;
+
+    let path = Path::new(dir).join("gitv.yaml");
+    fs::write(
+        &path,
+        CONTENT_DEMO.replace("{dir}", dir.trim_end_matches('/')),
+    )?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_dataset() {
+        let today = FixedOffset::east(8 * 3600)
+            .ymd(2022, 3, 20)
+            .and_hms(12, 0, 0);
+        let dataset = Dataset::generate(today);
+        let total = REPOS.iter().map(|(_, n)| n).sum::<usize>();
+        assert_eq!(dataset.commits.len(), total);
+        assert_eq!(dataset.actives.len(), REPOS.len());
+        assert_eq!(dataset.tags.len(), 8 + 5 + 3);
+
+        let hashs = dataset
+            .commits
+            .iter()
+            .map(|c| &c.hash)
+            .collect::<HashSet<_>>();
+        assert_eq!(hashs.len(), total);
+        assert!(dataset
+            .changes
+            .iter()
+            .all(|c| hashs.contains(&c.hash) && c.deletion < c.insertion));
+        assert!(dataset
+            .commits
+            .iter()
+            .all(|c| c.datetime.as_str() < "2022-03-20" && c.datetime.as_str() >= "2021-03-20"));
+        assert!(dataset.commits.iter().any(|c| c.emoji == "✨"));
+
+        let again = Dataset::generate(today);
+        assert_eq!(
+            dataset
+                .commits
+                .iter()
+                .map(|c| &c.author_name)
+                .collect::<Vec<_>>(),
+            again
+                .commits
+                .iter()
+                .map(|c| &c.author_name)
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,372 @@
+use crate::{config, executor::Executor, render, svg};
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::SystemTime,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::Mutex,
+    time,
+};
+
+static VERSION_PATH: &str = "/__gitv/version";
+/// svg 模式下 `gallery: true` 生成的图表索引页
+static GALLERY_INDEX: &str = "index.html";
+
+/// 注入到 html 页面中的脚本，轮询版本号变化后自动刷新页面
+static RELOAD_SCRIPT: &str = r#"<script>
+(function () {
+    let version = null;
+    setInterval(function () {
+        fetch("/__gitv/version").then(r => r.text()).then(v => {
+            if (version !== null && v !== version) { location.reload(); }
+            version = v;
+        }).catch(() => {});
+    }, 1000);
+})();
+</script>"#;
+
+/// 渲染图表后启动静态文件服务
+///
+/// `gitv -r --dev` 开发模式：配置文件变化时重新渲染，destination 目录中的文件变化时通知浏览器自动刷新
+///
+/// `gitv --serve` 服务模式：用于在内网服务器上托管报告，可选在每次请求 html 页面时重新执行查询
+pub struct DevServer {
+    config_paths: Vec<String>,
+    addr: String,
+    version: Arc<AtomicU64>,
+    watch: bool,
+    refresh: bool,
+    render_lock: Arc<Mutex<()>>,
+}
+
+impl DevServer {
+    pub fn new(config_paths: &[String], addr: &str) -> Self {
+        Self {
+            config_paths: config_paths.to_vec(),
+            addr: addr.to_string(),
+            version: Arc::new(AtomicU64::new(0)),
+            watch: true,
+            refresh: false,
+            render_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// 服务模式，不监听文件变化也不注入自动刷新脚本，refresh 为 true 时每次请求 html 页面都重新渲染
+    pub fn serving(config_paths: &[String], addr: &str, refresh: bool) -> Self {
+        Self {
+            watch: false,
+            refresh,
+            ..Self::new(config_paths, addr)
+        }
+    }
+
+    async fn render(config_paths: &[String]) -> Result<String> {
+        let c = config::load_configs(config_paths)?;
+        let render_config = c
+            .render
+            .ok_or_else(|| anyhow!("Render action is not configured"))?;
+        let destination = render_config.display.destination.clone();
+        let ctx = Executor::create_context(
+            render_config.executions.clone(),
+            render_config.periods.clone(),
+            render_config.display.timezone.clone(),
+        )
+        .await?;
+        render::create_render(ctx, render_config).render().await?;
+        Ok(destination)
+    }
+
+    fn modified(p: &Path) -> Option<SystemTime> {
+        fs::metadata(p).ok()?.modified().ok()
+    }
+
+    /// 所有配置文件的数量以及最新修改时间，配置目录中新增或者删除文件时同样会变化
+    fn config_modified(config_paths: &[String]) -> Option<(usize, SystemTime)> {
+        let files = config::config_files(config_paths).ok()?;
+        let latest = files
+            .iter()
+            .filter_map(|f| Self::modified(Path::new(f)))
+            .max()?;
+        Some((files.len(), latest))
+    }
+
+    /// destination 目录中所有文件的最新修改时间
+    fn latest_modified(dir: &Path) -> Option<SystemTime> {
+        fs::read_dir(dir)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| Self::modified(&entry.path()))
+            .max()
+    }
+
+    pub async fn serve(&self) -> Result<()> {
+        let destination = PathBuf::from(Self::render(&self.config_paths).await?);
+        let listener = TcpListener::bind(&self.addr).await?;
+        info!(
+            "[{}] serving '{}' on http://{}",
+            self.tag(),
+            destination.display(),
+            self.addr
+        );
+
+        if self.watch {
+            self.spawn_watcher(destination.clone());
+        }
+
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let dir = destination.clone();
+            let version = self.watch.then(|| self.version.load(Ordering::SeqCst));
+            let refresh = self
+                .refresh
+                .then(|| (self.config_paths.clone(), self.render_lock.clone()));
+            let tag = self.tag();
+            tokio::spawn(async move {
+                if let Err(e) = Self::handle(stream, &dir, version, refresh).await {
+                    error!("[{}] handle request error: {}", tag, e);
+                }
+            });
+        }
+    }
+
+    fn tag(&self) -> &'static str {
+        match self.watch {
+            true => "dev",
+            false => "serve",
+        }
+    }
+
+    /// 监听配置文件以及 destination 目录的变化
+    fn spawn_watcher(&self, dir: PathBuf) {
+        let config_paths = self.config_paths.clone();
+        let version = self.version.clone();
+        tokio::spawn(async move {
+            let mut config_modified = Self::config_modified(&config_paths);
+            let mut dir_modified = Self::latest_modified(&dir);
+            loop {
+                time::sleep(time::Duration::from_secs(1)).await;
+
+                let modified = Self::config_modified(&config_paths);
+                if modified != config_modified {
+                    config_modified = modified;
+                    info!(
+                        "[dev] config '{}' changed, re-rendering...",
+                        config_paths.join(", ")
+                    );
+                    if let Err(e) = Self::render(&config_paths).await {
+                        error!("[dev] render error: {:#}", e);
+                    }
+                }
+
+                let modified = Self::latest_modified(&dir);
+                if modified != dir_modified {
+                    dir_modified = modified;
+                    version.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        });
+    }
+
+    fn content_type(p: &Path) -> &'static str {
+        match p
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+        {
+            "html" => "text/html; charset=utf-8",
+            "js" => "application/javascript",
+            "css" => "text/css",
+            "csv" => "text/csv; charset=utf-8",
+            "svg" => "image/svg+xml",
+            "json" => "application/json",
+            "png" => "image/png",
+            _ => "application/octet-stream",
+        }
+    }
+
+    /// 将请求路径映射为 destination 目录下的文件，拒绝跳出目录的路径
+    fn resolve(dir: &Path, path: &str) -> Option<PathBuf> {
+        let path = percent_decode(path.split('?').next()?)?;
+        let mut p = dir.to_path_buf();
+        for component in Path::new(path.trim_start_matches('/')).components() {
+            match component {
+                Component::Normal(c) => p.push(c),
+                _ => return None,
+            }
+        }
+        Some(p)
+    }
+
+    /// 存在图表索引页（gallery）时首页为索引页，否则为 destination 目录的文件索引
+    fn home(dir: &Path, path: String) -> String {
+        match path == "/" && dir.join(GALLERY_INDEX).is_file() {
+            true => format!("/{}", GALLERY_INDEX),
+            false => path,
+        }
+    }
+
+    fn index(dir: &Path) -> String {
+        let mut names = fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        names.sort();
+
+        let items = names
+            .iter()
+            .map(|name| {
+                format!(
+                    r#"<li><a href="/{}">{}</a></li>"#,
+                    render::percent_encode(name),
+                    svg::escape(name)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!(
+            "<!DOCTYPE html>\n<html>\n<body>\n<ul>\n{}\n</ul>\n</body>\n</html>",
+            items
+        )
+    }
+
+    /// 处理单个请求，version 为 None 时不注入自动刷新脚本，refresh 不为 None 时请求 html 页面前重新渲染
+    async fn handle(
+        mut stream: TcpStream,
+        dir: &Path,
+        version: Option<u64>,
+        refresh: Option<(Vec<String>, Arc<Mutex<()>>)>,
+    ) -> Result<()> {
+        let mut buf = vec![0; 8192];
+        let n = stream.read(&mut buf).await?;
+        let request = String::from_utf8_lossy(&buf[..n]);
+        let path = request
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .unwrap_or("/")
+            .to_string();
+        let path = Self::home(dir, path);
+
+        if let Some((config_paths, lock)) = refresh {
+            let page = Self::resolve(dir, &path)
+                .filter(|p| Self::content_type(p).starts_with("text/html"));
+            if page.is_some() {
+                let _guard = lock.lock().await;
+                if let Err(e) = Self::render(&config_paths).await {
+                    error!("[serve] render error: {:#}", e);
+                }
+            }
+        }
+
+        let (status, content_type, mut body) = if path == VERSION_PATH && version.is_some() {
+            (
+                "200 OK",
+                "text/plain",
+                version.unwrap_or_default().to_string().into_bytes(),
+            )
+        } else if path == "/" {
+            (
+                "200 OK",
+                "text/html; charset=utf-8",
+                Self::index(dir).into_bytes(),
+            )
+        } else {
+            match Self::resolve(dir, &path).and_then(|p| Some((fs::read(&p).ok()?, p))) {
+                Some((content, p)) => ("200 OK", Self::content_type(&p), content),
+                None => ("404 Not Found", "text/plain", b"Not Found".to_vec()),
+            }
+        };
+
+        if version.is_some() && content_type.starts_with("text/html") {
+            let html = String::from_utf8_lossy(&body).to_string();
+            body = match html.rfind("</body>") {
+                Some(idx) => format!("{}{}\n{}", &html[..idx], RELOAD_SCRIPT, &html[idx..]),
+                None => format!("{}\n{}", html, RELOAD_SCRIPT),
+            }
+            .into_bytes();
+        }
+
+        let header = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+            status,
+            content_type,
+            body.len()
+        );
+        stream.write_all(header.as_bytes()).await?;
+        stream.write_all(&body).await?;
+        stream.flush().await?;
+        Ok(())
+    }
+}
+
+/// 解码 url 路径中的百分号编码，编码不合法或者解码结果不是 utf-8 时返回 None
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    #[test]
+    fn test_resolve() {
+        let dir = Path::new("/tmp/gitstats");
+        assert_eq!(
+            DevServer::resolve(dir, "/chart.html?t=1"),
+            Some(PathBuf::from("/tmp/gitstats/chart.html"))
+        );
+        assert_eq!(DevServer::resolve(dir, "/../etc/passwd"), None);
+        assert_eq!(
+            DevServer::resolve(dir, "/top%20authors.html"),
+            Some(PathBuf::from("/tmp/gitstats/top authors.html"))
+        );
+        assert_eq!(DevServer::resolve(dir, "/%2E%2E/etc/passwd"), None);
+        assert_eq!(DevServer::resolve(dir, "/%zz.html"), None);
+    }
+
+    #[test]
+    fn test_index() {
+        let dir = TempDir::new("index").unwrap();
+        fs::write(dir.join("<img src=x>.html"), "").unwrap();
+        fs::write(dir.join("a&b #1.csv"), "").unwrap();
+
+        let index = DevServer::index(&dir);
+        assert!(!index.contains("<img"));
+        assert!(index.contains(r#"<a href="/%3Cimg%20src%3Dx%3E.html">&lt;img src=x&gt;.html</a>"#));
+        assert!(index.contains(r#"<a href="/a%26b%20%231.csv">a&amp;b #1.csv</a>"#));
+        assert_eq!(percent_decode("a%26b%20%231.csv").unwrap(), "a&b #1.csv");
+
+        let home = |path: &str| DevServer::home(&dir, path.to_string());
+        assert_eq!(home("/"), "/");
+        fs::write(dir.join(GALLERY_INDEX), "").unwrap();
+        assert_eq!(home("/"), "/index.html");
+        assert_eq!(home("/a.svg"), "/a.svg");
+    }
+}
@@ -0,0 +1,138 @@
+use crate::record::{RecordChange, RecordCommit, RecordEnrichment};
+use lazy_static::lazy_static;
+use std::sync::RwLock;
+
+/// 自定义记录增强插件，在 Commit/Change 记录序列化之前调用
+///
+/// 插件可以直接修改记录内容（如统一作者邮箱），也可以返回额外的 `(key, value)` 字段，
+/// 额外字段会写入 `enrichment` 表，通过 `repo_name` 和 `hash` 与 commit/change 表关联。
+pub trait RecordEnricher: Send + Sync {
+    fn enrich_commit(&self, _record: &mut RecordCommit) -> Vec<(String, String)> {
+        vec![]
+    }
+
+    fn enrich_change(&self, _record: &mut RecordChange) -> Vec<(String, String)> {
+        vec![]
+    }
+}
+
+lazy_static! {
+    static ref ENRICHERS: RwLock<Vec<Box<dyn RecordEnricher>>> = RwLock::new(vec![]);
+}
+
+/// 注册自定义插件，需要在 create 之前调用，多个插件按照注册顺序依次调用
+pub fn register_enricher(enricher: Box<dyn RecordEnricher>) {
+    ENRICHERS.write().unwrap().push(enricher);
+}
+
+/// 依次调用所有插件处理 commit 记录，返回额外字段
+pub fn enrich_commit(record: &mut RecordCommit) -> Vec<RecordEnrichment> {
+    let mut extras = vec![];
+    for enricher in ENRICHERS.read().unwrap().iter() {
+        extras.extend(enricher.enrich_commit(record));
+    }
+    extras
+        .into_iter()
+        .map(|(key, value)| RecordEnrichment {
+            repo_name: record.repo_name.clone(),
+            hash: record.hash.clone(),
+            source: RecordCommit::name(),
+            ext: String::new(),
+            key,
+            value,
+        })
+        .collect()
+}
+
+/// 依次调用所有插件处理 change 记录，返回额外字段
+pub fn enrich_change(record: &mut RecordChange) -> Vec<RecordEnrichment> {
+    let mut extras = vec![];
+    for enricher in ENRICHERS.read().unwrap().iter() {
+        extras.extend(enricher.enrich_change(record));
+    }
+    extras
+        .into_iter()
+        .map(|(key, value)| RecordEnrichment {
+            repo_name: record.repo_name.clone(),
+            hash: record.hash.clone(),
+            source: RecordChange::name(),
+            ext: record.ext.clone(),
+            key,
+            value,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 只处理测试仓库的记录，避免影响其他测试
+    struct TestEnricher;
+
+    static TEST_REPO: &str = "gitv-enricher-test";
+
+    impl RecordEnricher for TestEnricher {
+        fn enrich_commit(&self, record: &mut RecordCommit) -> Vec<(String, String)> {
+            if record.repo_name != TEST_REPO {
+                return vec![];
+            }
+            record.author_email = record.author_email.to_lowercase();
+            vec![(String::from("ticket"), String::from("GITV-1"))]
+        }
+
+        fn enrich_change(&self, record: &mut RecordChange) -> Vec<(String, String)> {
+            if record.repo_name != TEST_REPO {
+                return vec![];
+            }
+            vec![(
+                String::from("generated"),
+                (record.ext == "lock").to_string(),
+            )]
+        }
+    }
+
+    #[test]
+    fn test_register_enricher() {
+        register_enricher(Box::new(TestEnricher));
+
+        let mut commit = RecordCommit {
+            repo_name: TEST_REPO.to_string(),
+            hash: String::from("h1"),
+            author_email: String::from("Foo@Example.com"),
+            ..Default::default()
+        };
+        let extras = enrich_commit(&mut commit);
+        assert_eq!(commit.author_email, "foo@example.com");
+        assert_eq!(extras.len(), 1);
+        assert_eq!(extras[0].repo_name, TEST_REPO);
+        assert_eq!(extras[0].hash, "h1");
+        assert_eq!(extras[0].source, RecordCommit::name());
+        assert_eq!(extras[0].ext, "");
+        assert_eq!(
+            (extras[0].key.as_str(), extras[0].value.as_str()),
+            ("ticket", "GITV-1")
+        );
+
+        let mut change = RecordChange {
+            repo_name: TEST_REPO.to_string(),
+            hash: String::from("h1"),
+            ext: String::from("lock"),
+            ..Default::default()
+        };
+        let extras = enrich_change(&mut change);
+        assert_eq!(extras.len(), 1);
+        assert_eq!(extras[0].source, RecordChange::name());
+        assert_eq!(extras[0].ext, "lock");
+        assert_eq!(
+            (extras[0].key.as_str(), extras[0].value.as_str()),
+            ("generated", "true")
+        );
+
+        let mut other = RecordCommit {
+            repo_name: String::from("other"),
+            ..Default::default()
+        };
+        assert!(enrich_commit(&mut other).is_empty());
+    }
+}
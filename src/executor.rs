@@ -1,39 +1,63 @@
 use crate::{config, record};
-use chrono::{prelude::*, Duration};
+use chrono::{
+    format::{Item, StrftimeItems},
+    prelude::*,
+    Duration,
+};
+use chrono_tz::Tz;
 use datafusion::{
     arrow::{
         array,
         array::ArrayRef,
-        datatypes::{DataType, Field},
+        datatypes::{DataType, Field, Schema},
     },
+    datasource::MemTable,
     error::{DataFusionError, Result},
     logical_plan::create_udaf,
     physical_plan::{
-        functions::{make_scalar_function, Volatility},
+        aggregates::{AccumulatorFunctionImplementation, StateTypeFunction},
+        functions::{
+            make_scalar_function, ReturnTypeFunction, Signature, TypeSignature, Volatility,
+        },
         udaf::AggregateUDF,
         udf::ScalarUDF,
-        Accumulator,
+        Accumulator, ColumnarValue,
     },
     prelude::*,
     scalar::ScalarValue,
 };
 use lazy_static::lazy_static;
-use std::{path::Path, sync::Arc};
+use log::{debug, info};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::Arc,
+};
 
 lazy_static! {
     /// udf 函数集合
     static ref UDFS: Vec<fn() -> ScalarUDF> = vec![
         udf_year,
         udf_month,
+        udf_weekofyear,
+        udf_quarter,
         udf_weekday,
         udf_weeknum,
         udf_dateday,
         udf_hour,
         udf_period,
+        udf_period_emoji,
         udf_timestamp,
         udf_timezone,
+        udf_date_format,
+        udf_to_timezone,
         udf_duration,
+        udf_duration_seconds,
+        udf_duration_iso8601,
+        udf_domain,
         udf_timestamp_rfc3339,
+        udf_human_bytes,
+        udf_bytes,
     ];
 
     /// udaf 函数集合
@@ -41,54 +65,347 @@ lazy_static! {
         udaf_active_longest_days,
         udaf_active_longest_start,
         udaf_active_longest_end,
+        udaf_histogram,
+        udaf_histogram_labels,
+        udaf_active_days,
+        udaf_activity_ratio,
+        udaf_active_current_streak,
+        udaf_most_active_weekday,
+        udaf_most_active_hour,
+        udaf_first_commit_date,
+        udaf_last_commit_date,
+        udaf_project_age_days,
+        udaf_contribution_gini,
+        udaf_bus_factor,
     ];
 }
 
+/// 每个 database 下注册的 table 名称
+pub fn table_names() -> Vec<String> {
+    vec![
+        record::RecordCommit::name(),
+        record::RecordChange::name(),
+        record::RecordTag::name(),
+        record::RecordSnapshot::name(),
+        record::RecordSnapshotHistory::name(),
+        record::RecordActive::name(),
+        record::RecordStar::name(),
+        record::RecordIssue::name(),
+        record::RecordPullRequest::name(),
+        record::RecordFirstResponse::name(),
+        record::RecordRepo::name(),
+        record::RecordStatus::name(),
+        record::RecordRetention::name(),
+        record::RecordMigration::name(),
+        record::RecordWorkingPattern::name(),
+        record::RecordFirstContribution::name(),
+        record::RecordDuplicate::name(),
+        record::RecordEnrichment::name(),
+        record::RecordMeta::name(),
+    ]
+}
+
+/// 所有 udf/udaf 函数名称
+pub fn function_names() -> Vec<String> {
+    UDFS.iter()
+        .map(|udf| udf().name)
+        .chain(UDAFS.iter().map(|udaf| udaf().name))
+        .collect()
+}
+
 /// sql 查询执行器
 pub struct Executor;
 
 impl Executor {
-    pub async fn create_context(config: Vec<config::Execution>) -> Result<ExecutionContext> {
+    pub async fn create_context(
+        config: Vec<config::Execution>,
+        periods: Option<Vec<config::Period>>,
+        timezone: Option<String>,
+    ) -> Result<ExecutionContext> {
+        if let Some(tz) = &timezone {
+            if tz != AUTHOR_TIMEZONE && parse_timezone(tz).is_none() {
+                return Err(DataFusionError::Plan(format!(
+                    "Invalid display timezone: '{}'",
+                    tz
+                )));
+            }
+        }
+        let wrap = |udf: ScalarUDF| match TIMEZONE_FUNCTIONS.contains(&udf.name.as_str()) {
+            true => with_timezone(udf, timezone.clone()),
+            false => udf,
+        };
+
         let mut ctx = ExecutionContext::new();
         for udf in UDFS.iter() {
-            ctx.register_udf(udf());
+            ctx.register_udf(wrap(udf()));
+        }
+        // 自定义的时段划分覆盖默认的 period 函数
+        if let Some(periods) = periods {
+            ctx.register_udf(wrap(create_period_udf("period", periods.clone(), false)));
+            ctx.register_udf(wrap(create_period_udf("period_emoji", periods, true)));
         }
         for udaf in UDAFS.iter() {
             ctx.register_udaf(udaf())
         }
 
         for c in config {
-            Self::register(&mut ctx, &c.dir, &c.db_name, record::RecordCommit::name()).await?;
-            Self::register(&mut ctx, &c.dir, &c.db_name, record::RecordChange::name()).await?;
-            Self::register(&mut ctx, &c.dir, &c.db_name, record::RecordTag::name()).await?;
-            Self::register(&mut ctx, &c.dir, &c.db_name, record::RecordSnapshot::name()).await?;
-            Self::register(&mut ctx, &c.dir, &c.db_name, record::RecordActive::name()).await?;
+            let mut tables = vec![];
+            for name in table_names() {
+                if Self::register(&mut ctx, &c.dir, &c.db_name, name.clone()).await? {
+                    tables.push(name);
+                }
+            }
+            info!(
+                "[executor] database '{}' registered tables: [{}]",
+                c.db_name,
+                tables.join(", ")
+            );
         }
         Ok(ctx)
     }
 
+    /// 注册数据文件为 table，返回是否注册成功
+    ///
+    /// 数据文件不存在时跳过，数据文件为空时注册一个空表，避免 schema 推断失败
     async fn register(
         ctx: &mut ExecutionContext,
         dir: &str,
         db_name: &str,
         name: String,
-    ) -> Result<()> {
+    ) -> Result<bool> {
         let mut p = Path::new(dir).join(&name);
         p.set_extension("csv");
-        if p.exists() {
-            ctx.register_csv(
-                format!("{}.{}", db_name, name).as_str(),
-                p.to_str().unwrap(),
-                CsvReadOptions::new(),
-            )
-            .await?;
+        let table = format!("{}.{}", db_name, name);
+        if !p.exists() {
+            debug!(
+                "[executor] skip table '{}': file '{}' not found",
+                table,
+                p.display()
+            );
+            return Ok(false);
         }
-        Ok(())
+
+        let empty = p.metadata().map(|m| m.len() == 0).unwrap_or(true);
+        if empty {
+            let schema = match empty_table_schema(&name) {
+                Some(schema) => Arc::new(schema),
+                None => {
+                    debug!(
+                        "[executor] skip table '{}': file '{}' is empty",
+                        table,
+                        p.display()
+                    );
+                    return Ok(false);
+                }
+            };
+            debug!(
+                "[executor] file '{}' is empty, register empty table '{}'",
+                p.display(),
+                table
+            );
+            let provider = MemTable::try_new(schema, vec![vec![]])?;
+            ctx.register_table(table.as_str(), Arc::new(provider))?;
+            return Ok(true);
+        }
+
+        let delimiter = record::csv_delimiter(&p).map_err(|e| {
+            DataFusionError::Execution(format!("Failed to read header of '{}': {}", p.display(), e))
+        })?;
+        let opts = CsvReadOptions::new().delimiter(delimiter);
+        ctx.register_csv(table.as_str(), p.to_str().unwrap(), opts)
+            .await
+            .map_err(|e| {
+                DataFusionError::Execution(format!(
+                    "Failed to register table '{}' from '{}': {}",
+                    table,
+                    p.display(),
+                    e
+                ))
+            })?;
+        Ok(true)
     }
 }
 
+/// 数据文件为空时使用的表结构，字段与 record 模块中的定义保持一致
+fn empty_table_schema(name: &str) -> Option<Schema> {
+    let utf8 = |names: &[&str]| {
+        names
+            .iter()
+            .map(|n| Field::new(n, DataType::Utf8, true))
+            .collect::<Vec<_>>()
+    };
+    let int64 = |names: &[&str]| {
+        names
+            .iter()
+            .map(|n| Field::new(n, DataType::Int64, true))
+            .collect::<Vec<_>>()
+    };
+    let author = [
+        "repo_name",
+        "hash",
+        "branch",
+        "datetime",
+        "author_name",
+        "author_email",
+        "author_domain",
+    ];
+
+    let fields = if name == record::RecordCommit::name() {
+        [
+            utf8(&author),
+            utf8(&["subject"]),
+            ["is_merge", "is_co_author"]
+                .iter()
+                .map(|n| Field::new(n, DataType::Boolean, true))
+                .collect(),
+            utf8(&["emoji"]),
+        ]
+        .concat()
+    } else if name == record::RecordChange::name() {
+        [
+            utf8(&author),
+            utf8(&["ext"]),
+            int64(&["insertion", "deletion"]),
+        ]
+        .concat()
+    } else if name == record::RecordTag::name() {
+        [
+            utf8(&[
+                "repo_name",
+                "branch",
+                "datetime",
+                "tag",
+                "tagger_name",
+                "tag_datetime",
+            ]),
+            vec![Field::new("is_annotated", DataType::Boolean, true)],
+        ]
+        .concat()
+    } else if name == record::RecordSnapshot::name() {
+        [
+            utf8(&["repo_name", "branch", "datetime", "ext"]),
+            int64(&["code", "comments", "blanks", "insertions", "deletions"]),
+        ]
+        .concat()
+    } else if name == record::RecordSnapshotHistory::name() {
+        [
+            utf8(&["repo_name", "branch", "period", "datetime", "ext"]),
+            int64(&["code", "comments", "blanks"]),
+        ]
+        .concat()
+    } else if name == record::RecordActive::name() {
+        [utf8(&["repo_name"]), int64(&["forks", "stars"])].concat()
+    } else if name == record::RecordRepo::name() {
+        [
+            utf8(&["repo_name", "default_branch", "visibility"]),
+            ["private", "archived", "fork"]
+                .iter()
+                .map(|n| Field::new(n, DataType::Boolean, true))
+                .collect(),
+            int64(&["size"]),
+            utf8(&["license"]),
+        ]
+        .concat()
+    } else if name == record::RecordStatus::name() {
+        [
+            utf8(&["repo_name", "branch", "last_commit"]),
+            int64(&[
+                "inactive_days",
+                "recent_authors",
+                "previous_authors",
+                "trend",
+            ]),
+            utf8(&["status"]),
+        ]
+        .concat()
+    } else if name == record::RecordRetention::name() {
+        [
+            utf8(&["repo_name", "branch", "quarter"]),
+            int64(&["authors", "new", "retained", "churned"]),
+        ]
+        .concat()
+    } else if name == record::RecordMigration::name() {
+        [
+            utf8(&[
+                "repo_name",
+                "branch",
+                "period",
+                "from_language",
+                "to_language",
+            ]),
+            vec![Field::new("magnitude", DataType::Float64, true)],
+        ]
+        .concat()
+    } else if name == record::RecordWorkingPattern::name() {
+        [
+            utf8(&["repo_name", "branch", "author_name", "author_email"]),
+            int64(&["commits"]),
+            ["weekend_ratio", "night_ratio", "early_ratio"]
+                .iter()
+                .map(|n| Field::new(n, DataType::Float64, true))
+                .collect(),
+            utf8(&["pattern"]),
+        ]
+        .concat()
+    } else if name == record::RecordStar::name() {
+        utf8(&["repo_name", "user", "starred_at"])
+    } else if name == record::RecordIssue::name() {
+        [
+            utf8(&["repo_name"]),
+            int64(&["number"]),
+            utf8(&["author", "state", "labels"]),
+            int64(&["comments"]),
+            utf8(&["created_at", "closed_at"]),
+        ]
+        .concat()
+    } else if name == record::RecordPullRequest::name() {
+        [
+            utf8(&["repo_name"]),
+            int64(&["number"]),
+            utf8(&["author", "state", "labels"]),
+            int64(&["comments"]),
+            utf8(&["created_at", "closed_at", "merged_at"]),
+        ]
+        .concat()
+    } else if name == record::RecordFirstResponse::name() {
+        [
+            utf8(&["repo_name"]),
+            int64(&["number"]),
+            utf8(&["kind", "author"]),
+            vec![Field::new("is_first_time", DataType::Boolean, true)],
+            utf8(&["created_at", "responder", "responded_at"]),
+            vec![Field::new("response_hours", DataType::Float64, true)],
+        ]
+        .concat()
+    } else if name == record::RecordFirstContribution::name() {
+        utf8(&[
+            "repo_name",
+            "branch",
+            "author_name",
+            "author_email",
+            "hash",
+            "datetime",
+        ])
+    } else if name == record::RecordEnrichment::name() {
+        utf8(&["repo_name", "hash", "source", "ext", "key", "value"])
+    } else if name == record::RecordMeta::name() {
+        utf8(&["key", "value"])
+    } else if name == record::RecordDuplicate::name() {
+        [
+            utf8(&["repo_name", "hash", "canonical"]),
+            vec![Field::new("kept", DataType::Boolean, true)],
+        ]
+        .concat()
+    } else {
+        return None;
+    };
+    Some(Schema::new(fields))
+}
+
 enum ExecutionErr {
     DateTimeMismatch,
+    StringMismatch,
+    IntegerMismatch,
 }
 
 impl ExecutionErr {
@@ -97,6 +414,12 @@ impl ExecutionErr {
             ExecutionErr::DateTimeMismatch => DataFusionError::Execution(String::from(
                 "Mismatched: except rfc3339 datetime string",
             )),
+            ExecutionErr::StringMismatch => {
+                DataFusionError::Execution(String::from("Mismatched: except utf8 string"))
+            }
+            ExecutionErr::IntegerMismatch => {
+                DataFusionError::Execution(String::from("Mismatched: except int64 number"))
+            }
         }
     }
 }
@@ -173,6 +496,78 @@ fn udf_month() -> ScalarUDF {
     )
 }
 
+/// 计算给定时间的 ISO 8601 周数（1-53），每周从周一开始，年初的几天可能属于上一年的最后一周
+///
+/// # Example
+/// ```rust
+/// input<arg1: rfc3339>: "2021-10-12T14:20:50.52+07:00"
+/// output: 41
+/// ```
+fn udf_weekofyear() -> ScalarUDF {
+    let weekofyear = |args: &[array::ArrayRef]| {
+        let base = &args[0].as_any().downcast_ref::<array::StringArray>();
+        if base.is_none() {
+            return Err(ExecutionErr::DateTimeMismatch.err());
+        }
+
+        let array = base
+            .unwrap()
+            .iter()
+            .map(|x| match DateTime::parse_from_rfc3339(x.unwrap()) {
+                Ok(t) => Some(t.iso_week().week()),
+                Err(_) => None,
+            })
+            .collect::<array::UInt32Array>();
+
+        Ok(Arc::new(array) as array::ArrayRef)
+    };
+
+    let weekofyear = make_scalar_function(weekofyear);
+    create_udf(
+        "weekofyear",
+        vec![DataType::Utf8],
+        Arc::new(DataType::UInt32),
+        Volatility::Immutable,
+        weekofyear,
+    )
+}
+
+/// 计算给定时间的季度（1-4）
+///
+/// # Example
+/// ```rust
+/// input<arg1: rfc3339>: "2021-10-12T14:20:50.52+07:00"
+/// output: 4
+/// ```
+fn udf_quarter() -> ScalarUDF {
+    let quarter = |args: &[array::ArrayRef]| {
+        let base = &args[0].as_any().downcast_ref::<array::StringArray>();
+        if base.is_none() {
+            return Err(ExecutionErr::DateTimeMismatch.err());
+        }
+
+        let array = base
+            .unwrap()
+            .iter()
+            .map(|x| match DateTime::parse_from_rfc3339(x.unwrap()) {
+                Ok(t) => Some((t.month() - 1) / 3 + 1),
+                Err(_) => None,
+            })
+            .collect::<array::UInt32Array>();
+
+        Ok(Arc::new(array) as array::ArrayRef)
+    };
+
+    let quarter = make_scalar_function(quarter);
+    create_udf(
+        "quarter",
+        vec![DataType::Utf8],
+        Arc::new(DataType::UInt32),
+        Volatility::Immutable,
+        quarter,
+    )
+}
+
 /// 计算给定时间的星期字符
 ///
 /// # Example
@@ -324,11 +719,54 @@ fn udf_hour() -> ScalarUDF {
 /// input<arg1: rfc3339>: "2021-10-12T14:20:50.52+07:00"
 /// output: "Afternoon"
 /// ```
-/// `hour`  |  `[0, 8)`  | `[8, 12)` |  `[12, 18)` | `[18, 24)`
+/// `hour`  |  `[0, 8)`  | `[8, 12)` |  `[12, 19)` | `[19, 24)`
 /// ------- | ---------- | --------- | ----------- | ---------
 /// `period`| `Midnight` | `Morning` | `Afternoon` | `Evening`
+///
+/// 时段划分可以通过 shell/render 配置中的 `periods` 自定义
 fn udf_period() -> ScalarUDF {
-    let period = |args: &[array::ArrayRef]| {
+    create_period_udf("period", default_periods(), false)
+}
+
+/// 计算给定时间的状态对应的 emoji
+///
+/// # Example
+/// ```rust
+/// input<arg1: rfc3339>: "2021-10-12T14:20:50.52+07:00"
+/// output: "☀️"
+/// ```
+fn udf_period_emoji() -> ScalarUDF {
+    create_period_udf("period_emoji", default_periods(), true)
+}
+
+fn default_periods() -> Vec<config::Period> {
+    [
+        (0, "Midnight", "🌙"),
+        (8, "Morning", "🌅"),
+        (12, "Afternoon", "☀️"),
+        (19, "Evening", "🌆"),
+    ]
+    .into_iter()
+    .map(|(start, label, emoji)| config::Period {
+        start,
+        label: label.to_string(),
+        emoji: Some(emoji.to_string()),
+    })
+    .collect()
+}
+
+/// 查找给定小时所在的时段，早于第一个时段起始小时的时间属于最后一个时段（跨越午夜）
+fn find_period(periods: &[config::Period], hour: u32) -> Option<&config::Period> {
+    periods
+        .iter()
+        .rev()
+        .find(|p| p.start <= hour)
+        .or_else(|| periods.last())
+}
+
+fn create_period_udf(name: &str, mut periods: Vec<config::Period>, emoji: bool) -> ScalarUDF {
+    periods.sort_by_key(|p| p.start);
+    let period = move |args: &[array::ArrayRef]| {
         let base = &args[0].as_any().downcast_ref::<array::StringArray>();
         if base.is_none() {
             return Err(ExecutionErr::DateTimeMismatch.err());
@@ -338,16 +776,13 @@ fn udf_period() -> ScalarUDF {
             .unwrap()
             .iter()
             .map(|x| match DateTime::parse_from_rfc3339(x.unwrap()) {
-                Ok(t) => {
-                    let s = match t.hour() {
-                        0..=7 => String::from("Midnight"),
-                        8..=11 => String::from("Morning"),
-                        12..=18 => String::from("Afternoon"),
-                        19..=23 => String::from("Evening"),
-                        _ => unreachable!(),
-                    };
-                    Some(s)
-                }
+                Ok(t) => find_period(&periods, t.hour()).map(|p| {
+                    if emoji {
+                        p.emoji.clone().unwrap_or_else(|| p.label.clone())
+                    } else {
+                        p.label.clone()
+                    }
+                }),
                 Err(_) => None,
             })
             .collect::<array::StringArray>();
@@ -357,7 +792,7 @@ fn udf_period() -> ScalarUDF {
 
     let period = make_scalar_function(period);
     create_udf(
-        "period",
+        name,
         vec![DataType::Utf8],
         Arc::new(DataType::Utf8),
         Volatility::Immutable,
@@ -437,87 +872,588 @@ fn udf_timezone() -> ScalarUDF {
     )
 }
 
-/// 计算给定时间到现在时间的长度
+/// 按照 strftime 格式格式化给定时间，保留原有的时区，非法的格式返回错误
 ///
 /// # Example
 /// ```rust
-/// input<arg1: unix timestamp>: 1647272093
-/// output: "30hours 2minutes"
+/// input<arg1: rfc3339, arg2: format>: "2021-10-12T14:20:50.52+07:00", "%Y-%m"
+/// output: "2021-10"
 /// ```
-fn udf_duration() -> ScalarUDF {
-    let duration = |args: &[array::ArrayRef]| {
-        let base = &args[0].as_any().downcast_ref::<array::Int64Array>();
-        if base.is_none() {
-            return Err(ExecutionErr::DateTimeMismatch.err());
+fn udf_date_format() -> ScalarUDF {
+    let date_format = |args: &[array::ArrayRef]| {
+        let base = args[0].as_any().downcast_ref::<array::StringArray>();
+        let formats = args[1].as_any().downcast_ref::<array::StringArray>();
+        let (base, formats) = match (base, formats) {
+            (Some(base), Some(formats)) => (base, formats),
+            (None, _) => return Err(ExecutionErr::DateTimeMismatch.err()),
+            _ => return Err(ExecutionErr::StringMismatch.err()),
+        };
+
+        let mut values = vec![];
+        for (x, format) in base.iter().zip(formats.iter()) {
+            let (x, format) = match (x, format) {
+                (Some(x), Some(format)) => (x, format),
+                _ => {
+                    values.push(None);
+                    continue;
+                }
+            };
+            let items = StrftimeItems::new(format).collect::<Vec<_>>();
+            if items.contains(&Item::Error) {
+                return Err(DataFusionError::Execution(format!(
+                    "Invalid strftime format: '{}'",
+                    format
+                )));
+            }
+            values.push(
+                DateTime::parse_from_rfc3339(x)
+                    .ok()
+                    .map(|t| t.format_with_items(items.iter()).to_string()),
+            );
+        }
+
+        let array = values.into_iter().collect::<array::StringArray>();
+        Ok(Arc::new(array) as array::ArrayRef)
+    };
+
+    let date_format = make_scalar_function(date_format);
+    create_udf(
+        "date_format",
+        vec![DataType::Utf8, DataType::Utf8],
+        Arc::new(DataType::Utf8),
+        Volatility::Immutable,
+        date_format,
+    )
+}
+
+/// 时区，IANA 时区名称或者固定的时区偏移
+enum TimeZoneSpec {
+    Named(Tz),
+    Fixed(FixedOffset),
+}
+
+impl TimeZoneSpec {
+    fn convert(&self, t: DateTime<FixedOffset>) -> String {
+        match self {
+            TimeZoneSpec::Named(tz) => t.with_timezone(tz).to_rfc3339(),
+            TimeZoneSpec::Fixed(offset) => t.with_timezone(offset).to_rfc3339(),
+        }
+    }
+}
+
+/// 解析 IANA 时区名称（如 Asia/Shanghai）或者固定的时区偏移（如 +08:00、-0530）
+fn parse_timezone(tz: &str) -> Option<TimeZoneSpec> {
+    if let Ok(tz) = tz.parse::<Tz>() {
+        return Some(TimeZoneSpec::Named(tz));
+    }
+
+    let (sign, offset) = match tz.as_bytes().first()? {
+        b'+' => (1, &tz[1..]),
+        b'-' => (-1, &tz[1..]),
+        _ => return None,
+    };
+    let digits = offset.replace(':', "");
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let hours = digits[..2].parse::<i32>().ok()?;
+    let minutes = digits[2..].parse::<i32>().ok()?;
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).map(TimeZoneSpec::Fixed)
+}
+
+/// 支持可选时区参数的时间函数
+static TIMEZONE_FUNCTIONS: [&str; 11] = [
+    "year",
+    "month",
+    "weekofyear",
+    "quarter",
+    "weekday",
+    "weeknum",
+    "dateday",
+    "hour",
+    "period",
+    "period_emoji",
+    "date_format",
+];
+
+/// 特殊的时区参数，表示使用时间自带的时区，即提交者的本地时间
+static AUTHOR_TIMEZONE: &str = "author";
+
+/// 为时间函数追加一个可选的时区参数，计算前先将时间转换到该时区
+///
+/// 省略时区参数时使用默认时区（display.timezone），默认时区也未设置时保留时间自带的时区
+///
+/// # Example
+/// ```rust
+/// input<arg1: rfc3339, arg2: timezone>: "2021-10-12T14:20:50.52+07:00", "Asia/Shanghai"
+/// hour output: 15
+/// ```
+fn with_timezone(udf: ScalarUDF, default: Option<String>) -> ScalarUDF {
+    let types = match &udf.signature.type_signature {
+        TypeSignature::Exact(types) => types.clone(),
+        _ => return udf,
+    };
+    let arity = types.len();
+    let inner = udf.fun.clone();
+    let fun = move |args: &[array::ArrayRef]| {
+        let base = match args[0].as_any().downcast_ref::<array::StringArray>() {
+            Some(base) => base,
+            None => return Err(ExecutionErr::DateTimeMismatch.err()),
+        };
+        let timezones = match args.get(arity) {
+            Some(arg) => match arg.as_any().downcast_ref::<array::StringArray>() {
+                Some(timezones) => timezones.iter().collect::<Vec<_>>(),
+                None => return Err(ExecutionErr::StringMismatch.err()),
+            },
+            None => vec![default.as_deref(); base.len()],
+        };
+
+        let mut values = vec![];
+        for (x, tz) in base.iter().zip(timezones) {
+            let value = match (x, tz) {
+                (Some(x), Some(tz)) if tz != AUTHOR_TIMEZONE => {
+                    let spec = match parse_timezone(tz) {
+                        Some(spec) => spec,
+                        None => {
+                            return Err(DataFusionError::Execution(format!(
+                                "Invalid timezone: '{}'",
+                                tz
+                            )))
+                        }
+                    };
+                    // 无法解析的时间保持原样，交由原函数处理
+                    match DateTime::parse_from_rfc3339(x) {
+                        Ok(t) => Some(spec.convert(t)),
+                        Err(_) => Some(x.to_string()),
+                    }
+                }
+                (x, _) => x.map(|x| x.to_string()),
+            };
+            values.push(value);
+        }
+
+        let converted = values.into_iter().collect::<array::StringArray>();
+        let mut inner_args = vec![ColumnarValue::Array(Arc::new(converted))];
+        inner_args.extend(
+            args[1..arity]
+                .iter()
+                .map(|arg| ColumnarValue::Array(arg.clone())),
+        );
+        match inner(&inner_args)? {
+            ColumnarValue::Array(array) => Ok(array),
+            ColumnarValue::Scalar(scalar) => Ok(scalar.to_array_of_size(base.len())),
+        }
+    };
+
+    let signature = Signature::one_of(
+        vec![
+            TypeSignature::Exact(types.clone()),
+            TypeSignature::Exact([types, vec![DataType::Utf8]].concat()),
+        ],
+        udf.signature.volatility,
+    );
+    ScalarUDF::new(
+        &udf.name,
+        &signature,
+        &udf.return_type,
+        &make_scalar_function(fun),
+    )
+}
+
+/// 将给定时间转换为指定时区的时间，非法的时区返回错误
+///
+/// 其余时间函数默认使用时间自带的时区，先转换为同一时区后再计算 hour/period 等分布，
+/// 也可以直接在时间函数的最后一个参数中指定时区
+///
+/// # Example
+/// ```rust
+/// input<arg1: rfc3339, arg2: timezone>: "2021-10-12T14:20:50.52+07:00", "Asia/Shanghai"
+/// output: "2021-10-12T15:20:50.520+08:00"
+/// ```
+fn udf_to_timezone() -> ScalarUDF {
+    let to_timezone = |args: &[array::ArrayRef]| {
+        let base = args[0].as_any().downcast_ref::<array::StringArray>();
+        let timezones = args[1].as_any().downcast_ref::<array::StringArray>();
+        let (base, timezones) = match (base, timezones) {
+            (Some(base), Some(timezones)) => (base, timezones),
+            (None, _) => return Err(ExecutionErr::DateTimeMismatch.err()),
+            _ => return Err(ExecutionErr::StringMismatch.err()),
         };
 
+        let mut values = vec![];
+        for (x, tz) in base.iter().zip(timezones.iter()) {
+            let (x, tz) = match (x, tz) {
+                (Some(x), Some(tz)) => (x, tz),
+                _ => {
+                    values.push(None);
+                    continue;
+                }
+            };
+            let spec = match parse_timezone(tz) {
+                Some(spec) => spec,
+                None => {
+                    return Err(DataFusionError::Execution(format!(
+                        "Invalid timezone: '{}'",
+                        tz
+                    )))
+                }
+            };
+            values.push(
+                DateTime::parse_from_rfc3339(x)
+                    .ok()
+                    .map(|t| spec.convert(t)),
+            );
+        }
+
+        let array = values.into_iter().collect::<array::StringArray>();
+        Ok(Arc::new(array) as array::ArrayRef)
+    };
+
+    let to_timezone = make_scalar_function(to_timezone);
+    create_udf(
+        "to_timezone",
+        vec![DataType::Utf8, DataType::Utf8],
+        Arc::new(DataType::Utf8),
+        Volatility::Immutable,
+        to_timezone,
+    )
+}
+
+/// 提取邮箱地址的域名部分（小写），非法邮箱返回 None
+fn email_domain(email: &str) -> Option<String> {
+    let (_, domain) = email.trim().rsplit_once('@')?;
+    if domain.is_empty() {
+        return None;
+    }
+    Some(domain.to_lowercase())
+}
+
+/// 计算给定邮箱的域名
+///
+/// # Example
+/// ```rust
+/// input<arg1: email>: "chenjiandongx@QQ.com"
+/// output: "qq.com"
+/// ```
+fn udf_domain() -> ScalarUDF {
+    let domain = |args: &[array::ArrayRef]| {
+        let base = &args[0].as_any().downcast_ref::<array::StringArray>();
+        if base.is_none() {
+            return Err(ExecutionErr::StringMismatch.err());
+        }
+
         let array = base
             .unwrap()
             .iter()
-            .map(|x| {
-                let t = Utc::now().timestamp() - x.unwrap();
-                Some(humantime::format_duration(Duration::seconds(t).to_std().unwrap()).to_string())
-            })
+            .map(|x| x.and_then(email_domain))
             .collect::<array::StringArray>();
 
         Ok(Arc::new(array) as array::ArrayRef)
     };
 
-    let duration = make_scalar_function(duration);
+    let domain = make_scalar_function(domain);
     create_udf(
-        "duration",
-        vec![DataType::Int64],
+        "domain",
+        vec![DataType::Utf8],
         Arc::new(DataType::Utf8),
         Volatility::Immutable,
-        duration,
+        domain,
     )
 }
 
-/// 格式化时间戳时间
+static BYTE_UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+/// 人性化的字节大小，使用 1024 进制的单位，保留一位小数
+fn human_bytes(n: i64) -> String {
+    let mut size = n.unsigned_abs() as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < BYTE_UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    let sign = if n < 0 { "-" } else { "" };
+    match unit {
+        0 => format!("{}{} B", sign, size),
+        _ => format!("{}{:.1} {}", sign, size, BYTE_UNITS[unit]),
+    }
+}
+
+/// 解析带单位的字节大小，KiB/MiB 以及 K/M 为 1024 进制，KB/MB 为 1000 进制，单位不区分大小写
+fn parse_bytes(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let idx = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(idx);
+    let num = num.parse::<f64>().ok()?;
+
+    let unit = unit.trim().to_lowercase();
+    let (power, base) = match unit.trim_end_matches('b') {
+        "" => (0, 1024.0),
+        u => {
+            let power = "kmgtp".find(u.chars().next()?)? as i32 + 1;
+            match &u[1..] {
+                "" if unit.ends_with('b') => (power, 1000.0),
+                "" | "i" => (power, 1024.0),
+                _ => return None,
+            }
+        }
+    };
+    Some((num * f64::powi(base, power)).round() as i64)
+}
+
+/// 将字节数格式化为人性化的大小
 ///
 /// # Example
 /// ```rust
-/// input<arg1: unix timestamp, arg2: String>: 1647272093
-/// output: "2021-10-12T14:20:50.52+07:00"
+/// input<arg1: Int64>: 1468006
+/// output: "1.4 MiB"
 /// ```
-fn udf_timestamp_rfc3339() -> ScalarUDF {
-    let date = |args: &[array::ArrayRef]| {
+fn udf_human_bytes() -> ScalarUDF {
+    let human = |args: &[array::ArrayRef]| {
         let base = &args[0].as_any().downcast_ref::<array::Int64Array>();
         if base.is_none() {
-            return Err(ExecutionErr::DateTimeMismatch.err());
-        };
+            return Err(ExecutionErr::IntegerMismatch.err());
+        }
 
         let array = base
             .unwrap()
             .iter()
-            .map(|x| Some(Utc.timestamp(x.unwrap(), 0).to_rfc3339()))
+            .map(|x| x.map(human_bytes))
             .collect::<array::StringArray>();
+
         Ok(Arc::new(array) as array::ArrayRef)
     };
 
-    let date = make_scalar_function(date);
+    let human = make_scalar_function(human);
     create_udf(
-        "timestamp_rfc3339",
+        "human_bytes",
         vec![DataType::Int64],
         Arc::new(DataType::Utf8),
         Volatility::Immutable,
-        date,
+        human,
     )
 }
 
-/// 计算最大连续多少天有提交记录
+/// 将带单位的大小解析为字节数，无法解析时为 null
 ///
 /// # Example
 /// ```rust
-/// input<arg1: rfc3339>: "2021-10-12T14:20:50.52+07:00"
-/// output: 1
+/// input<arg1: String>: "1.4 MiB"
+/// output: 1468006
 /// ```
-fn udaf_active_longest_days() -> AggregateUDF {
-    create_udaf(
-        "active_longest_days",
-        DataType::Utf8,
-        Arc::new(DataType::Int64),
-        Volatility::Immutable,
+fn udf_bytes() -> ScalarUDF {
+    let bytes = |args: &[array::ArrayRef]| {
+        let base = &args[0].as_any().downcast_ref::<array::StringArray>();
+        if base.is_none() {
+            return Err(ExecutionErr::StringMismatch.err());
+        }
+
+        let array = base
+            .unwrap()
+            .iter()
+            .map(|x| x.and_then(parse_bytes))
+            .collect::<array::Int64Array>();
+
+        Ok(Arc::new(array) as array::ArrayRef)
+    };
+
+    let bytes = make_scalar_function(bytes);
+    create_udf(
+        "bytes",
+        vec![DataType::Utf8],
+        Arc::new(DataType::Int64),
+        Volatility::Immutable,
+        bytes,
+    )
+}
+
+/// 人性化的时长，未来时间（负数）使用 `-` 前缀
+fn humanize_duration(secs: i64) -> String {
+    let d = std::time::Duration::from_secs(secs.unsigned_abs());
+    let s = humantime::format_duration(d).to_string();
+    if secs < 0 {
+        format!("-{}", s)
+    } else {
+        s
+    }
+}
+
+/// ISO-8601 格式的时长，只使用天及以下的单位以避免月份长度的歧义
+fn iso8601_duration(secs: i64) -> String {
+    let sign = if secs < 0 { "-" } else { "" };
+    let mut secs = secs.unsigned_abs();
+    let days = secs / 86400;
+    secs %= 86400;
+    let (hours, minutes, seconds) = (secs / 3600, secs % 3600 / 60, secs % 60);
+
+    let mut s = format!("{}P", sign);
+    if days > 0 {
+        s.push_str(&format!("{}D", days));
+    }
+    if hours > 0 || minutes > 0 || seconds > 0 || days == 0 {
+        s.push('T');
+        if hours > 0 {
+            s.push_str(&format!("{}H", hours));
+        }
+        if minutes > 0 {
+            s.push_str(&format!("{}M", minutes));
+        }
+        if seconds > 0 || (hours == 0 && minutes == 0) {
+            s.push_str(&format!("{}S", seconds));
+        }
+    }
+    s
+}
+
+/// 计算给定时间到现在时间的长度
+///
+/// # Example
+/// ```rust
+/// input<arg1: unix timestamp>: 1647272093
+/// output: "30hours 2minutes"
+/// ```
+fn udf_duration() -> ScalarUDF {
+    let duration = |args: &[array::ArrayRef]| {
+        let base = &args[0].as_any().downcast_ref::<array::Int64Array>();
+        if base.is_none() {
+            return Err(ExecutionErr::DateTimeMismatch.err());
+        };
+
+        let now = Utc::now().timestamp();
+        let array = base
+            .unwrap()
+            .iter()
+            .map(|x| x.map(|x| humanize_duration(now - x)))
+            .collect::<array::StringArray>();
+
+        Ok(Arc::new(array) as array::ArrayRef)
+    };
+
+    let duration = make_scalar_function(duration);
+    create_udf(
+        "duration",
+        vec![DataType::Int64],
+        Arc::new(DataType::Utf8),
+        Volatility::Immutable,
+        duration,
+    )
+}
+
+/// 计算给定时间到现在时间的秒数，未来时间为负数
+///
+/// # Example
+/// ```rust
+/// input<arg1: unix timestamp>: 1647272093
+/// output: 108120
+/// ```
+fn udf_duration_seconds() -> ScalarUDF {
+    let duration = |args: &[array::ArrayRef]| {
+        let base = &args[0].as_any().downcast_ref::<array::Int64Array>();
+        if base.is_none() {
+            return Err(ExecutionErr::DateTimeMismatch.err());
+        };
+
+        let now = Utc::now().timestamp();
+        let array = base
+            .unwrap()
+            .iter()
+            .map(|x| x.map(|x| now - x))
+            .collect::<array::Int64Array>();
+
+        Ok(Arc::new(array) as array::ArrayRef)
+    };
+
+    let duration = make_scalar_function(duration);
+    create_udf(
+        "duration_seconds",
+        vec![DataType::Int64],
+        Arc::new(DataType::Int64),
+        Volatility::Immutable,
+        duration,
+    )
+}
+
+/// 计算给定时间到现在时间的长度，输出 ISO-8601 格式
+///
+/// # Example
+/// ```rust
+/// input<arg1: unix timestamp>: 1647272093
+/// output: "P1DT6H2M"
+/// ```
+fn udf_duration_iso8601() -> ScalarUDF {
+    let duration = |args: &[array::ArrayRef]| {
+        let base = &args[0].as_any().downcast_ref::<array::Int64Array>();
+        if base.is_none() {
+            return Err(ExecutionErr::DateTimeMismatch.err());
+        };
+
+        let now = Utc::now().timestamp();
+        let array = base
+            .unwrap()
+            .iter()
+            .map(|x| x.map(|x| iso8601_duration(now - x)))
+            .collect::<array::StringArray>();
+
+        Ok(Arc::new(array) as array::ArrayRef)
+    };
+
+    let duration = make_scalar_function(duration);
+    create_udf(
+        "duration_iso8601",
+        vec![DataType::Int64],
+        Arc::new(DataType::Utf8),
+        Volatility::Immutable,
+        duration,
+    )
+}
+
+/// 格式化时间戳时间
+///
+/// # Example
+/// ```rust
+/// input<arg1: unix timestamp, arg2: String>: 1647272093
+/// output: "2021-10-12T14:20:50.52+07:00"
+/// ```
+fn udf_timestamp_rfc3339() -> ScalarUDF {
+    let date = |args: &[array::ArrayRef]| {
+        let base = &args[0].as_any().downcast_ref::<array::Int64Array>();
+        if base.is_none() {
+            return Err(ExecutionErr::DateTimeMismatch.err());
+        };
+
+        let array = base
+            .unwrap()
+            .iter()
+            .map(|x| Some(Utc.timestamp(x.unwrap(), 0).to_rfc3339()))
+            .collect::<array::StringArray>();
+        Ok(Arc::new(array) as array::ArrayRef)
+    };
+
+    let date = make_scalar_function(date);
+    create_udf(
+        "timestamp_rfc3339",
+        vec![DataType::Int64],
+        Arc::new(DataType::Utf8),
+        Volatility::Immutable,
+        date,
+    )
+}
+
+/// 计算最大连续多少天有提交记录
+///
+/// # Example
+/// ```rust
+/// input<arg1: rfc3339>: "2021-10-12T14:20:50.52+07:00"
+/// output: 1
+/// ```
+fn udaf_active_longest_days() -> AggregateUDF {
+    create_udaf(
+        "active_longest_days",
+        DataType::Utf8,
+        Arc::new(DataType::Int64),
+        Volatility::Immutable,
         Arc::new(|| Ok(Box::new(ActiveLongestCount::new()))),
         Arc::new(vec![DataType::List(Box::new(Field::new(
             "item",
@@ -571,6 +1507,922 @@ fn udaf_active_longest_end() -> AggregateUDF {
     )
 }
 
+/// 按给定的桶大小计算数值分布，返回每个桶的计数列表（包含中间的空桶）
+///
+/// # Example
+/// ```rust
+/// input<arg1: Int64, arg2: bucket_size>: [1, 5, 12, 35], 10
+/// output: [2, 1, 0, 1]
+/// ```
+fn udaf_histogram() -> AggregateUDF {
+    create_histogram_udaf("histogram", HistogramType::Counts)
+}
+
+/// 计算 histogram 每个桶对应的区间标签，与 histogram 的结果一一对应
+///
+/// # Example
+/// ```rust
+/// input<arg1: Int64, arg2: bucket_size>: [1, 5, 12, 35], 10
+/// output: ["[0, 10)", "[10, 20)", "[20, 30)", "[30, 40)"]
+/// ```
+fn udaf_histogram_labels() -> AggregateUDF {
+    create_histogram_udaf("histogram_labels", HistogramType::Labels)
+}
+
+fn create_histogram_udaf(name: &str, typ: HistogramType) -> AggregateUDF {
+    let item = match typ {
+        HistogramType::Counts => DataType::Int64,
+        HistogramType::Labels => DataType::Utf8,
+    };
+    let return_type: ReturnTypeFunction = Arc::new(move |_| {
+        Ok(Arc::new(DataType::List(Box::new(Field::new(
+            "item",
+            item.clone(),
+            true,
+        )))))
+    });
+    let accumulator: AccumulatorFunctionImplementation =
+        Arc::new(move || Ok(Box::new(Histogram::new(typ))));
+    let state_type: StateTypeFunction = Arc::new(|_| {
+        Ok(Arc::new(vec![
+            DataType::List(Box::new(Field::new("item", DataType::Int64, true))),
+            DataType::Int64,
+        ]))
+    });
+
+    AggregateUDF::new(
+        name,
+        &Signature::exact(
+            vec![DataType::Int64, DataType::Int64],
+            Volatility::Immutable,
+        ),
+        &return_type,
+        &accumulator,
+        &state_type,
+    )
+}
+
+/// 计算有提交记录的天/周/月的数量，日期按照提交时间所在时区计算，unit 省略时为 day
+///
+/// # Example
+/// ```rust
+/// input<arg1: rfc3339, arg2: unit>: ["2021-10-12T14:20:50.52+07:00", "2021-10-13T14:20:50.52+07:00"], 'week'
+/// output: 1
+/// ```
+fn udaf_active_days() -> AggregateUDF {
+    let signature = Signature::one_of(
+        vec![
+            TypeSignature::Exact(vec![DataType::Utf8]),
+            TypeSignature::Exact(vec![DataType::Utf8, DataType::Utf8]),
+        ],
+        Volatility::Immutable,
+    );
+    create_active_buckets_udaf("active_days", ActiveBucketsType::Count, signature)
+}
+
+/// 计算给定时间窗口 [since, until] 内有提交记录的天数占比
+///
+/// # Example
+/// ```rust
+/// input<arg1: rfc3339, arg2: since, arg3: until>: ["2021-10-12T14:20:50.52+07:00"], '2021-10-11', '2021-10-14'
+/// output: 0.25
+/// ```
+fn udaf_activity_ratio() -> AggregateUDF {
+    let signature = Signature::exact(vec![DataType::Utf8; 3], Volatility::Immutable);
+    create_active_buckets_udaf("activity_ratio", ActiveBucketsType::Ratio, signature)
+}
+
+/// 计算截止到今天仍在持续的连续提交天数，今天还没有提交时从昨天开始计算，昨天也没有提交时为 0
+///
+/// # Example
+/// ```rust
+/// input<arg1: rfc3339>: ["2021-10-11T14:20:50.52+07:00", "2021-10-12T14:20:50.52+07:00"] (today: 2021-10-12)
+/// output: 2
+/// ```
+fn udaf_active_current_streak() -> AggregateUDF {
+    let signature = Signature::exact(vec![DataType::Utf8], Volatility::Volatile);
+    create_active_buckets_udaf(
+        "active_current_streak",
+        ActiveBucketsType::Streak,
+        signature,
+    )
+}
+
+fn create_active_buckets_udaf(
+    name: &str,
+    typ: ActiveBucketsType,
+    signature: Signature,
+) -> AggregateUDF {
+    let return_type: ReturnTypeFunction = Arc::new(move |_| {
+        Ok(Arc::new(match typ {
+            ActiveBucketsType::Count | ActiveBucketsType::Streak => DataType::Int64,
+            ActiveBucketsType::Ratio => DataType::Float64,
+        }))
+    });
+    let accumulator: AccumulatorFunctionImplementation =
+        Arc::new(move || Ok(Box::new(ActiveBuckets::new(typ))));
+    let state_type: StateTypeFunction = Arc::new(|_| {
+        Ok(Arc::new(vec![
+            DataType::List(Box::new(Field::new("item", DataType::Int64, true))),
+            DataType::List(Box::new(Field::new("item", DataType::Utf8, true))),
+        ]))
+    });
+
+    AggregateUDF::new(name, &signature, &return_type, &accumulator, &state_type)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ActiveBucketsType {
+    /// 有提交记录的天/周/月数量
+    Count,
+
+    /// 时间窗口内有提交记录的天数占比
+    Ratio,
+
+    /// 截止到今天的连续提交天数
+    Streak,
+}
+
+#[derive(Debug)]
+struct ActiveBuckets {
+    /// 提交日期，从公元元年开始的天数
+    days: Vec<i64>,
+    /// 除时间以外的参数
+    args: Vec<String>,
+    typ: ActiveBucketsType,
+}
+
+impl ActiveBuckets {
+    fn new(typ: ActiveBucketsType) -> Self {
+        Self {
+            days: vec![],
+            args: vec![],
+            typ,
+        }
+    }
+
+    fn parse_day(s: &str) -> Option<i64> {
+        let date = match DateTime::parse_from_rfc3339(s) {
+            Ok(t) => t.naive_local().date(),
+            Err(_) => NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?,
+        };
+        Some(date.num_days_from_ce() as i64)
+    }
+
+    /// 按照 unit 将日期归到对应的桶中，公元元年 1 月 1 日为周一
+    fn bucket(day: i64, unit: &str) -> Result<i64> {
+        match unit {
+            "day" => Ok(day),
+            "week" => Ok((day - 1).div_euclid(7)),
+            "month" => {
+                let date = NaiveDate::from_num_days_from_ce(day as i32);
+                Ok(date.year() as i64 * 12 + date.month0() as i64)
+            }
+            _ => Err(DataFusionError::Execution(format!(
+                "Mismatched: unit '{}' should be one of day/week/month",
+                unit
+            ))),
+        }
+    }
+
+    fn count(&self) -> Result<i64> {
+        let unit = self.args.first().map(|s| s.as_str()).unwrap_or("day");
+        let mut buckets = self
+            .days
+            .iter()
+            .map(|day| Self::bucket(*day, unit))
+            .collect::<Result<Vec<_>>>()?;
+        buckets.sort_unstable();
+        buckets.dedup();
+        Ok(buckets.len() as i64)
+    }
+
+    fn ratio(&self) -> Result<f64> {
+        let window = self
+            .args
+            .iter()
+            .take(2)
+            .map(|s| Self::parse_day(s))
+            .collect::<Option<Vec<_>>>()
+            .filter(|w| w.len() == 2);
+        let (since, until) = match window {
+            Some(w) => (w[0], w[1]),
+            None if self.days.is_empty() => return Ok(0.0),
+            None => {
+                return Err(DataFusionError::Execution(String::from(
+                    "Mismatched: since and until should be formatted as YYYY-MM-DD",
+                )))
+            }
+        };
+        if until < since {
+            return Ok(0.0);
+        }
+
+        let mut days = self
+            .days
+            .iter()
+            .filter(|day| **day >= since && **day <= until)
+            .collect::<Vec<_>>();
+        days.sort_unstable();
+        days.dedup();
+        Ok(days.len() as f64 / (until - since + 1) as f64)
+    }
+
+    /// 从 today（或者 today 的前一天）开始往前计算连续有提交记录的天数
+    fn streak(&self, today: i64) -> i64 {
+        let days = self.days.iter().copied().collect::<HashSet<_>>();
+        let mut day = match (days.contains(&today), days.contains(&(today - 1))) {
+            (true, _) => today,
+            (false, true) => today - 1,
+            (false, false) => return 0,
+        };
+        let mut n = 0;
+        while days.contains(&day) {
+            n += 1;
+            day -= 1;
+        }
+        n
+    }
+}
+
+impl Accumulator for ActiveBuckets {
+    fn state(&self) -> Result<Vec<ScalarValue>> {
+        let days = self.days.iter().map(|d| ScalarValue::from(*d)).collect();
+        let args = self
+            .args
+            .iter()
+            .map(|a| ScalarValue::from(a.as_str()))
+            .collect();
+        Ok(vec![
+            ScalarValue::List(Some(Box::new(days)), Box::new(DataType::Int64)),
+            ScalarValue::List(Some(Box::new(args)), Box::new(DataType::Utf8)),
+        ])
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        if values.is_empty() {
+            return Ok(());
+        };
+        (0..values[0].len()).try_for_each(|index| {
+            if let ScalarValue::Utf8(Some(v)) = ScalarValue::try_from_array(&values[0], index)? {
+                self.days.extend(Self::parse_day(&v));
+            }
+            let mut args = vec![];
+            for array in values.iter().skip(1) {
+                if let ScalarValue::Utf8(Some(v)) = ScalarValue::try_from_array(array, index)? {
+                    args.push(v);
+                }
+            }
+            if !args.is_empty() {
+                self.args = args;
+            }
+            Ok(())
+        })
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        if states.len() < 2 {
+            return Ok(());
+        };
+        (0..states[0].len()).try_for_each(|index| {
+            if let ScalarValue::List(Some(values), _) =
+                ScalarValue::try_from_array(&states[0], index)?
+            {
+                for v in values.iter() {
+                    if let ScalarValue::Int64(Some(i)) = v {
+                        self.days.push(*i);
+                    }
+                }
+            }
+            if let ScalarValue::List(Some(values), _) =
+                ScalarValue::try_from_array(&states[1], index)?
+            {
+                let args = values
+                    .iter()
+                    .filter_map(|v| match v {
+                        ScalarValue::Utf8(Some(s)) => Some(s.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                if !args.is_empty() {
+                    self.args = args;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn evaluate(&self) -> Result<ScalarValue> {
+        match self.typ {
+            ActiveBucketsType::Count => Ok(ScalarValue::from(self.count()?)),
+            ActiveBucketsType::Ratio => Ok(ScalarValue::from(self.ratio()?)),
+            ActiveBucketsType::Streak => {
+                let today = Local::now().naive_local().date().num_days_from_ce() as i64;
+                Ok(ScalarValue::from(self.streak(today)))
+            }
+        }
+    }
+}
+
+/// 计算提交最多的星期（众数），提交数相同时取一周中较早的一天（周一为一周的开始）
+///
+/// # Example
+/// ```rust
+/// input<arg1: rfc3339>: ["2021-10-12T14:20:50.52+07:00", "2021-10-13T14:20:50.52+07:00", "2021-10-19T14:20:50.52+07:00"]
+/// output: "Tue"
+/// ```
+fn udaf_most_active_weekday() -> AggregateUDF {
+    create_most_active_udaf("most_active_weekday", MostActiveType::Weekday)
+}
+
+/// 计算提交最多的小时（众数），提交数相同时取较早的小时
+///
+/// # Example
+/// ```rust
+/// input<arg1: rfc3339>: ["2021-10-12T14:20:50.52+07:00", "2021-10-13T14:50:50.52+07:00", "2021-10-19T09:20:50.52+07:00"]
+/// output: 14
+/// ```
+fn udaf_most_active_hour() -> AggregateUDF {
+    create_most_active_udaf("most_active_hour", MostActiveType::Hour)
+}
+
+fn create_most_active_udaf(name: &str, typ: MostActiveType) -> AggregateUDF {
+    let return_type = match typ {
+        MostActiveType::Weekday => DataType::Utf8,
+        MostActiveType::Hour => DataType::UInt32,
+    };
+    create_udaf(
+        name,
+        DataType::Utf8,
+        Arc::new(return_type),
+        Volatility::Immutable,
+        Arc::new(move || Ok(Box::new(MostActive::new(typ)))),
+        Arc::new(vec![DataType::List(Box::new(Field::new(
+            "item",
+            DataType::Int64,
+            true,
+        )))]),
+    )
+}
+
+#[derive(Debug, Clone, Copy)]
+enum MostActiveType {
+    /// 按星期统计，周一为 0
+    Weekday,
+
+    /// 按小时统计
+    Hour,
+}
+
+impl MostActiveType {
+    fn size(&self) -> usize {
+        match self {
+            MostActiveType::Weekday => 7,
+            MostActiveType::Hour => 24,
+        }
+    }
+
+    fn bucket(&self, t: &DateTime<FixedOffset>) -> usize {
+        match self {
+            MostActiveType::Weekday => t.weekday().num_days_from_monday() as usize,
+            MostActiveType::Hour => t.hour() as usize,
+        }
+    }
+}
+
+static WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+#[derive(Debug)]
+struct MostActive {
+    /// 每个星期或者小时的提交数
+    counts: Vec<i64>,
+    typ: MostActiveType,
+}
+
+impl MostActive {
+    fn new(typ: MostActiveType) -> Self {
+        Self {
+            counts: vec![0; typ.size()],
+            typ,
+        }
+    }
+
+    /// 提交数最多的星期或者小时，提交数相同时取较小的值，没有提交时返回 None
+    fn mode(&self) -> Option<usize> {
+        let max = self.counts.iter().copied().max().unwrap_or_default();
+        if max == 0 {
+            return None;
+        }
+        self.counts.iter().position(|c| *c == max)
+    }
+}
+
+impl Accumulator for MostActive {
+    fn state(&self) -> Result<Vec<ScalarValue>> {
+        let counts = self.counts.iter().map(|c| ScalarValue::from(*c)).collect();
+        Ok(vec![ScalarValue::List(
+            Some(Box::new(counts)),
+            Box::new(DataType::Int64),
+        )])
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        if values.is_empty() {
+            return Ok(());
+        };
+        (0..values[0].len()).try_for_each(|index| {
+            if let ScalarValue::Utf8(Some(v)) = ScalarValue::try_from_array(&values[0], index)? {
+                if let Ok(t) = DateTime::parse_from_rfc3339(&v) {
+                    self.counts[self.typ.bucket(&t)] += 1;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        if states.is_empty() {
+            return Ok(());
+        };
+        (0..states[0].len()).try_for_each(|index| {
+            if let ScalarValue::List(Some(values), _) =
+                ScalarValue::try_from_array(&states[0], index)?
+            {
+                for (i, v) in values.iter().enumerate().take(self.counts.len()) {
+                    if let ScalarValue::Int64(Some(c)) = v {
+                        self.counts[i] += *c;
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn evaluate(&self) -> Result<ScalarValue> {
+        let mode = self.mode();
+        Ok(match self.typ {
+            MostActiveType::Weekday => ScalarValue::Utf8(mode.map(|i| WEEKDAYS[i].to_string())),
+            MostActiveType::Hour => ScalarValue::UInt32(mode.map(|h| h as u32)),
+        })
+    }
+}
+
+/// 计算最早的提交日期，日期按照提交时间所在时区计算
+///
+/// # Example
+/// ```rust
+/// input<arg1: rfc3339>: ["2021-10-12T14:20:50.52+07:00", "2020-01-02T22:20:50.52+07:00"]
+/// output: "2020-01-02"
+/// ```
+fn udaf_first_commit_date() -> AggregateUDF {
+    create_commit_range_udaf("first_commit_date", CommitRangeType::First)
+}
+
+/// 计算最近的提交日期，日期按照提交时间所在时区计算
+///
+/// # Example
+/// ```rust
+/// input<arg1: rfc3339>: ["2021-10-12T14:20:50.52+07:00", "2020-01-02T22:20:50.52+07:00"]
+/// output: "2021-10-12"
+/// ```
+fn udaf_last_commit_date() -> AggregateUDF {
+    create_commit_range_udaf("last_commit_date", CommitRangeType::Last)
+}
+
+/// 计算最早的提交距今的天数
+///
+/// # Example
+/// ```rust
+/// input<arg1: rfc3339>: ["2021-10-12T14:20:50.52+07:00", "2021-10-02T14:20:50.52+07:00"] (now: 2021-10-22T14:20:50.52+07:00)
+/// output: 20
+/// ```
+fn udaf_project_age_days() -> AggregateUDF {
+    create_commit_range_udaf("project_age_days", CommitRangeType::AgeDays)
+}
+
+fn create_commit_range_udaf(name: &str, typ: CommitRangeType) -> AggregateUDF {
+    let (return_type, volatility) = match typ {
+        CommitRangeType::First | CommitRangeType::Last => (DataType::Utf8, Volatility::Immutable),
+        CommitRangeType::AgeDays => (DataType::Int64, Volatility::Volatile),
+    };
+    create_udaf(
+        name,
+        DataType::Utf8,
+        Arc::new(return_type),
+        volatility,
+        Arc::new(move || Ok(Box::new(CommitRange::new(typ)))),
+        Arc::new(vec![DataType::Utf8, DataType::Utf8]),
+    )
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CommitRangeType {
+    /// 最早的提交日期
+    First,
+
+    /// 最近的提交日期
+    Last,
+
+    /// 最早的提交距今的天数
+    AgeDays,
+}
+
+/// 最早以及最近的提交时间，按照时间戳比较，保留提交原始的时区
+#[derive(Debug)]
+struct CommitRange {
+    first: Option<DateTime<FixedOffset>>,
+    last: Option<DateTime<FixedOffset>>,
+    typ: CommitRangeType,
+}
+
+impl CommitRange {
+    fn new(typ: CommitRangeType) -> Self {
+        Self {
+            first: None,
+            last: None,
+            typ,
+        }
+    }
+
+    fn update(&mut self, datetime: &str) {
+        let t = match DateTime::parse_from_rfc3339(datetime) {
+            Ok(t) => t,
+            Err(_) => return,
+        };
+        if self.first.map_or(true, |first| t < first) {
+            self.first = Some(t);
+        }
+        if self.last.map_or(true, |last| t > last) {
+            self.last = Some(t);
+        }
+    }
+
+    fn age_days(&self, now: DateTime<Utc>) -> Option<i64> {
+        self.first
+            .map(|first| (now.timestamp() - first.timestamp()).div_euclid(86400))
+    }
+}
+
+impl Accumulator for CommitRange {
+    fn state(&self) -> Result<Vec<ScalarValue>> {
+        Ok(vec![
+            ScalarValue::Utf8(self.first.map(|t| t.to_rfc3339())),
+            ScalarValue::Utf8(self.last.map(|t| t.to_rfc3339())),
+        ])
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        if values.is_empty() {
+            return Ok(());
+        };
+        (0..values[0].len()).try_for_each(|index| {
+            if let ScalarValue::Utf8(Some(v)) = ScalarValue::try_from_array(&values[0], index)? {
+                self.update(&v);
+            }
+            Ok(())
+        })
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        for state in states {
+            for index in 0..state.len() {
+                if let ScalarValue::Utf8(Some(v)) = ScalarValue::try_from_array(state, index)? {
+                    self.update(&v);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> Result<ScalarValue> {
+        let date = |t: Option<DateTime<FixedOffset>>| {
+            ScalarValue::Utf8(t.map(|t| t.format("%Y-%m-%d").to_string()))
+        };
+        Ok(match self.typ {
+            CommitRangeType::First => date(self.first),
+            CommitRangeType::Last => date(self.last),
+            CommitRangeType::AgeDays => ScalarValue::Int64(self.age_days(Utc::now())),
+        })
+    }
+}
+
+/// 计算贡献在作者之间的集中程度（基尼系数），0 表示所有作者贡献相同，越接近 1 表示贡献越集中在少数作者
+///
+/// weight 为每行的贡献量，如提交数 1 或者新增行数
+///
+/// # Example
+/// ```rust
+/// input<arg1: author, arg2: weight>: [("a", 3), ("b", 1)]
+/// output: 0.25
+/// ```
+fn udaf_contribution_gini() -> AggregateUDF {
+    create_contribution_udaf("contribution_gini", ContributionType::Gini)
+}
+
+/// 计算贡献量累计达到总量一半所需的最少作者数（bus factor），越小表示项目越依赖少数作者
+///
+/// # Example
+/// ```rust
+/// input<arg1: author, arg2: weight>: [("a", 3), ("b", 1)]
+/// output: 1
+/// ```
+fn udaf_bus_factor() -> AggregateUDF {
+    create_contribution_udaf("bus_factor", ContributionType::BusFactor)
+}
+
+fn create_contribution_udaf(name: &str, typ: ContributionType) -> AggregateUDF {
+    let return_type: ReturnTypeFunction = Arc::new(move |_| {
+        Ok(Arc::new(match typ {
+            ContributionType::Gini => DataType::Float64,
+            ContributionType::BusFactor => DataType::Int64,
+        }))
+    });
+    let accumulator: AccumulatorFunctionImplementation =
+        Arc::new(move || Ok(Box::new(Contribution::new(typ))));
+    let state_type: StateTypeFunction = Arc::new(|_| {
+        Ok(Arc::new(vec![
+            DataType::List(Box::new(Field::new("item", DataType::Utf8, true))),
+            DataType::List(Box::new(Field::new("item", DataType::Int64, true))),
+        ]))
+    });
+
+    AggregateUDF::new(
+        name,
+        &Signature::exact(vec![DataType::Utf8, DataType::Int64], Volatility::Immutable),
+        &return_type,
+        &accumulator,
+        &state_type,
+    )
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ContributionType {
+    /// 基尼系数
+    Gini,
+
+    /// 贡献量达到一半所需的最少作者数
+    BusFactor,
+}
+
+#[derive(Debug)]
+struct Contribution {
+    /// 每个作者的贡献量
+    weights: HashMap<String, i64>,
+    typ: ContributionType,
+}
+
+impl Contribution {
+    fn new(typ: ContributionType) -> Self {
+        Self {
+            weights: HashMap::new(),
+            typ,
+        }
+    }
+
+    fn add(&mut self, author: String, weight: i64) {
+        *self.weights.entry(author).or_default() += weight;
+    }
+
+    /// 贡献量为正数的作者的贡献量（升序）
+    fn sorted(&self) -> Vec<i64> {
+        let mut weights = self
+            .weights
+            .values()
+            .copied()
+            .filter(|w| *w > 0)
+            .collect::<Vec<_>>();
+        weights.sort_unstable();
+        weights
+    }
+
+    fn gini(&self) -> Option<f64> {
+        let weights = self.sorted();
+        let total = weights.iter().sum::<i64>() as f64;
+        if weights.is_empty() {
+            return None;
+        }
+        let n = weights.len() as f64;
+        let ranked = weights
+            .iter()
+            .enumerate()
+            .map(|(i, w)| (i + 1) as f64 * *w as f64)
+            .sum::<f64>();
+        Some(2.0 * ranked / (n * total) - (n + 1.0) / n)
+    }
+
+    fn bus_factor(&self) -> Option<i64> {
+        let weights = self.sorted();
+        if weights.is_empty() {
+            return None;
+        }
+        let total = weights.iter().sum::<i64>();
+        let mut covered = 0;
+        for (i, w) in weights.iter().rev().enumerate() {
+            covered += w;
+            if covered * 2 >= total {
+                return Some(i as i64 + 1);
+            }
+        }
+        Some(weights.len() as i64)
+    }
+}
+
+impl Accumulator for Contribution {
+    fn state(&self) -> Result<Vec<ScalarValue>> {
+        let authors = self
+            .weights
+            .keys()
+            .map(|a| ScalarValue::from(a.as_str()))
+            .collect();
+        let weights = self
+            .weights
+            .values()
+            .map(|w| ScalarValue::from(*w))
+            .collect();
+        Ok(vec![
+            ScalarValue::List(Some(Box::new(authors)), Box::new(DataType::Utf8)),
+            ScalarValue::List(Some(Box::new(weights)), Box::new(DataType::Int64)),
+        ])
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        if values.len() < 2 {
+            return Ok(());
+        };
+        (0..values[0].len()).try_for_each(|index| {
+            let author = ScalarValue::try_from_array(&values[0], index)?;
+            let weight = ScalarValue::try_from_array(&values[1], index)?;
+            if let (ScalarValue::Utf8(Some(a)), ScalarValue::Int64(Some(w))) = (author, weight) {
+                self.add(a, w);
+            }
+            Ok(())
+        })
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        if states.len() < 2 {
+            return Ok(());
+        };
+        (0..states[0].len()).try_for_each(|index| {
+            let authors = ScalarValue::try_from_array(&states[0], index)?;
+            let weights = ScalarValue::try_from_array(&states[1], index)?;
+            if let (ScalarValue::List(Some(authors), _), ScalarValue::List(Some(weights), _)) =
+                (authors, weights)
+            {
+                for (author, weight) in authors.iter().zip(weights.iter()) {
+                    if let (ScalarValue::Utf8(Some(a)), ScalarValue::Int64(Some(w))) =
+                        (author, weight)
+                    {
+                        self.add(a.clone(), *w);
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn evaluate(&self) -> Result<ScalarValue> {
+        Ok(match self.typ {
+            ContributionType::Gini => ScalarValue::Float64(self.gini()),
+            ContributionType::BusFactor => ScalarValue::Int64(self.bus_factor()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum HistogramType {
+    /// 每个桶的计数
+    Counts,
+
+    /// 每个桶的区间标签
+    Labels,
+}
+
+/// histogram 最多输出的桶数，避免数据跨度过大或者桶过小时分配过大的内存
+const HISTOGRAM_MAX_BUCKETS: i64 = 10_000;
+
+#[derive(Debug)]
+struct Histogram {
+    data: Vec<i64>,
+    bucket_size: i64,
+    typ: HistogramType,
+}
+
+impl Histogram {
+    fn new(typ: HistogramType) -> Self {
+        Self {
+            data: vec![],
+            bucket_size: 0,
+            typ,
+        }
+    }
+
+    /// 计算起始桶的序号以及每个桶的计数，桶数超过 HISTOGRAM_MAX_BUCKETS 时返回错误
+    fn buckets(&self) -> Result<(i64, Vec<i64>)> {
+        let size = self.bucket_size;
+        let min = self.data.iter().min().map(|d| d.div_euclid(size));
+        let max = self.data.iter().max().map(|d| d.div_euclid(size));
+        let (min, max) = match (min, max) {
+            (Some(min), Some(max)) => (min, max),
+            _ => return Ok((0, vec![])),
+        };
+
+        let len = match max.checked_sub(min) {
+            Some(n) if n < HISTOGRAM_MAX_BUCKETS => n + 1,
+            _ => {
+                return Err(DataFusionError::Execution(format!(
+                    "Mismatched: histogram exceeds {} buckets, bucket size {} is too small",
+                    HISTOGRAM_MAX_BUCKETS, size
+                )))
+            }
+        };
+        let mut counts = vec![0; len as usize];
+        for d in self.data.iter() {
+            counts[(d.div_euclid(size) - min) as usize] += 1;
+        }
+        Ok((min, counts))
+    }
+}
+
+impl Accumulator for Histogram {
+    fn state(&self) -> Result<Vec<ScalarValue>> {
+        let values = self.data.iter().map(|d| ScalarValue::from(*d)).collect();
+        Ok(vec![
+            ScalarValue::List(Some(Box::new(values)), Box::new(DataType::Int64)),
+            ScalarValue::from(self.bucket_size),
+        ])
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        if values.len() < 2 {
+            return Ok(());
+        };
+        (0..values[0].len()).try_for_each(|index| {
+            if let ScalarValue::Int64(Some(v)) = ScalarValue::try_from_array(&values[0], index)? {
+                self.data.push(v);
+            }
+            if let ScalarValue::Int64(Some(b)) = ScalarValue::try_from_array(&values[1], index)? {
+                self.bucket_size = b;
+            }
+            Ok(())
+        })
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        if states.len() < 2 {
+            return Ok(());
+        };
+        (0..states[0].len()).try_for_each(|index| {
+            if let ScalarValue::List(Some(values), _) =
+                ScalarValue::try_from_array(&states[0], index)?
+            {
+                for v in values.iter() {
+                    if let ScalarValue::Int64(Some(i)) = v {
+                        self.data.push(*i);
+                    }
+                }
+            }
+            if let ScalarValue::Int64(Some(b)) = ScalarValue::try_from_array(&states[1], index)? {
+                if b > 0 {
+                    self.bucket_size = b;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn evaluate(&self) -> Result<ScalarValue> {
+        if !self.data.is_empty() && self.bucket_size <= 0 {
+            return Err(DataFusionError::Execution(String::from(
+                "Mismatched: bucket size should be positive",
+            )));
+        }
+
+        let (min, counts) = self.buckets()?;
+        let value = match self.typ {
+            HistogramType::Counts => ScalarValue::List(
+                Some(Box::new(
+                    counts.into_iter().map(ScalarValue::from).collect(),
+                )),
+                Box::new(DataType::Int64),
+            ),
+            HistogramType::Labels => ScalarValue::List(
+                Some(Box::new(
+                    (0..counts.len() as i64)
+                        .map(|i| {
+                            let start = (min + i) * self.bucket_size;
+                            let label = format!("[{}, {})", start, start + self.bucket_size);
+                            ScalarValue::from(label.as_str())
+                        })
+                        .collect(),
+                )),
+                Box::new(DataType::Utf8),
+            ),
+        };
+        Ok(value)
+    }
+}
+
 /// 所有时间输入类型的 Accumulator 的基类
 #[derive(Debug)]
 struct TimeInputAccumulator {
@@ -835,6 +2687,105 @@ mod tests {
         assert_eq!((5, 1, 5), active_longest.calc_longest(data, 1));
     }
 
+    #[test]
+    fn test_active_current_streak() {
+        let mut buckets = ActiveBuckets::new(ActiveBucketsType::Streak);
+        assert_eq!(buckets.streak(100), 0);
+
+        buckets.days = vec![90, 97, 98, 98, 99, 100];
+        assert_eq!(buckets.streak(100), 4);
+        assert_eq!(buckets.streak(101), 4);
+        assert_eq!(buckets.streak(102), 0);
+        assert_eq!(buckets.streak(91), 1);
+    }
+
+    #[test]
+    fn test_empty_table_schema() {
+        fn header<T: serde::Serialize>(record: T) -> Vec<String> {
+            let mut wtr = csv::Writer::from_writer(vec![]);
+            wtr.serialize(record).unwrap();
+            let data = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+            data.lines()
+                .next()
+                .unwrap()
+                .split(',')
+                .map(|s| s.to_string())
+                .collect()
+        }
+        fn fields(name: String) -> Vec<String> {
+            empty_table_schema(&name)
+                .unwrap()
+                .fields()
+                .iter()
+                .map(|f| f.name().to_string())
+                .collect()
+        }
+
+        use record::*;
+        assert_eq!(
+            header(RecordCommit::default()),
+            fields(RecordCommit::name())
+        );
+        assert_eq!(
+            header(RecordChange::default()),
+            fields(RecordChange::name())
+        );
+        assert_eq!(header(RecordTag::default()), fields(RecordTag::name()));
+        assert_eq!(
+            header(RecordSnapshot::default()),
+            fields(RecordSnapshot::name())
+        );
+        assert_eq!(
+            header(RecordSnapshotHistory::default()),
+            fields(RecordSnapshotHistory::name())
+        );
+        assert_eq!(
+            header(RecordActive::default()),
+            fields(RecordActive::name())
+        );
+        assert_eq!(header(RecordStar::default()), fields(RecordStar::name()));
+        assert_eq!(header(RecordIssue::default()), fields(RecordIssue::name()));
+        assert_eq!(
+            header(RecordPullRequest::default()),
+            fields(RecordPullRequest::name())
+        );
+        assert_eq!(
+            header(RecordFirstResponse::default()),
+            fields(RecordFirstResponse::name())
+        );
+        assert_eq!(header(RecordRepo::default()), fields(RecordRepo::name()));
+        assert_eq!(
+            header(RecordStatus::default()),
+            fields(RecordStatus::name())
+        );
+        assert_eq!(
+            header(RecordRetention::default()),
+            fields(RecordRetention::name())
+        );
+        assert_eq!(
+            header(RecordMigration::default()),
+            fields(RecordMigration::name())
+        );
+        assert_eq!(
+            header(RecordWorkingPattern::default()),
+            fields(RecordWorkingPattern::name())
+        );
+        assert_eq!(
+            header(RecordFirstContribution::default()),
+            fields(RecordFirstContribution::name())
+        );
+        assert_eq!(
+            header(RecordDuplicate::default()),
+            fields(RecordDuplicate::name())
+        );
+        assert_eq!(
+            header(RecordEnrichment::default()),
+            fields(RecordEnrichment::name())
+        );
+        assert_eq!(header(RecordMeta::default()), fields(RecordMeta::name()));
+        assert!(empty_table_schema("unknown").is_none());
+    }
+
     fn get_datetime_context() -> ExecutionContext {
         let mut ctx = ExecutionContext::new();
         let datetime_array: array::LargeStringArray = vec![
@@ -877,6 +2828,45 @@ mod tests {
         ctx
     }
 
+    #[test]
+    fn test_human_bytes() {
+        assert_eq!(human_bytes(512), "512 B");
+        assert_eq!(human_bytes(1468006), "1.4 MiB");
+        assert_eq!(human_bytes(-2048), "-2.0 KiB");
+
+        assert_eq!(parse_bytes("1.4 MiB"), Some(1468006));
+        assert_eq!(parse_bytes("2k"), Some(2048));
+        assert_eq!(parse_bytes("10KB"), Some(10000));
+        assert_eq!(parse_bytes("42"), Some(42));
+        assert_eq!(parse_bytes("3 parsecs"), None);
+    }
+
+    #[test]
+    fn test_email_domain() {
+        assert_eq!(
+            email_domain("a@PingCAP.com"),
+            Some("pingcap.com".to_string())
+        );
+        assert_eq!(email_domain("a+b@c@d.io"), Some("d.io".to_string()));
+        assert_eq!(email_domain("root"), None);
+        assert_eq!(email_domain("root@"), None);
+    }
+
+    #[test]
+    fn test_iso8601_duration() {
+        assert_eq!(iso8601_duration(0), "PT0S");
+        assert_eq!(iso8601_duration(59), "PT59S");
+        assert_eq!(iso8601_duration(86400), "P1D");
+        assert_eq!(iso8601_duration(108120), "P1DT6H2M");
+        assert_eq!(iso8601_duration(-3661), "-PT1H1M1S");
+    }
+
+    #[test]
+    fn test_humanize_duration() {
+        assert_eq!(humanize_duration(108120), "1day 6h 2m");
+        assert_eq!(humanize_duration(-60), "-1m");
+    }
+
     #[tokio::test]
     async fn test_udf_year() {
         let mut ctx = get_datetime_context();
@@ -925,6 +2915,54 @@ mod tests {
         datafusion::assert_batches_sorted_eq!(expected, &result);
     }
 
+    #[tokio::test]
+    async fn test_udf_weekofyear() {
+        let mut ctx = get_datetime_context();
+        let result: Vec<RecordBatch> = ctx
+            .sql("select weekofyear(datetime) from repo;")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+---------------------------+",
+            "| weekofyear(repo.datetime) |",
+            "+---------------------------+",
+            "| 1                         |",
+            "| 10                        |",
+            "| 41                        |",
+            "| 41                        |",
+            "+---------------------------+",
+        ];
+        datafusion::assert_batches_sorted_eq!(expected, &result);
+    }
+
+    #[tokio::test]
+    async fn test_udf_quarter() {
+        let mut ctx = get_datetime_context();
+        let result: Vec<RecordBatch> = ctx
+            .sql("select quarter(datetime) from repo;")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+------------------------+",
+            "| quarter(repo.datetime) |",
+            "+------------------------+",
+            "| 1                      |",
+            "| 1                      |",
+            "| 4                      |",
+            "| 4                      |",
+            "+------------------------+",
+        ];
+        datafusion::assert_batches_sorted_eq!(expected, &result);
+    }
+
     #[tokio::test]
     async fn test_udf_weekday() {
         let mut ctx = get_datetime_context();
@@ -1045,6 +3083,47 @@ mod tests {
         datafusion::assert_batches_sorted_eq!(expected, &result);
     }
 
+    #[test]
+    fn test_find_period() {
+        let periods = default_periods();
+        assert_eq!("Midnight", find_period(&periods, 0).unwrap().label);
+        assert_eq!("Morning", find_period(&periods, 8).unwrap().label);
+        assert_eq!("Afternoon", find_period(&periods, 18).unwrap().label);
+        assert_eq!("Evening", find_period(&periods, 23).unwrap().label);
+        assert_eq!(None, find_period(&[], 23).map(|p| p.label.clone()));
+    }
+
+    #[tokio::test]
+    async fn test_udf_period_custom() {
+        let mut ctx = get_datetime_context();
+        let periods = vec![
+            config::Period {
+                start: 9,
+                label: String::from("Work"),
+                emoji: None,
+            },
+            config::Period {
+                start: 18,
+                label: String::from("Rest"),
+                emoji: None,
+            },
+        ];
+        ctx.register_udf(create_period_udf("period", periods, false));
+        let result: Vec<RecordBatch> = ctx
+            .sql("select period(datetime) as p from repo;")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+------+", "| p    |", "+------+", "| Rest |", "| Rest |", "| Work |", "| Work |",
+            "+------+",
+        ];
+        datafusion::assert_batches_sorted_eq!(expected, &result);
+    }
+
     #[tokio::test]
     async fn test_udf_timestamp() {
         let mut ctx = get_datetime_context();
@@ -1093,6 +3172,108 @@ mod tests {
         datafusion::assert_batches_sorted_eq!(expected, &result);
     }
 
+    #[tokio::test]
+    async fn test_udf_date_format() {
+        let mut ctx = get_datetime_context();
+        let result: Vec<RecordBatch> = ctx
+            .sql("select date_format(datetime, '%Y-%m') as m, date_format(datetime, '%G-W%V %H:00') as w from repo;")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+---------+----------------+",
+            "| m       | w              |",
+            "+---------+----------------+",
+            "| 2020-01 | 2020-W01 22:00 |",
+            "| 2020-03 | 2020-W10 11:00 |",
+            "| 2021-10 | 2021-W41 08:00 |",
+            "| 2021-10 | 2021-W41 14:00 |",
+            "+---------+----------------+",
+        ];
+        datafusion::assert_batches_sorted_eq!(expected, &result);
+
+        let result = ctx
+            .sql("select date_format(datetime, '%Q') from repo;")
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_udf_to_timezone() {
+        let mut ctx = get_datetime_context();
+        let result: Vec<RecordBatch> = ctx
+            .sql("select hour(to_timezone(datetime, 'Asia/Shanghai')) as a, hour(to_timezone(datetime, '-05:00')) as b from repo;")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+----+----+",
+            "| a  | b  |",
+            "+----+----+",
+            "| 12 | 23 |",
+            "| 14 | 1  |",
+            "| 23 | 10 |",
+            "| 8  | 19 |",
+            "+----+----+",
+        ];
+        datafusion::assert_batches_sorted_eq!(expected, &result);
+
+        let result = ctx
+            .sql("select to_timezone(datetime, 'Mars/Olympus') from repo;")
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_udf_default_timezone() {
+        let mut ctx = get_datetime_context();
+        for udf in UDFS.iter() {
+            let udf = udf();
+            if TIMEZONE_FUNCTIONS.contains(&udf.name.as_str()) {
+                ctx.register_udf(with_timezone(udf, Some(String::from("-05:00"))));
+            }
+        }
+        let result: Vec<RecordBatch> = ctx
+            .sql("select hour(datetime) as a, hour(datetime, 'author') as b, date_format(datetime, '%d', 'Asia/Shanghai') as c from repo;")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+----+----+----+",
+            "| a  | b  | c  |",
+            "+----+----+----+",
+            "| 1  | 14 | 12 |",
+            "| 10 | 22 | 02 |",
+            "| 19 | 8  | 13 |",
+            "| 23 | 11 | 03 |",
+            "+----+----+----+",
+        ];
+        datafusion::assert_batches_sorted_eq!(expected, &result);
+
+        let result = ctx
+            .sql("select hour(datetime, 'Mars/Olympus') from repo;")
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     async fn test_udf_timestamp_rfc3339() {
         let mut ctx = get_datetime_context();
@@ -1156,6 +3337,192 @@ mod tests {
         datafusion::assert_batches_sorted_eq!(expected, &result);
     }
 
+    #[test]
+    fn test_histogram_buckets() {
+        let mut histogram = Histogram::new(HistogramType::Counts);
+        assert_eq!((0, vec![]), histogram.buckets().unwrap());
+
+        histogram.bucket_size = 10;
+        histogram.data = vec![1, 5, 12, 35];
+        assert_eq!((0, vec![2, 1, 0, 1]), histogram.buckets().unwrap());
+
+        histogram.data = vec![-3, 8, 21];
+        assert_eq!((-1, vec![1, 1, 0, 1]), histogram.buckets().unwrap());
+
+        histogram.bucket_size = 1;
+        histogram.data = vec![0, HISTOGRAM_MAX_BUCKETS - 1];
+        assert_eq!(
+            HISTOGRAM_MAX_BUCKETS as usize,
+            histogram.buckets().unwrap().1.len()
+        );
+
+        histogram.data = vec![0, HISTOGRAM_MAX_BUCKETS];
+        assert!(histogram.buckets().is_err());
+
+        histogram.data = vec![i64::MIN, i64::MAX];
+        assert!(histogram.buckets().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_udaf_active_days() {
+        let mut ctx = get_datetime_context();
+        let result: Vec<RecordBatch> = ctx
+            .sql("select active_days(datetime, 'day') as d, active_days(datetime, 'week') as w, active_days(datetime, 'month') as m, activity_ratio(datetime, '2021-10-11', '2021-10-14') as r from repo;")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+---+---+---+-----+",
+            "| d | w | m | r   |",
+            "+---+---+---+-----+",
+            "| 4 | 3 | 3 | 0.5 |",
+            "+---+---+---+-----+",
+        ];
+        datafusion::assert_batches_sorted_eq!(expected, &result);
+    }
+
+    #[tokio::test]
+    async fn test_udaf_active_days_default_unit() {
+        let mut ctx = get_datetime_context();
+        let result: Vec<RecordBatch> = ctx
+            .sql("select repo_name, active_days(datetime) as d from repo group by repo_name;")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+--------------------+---+",
+            "| repo_name          | d |",
+            "+--------------------+---+",
+            "| chenjiandongx/gitv | 3 |",
+            "| rust-lang/rust     | 1 |",
+            "+--------------------+---+",
+        ];
+        datafusion::assert_batches_sorted_eq!(expected, &result);
+    }
+
+    #[tokio::test]
+    async fn test_udaf_most_active() {
+        let mut ctx = get_datetime_context();
+        let result: Vec<RecordBatch> = ctx
+            .sql("select repo_name, most_active_weekday(datetime) as w, most_active_hour(datetime) as h from repo group by repo_name;")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+--------------------+-----+----+",
+            "| repo_name          | w   | h  |",
+            "+--------------------+-----+----+",
+            "| chenjiandongx/gitv | Tue | 8  |",
+            "| rust-lang/rust     | Tue | 11 |",
+            "+--------------------+-----+----+",
+        ];
+        datafusion::assert_batches_sorted_eq!(expected, &result);
+    }
+
+    #[tokio::test]
+    async fn test_udaf_commit_range() {
+        let mut ctx = get_datetime_context();
+        let result: Vec<RecordBatch> = ctx
+            .sql("select repo_name, first_commit_date(datetime) as f, last_commit_date(datetime) as l from repo group by repo_name;")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+--------------------+------------+------------+",
+            "| repo_name          | f          | l          |",
+            "+--------------------+------------+------------+",
+            "| chenjiandongx/gitv | 2020-01-02 | 2021-10-13 |",
+            "| rust-lang/rust     | 2020-03-03 | 2020-03-03 |",
+            "+--------------------+------------+------------+",
+        ];
+        datafusion::assert_batches_sorted_eq!(expected, &result);
+    }
+
+    #[test]
+    fn test_project_age_days() {
+        let mut range = CommitRange::new(CommitRangeType::AgeDays);
+        let now = Utc.ymd(2021, 10, 22).and_hms(7, 20, 50);
+        assert_eq!(range.age_days(now), None);
+
+        range.update("2021-10-12T14:20:50.52+07:00");
+        range.update("2021-10-02T14:20:51+07:00");
+        range.update("invalid");
+        assert_eq!(range.age_days(now), Some(19));
+    }
+
+    #[test]
+    fn test_contribution() {
+        let mut contribution = Contribution::new(ContributionType::Gini);
+        assert_eq!(contribution.gini(), None);
+        assert_eq!(contribution.bus_factor(), None);
+
+        for author in ["a", "b", "c", "d"] {
+            contribution.add(author.to_string(), 5);
+        }
+        assert_eq!(contribution.gini(), Some(0.0));
+        assert_eq!(contribution.bus_factor(), Some(2));
+
+        contribution.add("a".to_string(), 80);
+        contribution.add("e".to_string(), 0);
+        assert_eq!(contribution.bus_factor(), Some(1));
+        let gini = contribution.gini().unwrap();
+        assert!((gini - 0.6).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_udaf_contribution() {
+        let mut ctx = get_datetime_context();
+        let result: Vec<RecordBatch> = ctx
+            .sql("select contribution_gini(repo_name, 1) as g, bus_factor(repo_name, 1) as b from repo;")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+------+---+",
+            "| g    | b |",
+            "+------+---+",
+            "| 0.25 | 1 |",
+            "+------+---+",
+        ];
+        datafusion::assert_batches_sorted_eq!(expected, &result);
+    }
+
+    #[tokio::test]
+    async fn test_udaf_histogram() {
+        let mut ctx = get_datetime_context();
+        let result: Vec<RecordBatch> = ctx
+            .sql("select histogram(timestamp(datetime), 100000000) as h, histogram_labels(timestamp(datetime), 100000000) as l from repo;")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+--------+------------------------------------------------------+",
+            "| h      | l                                                    |",
+            "+--------+------------------------------------------------------+",
+            "| [2, 2] | [[1500000000, 1600000000), [1600000000, 1700000000)] |",
+            "+--------+------------------------------------------------------+",
+        ];
+        datafusion::assert_batches_sorted_eq!(expected, &result);
+    }
+
     #[tokio::test]
     async fn test_udaf_active_longest_end() {
         let mut ctx = get_datetime_context();
@@ -1,7 +1,20 @@
-use crate::{config, Repository};
-use anyhow::Result;
-use serde::Deserialize;
-use std::{fs::File, path::Path};
+use crate::{
+    auth::{self, GithubAccount, GithubToken},
+    config, http, license,
+    model::{Author, Repository, Stargazer},
+    record::{RecordFirstResponse, RecordIssue, RecordPullRequest},
+};
+use anyhow::{anyhow, Result};
+use chrono::DateTime;
+use log::{debug, info, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::Write,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
 use tokio::{task::JoinHandle, time};
 
 #[derive(Debug, Clone)]
@@ -19,26 +32,206 @@ impl GithubConfig {
             GithubConfig::Org(c) => c.destination.clone(),
         }
     }
+
+    fn issues_dir(&self) -> Option<String> {
+        match self {
+            GithubConfig::Authenticated(c) => c.issues_dir.clone(),
+            GithubConfig::User(c) => c.issues_dir.clone(),
+            GithubConfig::Org(c) => c.issues_dir.clone(),
+        }
+    }
+
+    fn token(&self) -> Result<GithubToken> {
+        match self {
+            GithubConfig::Authenticated(c) => GithubToken::personal(&c.token),
+            GithubConfig::User(c) => GithubToken::try_new(
+                &c.token,
+                c.app.clone(),
+                GithubAccount::User(c.username.clone()),
+            ),
+            GithubConfig::Org(c) => {
+                GithubToken::try_new(&c.token, c.app.clone(), GithubAccount::Org(c.org.clone()))
+            }
+        }
+    }
 }
 
 /// 从不同数据源拉取 Repository 并写入本地磁盘
 ///
-/// Fetcher Source: 目前只支持 Github
+/// Fetcher Source: 目前支持 Github、Bitbucket 以及 Gitea
 pub struct RepoFetcher {
     opts: config::FetchAction,
+    dry_run: bool,
 }
 
 impl RepoFetcher {
-    pub fn new(opts: config::FetchAction) -> Self {
-        Self { opts }
+    pub fn new(opts: config::FetchAction, dry_run: bool) -> Self {
+        Self { opts, dry_run }
+    }
+
+    /// dry-run 模式下只打印将要保存的仓库列表，不写入文件
+    fn print_repos(destination: &str, repos: &[Repository]) {
+        println!("[dry-run] '{}' => {} repos", destination, repos.len());
+        println!(
+            "{:<48} {:<10} {:>10} {:<8}",
+            "name", "visibility", "size(KB)", "archived"
+        );
+        for repo in repos {
+            println!(
+                "{:<48} {:<10} {:>10} {:<8}",
+                repo.name,
+                repo.visibility.clone().unwrap_or_default(),
+                repo.size.unwrap_or_default(),
+                repo.archived.unwrap_or_default(),
+            );
+        }
+        println!();
+    }
+
+    /// 保存仓库列表文件，dry-run 模式下只打印
+    fn save_repos(destination: &str, repos: &[Repository], dry_run: bool) -> Result<()> {
+        if dry_run {
+            Self::print_repos(destination, repos);
+            return Ok(());
+        }
+
+        let f = File::create(destination)?;
+        serde_yaml::to_writer(f, repos)?;
+        info!("save database file '{}'", destination);
+        Ok(())
+    }
+
+    /// 保存 issue.csv、pull_request.csv 以及 first_response.csv，dry-run 模式下只打印数量
+    fn save_issues(dir: &str, issues: IssueRecords, dry_run: bool) -> Result<()> {
+        let (issues, pulls, comments) = issues;
+        let responses = first_responses(&issues, &pulls, &comments);
+        if dry_run {
+            println!(
+                "[dry-run] '{}' => {} issues, {} pull requests, {} comments",
+                dir,
+                issues.len(),
+                pulls.len(),
+                comments.len()
+            );
+            return Ok(());
+        }
+
+        Self::save_records(dir, &RecordIssue::name(), issues)?;
+        Self::save_records(dir, &RecordPullRequest::name(), pulls)?;
+        Self::save_records(dir, &RecordFirstResponse::name(), responses)
+    }
+
+    /// 保存组织成员生成的作者映射文件，dry-run 模式下只打印数量
+    fn save_member_mappings(
+        path: &str,
+        org: &str,
+        members: &[GithubProfileResponse],
+        dry_run: bool,
+    ) -> Result<()> {
+        let mappings = member_mappings(members);
+        if dry_run {
+            println!(
+                "[dry-run] '{}' => {} members, {} author mappings",
+                path,
+                members.len(),
+                mappings.len()
+            );
+            return Ok(());
+        }
+
+        let mut f = File::create(path)?;
+        writeln!(
+            f,
+            "# Generated by `gitv --fetch` from the members of github org '{}', review before use",
+            org
+        )?;
+        serde_yaml::to_writer(f, &mappings)?;
+        info!("save author mappings file '{}'", path);
+        Ok(())
+    }
+
+    fn save_records<T: Serialize>(dir: &str, name: &str, records: Vec<T>) -> Result<()> {
+        let path = Path::new(dir).join(format!("{}.csv", name));
+        let mut wtr = csv::Writer::from_path(&path)?;
+        for record in records {
+            wtr.serialize(record)?;
+        }
+        wtr.flush()?;
+        info!("save {} file '{}'", name, path.display());
+        Ok(())
     }
 
     pub async fn fetch(&self) -> Result<()> {
-        self.fetch_github().await
+        http::set_user_agent(self.opts.user_agent.clone());
+        self.fetch_github().await?;
+        self.fetch_bitbucket().await?;
+        self.fetch_gitea().await
+    }
+
+    async fn fetch_gitea(&self) -> Result<()> {
+        let configs = self.opts.gitea.clone().unwrap_or_default();
+        if configs.is_empty() {
+            return Ok(());
+        }
+
+        info!("start to fetch gitea repos...");
+        let now = time::Instant::now();
+
+        let mut handles: Vec<JoinHandle<Result<(), anyhow::Error>>> = vec![];
+        for config in configs {
+            let dry_run = self.dry_run;
+            let ssh = self.opts.ssh.unwrap_or(false);
+            let handle = tokio::spawn(async move {
+                let repos = GiteaRepoFetcher::repos(&config, ssh).await?;
+                Self::save_repos(&config.destination, &repos, dry_run)
+            });
+            handles.push(handle);
+        }
+
+        for handle in handles {
+            handle.await??;
+        }
+
+        info!(
+            "[gitea]: all repos have been fetched, elapsed: {:#?}",
+            now.elapsed()
+        );
+        Ok(())
+    }
+
+    async fn fetch_bitbucket(&self) -> Result<()> {
+        let configs = self.opts.bitbucket_workspace.clone().unwrap_or_default();
+        if configs.is_empty() {
+            return Ok(());
+        }
+
+        info!("start to fetch bitbucket repos...");
+        let now = time::Instant::now();
+
+        let mut handles: Vec<JoinHandle<Result<(), anyhow::Error>>> = vec![];
+        for config in configs {
+            let dry_run = self.dry_run;
+            let ssh = self.opts.ssh.unwrap_or(false);
+            let handle = tokio::spawn(async move {
+                let repos = BitbucketRepoFetcher::workspace_repos(&config, ssh).await?;
+                Self::save_repos(&config.destination, &repos, dry_run)
+            });
+            handles.push(handle);
+        }
+
+        for handle in handles {
+            handle.await??;
+        }
+
+        info!(
+            "[bitbucket]: all repos have been fetched, elapsed: {:#?}",
+            now.elapsed()
+        );
+        Ok(())
     }
 
     async fn fetch_github(&self) -> Result<()> {
-        println!("start to fetch github repos...");
+        info!("start to fetch github repos...");
         let now = time::Instant::now();
 
         let mut configs = vec![];
@@ -55,19 +248,33 @@ impl RepoFetcher {
         let mut handles: Vec<JoinHandle<Result<(), anyhow::Error>>> = vec![];
         for config in configs {
             let config = config.clone();
+            let dry_run = self.dry_run;
+            let fetcher = GithubRepoFetcher::new(
+                self.opts.max_retries.unwrap_or(3),
+                self.opts.api.unwrap_or_default(),
+            )
+            .with_ssh(self.opts.ssh.unwrap_or(false));
             let handle = tokio::spawn(async move {
+                let token = config.token()?;
                 let repos = match config {
                     GithubConfig::Authenticated(ref config) => {
-                        GithubRepoFetcher::authenticated_repos(config).await?
+                        fetcher.authenticated_repos(config, &token).await?
                     }
-                    GithubConfig::User(ref config) => GithubRepoFetcher::user_repos(config).await?,
-                    GithubConfig::Org(ref config) => GithubRepoFetcher::org_repos(config).await?,
+                    GithubConfig::User(ref config) => fetcher.user_repos(config, &token).await?,
+                    GithubConfig::Org(ref config) => fetcher.org_repos(config, &token).await?,
                 };
 
-                let f = File::create(&config.destination())?;
-                serde_yaml::to_writer(f, &repos)?;
-                println!("save database file '{}'", &config.destination());
-                Ok(())
+                if let Some(dir) = config.issues_dir() {
+                    let issues = fetcher.issues(&repos, &token).await?;
+                    Self::save_issues(&dir, issues, dry_run)?;
+                }
+                if let GithubConfig::Org(ref org) = config {
+                    if let Some(path) = &org.member_mappings {
+                        let members = fetcher.members(&org.org, &token).await?;
+                        Self::save_member_mappings(path, &org.org, &members, dry_run)?;
+                    }
+                }
+                Self::save_repos(&config.destination(), &repos, dry_run)
             });
             handles.push(handle);
         }
@@ -76,7 +283,7 @@ impl RepoFetcher {
             handle.await??;
         }
 
-        println!(
+        info!(
             "[github]: all repos have been fetched, elapsed: {:#?}",
             now.elapsed()
         );
@@ -84,8 +291,54 @@ impl RepoFetcher {
     }
 }
 
+/// 拉取 Github 仓库的 star 时间线，按照 star 时间升序排列
+pub async fn fetch_stargazers(
+    full_name: &str,
+    config: &config::Stargazers,
+) -> Result<Vec<Stargazer>> {
+    let token = GithubToken::personal(&config.token)?;
+    let fetcher = GithubRepoFetcher::new(config.max_retries.unwrap_or(3), Default::default());
+    fetcher.stargazers(full_name, &token).await
+}
+
+/// 仓库名称以 excludeRepos 中任一前缀开头时返回 true，所有 Fetcher 共用
+fn exclude_repos_filter(exclude_repos: &[String], repo: &Repository) -> bool {
+    for excluded in exclude_repos.iter() {
+        if repo.name.starts_with(excluded) {
+            debug!("[excludeRepos] skip repo '{}' ", repo.name);
+            return true;
+        }
+    }
+    false
+}
+
 /// Github Fetcher 实现
-struct GithubRepoFetcher;
+///
+/// 请求失败（网络错误或者 5xx）时按指数退避重试，触发限流时等待至 `X-RateLimit-Reset` 后重试
+struct GithubRepoFetcher {
+    max_retries: u32,
+    api: config::GithubApiKind,
+    ssh: bool,
+}
+
+static GITHUB_ACCEPT: &str = "application/vnd.github.v3+json";
+/// 返回结果中额外包含 starred_at 字段
+static GITHUB_STAR_ACCEPT: &str = "application/vnd.github.star+json";
+
+static GITHUB_GRAPHQL_URL: &str = "https://api.github.com/graphql";
+
+/// 分页查询仓库列表，`{owner}` 以及 `{args}` 会被替换为仓库所属账户以及过滤参数
+static GITHUB_GRAPHQL_QUERY: &str = r#"query($cursor: String) {
+  owner: {owner} {
+    repositories(first: 100, after: $cursor{args}) {
+      pageInfo { hasNextPage endCursor }
+      nodes {
+        nameWithOwner url sshUrl stargazerCount forkCount visibility diskUsage isArchived isFork isPrivate
+        defaultBranchRef { name } licenseInfo { spdxId }
+      }
+    }
+  }
+}"#;
 
 enum GithubApi {
     Authenticated,
@@ -101,39 +354,447 @@ impl GithubApi {
             GithubApi::Org => format!("https://api.github.com/orgs/{}/repos", s),
         }
     }
+
+    fn graphql_owner(&self, s: &str) -> String {
+        let login = serde_json::Value::from(s);
+        match self {
+            GithubApi::Authenticated => String::from("viewer"),
+            GithubApi::User => format!("user(login: {})", login),
+            GithubApi::Org => format!("organization(login: {})", login),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 struct GithubRepoResponse {
     full_name: String,
     clone_url: String,
+    ssh_url: String,
     default_branch: String,
     forks_count: usize,
     stargazers_count: usize,
+    visibility: Option<String>,
+    size: usize,
+    archived: bool,
+    fork: bool,
+    private: bool,
+    license: Option<GithubLicenseResponse>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct GithubLicenseResponse {
+    spdx_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubStarResponse {
+    starred_at: String,
+    /// 已注销的用户为 null
+    user: Option<GithubUserResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubUserResponse {
+    login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubIssueResponse {
+    number: u64,
+    state: String,
+    user: Option<GithubUserResponse>,
+    labels: Vec<GithubLabelResponse>,
+    comments: u64,
+    created_at: String,
+    closed_at: Option<String>,
+    /// 只有 pull request 才会返回该字段
+    pull_request: Option<GithubIssuePullResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubCommentResponse {
+    /// 形如 `https://api.github.com/repos/{owner}/{repo}/issues/{number}`
+    issue_url: String,
+    user: Option<GithubUserResponse>,
+    created_at: String,
+    author_association: String,
+}
+
+/// issue 以及 pull request 下的评论
+#[derive(Debug, Clone, Default)]
+struct IssueComment {
+    repo_name: String,
+    number: u64,
+    user: String,
+    created_at: String,
+    /// 评论者为仓库的 owner、组织成员或者协作者
+    maintainer: bool,
+}
+
+type IssueRecords = (Vec<RecordIssue>, Vec<RecordPullRequest>, Vec<IssueComment>);
+
+/// 计算每个 issue/pull request 的首次维护者回复
+///
+/// 维护者回复为 owner、组织成员或者协作者（不包括作者本人以及 bot）在创建之后的第一条评论，
+/// 只统计 issue 页面下的评论，不包括 pull request 的 review
+fn first_responses(
+    issues: &[RecordIssue],
+    pulls: &[RecordPullRequest],
+    comments: &[IssueComment],
+) -> Vec<RecordFirstResponse> {
+    let mut records = issues
+        .iter()
+        .map(|i| RecordFirstResponse {
+            repo_name: i.repo_name.clone(),
+            number: i.number,
+            kind: RecordIssue::name(),
+            author: i.author.clone(),
+            created_at: i.created_at.clone(),
+            ..Default::default()
+        })
+        .chain(pulls.iter().map(|p| RecordFirstResponse {
+            repo_name: p.repo_name.clone(),
+            number: p.number,
+            kind: RecordPullRequest::name(),
+            author: p.author.clone(),
+            created_at: p.created_at.clone(),
+            ..Default::default()
+        }))
+        .collect::<Vec<_>>();
+
+    // github 返回的时间均为 UTC，可以直接按字符串比较
+    let mut firsts: HashMap<(&str, &str, &str), (&str, u64)> = HashMap::new();
+    for r in records.iter() {
+        let first = firsts
+            .entry((r.repo_name.as_str(), r.kind.as_str(), r.author.as_str()))
+            .or_insert((r.created_at.as_str(), r.number));
+        if (r.created_at.as_str(), r.number) < *first {
+            *first = (r.created_at.as_str(), r.number);
+        }
+    }
+    let firsts = firsts
+        .into_iter()
+        .map(|((repo, kind, _), (_, number))| (repo.to_string(), kind.to_string(), number))
+        .collect::<HashSet<_>>();
+
+    let mut responses: HashMap<(&str, u64), Vec<&IssueComment>> = HashMap::new();
+    for c in comments
+        .iter()
+        .filter(|c| c.maintainer && !c.user.ends_with("[bot]"))
+    {
+        responses
+            .entry((c.repo_name.as_str(), c.number))
+            .or_default()
+            .push(c);
+    }
+
+    for r in records.iter_mut() {
+        r.is_first_time = firsts.contains(&(r.repo_name.clone(), r.kind.clone(), r.number));
+        let response = responses
+            .get(&(r.repo_name.as_str(), r.number))
+            .into_iter()
+            .flatten()
+            .filter(|c| c.user != r.author && c.created_at >= r.created_at)
+            .min_by(|a, b| a.created_at.cmp(&b.created_at));
+        if let Some(c) = response {
+            r.responder = c.user.clone();
+            r.responded_at = c.created_at.clone();
+            r.response_hours = match (
+                DateTime::parse_from_rfc3339(&r.created_at),
+                DateTime::parse_from_rfc3339(&c.created_at),
+            ) {
+                (Ok(created), Ok(responded)) => {
+                    let hours = (responded - created).num_seconds() as f64 / 3600.0;
+                    Some((hours * 100.0).round() / 100.0)
+                }
+                _ => None,
+            };
+        }
+    }
+    records
+}
+
+/// `/users/{login}` 返回的用户资料，未公开时 name 以及 email 为 null
+#[derive(Debug, Clone, Default, Deserialize)]
+struct GithubProfileResponse {
+    login: String,
+    name: Option<String>,
+    email: Option<String>,
+}
+
+/// 作者映射规则，与 create 中 authorMappings 的格式一致
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct MemberMapping {
+    source: MemberSource,
+    destination: Author,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+struct MemberSource {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+}
+
+/// 根据组织成员的资料生成作者映射，映射到成员的公开名称以及邮箱（未公开时使用 login 以及 noreply 邮箱）
+///
+/// 匹配的提交：noreply 邮箱（`[id+]login@users.noreply.github.com`）、成员公开的邮箱以及名称为 login 的提交
+fn member_mappings(members: &[GithubProfileResponse]) -> Vec<MemberMapping> {
+    let mut mappings = vec![];
+    for member in members {
+        let noreply = format!("{}@users.noreply.github.com", member.login);
+        let destination = Author {
+            name: member
+                .name
+                .clone()
+                .filter(|name| !name.trim().is_empty())
+                .unwrap_or_else(|| member.login.clone()),
+            email: member
+                .email
+                .clone()
+                .filter(|email| !email.trim().is_empty())
+                .unwrap_or_else(|| noreply.clone()),
+        };
+        let email = |pattern: String| MemberSource {
+            email: Some(pattern),
+            ..Default::default()
+        };
+
+        let mut sources = vec![email(format!(
+            "regex:(?i)^(\\d+\\+)?{}$",
+            regex::escape(&noreply)
+        ))];
+        if destination.email != noreply {
+            sources.push(email(format!(
+                "regex:(?i)^{}$",
+                regex::escape(&destination.email)
+            )));
+        }
+        sources.push(MemberSource {
+            name: Some(member.login.clone()),
+            ..Default::default()
+        });
+        mappings.extend(sources.into_iter().map(|source| MemberMapping {
+            source,
+            destination: destination.clone(),
+        }));
+    }
+    mappings
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubLabelResponse {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubIssuePullResponse {
+    merged_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphqlResponse<T> {
+    data: Option<T>,
+    errors: Option<Vec<GraphqlError>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphqlError {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphqlOwnerData {
+    owner: Option<GraphqlOwner>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphqlOwner {
+    repositories: GraphqlRepoConnection,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphqlRepoConnection {
+    page_info: GraphqlPageInfo,
+    nodes: Vec<GraphqlRepo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphqlPageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphqlRepo {
+    name_with_owner: String,
+    url: String,
+    ssh_url: String,
+    stargazer_count: usize,
+    fork_count: usize,
+    visibility: String,
+    disk_usage: Option<usize>,
+    is_archived: bool,
+    is_fork: bool,
+    is_private: bool,
+    default_branch_ref: Option<GraphqlRef>,
+    license_info: Option<GraphqlLicense>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphqlLicense {
+    spdx_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphqlRef {
+    name: String,
+}
+
+impl From<GraphqlRepo> for GithubRepoResponse {
+    fn from(repo: GraphqlRepo) -> Self {
+        GithubRepoResponse {
+            full_name: repo.name_with_owner,
+            clone_url: format!("{}.git", repo.url),
+            ssh_url: repo.ssh_url,
+            default_branch: repo.default_branch_ref.map(|r| r.name).unwrap_or_default(),
+            forks_count: repo.fork_count,
+            stargazers_count: repo.stargazer_count,
+            visibility: Some(repo.visibility.to_lowercase()),
+            size: repo.disk_usage.unwrap_or_default(),
+            archived: repo.is_archived,
+            fork: repo.is_fork,
+            private: repo.is_private,
+            license: repo
+                .license_info
+                .map(|l| GithubLicenseResponse { spdx_id: l.spdx_id }),
+        }
+    }
 }
 
 impl GithubRepoFetcher {
-    fn exclude_orgs_filter(exclude_orgs: &[String], repo: &Repository) -> bool {
-        for excluded in exclude_orgs.iter() {
-            if repo.name.starts_with(excluded) {
-                println!("[excludeOrgs] skip repo '{}' ", repo.name);
-                return true;
+    fn new(max_retries: u32, api: config::GithubApiKind) -> Self {
+        Self {
+            max_retries,
+            api,
+            ssh: false,
+        }
+    }
+
+    /// 生成的仓库列表使用 ssh 地址作为 remote
+    fn with_ssh(mut self, ssh: bool) -> Self {
+        self.ssh = ssh;
+        self
+    }
+
+    fn header_u64(response: &reqwest::Response, name: &str) -> Option<u64> {
+        response.headers().get(name)?.to_str().ok()?.parse().ok()
+    }
+
+    /// 距离限流重置的等待时长，剩余请求数不为 0 时返回 None
+    fn rate_limit_wait(response: &reqwest::Response) -> Option<time::Duration> {
+        // secondary rate limit 会返回 Retry-After
+        if let Some(secs) = Self::header_u64(response, "Retry-After") {
+            return Some(time::Duration::from_secs(secs));
+        }
+        if Self::header_u64(response, "X-RateLimit-Remaining")? > 0 {
+            return None;
+        }
+        let reset = Self::header_u64(response, "X-RateLimit-Reset")?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+        Some(time::Duration::from_secs(reset.saturating_sub(now) + 1))
+    }
+
+    fn backoff(attempt: u32) -> time::Duration {
+        time::Duration::from_secs(2u64.pow(attempt.min(6)))
+    }
+
+    /// 发送请求，body 不为 None 时使用 POST 请求（GraphQL），否则使用 GET 请求
+    async fn request<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        params: &[(&str, String)],
+        body: Option<&serde_json::Value>,
+        accept: &str,
+        token: &GithubToken,
+    ) -> Result<T> {
+        let mut attempt = 0;
+        loop {
+            let client = http::client()?;
+            let builder = match body {
+                Some(body) => client.post(url).json(body),
+                None => client.get(url).query(params),
+            };
+            let result = builder
+                .bearer_auth(token.token().await?)
+                .header("Accept", accept)
+                .send()
+                .await;
+
+            let (wait, err) = match result {
+                Err(e) => (Self::backoff(attempt), anyhow!(e)),
+                Ok(response) => {
+                    http::log_response(&response);
+                    let status = response.status();
+                    if status.is_success() {
+                        // 本次请求已经耗尽配额，等待重置后再请求下一页
+                        if let Some(wait) = Self::rate_limit_wait(&response) {
+                            warn!("[github]: rate limit exhausted, sleep {:#?}", wait);
+                            time::sleep(wait).await;
+                        }
+                        return http::json::<T>(response).await;
+                    }
+
+                    let limited = status == reqwest::StatusCode::FORBIDDEN
+                        || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+                    match Self::rate_limit_wait(&response) {
+                        Some(wait) if limited => (wait, anyhow!("rate limited: {}", status)),
+                        _ if status.is_server_error() => {
+                            (Self::backoff(attempt), anyhow!("server error: {}", status))
+                        }
+                        _ => {
+                            let body = response.text().await.unwrap_or_default();
+                            return Err(anyhow!("request '{}' failed: {} {}", url, status, body));
+                        }
+                    }
+                }
+            };
+
+            if attempt >= self.max_retries {
+                return Err(err.context(format!(
+                    "request '{}' failed after {} retries",
+                    url, self.max_retries
+                )));
             }
+            attempt += 1;
+            warn!(
+                "[github]: {}, retry {}/{} after {:#?}",
+                err, attempt, self.max_retries, wait
+            );
+            time::sleep(wait).await;
         }
-        false
     }
 
-    fn exclude_repos_filter(exclude_repos: &[String], repo: &Repository) -> bool {
-        for excluded in exclude_repos.iter() {
+    fn exclude_orgs_filter(exclude_orgs: &[String], repo: &Repository) -> bool {
+        for excluded in exclude_orgs.iter() {
             if repo.name.starts_with(excluded) {
-                println!("[excludeRepos] skip repo '{}' ", repo.name);
+                debug!("[excludeOrgs] skip repo '{}' ", repo.name);
                 return true;
             }
         }
         false
     }
 
-    async fn authenticated_repos(config: &config::GithubAuthenticated) -> Result<Vec<Repository>> {
+    async fn authenticated_repos(
+        &self,
+        config: &config::GithubAuthenticated,
+        token: &GithubToken,
+    ) -> Result<Vec<Repository>> {
         let visibility = config.visibility.clone();
         let affiliation = config.affiliation.clone();
         let params = vec![
@@ -142,12 +803,13 @@ impl GithubRepoFetcher {
         ];
         let api = GithubApi::Authenticated;
 
-        let repos = Self::repositories(&config.clone_dir, params, &api.url(""), &config.token)
+        let repos = self
+            .fetch_repos(&config.clone_dir, api, "", params, token)
             .await?
             .into_iter()
             .filter(|repo| {
                 !(Self::exclude_orgs_filter(&config.clone().exclude_orgs.unwrap_or_default(), repo)
-                    || Self::exclude_repos_filter(
+                    || exclude_repos_filter(
                         &config.clone().exclude_repos.unwrap_or_default(),
                         repo,
                     ))
@@ -157,75 +819,535 @@ impl GithubRepoFetcher {
         Ok(repos)
     }
 
-    async fn org_repos(config: &config::GithubOrg) -> Result<Vec<Repository>> {
+    async fn org_repos(
+        &self,
+        config: &config::GithubOrg,
+        token: &GithubToken,
+    ) -> Result<Vec<Repository>> {
         let params = vec![("type", config.typ.clone())];
         let api = GithubApi::Org;
 
-        let repos = Self::repositories(
-            &config.clone_dir,
-            params,
-            &api.url(&config.org),
-            &config.token,
-        )
-        .await?
-        .into_iter()
-        .filter(|repo| {
-            !Self::exclude_repos_filter(&config.clone().exclude_repos.unwrap_or_default(), repo)
-        })
-        .collect::<Vec<_>>();
+        let repos = self
+            .fetch_repos(&config.clone_dir, api, &config.org, params, token)
+            .await?
+            .into_iter()
+            .filter(|repo| {
+                !exclude_repos_filter(&config.clone().exclude_repos.unwrap_or_default(), repo)
+            })
+            .collect::<Vec<_>>();
 
         Ok(repos)
     }
 
-    async fn user_repos(config: &config::GithubUser) -> Result<Vec<Repository>> {
+    async fn user_repos(
+        &self,
+        config: &config::GithubUser,
+        token: &GithubToken,
+    ) -> Result<Vec<Repository>> {
         let params = vec![("type", config.typ.clone())];
         let api = GithubApi::User;
 
-        let repos = Self::repositories(
-            &config.clone_dir,
-            params,
-            &api.url(&config.username),
-            &config.token,
-        )
-        .await?
-        .into_iter()
-        .filter(|repo| {
-            !Self::exclude_repos_filter(&config.clone().exclude_repos.unwrap_or_default(), repo)
-        })
-        .collect::<Vec<_>>();
+        let repos = self
+            .fetch_repos(&config.clone_dir, api, &config.username, params, token)
+            .await?
+            .into_iter()
+            .filter(|repo| {
+                !exclude_repos_filter(&config.clone().exclude_repos.unwrap_or_default(), repo)
+            })
+            .collect::<Vec<_>>();
 
         Ok(repos)
     }
 
     async fn repositories(
+        &self,
         clone_dir: &str,
         params: Vec<(&str, String)>,
         url: &str,
-        token: &str,
+        token: &GithubToken,
     ) -> Result<Vec<Repository>> {
         let mut finish = false;
         let mut page: u16 = 1;
         let mut repos = vec![];
 
         while !finish {
-            println!("fetching github repos page: {}", page);
+            debug!("fetching github repos page: {}", page);
             let mut params = params.clone();
             params.push(("per_page", "100".to_string()));
             params.push(("page", page.to_string()));
 
-            let response = reqwest::Client::new()
-                .get(url)
+            let response: Vec<GithubRepoResponse> = self
+                .request(url, &params, None, GITHUB_ACCEPT, token)
+                .await?;
+
+            page += 1;
+            if response.len() < 100 {
+                finish = true
+            }
+
+            for repo in response {
+                repos.push(self.repository(clone_dir, repo));
+            }
+        }
+
+        info!("[github]: fetch total {} repos", repos.len());
+        Ok(repos)
+    }
+
+    /// 拉取仓库所有的 star 记录，full_name 格式为 `owner/repo`
+    async fn stargazers(&self, full_name: &str, token: &GithubToken) -> Result<Vec<Stargazer>> {
+        let url = format!("https://api.github.com/repos/{}/stargazers", full_name);
+        let mut page: u16 = 1;
+        let mut stargazers = vec![];
+        loop {
+            let params = vec![("per_page", "100".to_string()), ("page", page.to_string())];
+            let response: Vec<GithubStarResponse> = self
+                .request(&url, &params, None, GITHUB_STAR_ACCEPT, token)
+                .await?;
+
+            page += 1;
+            let finish = response.len() < 100;
+            for star in response {
+                stargazers.push(Stargazer {
+                    user: star.user.map(|u| u.login).unwrap_or_default(),
+                    starred_at: star.starred_at,
+                });
+            }
+            if finish {
+                break;
+            }
+        }
+        Ok(stargazers)
+    }
+
+    /// 拉取组织成员列表以及每个成员的公开资料（名称以及邮箱）
+    async fn members(&self, org: &str, token: &GithubToken) -> Result<Vec<GithubProfileResponse>> {
+        let url = format!("https://api.github.com/orgs/{}/members", org);
+        let mut logins = vec![];
+        let mut page: u16 = 1;
+        loop {
+            debug!("fetching github members of '{}' page: {}", org, page);
+            let params = vec![("per_page", "100".to_string()), ("page", page.to_string())];
+            let response: Vec<GithubUserResponse> = self
+                .request(&url, &params, None, GITHUB_ACCEPT, token)
+                .await?;
+
+            page += 1;
+            let finish = response.len() < 100;
+            logins.extend(response.into_iter().map(|u| u.login));
+            if finish {
+                break;
+            }
+        }
+
+        let mut members = vec![];
+        for login in logins {
+            let url = format!("https://api.github.com/users/{}", login);
+            members.push(self.request(&url, &[], None, GITHUB_ACCEPT, token).await?);
+        }
+        info!("[github]: org '{}' has {} members", org, members.len());
+        Ok(members)
+    }
+
+    /// 拉取仓库下所有 issue 以及 pull request 的评论，用于计算首次维护者回复
+    async fn comments(&self, repo: &Repository, token: &GithubToken) -> Result<Vec<IssueComment>> {
+        let url = format!("https://api.github.com/repos/{}/issues/comments", repo.name);
+        let mut comments = vec![];
+        let mut page: u16 = 1;
+        loop {
+            debug!("fetching github comments of '{}' page: {}", repo.name, page);
+            let params = vec![
+                ("sort", "created".to_string()),
+                ("direction", "asc".to_string()),
+                ("per_page", "100".to_string()),
+                ("page", page.to_string()),
+            ];
+            let response: Vec<GithubCommentResponse> = self
+                .request(&url, &params, None, GITHUB_ACCEPT, token)
+                .await?;
+
+            page += 1;
+            let finish = response.len() < 100;
+            for comment in response {
+                let number = comment.issue_url.rsplit('/').next().unwrap_or_default();
+                comments.push(IssueComment {
+                    repo_name: repo.name.clone(),
+                    number: number.parse().unwrap_or_default(),
+                    user: comment.user.map(|u| u.login).unwrap_or_default(),
+                    created_at: comment.created_at,
+                    maintainer: matches!(
+                        comment.author_association.as_str(),
+                        "OWNER" | "MEMBER" | "COLLABORATOR"
+                    ),
+                });
+            }
+            if finish {
+                break;
+            }
+        }
+        Ok(comments)
+    }
+
+    /// 拉取所有仓库的 issues、pull requests 以及评论，issues 接口同时返回 issue 以及 pull request，通过 pull_request 字段区分
+    async fn issues(&self, repos: &[Repository], token: &GithubToken) -> Result<IssueRecords> {
+        let mut issues = vec![];
+        let mut pulls = vec![];
+        let mut comments = vec![];
+        for repo in repos {
+            comments.extend(self.comments(repo, token).await?);
+            let url = format!("https://api.github.com/repos/{}/issues", repo.name);
+            let mut page: u16 = 1;
+            loop {
+                debug!("fetching github issues of '{}' page: {}", repo.name, page);
+                let params = vec![
+                    ("state", "all".to_string()),
+                    ("per_page", "100".to_string()),
+                    ("page", page.to_string()),
+                ];
+                let response: Vec<GithubIssueResponse> = self
+                    .request(&url, &params, None, GITHUB_ACCEPT, token)
+                    .await?;
+
+                page += 1;
+                let finish = response.len() < 100;
+                for issue in response {
+                    let author = issue.user.map(|u| u.login).unwrap_or_default();
+                    let labels = issue
+                        .labels
+                        .into_iter()
+                        .map(|l| l.name)
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    match issue.pull_request {
+                        Some(pull) => pulls.push(RecordPullRequest {
+                            repo_name: repo.name.clone(),
+                            number: issue.number,
+                            author,
+                            state: issue.state,
+                            labels,
+                            comments: issue.comments,
+                            created_at: issue.created_at,
+                            closed_at: issue.closed_at.unwrap_or_default(),
+                            merged_at: pull.merged_at.unwrap_or_default(),
+                        }),
+                        None => issues.push(RecordIssue {
+                            repo_name: repo.name.clone(),
+                            number: issue.number,
+                            author,
+                            state: issue.state,
+                            labels,
+                            comments: issue.comments,
+                            created_at: issue.created_at,
+                            closed_at: issue.closed_at.unwrap_or_default(),
+                        }),
+                    }
+                }
+                if finish {
+                    break;
+                }
+            }
+        }
+
+        info!(
+            "[github]: fetch total {} issues, {} pull requests, {} comments",
+            issues.len(),
+            pulls.len(),
+            comments.len()
+        );
+        Ok((issues, pulls, comments))
+    }
+
+    fn repository(&self, clone_dir: &str, repo: GithubRepoResponse) -> Repository {
+        let name = repo.full_name;
+        let remote = match self.ssh {
+            true => repo.ssh_url,
+            false => repo.clone_url,
+        };
+        Repository {
+            name: name.clone(),
+            branch: Some(repo.default_branch),
+            reference: None,
+            remote: Some(remote),
+            path: Path::new(clone_dir)
+                .join(Path::new(&name))
+                .to_str()
+                .unwrap()
+                .to_string(),
+            forks_count: Some(repo.forks_count),
+            stargazers_count: Some(repo.stargazers_count),
+            visibility: repo.visibility,
+            size: Some(repo.size),
+            archived: Some(repo.archived),
+            fork: Some(repo.fork),
+            private: Some(repo.private),
+            // 无法识别的许可证交由分析时从 LICENSE 文件中识别
+            license: repo
+                .license
+                .and_then(|l| l.spdx_id)
+                .filter(|id| id != license::NOASSERTION),
+            exclude_paths: Default::default(),
+            credential: None,
+        }
+    }
+
+    /// 根据配置的 api 选择 REST 或者 GraphQL 拉取仓库列表
+    async fn fetch_repos(
+        &self,
+        clone_dir: &str,
+        api: GithubApi,
+        account: &str,
+        params: Vec<(&str, String)>,
+        token: &GithubToken,
+    ) -> Result<Vec<Repository>> {
+        match self.api {
+            config::GithubApiKind::Rest => {
+                self.repositories(clone_dir, params, &api.url(account), token)
+                    .await
+            }
+            config::GithubApiKind::Graphql => {
+                let query = GITHUB_GRAPHQL_QUERY
+                    .replace("{owner}", &api.graphql_owner(account))
+                    .replace("{args}", &Self::graphql_args(&params));
+                self.graphql_repositories(clone_dir, &query, token).await
+            }
+        }
+    }
+
+    /// 将 REST API 的过滤参数转换为 GraphQL 的 repositories 参数
+    fn graphql_args(params: &[(&str, String)]) -> String {
+        let enums = |value: &str| {
+            value
+                .split(',')
+                .map(|s| s.trim().to_uppercase())
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let mut args = vec![];
+        for (key, value) in params {
+            match (*key, value.as_str()) {
+                ("visibility" | "type", "public" | "private") => {
+                    args.push(format!("privacy: {}", value.to_uppercase()))
+                }
+                ("affiliation", affiliation) if !affiliation.is_empty() => {
+                    args.push(format!("affiliations: [{}]", enums(affiliation)));
+                    args.push(format!("ownerAffiliations: [{}]", enums(affiliation)));
+                }
+                ("type", "owner") => args.push(String::from("ownerAffiliations: [OWNER]")),
+                ("type", "member") => args.push(String::from(
+                    "ownerAffiliations: [COLLABORATOR, ORGANIZATION_MEMBER]",
+                )),
+                ("type", "forks") => args.push(String::from("isFork: true")),
+                ("type", "sources") => args.push(String::from("isFork: false")),
+                _ => {}
+            }
+        }
+        args.iter().map(|arg| format!(", {}", arg)).collect()
+    }
+
+    async fn graphql_repositories(
+        &self,
+        clone_dir: &str,
+        query: &str,
+        token: &GithubToken,
+    ) -> Result<Vec<Repository>> {
+        let mut cursor: Option<String> = None;
+        let mut page: u16 = 1;
+        let mut repos = vec![];
+
+        loop {
+            debug!("fetching github repos page: {} (graphql)", page);
+            let body = serde_json::json!({
+                "query": query,
+                "variables": { "cursor": cursor },
+            });
+            let response: GraphqlResponse<GraphqlOwnerData> = self
+                .request(GITHUB_GRAPHQL_URL, &[], Some(&body), GITHUB_ACCEPT, token)
+                .await?;
+            if let Some(errors) = response.errors {
+                let messages = errors.into_iter().map(|e| e.message).collect::<Vec<_>>();
+                return Err(anyhow!("graphql query failed: {}", messages.join("; ")));
+            }
+            let connection = response
+                .data
+                .and_then(|data| data.owner)
+                .ok_or_else(|| anyhow!("graphql query returned no repository owner"))?
+                .repositories;
+
+            page += 1;
+            for repo in connection.nodes {
+                repos.push(self.repository(clone_dir, repo.into()));
+            }
+            match connection.page_info.end_cursor {
+                Some(end) if connection.page_info.has_next_page => cursor = Some(end),
+                _ => break,
+            }
+        }
+
+        info!("[github]: fetch total {} repos", repos.len());
+        Ok(repos)
+    }
+}
+
+/// Bitbucket Fetcher 实现
+struct BitbucketRepoFetcher;
+
+#[derive(Debug, Deserialize, Clone)]
+struct BitbucketPageResponse {
+    values: Vec<BitbucketRepoResponse>,
+    next: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct BitbucketRepoResponse {
+    full_name: String,
+    is_private: bool,
+    size: usize,
+    mainbranch: Option<BitbucketBranch>,
+    links: BitbucketLinks,
+    /// fork 仓库才会返回 parent 字段
+    parent: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct BitbucketBranch {
+    name: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct BitbucketLinks {
+    clone: Vec<BitbucketCloneLink>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct BitbucketCloneLink {
+    name: String,
+    href: String,
+}
+
+impl BitbucketRepoFetcher {
+    async fn workspace_repos(
+        config: &config::BitbucketWorkspace,
+        ssh: bool,
+    ) -> Result<Vec<Repository>> {
+        let mut url = Some(format!(
+            "https://api.bitbucket.org/2.0/repositories/{}?pagelen=100",
+            config.workspace
+        ));
+        let mut repos = vec![];
+        let app_password = auth::resolve_secret(&config.app_password)?;
+
+        let mut page: u16 = 1;
+        while let Some(u) = url {
+            debug!("fetching bitbucket repos page: {}", page);
+            let response = http::client()?
+                .get(&u)
+                .basic_auth(&config.username, Some(&app_password))
+                .send()
+                .await?;
+            http::log_response(&response);
+            let response = http::json::<BitbucketPageResponse>(response).await?;
+
+            page += 1;
+            url = response.next;
+            for repo in response.values {
+                let name = repo.full_name;
+                let remote = repo
+                    .links
+                    .clone
+                    .into_iter()
+                    .find(|link| link.name == if ssh { "ssh" } else { "https" })
+                    .map(|link| link.href);
+                repos.push(Repository {
+                    name: name.clone(),
+                    branch: repo.mainbranch.map(|b| b.name),
+                    reference: None,
+                    remote,
+                    path: Path::new(&config.clone_dir)
+                        .join(Path::new(&name))
+                        .to_str()
+                        .unwrap()
+                        .to_string(),
+                    forks_count: None,
+                    stargazers_count: None,
+                    visibility: Some(String::from(if repo.is_private {
+                        "private"
+                    } else {
+                        "public"
+                    })),
+                    // Bitbucket 返回的 size 单位为 bytes，与 Github 保持一致使用 KB
+                    size: Some(repo.size / 1024),
+                    archived: None,
+                    fork: Some(repo.parent.is_some()),
+                    private: Some(repo.is_private),
+                    license: None,
+                    exclude_paths: Default::default(),
+                    credential: None,
+                });
+            }
+        }
+
+        let exclude_repos = config.exclude_repos.clone().unwrap_or_default();
+        let repos = repos
+            .into_iter()
+            .filter(|repo| !exclude_repos_filter(&exclude_repos, repo))
+            .collect::<Vec<_>>();
+
+        info!("[bitbucket]: fetch total {} repos", repos.len());
+        Ok(repos)
+    }
+}
+
+/// Gitea Fetcher 实现
+struct GiteaRepoFetcher;
+
+#[derive(Debug, Deserialize, Clone)]
+struct GiteaRepoResponse {
+    full_name: String,
+    clone_url: String,
+    ssh_url: String,
+    default_branch: String,
+    forks_count: usize,
+    stars_count: usize,
+    private: bool,
+    size: usize,
+    archived: bool,
+    fork: bool,
+}
+
+impl GiteaRepoFetcher {
+    fn url(config: &config::Gitea) -> String {
+        let base = config.base_url.trim_end_matches('/');
+        match (&config.org, &config.user) {
+            (Some(org), _) => format!("{}/api/v1/orgs/{}/repos", base, org),
+            (None, Some(user)) => format!("{}/api/v1/users/{}/repos", base, user),
+            (None, None) => format!("{}/api/v1/user/repos", base),
+        }
+    }
+
+    async fn repos(config: &config::Gitea, ssh: bool) -> Result<Vec<Repository>> {
+        const LIMIT: usize = 50;
+        let url = Self::url(config);
+        let mut finish = false;
+        let mut page: u16 = 1;
+        let mut repos = vec![];
+        let token = auth::resolve_secret(&config.token)?;
+
+        while !finish {
+            debug!("fetching gitea repos page: {}", page);
+            let params = vec![("limit", LIMIT.to_string()), ("page", page.to_string())];
+            let response = http::client()?
+                .get(&url)
                 .query(&params)
-                .bearer_auth(token)
-                .header("User-Agent", "rust/reqwest")
-                .header("Accept", "application/vnd.github.v3+json")
+                .header("Authorization", format!("token {}", token))
                 .send()
-                .await?
-                .json::<Vec<GithubRepoResponse>>()
                 .await?;
+            http::log_response(&response);
+            let response = http::json::<Vec<GiteaRepoResponse>>(response).await?;
 
             page += 1;
-            if response.len() < 100 {
+            if response.len() < LIMIT {
                 finish = true
             }
 
@@ -234,19 +1356,190 @@ impl GithubRepoFetcher {
                 repos.push(Repository {
                     name: name.clone(),
                     branch: Some(repo.default_branch),
-                    remote: Some(repo.clone_url),
-                    path: Path::new(clone_dir)
+                    reference: None,
+                    remote: Some(if ssh { repo.ssh_url } else { repo.clone_url }),
+                    path: Path::new(&config.clone_dir)
                         .join(Path::new(&name))
                         .to_str()
                         .unwrap()
                         .to_string(),
                     forks_count: Some(repo.forks_count),
-                    stargazers_count: Some(repo.stargazers_count),
+                    stargazers_count: Some(repo.stars_count),
+                    visibility: Some(String::from(if repo.private {
+                        "private"
+                    } else {
+                        "public"
+                    })),
+                    size: Some(repo.size),
+                    archived: Some(repo.archived),
+                    fork: Some(repo.fork),
+                    private: Some(repo.private),
+                    license: None,
+                    exclude_paths: Default::default(),
+                    credential: None,
                 });
             }
         }
 
-        println!("[github]: fetch total {} repos", repos.len());
+        let exclude_repos = config.exclude_repos.clone().unwrap_or_default();
+        let repos = repos
+            .into_iter()
+            .filter(|repo| !exclude_repos_filter(&exclude_repos, repo))
+            .collect::<Vec<_>>();
+
+        info!("[gitea]: fetch total {} repos", repos.len());
         Ok(repos)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_member_mappings() {
+        let members = vec![
+            GithubProfileResponse {
+                login: String::from("octocat"),
+                name: Some(String::from("The Octocat")),
+                email: Some(String::from("Octocat@GitHub.com")),
+            },
+            GithubProfileResponse {
+                login: String::from("hubot"),
+                name: Some(String::from(" ")),
+                email: None,
+            },
+        ];
+        let mappings = member_mappings(&members);
+        assert_eq!(mappings.len(), 5);
+        assert_eq!(
+            mappings[0].destination,
+            Author {
+                name: String::from("The Octocat"),
+                email: String::from("Octocat@GitHub.com"),
+            }
+        );
+        assert_eq!(mappings[2].source.name.as_deref(), Some("octocat"));
+        assert_eq!(
+            mappings[3].destination,
+            Author {
+                name: String::from("hubot"),
+                email: String::from("hubot@users.noreply.github.com"),
+            }
+        );
+
+        let content = serde_yaml::to_string(&mappings).unwrap();
+        let parsed: Vec<config::AuthorMapping> = serde_yaml::from_str(&content).unwrap();
+        let author = |name: &str, email: &str| Author {
+            name: name.to_string(),
+            email: email.to_string(),
+        };
+        let matched = |author: &Author| {
+            parsed
+                .iter()
+                .find(|m| m.source.matches(author))
+                .map(|m| m.destination.name.clone())
+        };
+        assert_eq!(
+            matched(&author("cat", "583231+octocat@users.noreply.github.com")),
+            Some(String::from("The Octocat"))
+        );
+        assert_eq!(
+            matched(&author("cat", "octocat@github.com")),
+            Some(String::from("The Octocat"))
+        );
+        assert_eq!(
+            matched(&author("hubot", "hubot@example.com")),
+            Some(String::from("hubot"))
+        );
+        assert_eq!(
+            matched(&author("cat", "xoctocat@users.noreply.github.com")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_first_responses() {
+        let pull = |number: u64, author: &str, created_at: &str| RecordPullRequest {
+            repo_name: String::from("a/b"),
+            number,
+            author: author.to_string(),
+            created_at: created_at.to_string(),
+            ..Default::default()
+        };
+        let comment = |number: u64, user: &str, created_at: &str, maintainer: bool| IssueComment {
+            repo_name: String::from("a/b"),
+            number,
+            user: user.to_string(),
+            created_at: created_at.to_string(),
+            maintainer,
+        };
+        let pulls = vec![
+            pull(1, "newcomer", "2022-03-01T00:00:00Z"),
+            pull(2, "newcomer", "2022-03-05T00:00:00Z"),
+            pull(3, "owner", "2022-03-02T00:00:00Z"),
+        ];
+        let issues = vec![RecordIssue {
+            repo_name: String::from("a/b"),
+            number: 4,
+            author: String::from("newcomer"),
+            created_at: String::from("2022-03-06T00:00:00Z"),
+            ..Default::default()
+        }];
+        let comments = vec![
+            comment(1, "newcomer", "2022-03-01T01:00:00Z", false),
+            comment(1, "dependabot[bot]", "2022-03-01T02:00:00Z", true),
+            comment(1, "owner", "2022-03-02T12:30:00Z", true),
+            comment(1, "member", "2022-03-03T00:00:00Z", true),
+            comment(3, "owner", "2022-03-02T01:00:00Z", true),
+        ];
+
+        let responses = first_responses(&issues, &pulls, &comments);
+        let find = |number: u64| responses.iter().find(|r| r.number == number).unwrap();
+        assert_eq!(responses.len(), 4);
+        assert_eq!(
+            find(1),
+            &RecordFirstResponse {
+                repo_name: String::from("a/b"),
+                number: 1,
+                kind: String::from("pull_request"),
+                author: String::from("newcomer"),
+                is_first_time: true,
+                created_at: String::from("2022-03-01T00:00:00Z"),
+                responder: String::from("owner"),
+                responded_at: String::from("2022-03-02T12:30:00Z"),
+                response_hours: Some(36.5),
+            }
+        );
+        assert!(!find(2).is_first_time);
+        assert_eq!(find(2).response_hours, None);
+        assert!(find(3).is_first_time);
+        assert_eq!(find(3).responder, "");
+        assert_eq!(find(4).kind, "issue");
+        assert!(find(4).is_first_time);
+    }
+
+    #[test]
+    fn test_graphql_args() {
+        let params = vec![
+            ("visibility", String::from("private")),
+            ("affiliation", String::from("owner,organization_member")),
+        ];
+        assert_eq!(
+            GithubRepoFetcher::graphql_args(&params),
+            ", privacy: PRIVATE, affiliations: [OWNER, ORGANIZATION_MEMBER], ownerAffiliations: [OWNER, ORGANIZATION_MEMBER]"
+        );
+        assert_eq!(
+            GithubRepoFetcher::graphql_args(&[("type", String::from("sources"))]),
+            ", isFork: false"
+        );
+        assert_eq!(
+            GithubRepoFetcher::graphql_args(&[("type", String::new())]),
+            ""
+        );
+        assert_eq!(
+            GithubApi::Org.graphql_owner("pyecharts"),
+            r#"organization(login: "pyecharts")"#
+        );
+    }
+}
@@ -1,129 +1,527 @@
-use crate::{config::AuthorMapping, Author, Repository};
+use crate::{
+    auth::GitCredential,
+    config::{
+        AuthorResolver, PatternThresholds, RemoteMismatch, SnapshotInterval, StatusThresholds,
+    },
+    model::{
+        Author, Commit, FileExtChange, FileExtStat, PathFilter, Repository, RfcDateTime, Snapshot,
+        Tag,
+    },
+    progress::Progress,
+};
 use anyhow::{anyhow, Result};
-use chrono::DateTime;
+use chrono::{DateTime, Datelike, TimeZone, Timelike, Utc, Weekday};
 use lazy_static::lazy_static;
 use std::{
-    collections::HashMap,
-    fs,
-    path::Path,
-    process::Command,
-    sync::{Arc, Mutex},
-    time,
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
+    env,
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    process::{self, Command, Output},
+    sync::{
+        atomic::{self, AtomicUsize},
+        mpsc, Arc, RwLock,
+    },
+    thread, time,
 };
-use tokei::{Config, Languages};
-use tokio::task::JoinHandle;
-
-/// 提交记录
-#[derive(Debug, Clone, Default, Hash, Eq, PartialEq)]
-pub struct Commit {
-    /// 仓库名称
-    pub repo: String,
-    /// Commit hash
-    pub hash: String,
-    /// 提交作者
-    pub author: Author,
-    /// 提交日期
-    pub datetime: RfcDateTime,
-    /// 变动文件数
-    pub change_files: i64,
-    /// 文件变更记录
-    pub changes: Vec<FileExtChange>,
+use tokei::{Config, LanguageType, Languages};
+use tokio::{sync::Semaphore, task::JoinHandle};
+
+/// 提交记录的采集选项
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogOptions {
+    /// 包含 merge 提交
+    pub merges: bool,
+    /// 解析 `Co-authored-by` trailer 中的共同作者
+    pub co_authors: bool,
+    /// numstat 中忽略只有空白字符或者空行的改动
+    pub ignore_whitespace: bool,
 }
 
-#[derive(Debug, Clone, Default, Hash, Eq, PartialEq)]
-pub struct RfcDateTime(String);
+impl LogOptions {
+    fn merges_arg(&self) -> Vec<&'static str> {
+        match self.merges {
+            true => vec![],
+            false => vec!["--no-merges"],
+        }
+    }
 
-impl RfcDateTime {
-    pub fn to_rfc339(&self) -> String {
-        match DateTime::parse_from_rfc2822(&self.0) {
-            Ok(t) => t.to_rfc3339(),
-            Err(_) => String::new(),
+    fn whitespace_args(&self) -> Vec<&'static str> {
+        match self.ignore_whitespace {
+            true => vec!["-w", "--ignore-blank-lines"],
+            false => vec![],
         }
     }
+
+    /// 提交信息格式，`[]` 中为父提交列表，`{}` 中为以 `\x1f` 分隔的共同作者
+    fn pretty_format(&self) -> String {
+        let trailers = match self.co_authors {
+            true => " {%(trailers:key=Co-authored-by,valueonly,separator=%x1f)}",
+            false => "",
+        };
+        format!(
+            "--pretty=format:<%ad> <%H> <%aN> <%aE> [%P]{} <%s>",
+            trailers
+        )
+    }
 }
 
-impl Commit {
-    pub fn new() -> Self {
-        Self::default()
+/// 仓库活跃度数据
+#[derive(Debug, Clone, Default)]
+pub struct Activity {
+    /// 最后一次提交时间
+    pub datetime: RfcDateTime,
+    /// 距离最后一次提交的天数
+    pub inactive_days: i64,
+    /// 最近一个周期内的贡献者数量
+    pub recent_authors: usize,
+    /// 上一个周期内的贡献者数量
+    pub previous_authors: usize,
+    /// 建议状态（active/maintenance/stale/abandoned）
+    pub status: String,
+}
+
+impl Activity {
+    /// 根据提交记录计算活跃度，`now` 为计算时的 Unix 时间戳
+    fn calc(commits: &[Commit], now: i64, thresholds: &StatusThresholds) -> Activity {
+        const DAY: i64 = 3600 * 24;
+        let mut activity = Activity::default();
+        if commits.is_empty() {
+            activity.status = Self::status(i64::MAX, thresholds);
+            return activity;
+        }
+
+        let latest = commits
+            .iter()
+            .max_by_key(|c| c.datetime.timestamp().unwrap_or_default())
+            .unwrap();
+        activity.datetime = latest.datetime.clone();
+        let latest = latest.datetime.timestamp().unwrap_or_default();
+        activity.inactive_days = (now - latest).max(0) / DAY;
+
+        let window = thresholds.trend_days() * DAY;
+        let mut recent = HashSet::new();
+        let mut previous = HashSet::new();
+        for commit in commits {
+            let elapsed = now - commit.datetime.timestamp().unwrap_or_default();
+            if elapsed < window {
+                recent.insert(&commit.author);
+            } else if elapsed < window * 2 {
+                previous.insert(&commit.author);
+            }
+        }
+        activity.recent_authors = recent.len();
+        activity.previous_authors = previous.len();
+        activity.status = Self::status(activity.inactive_days, thresholds);
+        activity
+    }
+
+    fn status(inactive_days: i64, thresholds: &StatusThresholds) -> String {
+        let status = if inactive_days <= thresholds.active_days() {
+            "active"
+        } else if inactive_days <= thresholds.maintenance_days() {
+            "maintenance"
+        } else if inactive_days <= thresholds.stale_days() {
+            "stale"
+        } else {
+            "abandoned"
+        };
+        status.to_string()
     }
 }
 
-/// 文件变更记录
-#[derive(Debug, Clone, Default, Hash, Eq, PartialEq)]
-pub struct FileExtChange {
-    /// 文件扩展名
-    pub ext: String,
-    /// 文件改动增加行数
-    pub insertion: usize,
-    /// 文件改动删除函数
-    pub deletion: usize,
+/// 相邻两个历史快照之间的语言迁移，即一种语言的占比下降而另一种语言的占比上升
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Migration {
+    /// 迁移发生的周期，即后一个快照的周期
+    pub period: String,
+    /// 占比下降的语言
+    pub from_language: String,
+    /// 占比上升最多的语言
+    pub to_language: String,
+    /// 迁移幅度，取下降与上升的占比变化中较小的一个（0~1）
+    pub magnitude: f64,
 }
 
-impl FileExtChange {
-    pub fn new() -> Self {
-        Self::default()
+impl Migration {
+    /// 各语言代码行数的占比，代码行数为 0 时返回 None
+    fn shares(snapshot: &Snapshot) -> Option<HashMap<&str, f64>> {
+        let total = snapshot.stats.iter().map(|s| s.code).sum::<usize>();
+        if total == 0 {
+            return None;
+        }
+        let mut shares = HashMap::new();
+        for stat in snapshot.stats.iter() {
+            *shares.entry(stat.ext.as_str()).or_default() += stat.code as f64 / total as f64;
+        }
+        Some(shares)
+    }
+
+    fn calc(snapshots: &[Snapshot], threshold: f64) -> Vec<Migration> {
+        let mut snapshots = snapshots.iter().collect::<Vec<_>>();
+        snapshots.sort_by_key(|s| s.datetime.timestamp().unwrap_or_default());
+
+        let mut migrations = vec![];
+        for pair in snapshots.windows(2) {
+            let (prev, curr) = match (Self::shares(pair[0]), Self::shares(pair[1])) {
+                (Some(prev), Some(curr)) => (prev, curr),
+                _ => continue,
+            };
+            let mut deltas = prev
+                .keys()
+                .chain(curr.keys())
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .map(|lang| {
+                    let delta = curr.get(lang).unwrap_or(&0.0) - prev.get(lang).unwrap_or(&0.0);
+                    (*lang, delta)
+                })
+                .collect::<Vec<_>>();
+            deltas.sort_by(|a, b| a.0.cmp(b.0));
+
+            let (to_language, rise) = match deltas
+                .iter()
+                .filter(|(_, delta)| *delta >= threshold)
+                .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+            {
+                Some(rising) => *rising,
+                None => continue,
+            };
+            for (lang, delta) in deltas.iter().filter(|(_, delta)| -delta >= threshold) {
+                migrations.push(Migration {
+                    period: pair[1].period.clone(),
+                    from_language: lang.to_string(),
+                    to_language: to_language.to_string(),
+                    magnitude: ((-delta).min(rise) * 10000.0).round() / 10000.0,
+                });
+            }
+        }
+        migrations
     }
 }
 
-/// Tags 数据
-#[derive(Debug, Clone, Default)]
-pub struct Tag {
-    /// 版本号
-    pub tag: String,
-    /// 提交时间
-    pub datetime: RfcDateTime,
+/// 作者的工作模式，根据提交时间（作者所在时区）的小时以及星期分布判定
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorkingPattern {
+    pub author: Author,
+    /// 提交数
+    pub commits: usize,
+    /// 周末提交的占比
+    pub weekend_ratio: f64,
+    /// 深夜（22:00~05:00）提交的占比
+    pub night_ratio: f64,
+    /// 清晨（05:00~09:00）提交的占比
+    pub early_ratio: f64,
+    /// 工作模式（night-owl/early-bird/weekend-warrior/weekday-worker）
+    pub pattern: String,
 }
 
-#[derive(Debug, Clone, Default)]
-pub struct Snapshot {
-    /// 提交时间
-    pub datetime: RfcDateTime,
-    /// 文件统计数据
-    pub stats: Vec<FileExtStat>,
+impl WorkingPattern {
+    /// 按照 night-owl、early-bird、weekend-warrior 的优先级判定，均不满足时为 weekday-worker
+    fn calc(commits: &[Commit], thresholds: &PatternThresholds) -> Vec<WorkingPattern> {
+        let mut counts: HashMap<&Author, [usize; 4]> = HashMap::new();
+        for commit in commits {
+            let t = match DateTime::parse_from_rfc2822(&commit.datetime.0) {
+                Ok(t) => t,
+                Err(_) => continue,
+            };
+            let count = counts.entry(&commit.author).or_default();
+            count[0] += 1;
+            if matches!(t.weekday(), Weekday::Sat | Weekday::Sun) {
+                count[1] += 1;
+            }
+            match t.hour() {
+                h if h >= 22 || h < 5 => count[2] += 1,
+                h if h < 9 => count[3] += 1,
+                _ => (),
+            }
+        }
+
+        let ratio = |n: usize, total: usize| (n as f64 / total as f64 * 10000.0).round() / 10000.0;
+        let mut patterns = counts
+            .into_iter()
+            .map(|(author, [total, weekend, night, early])| {
+                let mut pattern = WorkingPattern {
+                    author: author.clone(),
+                    commits: total,
+                    weekend_ratio: ratio(weekend, total),
+                    night_ratio: ratio(night, total),
+                    early_ratio: ratio(early, total),
+                    ..Default::default()
+                };
+                pattern.pattern = if pattern.night_ratio >= thresholds.night_ratio() {
+                    "night-owl"
+                } else if pattern.early_ratio >= thresholds.early_ratio() {
+                    "early-bird"
+                } else if pattern.weekend_ratio >= thresholds.weekend_ratio() {
+                    "weekend-warrior"
+                } else {
+                    "weekday-worker"
+                }
+                .to_string();
+                pattern
+            })
+            .collect::<Vec<_>>();
+        patterns.sort_by(|a, b| {
+            b.commits
+                .cmp(&a.commits)
+                .then_with(|| a.author.name.cmp(&b.author.name))
+        });
+        patterns
+    }
 }
 
-/// 文件统计
-#[derive(Debug, Clone, Default)]
-pub struct FileExtStat {
-    /// 文件扩展名
-    pub ext: String,
-    /// 文件代码行数
-    pub code: usize,
-    /// 文件注释行数
-    pub comments: usize,
-    /// 文件空格行数
-    pub blanks: usize,
+/// 季度贡献者留存数据
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Retention {
+    /// 季度，如 2021Q3
+    pub quarter: String,
+    /// 当季有提交的作者数
+    pub authors: usize,
+    /// 当季首次提交的作者数
+    pub new: usize,
+    /// 当季有提交且此前也有提交的作者数
+    pub retained: usize,
+    /// 当季流失的作者数，即在此季度达到 N 天没有提交
+    pub churned: usize,
+}
+
+impl Retention {
+    fn quarter(ts: i64) -> String {
+        let t = Utc.timestamp(ts, 0);
+        format!("{}Q{}", t.year(), (t.month() - 1) / 3 + 1)
+    }
+
+    /// 按季度统计作者的新增、留存以及流失数量
+    ///
+    /// 作者每一次超过 `churn_days` 天没有提交都会被记为一次流失，流失时间为最后提交时间加上 `churn_days`
+    fn calc(commits: &[Commit], now: i64, churn_days: i64) -> Vec<Retention> {
+        let churn = churn_days * 3600 * 24;
+        let mut authors: HashMap<&Author, Vec<i64>> = HashMap::new();
+        for commit in commits {
+            if let Some(ts) = commit.datetime.timestamp() {
+                authors.entry(&commit.author).or_default().push(ts);
+            }
+        }
+
+        let mut quarters: BTreeMap<String, (HashSet<&Author>, HashSet<&Author>, usize)> =
+            BTreeMap::new();
+        for (author, mut ts) in authors {
+            ts.sort_unstable();
+            for t in ts.iter() {
+                quarters
+                    .entry(Self::quarter(*t))
+                    .or_default()
+                    .0
+                    .insert(author);
+            }
+            quarters
+                .entry(Self::quarter(ts[0]))
+                .or_default()
+                .1
+                .insert(author);
+
+            for w in ts.windows(2) {
+                if w[1] - w[0] > churn {
+                    quarters.entry(Self::quarter(w[0] + churn)).or_default().2 += 1;
+                }
+            }
+            let last = ts[ts.len() - 1];
+            if now - last > churn {
+                quarters.entry(Self::quarter(last + churn)).or_default().2 += 1;
+            }
+        }
+
+        quarters
+            .into_iter()
+            .map(|(quarter, (active, new, churned))| Retention {
+                quarter,
+                authors: active.len(),
+                new: new.len(),
+                retained: active.difference(&new).count(),
+                churned,
+            })
+            .collect()
+    }
 }
 
 lazy_static! {
-    static ref COMMIT_INFO_REGEXP: regex::Regex =
-        regex::Regex::new(r"<(.*?)> <(.*)> <(.*)> <(.*?)>").unwrap();
+    static ref COMMIT_INFO_REGEXP: regex::Regex = regex::Regex::new(
+        r"^<(.*?)> <(.*?)> <(.*?)> <([^>]*)>(?: \[([0-9a-f ]*)\])?(?: \{([^}]*)\})?(?: <(.*)>)?$"
+    )
+    .unwrap();
     static ref COMMIT_CHANGE_REGEXP: regex::Regex =
         regex::Regex::new(r"([0-9-]+)\t([0-9-]+)\t(.*)").unwrap();
+    /// 所有 git 命令使用的可执行文件以及全局参数，由 GitImpl::configure 设置
+    static ref GIT_COMMAND: RwLock<(String, Vec<String>)> =
+        RwLock::new((String::from("git"), vec![]));
+}
+
+/// 共享缓存的文件锁，锁文件为 `${mirror}.lock`，内容为持有锁的进程 pid，Drop 时删除
+///
+/// 持有锁期间由后台线程每隔 CACHE_LOCK_REFRESH 重写锁文件刷新修改时间，
+/// 锁文件超过 CACHE_LOCK_STALE 未刷新时视为持有锁的进程已退出
+struct CacheLock {
+    path: PathBuf,
+    refresher: Option<(mpsc::Sender<()>, thread::JoinHandle<()>)>,
+}
+
+const CACHE_LOCK_STALE: time::Duration = time::Duration::from_secs(3600);
+const CACHE_LOCK_REFRESH: time::Duration = time::Duration::from_secs(60);
+
+impl CacheLock {
+    async fn acquire(mirror: &Path) -> Result<CacheLock> {
+        let path = mirror.with_extension("git.lock");
+        if let Some(p) = path.parent() {
+            fs::create_dir_all(p)?
+        }
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut f) => {
+                    f.write_all(process::id().to_string().as_bytes())?;
+                    let refresher = Self::refresh(path.clone());
+                    return Ok(Self {
+                        path,
+                        refresher: Some(refresher),
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let stale = fs::metadata(&path)
+                        .and_then(|m| m.modified())
+                        .ok()
+                        .and_then(|t| t.elapsed().ok())
+                        .map(|elapsed| elapsed > CACHE_LOCK_STALE)
+                        .unwrap_or(false);
+                    if stale {
+                        fs::remove_file(&path).unwrap_or(());
+                        continue;
+                    }
+                    tokio::time::sleep(time::Duration::from_secs(1)).await;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// 定期刷新锁文件的修改时间，避免耗时较长的 fetch 期间锁被其他进程视为过期，Sender 被 drop 时退出
+    fn refresh(path: PathBuf) -> (mpsc::Sender<()>, thread::JoinHandle<()>) {
+        let (tx, rx) = mpsc::channel::<()>();
+        let handle = thread::spawn(move || {
+            while let Err(mpsc::RecvTimeoutError::Timeout) = rx.recv_timeout(CACHE_LOCK_REFRESH) {
+                fs::write(&path, process::id().to_string()).unwrap_or(());
+            }
+        });
+        (tx, handle)
+    }
+}
+
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        if let Some((tx, handle)) = self.refresher.take() {
+            drop(tx);
+            handle.join().unwrap_or(());
+        }
+        fs::remove_file(&self.path).unwrap_or(());
+    }
+}
+
+/// 同一进程内 snapshot worktree 的序号，与 pid 一起保证临时目录唯一
+static WORKTREE_SEQ: AtomicUsize = AtomicUsize::new(0);
+
+/// 检出历史提交使用的临时 `git worktree`，Drop 时（包括中途出错提前返回）移除 worktree 以及临时目录
+struct SnapshotWorktree<'a> {
+    repo: &'a Repository,
+    path: PathBuf,
+}
+
+impl<'a> SnapshotWorktree<'a> {
+    fn new(repo: &'a Repository) -> Self {
+        let path = env::temp_dir().join(format!(
+            "gitv-snapshot-{}-{}-{}",
+            repo.name.replace('/', "-"),
+            process::id(),
+            WORKTREE_SEQ.fetch_add(1, atomic::Ordering::Relaxed)
+        ));
+        Self { repo, path }
+    }
+
+    fn path(&self) -> String {
+        self.path.to_string_lossy().to_string()
+    }
+}
+
+impl Drop for SnapshotWorktree<'_> {
+    fn drop(&mut self) {
+        let path = self.path();
+        if Git::git(self.repo, "worktree", &["remove", "--force", &path], '\n').is_err() {
+            fs::remove_dir_all(&self.path).unwrap_or(());
+            Git::git(self.repo, "worktree", &["prune"], '\n').unwrap_or_default();
+        }
+    }
 }
 
 /// `git` 可执行文件抽象，使用本地的 `git` 命令
 struct Git;
 
+/// 从环境变量中读取 https 凭证的 credential helper，只响应 `get` 请求
+static CREDENTIAL_HELPER: &str = "!f() { if [ \"$1\" = get ]; then echo \"username=$GITV_GIT_USERNAME\"; echo \"password=$GITV_GIT_TOKEN\"; fi; }; f";
+
 impl Git {
+    /// 禁止 git 交互式询问凭证，凭证缺失时直接失败而不是阻塞 create 流程
+    ///
+    /// 凭证通过环境变量传递给 credential helper，不会出现在命令行、`.git/config` 以及错误信息中
+    fn command(credential: Option<&GitCredential>) -> Command {
+        let git = GIT_COMMAND.read().unwrap();
+        let mut c = Command::new(&git.0);
+        c.args(&git.1);
+        c.env("GIT_TERMINAL_PROMPT", "0");
+        if env::var_os("GIT_SSH_COMMAND").is_none() {
+            c.env("GIT_SSH_COMMAND", "ssh -o BatchMode=yes");
+        }
+        if let Some(credential) = credential {
+            // 空值清空已配置的 credential helper，避免优先使用系统中保存的凭证
+            c.args(&["-c", "credential.helper=", "-c"])
+                .arg(format!("credential.helper={}", CREDENTIAL_HELPER))
+                .env("GITV_GIT_USERNAME", &credential.username)
+                .env("GITV_GIT_TOKEN", &credential.token);
+        }
+        c
+    }
+
+    fn output(repo: &Repository, command: &str, args: &[&str]) -> Result<Output> {
+        let mut c = Self::command(repo.credential.as_ref());
+        c.args(&[
+            format!("--git-dir={}/.git", repo.path),
+            format!("--work-tree={}", repo.path),
+        ]);
+        c.arg(command).args(args);
+        Ok(c.output()?)
+    }
+
+    fn error(command: &str, path: &str, out: &Output) -> anyhow::Error {
+        anyhow!(
+            "Failed to run 'git {}' in '{}': {}",
+            command,
+            path,
+            String::from_utf8_lossy(&out.stderr).trim()
+        )
+    }
+
+    /// 执行 git 命令并按照 delimiter 切分输出，命令以非 0 状态退出时返回 stderr 作为错误信息
     fn git(
         repo: &Repository,
         command: &str,
         args: &[&str],
         delimiter: char,
     ) -> Result<Vec<String>> {
-        let mut args = args.to_vec();
-        args.insert(0, command);
-
-        let mut c = Command::new("git");
-        c.args(&[
-            format!("--git-dir={}/.git", repo.path),
-            format!("--work-tree={}", repo.path),
-        ]);
-        c.args(args);
+        let out = Self::output(repo, command, args)?;
+        if !out.status.success() {
+            return Err(Self::error(command, &repo.path, &out));
+        }
 
-        let out = c.output()?.stdout;
-        let lines = String::from_utf8_lossy(&out)
+        let lines = String::from_utf8_lossy(&out.stdout)
             .split(delimiter)
             .filter(|x| !x.is_empty())
             .map(|x| x.to_string())
@@ -132,19 +530,58 @@ impl Git {
         Ok(lines)
     }
 
-    fn git_clone(repo: &Repository) -> Result<()> {
+    /// clone 仓库，reference 不为空时通过 alternates 复用共享缓存中的对象，不重复占用磁盘
+    fn git_clone(repo: &Repository, reference: Option<&Path>) -> Result<()> {
         if let Some(p) = Path::new(&repo.path).parent() {
             fs::create_dir_all(p)?
         }
 
-        let mut c = Command::new("git");
-        if repo.remote.is_some() {
+        let remote = match &repo.remote {
+            Some(remote) => remote,
+            None => return Ok(()),
+        };
+        let mut c = Self::command(repo.credential.as_ref());
+        c.arg("clone");
+        if let Some(reference) = reference {
+            c.arg("--reference-if-able").arg(reference);
+        }
+        let out = c.args(&[remote.as_str(), repo.path.as_str()]).output()?;
+        if !out.status.success() {
+            return Err(Self::error("clone", &repo.path, &out));
+        }
+        Ok(())
+    }
+
+    /// 创建或者更新共享缓存中的 mirror 仓库
+    fn git_mirror(remote: &str, mirror: &Path, credential: Option<&GitCredential>) -> Result<()> {
+        let mut c = Self::command(credential);
+        if mirror.exists() {
+            c.arg(format!("--git-dir={}", mirror.display()))
+                .args(&["fetch", "--prune", "--tags"]);
+        } else {
+            if let Some(p) = mirror.parent() {
+                fs::create_dir_all(p)?
+            }
+            // 其他用户的仓库通过 alternates 引用 mirror 中的对象，禁止 gc 清理对象
             c.args(&[
                 "clone",
-                &repo.remote.clone().unwrap_or_default(),
-                repo.path.as_str(),
+                "--mirror",
+                "--config",
+                "core.sharedRepository=group",
+                "--config",
+                "gc.auto=0",
+                remote,
             ])
-            .output()?;
+            .arg(mirror);
+        }
+
+        let out = c.output()?;
+        if !out.status.success() {
+            return Err(anyhow!(
+                "Failed to update cache '{}': {}",
+                mirror.display(),
+                String::from_utf8_lossy(&out.stderr).trim()
+            ));
         }
         Ok(())
     }
@@ -153,12 +590,37 @@ impl Git {
         Self::git(repo, "pull", &[], '\n')
     }
 
+    fn git_fetch(repo: &Repository, args: &[&str]) -> Result<Vec<String>> {
+        Self::git(repo, "fetch", args, '\n')
+    }
+
+    /// 未设置 `remote.origin.url` 时 `git config --get` 以状态 1 退出，返回 None
+    fn git_remote_url(repo: &Repository) -> Result<Option<String>> {
+        let out = Self::output(repo, "config", &["--get", "remote.origin.url"])?;
+        match out.status.code() {
+            Some(0) => Ok(String::from_utf8_lossy(&out.stdout)
+                .lines()
+                .next()
+                .map(|x| x.trim().to_string())),
+            Some(1) => Ok(None),
+            _ => Err(Self::error("config", &repo.path, &out)),
+        }
+    }
+
+    fn git_rev_parse(repo: &Repository, args: &[&str]) -> Result<Vec<String>> {
+        Self::git(repo, "rev-parse", args, '\n')
+    }
+
+    fn git_rev_list(repo: &Repository, args: &[&str]) -> Result<Vec<String>> {
+        Self::git(repo, "rev-list", args, '\n')
+    }
+
     fn git_log(repo: &Repository, args: &[&str]) -> Result<Vec<String>> {
         Self::git(repo, "log", args, '\n')
     }
 
-    fn git_show_ref(repo: &Repository, args: &[&str]) -> Result<Vec<String>> {
-        Self::git(repo, "show-ref", args, '\n')
+    fn git_for_each_ref(repo: &Repository, args: &[&str]) -> Result<Vec<String>> {
+        Self::git(repo, "for-each-ref", args, '\n')
     }
 
     fn git_checkout(repo: &Repository, args: &[&str]) -> Result<Vec<String>> {
@@ -173,9 +635,8 @@ impl Parser {
     fn parse_commit_info(
         commit: &mut Commit,
         line: &str,
-        author_mappings: Option<&[AuthorMapping]>,
+        authors: Option<&AuthorResolver>,
     ) -> Result<()> {
-        let author_mappings = author_mappings.unwrap_or_default();
         let caps = COMMIT_INFO_REGEXP.captures(line);
         if caps.is_none() {
             return Err(anyhow!("Invalid commit format: {}", line));
@@ -183,31 +644,78 @@ impl Parser {
 
         let caps = caps.unwrap();
         for i in 0..caps.len() {
-            let cap = caps.get(i).unwrap().as_str().to_string();
+            // subject 为可选字段
+            let cap = match caps.get(i) {
+                Some(cap) => cap.as_str().to_string(),
+                None => continue,
+            };
             match i {
                 1 => commit.datetime = RfcDateTime(cap),
                 2 => commit.hash = cap,
                 3 => commit.author.name = cap,
                 4 => commit.author.email = cap,
+                5 => commit.is_merge = cap.split_whitespace().count() > 1,
+                6 => commit.co_authors = Self::parse_co_authors(&cap),
+                7 => commit.subject = cap,
                 _ => (),
             }
         }
 
-        for author_mapping in author_mappings.iter() {
-            if commit.author == author_mapping.source {
-                commit.author = author_mapping.destination.clone();
-                break;
+        if let Some(authors) = authors {
+            commit.author = authors.resolve(&commit.author);
+            for co_author in commit.co_authors.iter_mut() {
+                *co_author = authors.resolve(co_author);
             }
         }
+        let mut seen = HashSet::from([commit.author.clone()]);
+        commit
+            .co_authors
+            .retain(|co_author| seen.insert(co_author.clone()));
         Ok(())
     }
 
-    fn parse_commit_changes(commit: &mut Commit, lines: &[String]) -> Result<()> {
+    /// 解析 `Name <email>` 格式的共同作者列表，以 `\x1f` 分隔
+    fn parse_co_authors(trailers: &str) -> Vec<Author> {
+        trailers
+            .split('\x1f')
+            .filter_map(|value| {
+                let (name, email) = value.trim().rsplit_once('<')?;
+                Some(Author {
+                    name: name.trim().to_string(),
+                    email: email.trim_end_matches('>').trim().to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// 解析 `git for-each-ref` 的输出，字段以 tab 分隔：tag 名称、对象类型、tagger、打标签时间、提交时间
+    fn parse_tags(lines: &[String]) -> Vec<Tag> {
+        let mut tags = vec![];
+        for line in lines {
+            let fields = line.split('\t').collect::<Vec<_>>();
+            if fields.len() < 5 || fields[4].is_empty() {
+                continue;
+            }
+            tags.push(Tag {
+                tag: fields[0].to_string(),
+                datetime: RfcDateTime(fields[4].to_string()),
+                annotated: fields[1] == "tag",
+                tagger_name: fields[2].to_string(),
+                tag_datetime: RfcDateTime(fields[3].to_string()),
+            });
+        }
+        tags
+    }
+
+    fn parse_commit_changes(
+        commit: &mut Commit,
+        lines: &[String],
+        exclude_paths: &PathFilter,
+    ) -> Result<()> {
         let mut count = 0;
         let mut changes: HashMap<String, FileExtChange> = HashMap::new();
 
         for line in lines.iter() {
-            count += 1;
             let mut change = FileExtChange::new();
             let caps = COMMIT_CHANGE_REGEXP.captures(line.as_str());
             if caps.is_none() {
@@ -215,6 +723,10 @@ impl Parser {
             }
 
             let caps = caps.unwrap();
+            if exclude_paths.is_excluded(&caps[3]) {
+                continue;
+            }
+            count += 1;
             for i in 0..caps.len() {
                 let cap = caps.get(i).unwrap().as_str();
                 match i {
@@ -255,10 +767,14 @@ impl Parser {
         Ok(())
     }
 
-    fn parse_commit(lines: &[String], author_mappings: &[AuthorMapping]) -> Result<Commit> {
+    fn parse_commit(
+        lines: &[String],
+        authors: &AuthorResolver,
+        exclude_paths: &PathFilter,
+    ) -> Result<Commit> {
         let mut commit = Commit::new();
-        Self::parse_commit_info(&mut commit, &lines[0], Some(author_mappings))?;
-        Self::parse_commit_changes(&mut commit, &lines[1..])?;
+        Self::parse_commit_info(&mut commit, &lines[0], Some(authors))?;
+        Self::parse_commit_changes(&mut commit, &lines[1..], exclude_paths)?;
         Ok(commit)
     }
 }
@@ -267,64 +783,222 @@ impl Parser {
 pub struct GitImpl;
 
 impl GitImpl {
-    pub fn commits_hash(repo: &Repository) -> Result<Vec<String>> {
-        Git::git_log(repo, &["--no-merges", "--pretty=format:%H", "HEAD"])
+    /// 所有提交的 hash（从最新的提交开始），设置 since 时只包含该时间之后的提交
+    pub fn commits_hash(
+        repo: &Repository,
+        opts: LogOptions,
+        since: Option<&str>,
+    ) -> Result<Vec<String>> {
+        let since = since.map(|s| format!("--since={}", s));
+        let mut args: Vec<&str> = opts.merges_arg();
+        args.extend(since.as_deref());
+        args.extend(["--pretty=format:%H", "HEAD"]);
+        Git::git_log(repo, &args)
     }
 }
 
 impl GitImpl {
-    pub async fn clone_or_pull(repos: Vec<Repository>, disable_pull: bool) -> Result<()> {
-        let mut handles: Vec<JoinHandle<Result<(), anyhow::Error>>> = vec![];
-        let mutex = Arc::new(Mutex::new(0));
-        let total = repos.len();
+    /// 设置 git 可执行文件的路径（默认为 PATH 中的 `git`）以及每个 git 命令前添加的全局参数，如 `-c http.proxy=...`
+    pub fn configure(binary: Option<String>, args: Vec<String>) {
+        *GIT_COMMAND.write().unwrap() = (binary.unwrap_or_else(|| String::from("git")), args);
+    }
+
+    /// Github 仓库的 `owner/repo`，remote 不是 Github 地址时返回 None，未设置 remote 时使用仓库名称
+    pub fn github_full_name(repo: &Repository) -> Option<String> {
+        match &repo.remote {
+            Some(remote) => Self::normalize_remote(remote)
+                .strip_prefix("github.com/")
+                .map(|name| name.to_string()),
+            None if repo.name.contains('/') => Some(repo.name.clone()),
+            None => None,
+        }
+    }
+
+    /// 统一 remote 地址格式后再比较，忽略协议、`git@host:` 形式、`.git` 后缀以及大小写
+    fn normalize_remote(url: &str) -> String {
+        let url = url.trim().trim_end_matches('/').trim_end_matches(".git");
+        let url = url.split_once("://").map(|(_, u)| u).unwrap_or(url);
+        let url = match url.split_once('@') {
+            Some((_, u)) => u,
+            None => url,
+        };
+        url.replacen(':', "/", 1).to_lowercase()
+    }
+
+    /// 校验本地仓库的 `remote.origin.url` 与配置的 remote 是否一致，返回是否需要重新 clone
+    fn verify_remote(repo: &Repository, mismatch: RemoteMismatch) -> Result<bool> {
+        let expected = match &repo.remote {
+            Some(remote) if mismatch != RemoteMismatch::Ignore => remote,
+            _ => return Ok(false),
+        };
+        let actual = Git::git_remote_url(repo)?.unwrap_or_default();
+        if Self::normalize_remote(&actual) == Self::normalize_remote(expected) {
+            return Ok(false);
+        }
+
+        match mismatch {
+            RemoteMismatch::Reclone => Ok(true),
+            _ => Err(anyhow!(
+                "Remote mismatched: '{}' points to '{}', expected '{}'",
+                repo.path,
+                actual,
+                expected
+            )),
+        }
+    }
+
+    /// 共享缓存中的 mirror 路径，如 `${cache_dir}/github.com/chenjiandongx/gitv.git`
+    fn cache_path(cache_dir: &Path, remote: &str) -> PathBuf {
+        cache_dir.join(format!("{}.git", Self::normalize_remote(remote)))
+    }
+
+    /// 在文件锁的保护下更新共享缓存，同一个 mirror 同时只有一个进程在更新
+    ///
+    /// 读取缓存（clone 时引用对象）无需加锁，git 写入对象以及引用都是原子操作
+    async fn sync_cache(
+        repo: &Repository,
+        cache_dir: &Path,
+        update: bool,
+    ) -> Result<Option<PathBuf>> {
+        let remote = match &repo.remote {
+            Some(remote) => remote,
+            None => return Ok(None),
+        };
+        let mirror = Self::cache_path(cache_dir, remote);
+        if mirror.exists() && !update {
+            return Ok(Some(mirror));
+        }
+
+        let _lock = CacheLock::acquire(&mirror).await?;
+        Git::git_mirror(remote, &mirror, repo.credential.as_ref())?;
+        Ok(Some(mirror))
+    }
+
+    /// 本地不存在时 clone 仓库，否则 pull 最新代码，返回执行的操作
+    async fn sync_repo(
+        repo: &Repository,
+        disable_pull: bool,
+        mismatch: RemoteMismatch,
+        cache_dir: Option<&Path>,
+    ) -> Result<Option<&'static str>> {
+        let cloned = Path::new(&repo.path).exists();
+        let mirror = match cache_dir {
+            Some(dir) => Self::sync_cache(repo, dir, !(cloned && disable_pull)).await?,
+            None => None,
+        };
+
+        if !cloned {
+            Git::git_clone(repo, mirror.as_deref())?;
+            return Ok(Some("clone"));
+        }
+        if Self::verify_remote(repo, mismatch)? {
+            fs::remove_dir_all(&repo.path)?;
+            Git::git_clone(repo, mirror.as_deref())?;
+            return Ok(Some("re-clone"));
+        }
+        if disable_pull {
+            return Ok(None);
+        }
+        // 没有 origin 的本地仓库（如 `gitv analyze`）无需更新
+        if repo.remote.is_none() && Git::git_remote_url(repo)?.is_none() {
+            return Ok(None);
+        }
+
+        // 固定 ref 的仓库处于 detached HEAD 状态，只需 fetch 即可
+        if repo.reference.is_some() {
+            Git::git_fetch(repo, &["--tags"])?;
+            return Ok(Some("fetch"));
+        }
+        Git::git_pull(repo)?;
+        Ok(Some("pull"))
+    }
+
+    /// 检测浅克隆的仓库，浅克隆会导致提交历史缺失
+    ///
+    /// 开启 unshallow 时自动执行 `git fetch --unshallow` 拉取完整历史，否则返回警告信息
+    fn check_shallow(repo: &Repository, unshallow: bool) -> Result<Option<String>> {
+        let shallow = Git::git_rev_parse(repo, &["--is-shallow-repository"])?;
+        if shallow.first().map(|x| x.trim()) != Some("true") {
+            return Ok(None);
+        }
+
+        if unshallow {
+            Git::git_fetch(repo, &["--unshallow"])?;
+            return Ok(Some(format!(
+                "'{}' is a shallow clone, full history fetched",
+                repo.name
+            )));
+        }
+        Ok(Some(format!(
+            "'{}' is a shallow clone, commits may be incomplete (set `unshallow: true` to fetch full history)",
+            repo.name
+        )))
+    }
+
+    /// clone 或者 pull 所有仓库，失败的仓库记录在 progress 中，由调用方结束进度条
+    pub async fn clone_or_pull(
+        repos: Vec<Repository>,
+        disable_pull: bool,
+        unshallow: bool,
+        mismatch: RemoteMismatch,
+        cache_dir: Option<String>,
+        concurrency: usize,
+        progress: &Progress,
+    ) -> Result<()> {
+        let mut handles: Vec<JoinHandle<()>> = vec![];
+        let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
 
         for repo in repos {
             let repo = repo.clone();
-            let mutex = mutex.clone();
+            let progress = progress.clone();
+            let cache_dir = cache_dir.clone().map(PathBuf::from);
+            let semaphore = semaphore.clone();
 
             let handle = tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
                 let now = time::Instant::now();
-                if Path::new(&repo.path).exists() {
-                    if !disable_pull {
-                        Git::git_pull(&repo)?;
-                        let mut lock = mutex.lock().unwrap();
-                        *lock += 1;
-                        let n = *lock;
-
-                        println!(
-                            "[{}/{}] git pull '{}' => elapsed {:#?}",
-                            n,
-                            total,
-                            &repo.name,
-                            now.elapsed(),
-                        )
+                progress.start(&repo.name);
+                let result = Self::sync_repo(&repo, disable_pull, mismatch, cache_dir.as_deref())
+                    .await
+                    .and_then(|action| Ok((action, Self::check_shallow(&repo, unshallow)?)));
+                match result {
+                    Ok((action, shallow)) => {
+                        if let Some(msg) = shallow {
+                            progress.warn(msg);
+                        }
+                        match action {
+                            Some(action) => progress.success(
+                                &repo.name,
+                                format!(
+                                    "git {} '{}' => elapsed {:#?}",
+                                    action,
+                                    &repo.name,
+                                    now.elapsed(),
+                                ),
+                            ),
+                            None => progress.skip(&repo.name),
+                        }
                     }
-                } else {
-                    Git::git_clone(&repo)?;
-                    let mut lock = mutex.lock().unwrap();
-                    *lock += 1;
-                    let n = *lock;
-
-                    println!(
-                        "[{}/{}] git clone '{}' => elapsed {:#?}",
-                        n,
-                        total,
-                        &repo.name,
-                        now.elapsed(),
-                    )
+                    Err(e) => progress.fail(&repo.name, e),
                 }
-                Ok(())
             });
             handles.push(handle);
         }
 
         for handle in handles {
-            handle.await??;
+            handle.await?;
         }
         Ok(())
     }
 
     pub fn checkout(repo: &Repository) -> Result<()> {
+        if let Some(reference) = &repo.reference {
+            if !reference.is_empty() {
+                Git::git_checkout(repo, &["--detach", reference.as_str()])?;
+                return Ok(());
+            }
+        }
+
         if repo.branch.is_some() {
             let branch = repo.branch.clone().unwrap();
             if !branch.is_empty() {
@@ -334,36 +1008,37 @@ impl GitImpl {
         Ok(())
     }
 
+    /// 待分析的提交数（不包含 merge 提交），统计 checkout 之后的 reference/branch
+    pub fn commit_count(repo: &Repository) -> Result<u64> {
+        let rev = [repo.reference.as_ref(), repo.branch.as_ref()]
+            .into_iter()
+            .flatten()
+            .find(|r| !r.is_empty())
+            .map(|r| r.as_str())
+            .unwrap_or("HEAD");
+        let lines = Git::git_rev_list(repo, &["--count", "--no-merges", rev])?;
+        let count = lines.first().map(|l| l.trim()).unwrap_or_default();
+        count
+            .parse()
+            .map_err(|_| anyhow!("Invalid commit count '{}' for repo '{}'", count, repo.name))
+    }
+
     pub fn commits(
         repo: &Repository,
-        author_mappings: &[AuthorMapping],
+        authors: &AuthorResolver,
         hash: &str,
+        opts: LogOptions,
     ) -> Result<Vec<Commit>> {
-        let lines = if hash.is_empty() {
-            Git::git_log(
-                repo,
-                &[
-                    "--no-merges",
-                    "--date=rfc",
-                    "--pretty=format:<%ad> <%H> <%aN> <%aE>",
-                    "--numstat",
-                    "HEAD",
-                ],
-            )?
+        let format = opts.pretty_format();
+        let mut args: Vec<&str> = opts.merges_arg();
+        args.extend(["--date=rfc", format.as_str(), "--numstat"]);
+        args.extend(opts.whitespace_args());
+        if hash.is_empty() {
+            args.push("HEAD");
         } else {
-            Git::git_log(
-                repo,
-                &[
-                    "--no-merges",
-                    "--date=rfc",
-                    "--pretty=format:<%ad> <%H> <%aN> <%aE>",
-                    "--numstat",
-                    hash,
-                    "-n",
-                    "1",
-                ],
-            )?
-        };
+            args.extend([hash, "-n", "1"]);
+        }
+        let lines = Git::git_log(repo, &args)?;
 
         let mut indexes = vec![];
         for (idx, line) in lines.iter().enumerate() {
@@ -376,7 +1051,7 @@ impl GitImpl {
         let mut data = vec![];
         for i in 1..indexes.len() {
             let (l, r) = (indexes[i - 1], indexes[i]);
-            if let Ok(commit) = Parser::parse_commit(&lines[l..r], author_mappings) {
+            if let Ok(commit) = Parser::parse_commit(&lines[l..r], authors, &repo.exclude_paths) {
                 data.push(commit);
             }
         }
@@ -402,8 +1077,24 @@ impl GitImpl {
         let mut commit = Commit::new();
         Parser::parse_commit_info(&mut commit, &lines[0], None)?;
 
+        Ok(Snapshot {
+            period: String::new(),
+            datetime: commit.datetime,
+            stats: Self::code_stats(&repo.path),
+        })
+    }
+
+    /// 将文件扩展名转换为 tokei 的语言名称（小写），与 snapshot 表的 ext 字段保持一致
+    pub fn ext_language(ext: &str) -> String {
+        match LanguageType::from_file_extension(ext) {
+            Some(ty) => ty.to_string().to_lowercase(),
+            None => ext.to_string(),
+        }
+    }
+
+    fn code_stats(path: &str) -> Vec<FileExtStat> {
         let mut languages = Languages::new();
-        languages.get_statistics(&[repo.path.clone()], &[], &Config::default());
+        languages.get_statistics(&[path.to_string()], &[], &Config::default());
 
         let mut stats = vec![];
         for (ty, language) in languages {
@@ -414,48 +1105,162 @@ impl GitImpl {
                 blanks: language.blanks,
             });
         }
+        stats
+    }
 
-        Ok(Snapshot {
-            datetime: commit.datetime,
-            stats,
+    fn snapshot_period(interval: SnapshotInterval, datetime: &RfcDateTime) -> Option<String> {
+        let t = DateTime::parse_from_rfc2822(&datetime.0).ok()?;
+        Some(match interval {
+            SnapshotInterval::Weekly => {
+                let week = t.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+            SnapshotInterval::Monthly => t.format("%Y-%m").to_string(),
+            SnapshotInterval::Quarterly => format!("{}Q{}", t.year(), (t.month() - 1) / 3 + 1),
+            SnapshotInterval::Yearly => t.format("%Y").to_string(),
         })
     }
 
-    pub fn tags(repo: &Repository, author_mappings: Vec<AuthorMapping>) -> Result<Vec<Tag>> {
-        let mut records = vec![];
-        let lines = Git::git_show_ref(repo, &["--tags"])?;
-        for line in lines {
-            let fields: Vec<String> = line.splitn(2, ' ').map(|x| x.to_string()).collect();
-            if fields.len() < 2 {
-                continue;
-            }
+    /// 按周期统计历史代码行数，每个周期取该周期内的最后一个提交
+    ///
+    /// 使用临时的 `git worktree` 检出历史提交，不影响仓库当前的工作目录。HEAD 所在的周期由 snapshot 表记录，此处跳过
+    pub fn snapshot_history(
+        repo: &Repository,
+        interval: SnapshotInterval,
+    ) -> Result<Vec<Snapshot>> {
+        let lines = Git::git_log(
+            repo,
+            &[
+                "--first-parent",
+                "--date=rfc",
+                "--pretty=format:<%ad> <%H> <%aN> <%aE>",
+                "HEAD",
+            ],
+        )?;
 
-            let hash = &fields[0];
-            let tag = &fields[1]["refs/tags/".len()..];
-
-            let logs = Git::git_log(
-                repo,
-                &[
-                    "--no-merges",
-                    "--date=rfc",
-                    "--pretty=format:<%ad> <%H> <%aN> <%aE>",
-                    "-n",
-                    "1",
-                    hash,
-                ],
-            )?;
-
-            if logs.is_empty() {
+        let mut boundaries: Vec<(String, Commit)> = vec![];
+        for line in lines.iter() {
+            let mut commit = Commit::new();
+            if Parser::parse_commit_info(&mut commit, line, None).is_err() {
                 continue;
             }
+            let period = match Self::snapshot_period(interval, &commit.datetime) {
+                Some(period) => period,
+                None => continue,
+            };
+            // git log 按时间倒序输出，周期内第一次出现的即为该周期最后的提交
+            if boundaries.iter().all(|(p, _)| p != &period) {
+                boundaries.push((period, commit));
+            }
+        }
+        if boundaries.len() <= 1 {
+            return Ok(vec![]);
+        }
 
-            let commit = Parser::parse_commit(&logs, &author_mappings)?;
-            records.push(Tag {
-                tag: tag.to_string(),
+        Git::git(repo, "worktree", &["prune"], '\n')?;
+        let worktree = SnapshotWorktree::new(repo);
+        let worktree_path = worktree.path();
+        let worktree_repo = Repository {
+            path: worktree_path.clone(),
+            ..Default::default()
+        };
+
+        let mut snapshots = vec![];
+        for (idx, (period, commit)) in boundaries.into_iter().skip(1).enumerate() {
+            if idx == 0 {
+                Git::git(
+                    repo,
+                    "worktree",
+                    &["add", "--detach", "--force", &worktree_path, &commit.hash],
+                    '\n',
+                )?;
+            } else {
+                Git::git_checkout(&worktree_repo, &["--detach", "--force", &commit.hash])?;
+            }
+            snapshots.push(Snapshot {
+                period,
                 datetime: commit.datetime,
+                stats: Self::code_stats(&worktree_path),
             });
         }
+        Ok(snapshots)
+    }
+
+    /// 只获取提交的基本信息，不包含文件变更记录
+    pub fn commits_info(repo: &Repository, authors: &AuthorResolver) -> Result<Vec<Commit>> {
+        let lines = Git::git_log(
+            repo,
+            &[
+                "--no-merges",
+                "--date=rfc",
+                "--pretty=format:<%ad> <%H> <%aN> <%aE>",
+                "HEAD",
+            ],
+        )?;
+
+        let mut commits = vec![];
+        for line in lines {
+            let mut commit = Commit::new();
+            if Parser::parse_commit_info(&mut commit, &line, Some(authors)).is_ok() {
+                commits.push(commit);
+            }
+        }
+        Ok(commits)
+    }
+
+    pub fn activity(commits: &[Commit], thresholds: &StatusThresholds) -> Activity {
+        Activity::calc(commits, Utc::now().timestamp(), thresholds)
+    }
+
+    /// 检测相邻历史快照之间占比变化均不小于 threshold 的语言迁移，如 javascript -> typescript
+    pub fn migrations(snapshots: &[Snapshot], threshold: f64) -> Vec<Migration> {
+        Migration::calc(snapshots, threshold)
+    }
+
+    pub fn working_patterns(
+        commits: &[Commit],
+        thresholds: &PatternThresholds,
+    ) -> Vec<WorkingPattern> {
+        WorkingPattern::calc(commits, thresholds)
+    }
+
+    pub fn retention(commits: &[Commit], churn_months: i64) -> Vec<Retention> {
+        Retention::calc(commits, Utc::now().timestamp(), churn_months * 30)
+    }
+
+    /// 每个作者在仓库中的首次提交，按提交时间升序排列
+    pub fn first_contributions(commits: &[Commit]) -> Vec<&Commit> {
+        let mut firsts: HashMap<&Author, (i64, &Commit)> = HashMap::new();
+        for commit in commits {
+            if let Some(ts) = commit.datetime.timestamp() {
+                let first = firsts.entry(&commit.author).or_insert((ts, commit));
+                if ts < first.0 {
+                    *first = (ts, commit);
+                }
+            }
+        }
+
+        let mut firsts = firsts.into_values().collect::<Vec<_>>();
+        firsts.sort_by(|a, b| {
+            a.0.cmp(&b.0)
+                .then_with(|| a.1.author.name.cmp(&b.1.author.name))
+        });
+        firsts.into_iter().map(|(_, commit)| commit).collect()
+    }
 
+    /// 使用单次 `git for-each-ref` 获取所有 tag 信息，避免为每个 tag 启动一个 git 进程
+    ///
+    /// 附注标签额外记录打标签的作者及时间，返回结果按照提交时间升序排列
+    pub fn tags(repo: &Repository) -> Result<Vec<Tag>> {
+        let lines = Git::git_for_each_ref(
+            repo,
+            &[
+                "--format=%(refname:strip=2)%09%(objecttype)%09%(taggername)%09%(taggerdate:rfc2822)%09%(*authordate:rfc2822)%(authordate:rfc2822)",
+                "refs/tags",
+            ],
+        )?;
+        let mut records = Parser::parse_tags(&lines);
+        records.sort_by_key(|tag| tag.datetime.timestamp());
         Ok(records)
     }
 }
@@ -463,6 +1268,245 @@ impl GitImpl {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{AuthorMapping, IdentityStats, NormalizeOptions};
+    use crate::tempdir::TempDir;
+
+    #[test]
+    fn test_ext_language() {
+        assert_eq!(GitImpl::ext_language("rs"), "rust");
+        assert_eq!(GitImpl::ext_language("md"), "markdown");
+        assert_eq!(GitImpl::ext_language("unknown-ext"), "unknown-ext");
+    }
+
+    #[test]
+    fn test_snapshot_period() {
+        let t = RfcDateTime(String::from("Sun, 20 Mar 2022 22:03:17 +0800"));
+        let cases = [
+            (SnapshotInterval::Weekly, "2022-W11"),
+            (SnapshotInterval::Monthly, "2022-03"),
+            (SnapshotInterval::Quarterly, "2022Q1"),
+            (SnapshotInterval::Yearly, "2022"),
+        ];
+        for (interval, expected) in cases {
+            assert_eq!(
+                GitImpl::snapshot_period(interval, &t),
+                Some(expected.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        let lines = vec![
+            String::from("v0.2.0\ttag\tchenjiandongx\tSun, 20 Mar 2022 23:00:00 +0800\tSun, 20 Mar 2022 22:03:17 +0800"),
+            String::from("v0.1.0\tcommit\t\t\tSat, 12 Mar 2022 10:00:00 +0800"),
+            String::from("broken\tcommit\t\t\t"),
+        ];
+        let tags = Parser::parse_tags(&lines);
+        assert_eq!(tags.len(), 2);
+        assert!(tags[0].annotated);
+        assert_eq!(tags[0].tagger_name, "chenjiandongx");
+        assert_eq!(
+            tags[0].tag_datetime.to_rfc339(),
+            "2022-03-20T23:00:00+08:00"
+        );
+        assert!(!tags[1].annotated);
+        assert_eq!(tags[1].tag_datetime.to_rfc339(), "");
+        assert_eq!(tags[1].datetime.timestamp(), Some(1647050400));
+    }
+
+    #[test]
+    fn test_checkout_nonexistent_ref() {
+        let dir = TempDir::new("checkout").unwrap();
+        let out = Git::command(None)
+            .args(&["init", "-q"])
+            .arg(dir.as_os_str())
+            .output()
+            .unwrap();
+        assert!(out.status.success());
+
+        let repo = Repository {
+            name: String::from("checkout"),
+            path: dir.to_string_lossy().to_string(),
+            reference: Some(String::from("nonexistent")),
+            ..Default::default()
+        };
+        let err = GitImpl::checkout(&repo).unwrap_err().to_string();
+        assert!(err.contains("git checkout"), "{}", err);
+        assert!(err.contains("nonexistent"), "{}", err);
+
+        let repo = Repository {
+            reference: None,
+            branch: Some(String::from("nonexistent")),
+            ..repo
+        };
+        assert!(GitImpl::checkout(&repo).is_err());
+        assert_eq!(Git::git_remote_url(&repo).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_cache_lock() {
+        let dir = TempDir::new("cache-lock").unwrap();
+        let mirror = dir.join("github.com/chenjiandongx/gitv.git");
+        let path = mirror.with_extension("git.lock");
+
+        let lock = CacheLock::acquire(&mirror).await.unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            process::id().to_string()
+        );
+        let now = time::Instant::now();
+        drop(lock);
+        assert!(now.elapsed() < CACHE_LOCK_REFRESH);
+        assert!(!path.exists());
+
+        // 锁被占用时等待释放
+        let lock = CacheLock::acquire(&mirror).await.unwrap();
+        let timeout = time::Duration::from_millis(1500);
+        assert!(tokio::time::timeout(timeout, CacheLock::acquire(&mirror))
+            .await
+            .is_err());
+        drop(lock);
+        assert!(tokio::time::timeout(timeout, CacheLock::acquire(&mirror))
+            .await
+            .is_ok());
+    }
+
+    #[test]
+    fn test_clone_failed() {
+        let dir = TempDir::new("clone").unwrap();
+        let repo = Repository {
+            name: String::from("clone"),
+            remote: Some(dir.join("nonexistent").to_string_lossy().to_string()),
+            path: dir.join("repo").to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        let err = Git::git_clone(&repo, None).unwrap_err().to_string();
+        assert!(err.contains("git clone"), "{}", err);
+        assert!(!Path::new(&repo.path).exists());
+    }
+
+    #[test]
+    fn test_normalize_remote() {
+        let expected = GitImpl::normalize_remote("https://github.com/chenjiandongx/gitv");
+        for url in [
+            "https://github.com/chenjiandongx/gitv.git",
+            "git@github.com:chenjiandongx/gitv.git",
+            "ssh://git@github.com/ChenJiandongx/gitv/",
+        ] {
+            assert_eq!(GitImpl::normalize_remote(url), expected);
+        }
+        assert_ne!(
+            GitImpl::normalize_remote("https://github.com/chenjiandongx/gitx"),
+            expected
+        );
+
+        let repo = Repository {
+            name: String::from("gitv"),
+            remote: Some(String::from("git@github.com:chenjiandongx/gitv.git")),
+            ..Default::default()
+        };
+        assert_eq!(
+            GitImpl::github_full_name(&repo),
+            Some(String::from("chenjiandongx/gitv"))
+        );
+        let repo = Repository {
+            remote: Some(String::from("https://gitea.example.com/foo/bar")),
+            ..repo
+        };
+        assert_eq!(GitImpl::github_full_name(&repo), None);
+        assert_eq!(
+            GitImpl::cache_path(Path::new("/var/cache/gitv"), "git@github.com:foo/Bar.git"),
+            PathBuf::from("/var/cache/gitv/github.com/foo/bar.git")
+        );
+    }
+
+    #[test]
+    fn test_author_mapping_pattern() {
+        let mappings: Vec<AuthorMapping> = serde_yaml::from_str(
+            r#"
+- source:
+    email: "*@old-company.com"
+  destination:
+    name: "old"
+    email: "old@company.com"
+- source:
+    name: 'regex:^bot-\d+$'
+  destination:
+    name: "bot"
+    email: "bot@company.com"
+"#,
+        )
+        .unwrap();
+        let authors = AuthorResolver::new(None, mappings);
+
+        let line = "<Sun, 20 Mar 2022 22:03:17 +0800> <5a1b2c> <dingdongx> <dd@old-company.com>";
+        let mut commit = Commit::default();
+        Parser::parse_commit_info(&mut commit, line, Some(&authors)).unwrap();
+        assert_eq!(commit.author.name, "old");
+
+        let line = "<Sun, 20 Mar 2022 22:03:17 +0800> <5a1b2c> <bot-12> <bot@example.com>";
+        let mut commit = Commit::default();
+        Parser::parse_commit_info(&mut commit, line, Some(&authors)).unwrap();
+        assert_eq!(commit.author.name, "bot");
+
+        let line = "<Sun, 20 Mar 2022 22:03:17 +0800> <5a1b2c> <bot-x> <dd@new-company.com>";
+        let mut commit = Commit::default();
+        Parser::parse_commit_info(&mut commit, line, Some(&authors)).unwrap();
+        assert_eq!(commit.author.name, "bot-x");
+    }
+
+    #[test]
+    fn test_author_normalize() {
+        let author = Author {
+            name: String::from(" chenjiandongx "),
+            email: String::from("ChenJiandongx+GitHub@QQ.com"),
+        };
+        let normalized = author.normalize(&NormalizeOptions::default());
+        assert_eq!(normalized.name, "chenjiandongx");
+        assert_eq!(normalized.email, "chenjiandongx@qq.com");
+        assert_eq!(normalized.domain(), "qq.com");
+
+        let opts = NormalizeOptions {
+            strip_plus: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(author.normalize(&opts).email, "chenjiandongx+github@qq.com");
+    }
+
+    #[test]
+    fn test_author_resolver_stats() {
+        let mappings: Vec<AuthorMapping> = serde_yaml::from_str(
+            r#"
+- source:
+    email: "dd@qq.com"
+  destination:
+    name: "chenjiandongx"
+    email: "chenjiandongx@qq.com"
+"#,
+        )
+        .unwrap();
+        let authors = AuthorResolver::new(Some(NormalizeOptions::default()), mappings);
+        for (name, email) in [
+            ("chenjiandongx", "chenjiandongx@qq.com"),
+            ("chenjiandongx", "ChenJiandongx+github@qq.com"),
+            ("dingdongx", "DD@qq.com"),
+        ] {
+            authors.resolve(&Author {
+                name: name.to_string(),
+                email: email.to_string(),
+            });
+        }
+        // DD@qq.com 归一化后命中映射规则
+        assert_eq!(
+            authors.stats(),
+            IdentityStats {
+                raw: 3,
+                normalized: 2,
+                resolved: 1,
+            }
+        );
+    }
 
     #[test]
     fn test_parse_commit() {
@@ -480,7 +1524,7 @@ mod tests {
 261	0	stat.go
 250	0	ui.go"#;
         let lines: Vec<String> = output.split('\n').map(|line| line.to_string()).collect();
-        let commit = Parser::parse_commit(&lines, &vec![]).unwrap();
+        let commit = Parser::parse_commit(&lines, &vec![], &PathFilter::default()).unwrap();
 
         let author = Author {
             name: "chenjiandongx".to_string(),
@@ -497,4 +1541,253 @@ mod tests {
         assert_eq!(0, changes.iter().map(|c| c.deletion).sum::<usize>());
         assert_eq!(1588, changes.iter().map(|c| c.insertion).sum::<usize>());
     }
+
+    #[test]
+    fn test_parse_commit_exclude_paths() {
+        let output = r#"<Mon, 8 Nov 2021 23:34:49 +0800> <414915ed> <chenjiandongx> <chenjiandongx@qq.com>
+10	2	src/main.rs
+300	0	vendor/github.com/pkg/errors.go
+120	10	Cargo.lock
+5	1	web/node_modules/lodash/index.js"#;
+        let lines: Vec<String> = output.split('\n').map(|line| line.to_string()).collect();
+        let filter = PathFilter::try_new(&[
+            "vendor/**".to_string(),
+            "**/node_modules/**".to_string(),
+            "*.lock".to_string(),
+        ])
+        .unwrap();
+        let commit = Parser::parse_commit(&lines, &vec![], &filter).unwrap();
+        assert_eq!(1, commit.change_files);
+        assert_eq!(1, commit.changes.len());
+        assert_eq!("rs", commit.changes[0].ext);
+        assert!(!filter.is_excluded("src/vendor/main.go"));
+    }
+
+    #[test]
+    fn test_parse_commit_subject() {
+        let line = "<Mon, 8 Nov 2021 23:34:49 +0800> <414915ed> <chenjiandongx> <chenjiandongx@qq.com> <feat: support <T> generic>";
+        let mut commit = Commit::new();
+        Parser::parse_commit_info(&mut commit, line, None).unwrap();
+        assert_eq!("414915ed", commit.hash);
+        assert_eq!("chenjiandongx@qq.com", commit.author.email);
+        assert_eq!("feat: support <T> generic", commit.subject);
+    }
+
+    #[test]
+    fn test_parse_commit_merge_co_authors() {
+        let line = "<Mon, 8 Nov 2021 23:34:49 +0800> <414915ed> <chenjiandongx> <chenjiandongx@qq.com> [a1b2c3 d4e5f6] {foo <foo@qq.com>\x1f chenjiandongx <chenjiandongx@qq.com>\x1fbar <bar@qq.com>} <Merge branch 'dev'>";
+        let mut commit = Commit::new();
+        Parser::parse_commit_info(&mut commit, line, None).unwrap();
+        assert!(commit.is_merge);
+        assert_eq!("Merge branch 'dev'", commit.subject);
+        assert_eq!(
+            commit.co_authors,
+            vec![
+                Author {
+                    name: "foo".to_string(),
+                    email: "foo@qq.com".to_string(),
+                },
+                Author {
+                    name: "bar".to_string(),
+                    email: "bar@qq.com".to_string(),
+                },
+            ]
+        );
+
+        let line = "<Mon, 8 Nov 2021 23:34:49 +0800> <414915ed> <chenjiandongx> <chenjiandongx@qq.com> [] <init>";
+        let mut commit = Commit::new();
+        Parser::parse_commit_info(&mut commit, line, None).unwrap();
+        assert!(!commit.is_merge);
+        assert!(commit.co_authors.is_empty());
+        assert_eq!("init", commit.subject);
+    }
+
+    #[test]
+    fn test_activity_calc() {
+        let commit = |datetime: &str, name: &str| Commit {
+            datetime: RfcDateTime(datetime.to_string()),
+            author: Author {
+                name: name.to_string(),
+                email: format!("{}@qq.com", name),
+            },
+            ..Default::default()
+        };
+        let commits = vec![
+            commit("Mon, 1 Nov 2021 00:00:00 +0000", "foo"),
+            commit("Mon, 1 Nov 2021 00:00:00 +0000", "bar"),
+            commit("Wed, 1 Sep 2021 00:00:00 +0000", "foo"),
+            commit("Sun, 1 Aug 2021 00:00:00 +0000", "baz"),
+            commit("Fri, 1 Jan 2021 00:00:00 +0000", "qux"),
+        ];
+
+        // 2021-11-11T00:00:00+00:00
+        let now = 1636588800;
+        let thresholds = StatusThresholds::default();
+        let activity = Activity::calc(&commits, now, &thresholds);
+        assert_eq!(10, activity.inactive_days);
+        assert_eq!(2, activity.recent_authors);
+        assert_eq!(1, activity.previous_authors);
+        assert_eq!("active", activity.status);
+
+        let activity = Activity::calc(&commits, now + 3600 * 24 * 100, &thresholds);
+        assert_eq!("maintenance", activity.status);
+
+        let activity = Activity::calc(&commits, now + 3600 * 24 * 200, &thresholds);
+        assert_eq!("stale", activity.status);
+
+        let activity = Activity::calc(&commits, now + 3600 * 24 * 400, &thresholds);
+        assert_eq!("abandoned", activity.status);
+
+        let activity = Activity::calc(&[], now, &thresholds);
+        assert_eq!("abandoned", activity.status);
+    }
+
+    #[test]
+    fn test_retention_calc() {
+        let commit = |datetime: &str, name: &str| Commit {
+            datetime: RfcDateTime(datetime.to_string()),
+            author: Author {
+                name: name.to_string(),
+                email: format!("{}@qq.com", name),
+            },
+            ..Default::default()
+        };
+        let commits = vec![
+            commit("Fri, 1 Jan 2021 00:00:00 +0000", "foo"),
+            commit("Fri, 1 Jan 2021 00:00:00 +0000", "bar"),
+            commit("Thu, 1 Apr 2021 00:00:00 +0000", "foo"),
+            commit("Thu, 1 Apr 2021 00:00:00 +0000", "baz"),
+        ];
+
+        // 2021-11-11T00:00:00+00:00
+        let now = 1636588800;
+        let retention = Retention::calc(&commits, now, 60);
+        let expected = vec![
+            Retention {
+                quarter: "2021Q1".to_string(),
+                authors: 2,
+                new: 2,
+                retained: 0,
+                churned: 2,
+            },
+            Retention {
+                quarter: "2021Q2".to_string(),
+                authors: 2,
+                new: 1,
+                retained: 1,
+                churned: 2,
+            },
+        ];
+        assert_eq!(expected, retention);
+    }
+
+    #[test]
+    fn test_migration_calc() {
+        let snapshot = |period: &str, datetime: &str, stats: &[(&str, usize)]| Snapshot {
+            period: period.to_string(),
+            datetime: RfcDateTime(datetime.to_string()),
+            stats: stats
+                .iter()
+                .map(|(ext, code)| FileExtStat {
+                    ext: ext.to_string(),
+                    code: *code,
+                    ..Default::default()
+                })
+                .collect(),
+        };
+        // git log 按时间倒序输出，计算时需要按时间升序比较
+        let snapshots = vec![
+            snapshot(
+                "2021-03",
+                "Wed, 31 Mar 2021 00:00:00 +0000",
+                &[("typescript", 900), ("css", 100)],
+            ),
+            snapshot(
+                "2021-02",
+                "Sun, 28 Feb 2021 00:00:00 +0000",
+                &[("javascript", 300), ("typescript", 600), ("css", 100)],
+            ),
+            snapshot(
+                "2021-01",
+                "Sun, 31 Jan 2021 00:00:00 +0000",
+                &[("javascript", 880), ("typescript", 20), ("css", 100)],
+            ),
+        ];
+
+        let migrations = GitImpl::migrations(&snapshots, 0.05);
+        let migration = |period: &str, magnitude: f64| Migration {
+            period: period.to_string(),
+            from_language: "javascript".to_string(),
+            to_language: "typescript".to_string(),
+            magnitude,
+        };
+        assert_eq!(
+            migrations,
+            vec![migration("2021-02", 0.58), migration("2021-03", 0.3)]
+        );
+        assert!(GitImpl::migrations(&snapshots, 0.5).len() == 1);
+    }
+
+    #[test]
+    fn test_working_patterns() {
+        let commit = |datetime: &str, name: &str| Commit {
+            datetime: RfcDateTime(datetime.to_string()),
+            author: Author {
+                name: name.to_string(),
+                email: format!("{}@qq.com", name),
+            },
+            ..Default::default()
+        };
+        let commits = vec![
+            // 周二 23:30 以及周三 01:00（作者所在时区）
+            commit("Tue, 12 Oct 2021 23:30:00 +0800", "owl"),
+            commit("Wed, 13 Oct 2021 01:00:00 +0800", "owl"),
+            commit("Wed, 13 Oct 2021 14:00:00 +0800", "owl"),
+            commit("Wed, 13 Oct 2021 07:00:00 +0800", "bird"),
+            commit("Wed, 13 Oct 2021 10:00:00 +0800", "bird"),
+            commit("Sat, 16 Oct 2021 10:00:00 +0800", "warrior"),
+            commit("Wed, 13 Oct 2021 10:00:00 +0800", "worker"),
+        ];
+
+        let patterns = GitImpl::working_patterns(&commits, &PatternThresholds::default())
+            .into_iter()
+            .map(|p| (p.author.name, p.commits, p.night_ratio, p.pattern))
+            .collect::<Vec<_>>();
+        let pattern = |name: &str, commits: usize, night: f64, pattern: &str| {
+            (name.to_string(), commits, night, pattern.to_string())
+        };
+        assert_eq!(
+            patterns,
+            vec![
+                pattern("owl", 3, 0.6667, "night-owl"),
+                pattern("bird", 2, 0.0, "early-bird"),
+                pattern("warrior", 1, 0.0, "weekend-warrior"),
+                pattern("worker", 1, 0.0, "weekday-worker"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_first_contributions() {
+        let commit = |datetime: &str, name: &str, hash: &str| Commit {
+            hash: hash.to_string(),
+            datetime: RfcDateTime(datetime.to_string()),
+            author: Author {
+                name: name.to_string(),
+                email: format!("{}@qq.com", name),
+            },
+            ..Default::default()
+        };
+        let commits = vec![
+            commit("Thu, 1 Apr 2021 00:00:00 +0000", "foo", "c"),
+            commit("Fri, 1 Jan 2021 08:00:00 +0800", "foo", "b"),
+            commit("Fri, 1 Jan 2021 00:00:00 +0000", "bar", "a"),
+            commit("invalid datetime", "baz", "d"),
+        ];
+        let firsts = GitImpl::first_contributions(&commits)
+            .into_iter()
+            .map(|c| (c.author.name.as_str(), c.hash.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(firsts, vec![("bar", "a"), ("foo", "b")]);
+    }
 }
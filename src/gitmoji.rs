@@ -0,0 +1,185 @@
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+
+/// gitmoji 代码与 emoji 的对应关系，参考 https://gitmoji.dev
+static GITMOJIS: [(&str, &str); 74] = [
+    ("art", "🎨"),
+    ("zap", "⚡"),
+    ("fire", "🔥"),
+    ("bug", "🐛"),
+    ("ambulance", "🚑"),
+    ("sparkles", "✨"),
+    ("memo", "📝"),
+    ("rocket", "🚀"),
+    ("lipstick", "💄"),
+    ("tada", "🎉"),
+    ("white_check_mark", "✅"),
+    ("lock", "🔒"),
+    ("closed_lock_with_key", "🔐"),
+    ("bookmark", "🔖"),
+    ("rotating_light", "🚨"),
+    ("construction", "🚧"),
+    ("green_heart", "💚"),
+    ("arrow_down", "⬇"),
+    ("arrow_up", "⬆"),
+    ("pushpin", "📌"),
+    ("construction_worker", "👷"),
+    ("chart_with_upwards_trend", "📈"),
+    ("recycle", "♻"),
+    ("heavy_plus_sign", "➕"),
+    ("heavy_minus_sign", "➖"),
+    ("wrench", "🔧"),
+    ("hammer", "🔨"),
+    ("globe_with_meridians", "🌐"),
+    ("pencil2", "✏"),
+    ("poop", "💩"),
+    ("rewind", "⏪"),
+    ("twisted_rightwards_arrows", "🔀"),
+    ("package", "📦"),
+    ("alien", "👽"),
+    ("truck", "🚚"),
+    ("page_facing_up", "📄"),
+    ("boom", "💥"),
+    ("bento", "🍱"),
+    ("wheelchair", "♿"),
+    ("bulb", "💡"),
+    ("beers", "🍻"),
+    ("speech_balloon", "💬"),
+    ("card_file_box", "🗃"),
+    ("loud_sound", "🔊"),
+    ("mute", "🔇"),
+    ("busts_in_silhouette", "👥"),
+    ("children_crossing", "🚸"),
+    ("building_construction", "🏗"),
+    ("iphone", "📱"),
+    ("clown_face", "🤡"),
+    ("egg", "🥚"),
+    ("see_no_evil", "🙈"),
+    ("camera_flash", "📸"),
+    ("alembic", "⚗"),
+    ("mag", "🔍"),
+    ("label", "🏷"),
+    ("seedling", "🌱"),
+    ("triangular_flag_on_post", "🚩"),
+    ("goal_net", "🥅"),
+    ("dizzy", "💫"),
+    ("wastebasket", "🗑"),
+    ("passport_control", "🛂"),
+    ("adhesive_bandage", "🩹"),
+    ("monocle_face", "🧐"),
+    ("coffin", "⚰"),
+    ("test_tube", "🧪"),
+    ("necktie", "👔"),
+    ("stethoscope", "🩺"),
+    ("bricks", "🧱"),
+    ("technologist", "🧑\u{200d}💻"),
+    ("money_with_wings", "💸"),
+    ("thread", "🧵"),
+    ("safety_vest", "🦺"),
+    ("airplane", "✈"),
+];
+
+lazy_static! {
+    static ref CODES: HashMap<&'static str, &'static str> = GITMOJIS.iter().cloned().collect();
+}
+
+/// 变体选择符，同一个 emoji 可能带或者不带，统一去掉后再比较
+const VARIATION_SELECTOR: char = '\u{fe0f}';
+
+const ZERO_WIDTH_JOINER: char = '\u{200d}';
+
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32,
+        0x1f000..=0x1faff
+        | 0x2190..=0x21ff
+        | 0x2300..=0x23ff
+        | 0x2600..=0x27bf
+        | 0x2b00..=0x2bff
+        | 0x3030
+        | 0x303d
+        | 0x3297
+        | 0x3299
+        | 0xa9
+        | 0xae
+        | 0x2122)
+}
+
+/// 解析提交标题开头的 emoji 或者 gitmoji 代码（如 `:sparkles:`），统一转换为不带变体选择符的 emoji
+///
+/// 未收录的 gitmoji 代码保持 `:code:` 原样，标题不以 emoji 开头时返回 None
+///
+/// # Example
+/// ```rust
+/// input: ":sparkles: Add gitmoji statistics"
+/// output: Some("✨")
+/// ```
+pub fn leading_emoji(subject: &str) -> Option<String> {
+    let subject = subject.trim_start();
+    if let Some(rest) = subject.strip_prefix(':') {
+        let code = &rest[..rest.find(':')?];
+        if code.is_empty()
+            || !code
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '+' || c == '-')
+        {
+            return None;
+        }
+        return Some(match CODES.get(code) {
+            Some(emoji) => emoji.to_string(),
+            None => format!(":{}:", code),
+        });
+    }
+
+    let mut emoji = String::new();
+    let mut joined = true;
+    for c in subject.chars() {
+        match c {
+            VARIATION_SELECTOR => continue,
+            ZERO_WIDTH_JOINER if !emoji.is_empty() => {
+                emoji.push(c);
+                joined = true;
+            }
+            c if joined && is_emoji(c) => {
+                emoji.push(c);
+                joined = false;
+            }
+            _ => break,
+        }
+    }
+    let emoji = emoji.trim_end_matches(ZERO_WIDTH_JOINER);
+    match emoji.is_empty() {
+        true => None,
+        false => Some(emoji.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leading_emoji() {
+        let cases = [
+            (":sparkles: Add gitmoji statistics", Some("✨")),
+            ("  :bug: Fix panic", Some("🐛")),
+            (":recycle: Refactor", Some("♻")),
+            (":unknown_code: Something", Some(":unknown_code:")),
+            ("✨ Add feature", Some("✨")),
+            ("♻️ Refactor config", Some("♻")),
+            ("🧑‍💻 Improve developer experience", Some("🧑\u{200d}💻")),
+            ("🐛🔥 Fix and remove", Some("🐛")),
+            ("Add feature :sparkles:", None),
+            ("fix: handle 10:30 timestamps", None),
+            (":: empty", None),
+            ("", None),
+        ];
+        for (subject, expected) in cases {
+            assert_eq!(
+                leading_emoji(subject).as_deref(),
+                expected,
+                "subject: {}",
+                subject
+            );
+        }
+    }
+}
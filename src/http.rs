@@ -0,0 +1,247 @@
+use crate::config;
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use log::{debug, log_enabled, trace, Level};
+use reqwest::{Client, Proxy, Response, Url};
+use serde::de::DeserializeOwned;
+use std::{env, sync::RwLock};
+
+static DEFAULT_USER_AGENT: &str = "rust/reqwest";
+
+/// debug 日志中输出的限流相关 header
+static RATE_LIMIT_HEADERS: [&str; 5] = [
+    "X-RateLimit-Limit",
+    "X-RateLimit-Remaining",
+    "X-RateLimit-Reset",
+    "X-RateLimit-Resource",
+    "Retry-After",
+];
+
+lazy_static! {
+    /// 配置文件中的代理设置，未设置时使用环境变量
+    static ref PROXY: RwLock<Option<config::Proxy>> = RwLock::new(None);
+    static ref USER_AGENT: RwLock<String> = RwLock::new(String::from(DEFAULT_USER_AGENT));
+}
+
+/// 设置所有 http 请求使用的代理
+pub fn configure(proxy: Option<config::Proxy>) {
+    *PROXY.write().unwrap() = proxy;
+}
+
+/// 设置请求的 User-Agent，None 时恢复默认值
+pub fn set_user_agent(user_agent: Option<String>) {
+    *USER_AGENT.write().unwrap() = user_agent.unwrap_or_else(|| String::from(DEFAULT_USER_AGENT));
+}
+
+/// 创建 http client，fetcher、Github App 验证、js 依赖下载以及 self-update 均通过该 client 发送请求
+pub fn client() -> Result<Client> {
+    let settings =
+        ProxySettings::resolve(PROXY.read().unwrap().as_ref(), |name| env::var(name).ok())?;
+    Ok(Client::builder()
+        .user_agent(USER_AGENT.read().unwrap().as_str())
+        .proxy(Proxy::custom(move |url| settings.intercept(url).cloned()))
+        .build()?)
+}
+
+/// 以 debug 级别输出响应的状态码以及限流 header，通过 `-v` 或者 `GITV_LOG=gitv::http=debug` 开启
+pub fn log_response(response: &Response) {
+    if !log_enabled!(Level::Debug) {
+        return;
+    }
+    let headers = RATE_LIMIT_HEADERS
+        .iter()
+        .filter_map(|name| {
+            let value = response.headers().get(*name)?.to_str().ok()?;
+            Some(format!("{}={}", name, value))
+        })
+        .collect::<Vec<_>>();
+    debug!(
+        "[http]: {} => {} {}",
+        response.url(),
+        response.status(),
+        headers.join(" ")
+    );
+}
+
+/// 读取响应并解析 json，解析失败时错误信息中带上 body 的开头部分，trace 级别下输出完整的 body
+pub async fn json<T: DeserializeOwned>(response: Response) -> Result<T> {
+    let url = response.url().to_string();
+    let body = response.text().await?;
+    trace!("[http]: {} => {}", url, body);
+    serde_json::from_str(&body).map_err(|e| {
+        anyhow!(
+            "Decode response of '{}' error: {}, body: {}",
+            url,
+            e,
+            snippet(&body, 256)
+        )
+    })
+}
+
+/// 截取 body 的前 n 个字符
+fn snippet(body: &str, n: usize) -> String {
+    let mut s = body.chars().take(n).collect::<String>();
+    if s.len() < body.len() {
+        s.push_str("...");
+    }
+    s
+}
+
+/// 生效的代理设置，配置文件未设置的字段从 `HTTPS_PROXY`/`HTTP_PROXY`/`NO_PROXY` 环境变量读取
+#[derive(Debug, Clone, Default)]
+struct ProxySettings {
+    http: Option<Url>,
+    https: Option<Url>,
+    no_proxy: Vec<String>,
+}
+
+impl ProxySettings {
+    fn resolve(
+        config: Option<&config::Proxy>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self> {
+        let var = |names: &[&str]| {
+            names
+                .iter()
+                .filter_map(|name| env(name))
+                .find(|v| !v.trim().is_empty())
+        };
+        let config = config.cloned().unwrap_or_default();
+
+        let (http, https) = match config.url {
+            Some(url) => (Some(url.clone()), Some(url)),
+            None => (
+                var(&["HTTP_PROXY", "http_proxy"]),
+                var(&["HTTPS_PROXY", "https_proxy"]),
+            ),
+        };
+        let no_proxy = match config.no_proxy {
+            Some(hosts) => hosts,
+            None => var(&["NO_PROXY", "no_proxy"])
+                .map(|v| v.split(',').map(|h| h.to_string()).collect())
+                .unwrap_or_default(),
+        };
+
+        Ok(Self {
+            http: http.map(|u| parse_url(&u)).transpose()?,
+            https: https.map(|u| parse_url(&u)).transpose()?,
+            no_proxy: no_proxy
+                .iter()
+                .map(|h| h.trim().to_lowercase())
+                .filter(|h| !h.is_empty())
+                .collect(),
+        })
+    }
+
+    /// 请求地址使用的代理，命中 no_proxy 列表时直连
+    fn intercept(&self, url: &Url) -> Option<&Url> {
+        let proxy = match url.scheme() {
+            "https" => self.https.as_ref(),
+            "http" => self.http.as_ref(),
+            _ => None,
+        }?;
+        let host = url.host_str()?.to_lowercase();
+        if self
+            .no_proxy
+            .iter()
+            .any(|entry| no_proxy_matches(entry, &host))
+        {
+            return None;
+        }
+        Some(proxy)
+    }
+}
+
+/// 代理地址未带协议时默认为 http，如 `127.0.0.1:7890`
+fn parse_url(url: &str) -> Result<Url> {
+    let url = url.trim();
+    let full = match url.contains("://") {
+        true => url.to_string(),
+        false => format!("http://{}", url),
+    };
+    Url::parse(&full).map_err(|e| anyhow!("Invalid proxy url '{}': {}", url, e))
+}
+
+/// no_proxy 规则：`*` 匹配所有地址，`example.com` 与 `.example.com` 均匹配该域名及其子域名，端口会被忽略
+fn no_proxy_matches(entry: &str, host: &str) -> bool {
+    if entry == "*" {
+        return true;
+    }
+    // `[::1]:8080` 或 `example.com:8080`
+    let entry = match entry.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None if entry.matches(':').count() == 1 => entry.split(':').next().unwrap_or_default(),
+        None => entry,
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let domain = entry.trim_start_matches('.');
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_snippet() {
+        assert_eq!(snippet("[]", 4), "[]");
+        assert_eq!(
+            snippet(r#"{"message":"Bad credentials"}"#, 10),
+            r#"{"message"..."#
+        );
+        assert_eq!(snippet("仓库列表", 2), "仓库...");
+    }
+
+    #[test]
+    fn test_proxy_settings() {
+        let vars = HashMap::from([
+            ("https_proxy", "http://10.0.0.1:3128"),
+            ("HTTP_PROXY", "10.0.0.2:3128"),
+            (
+                "NO_PROXY",
+                "localhost, .internal.example.com,gitea.local:3000,[::1]",
+            ),
+        ]);
+        let env = |name: &str| vars.get(name).map(|v| v.to_string());
+        let settings = ProxySettings::resolve(None, env).unwrap();
+        let intercept = |url: &str| {
+            settings
+                .intercept(&Url::parse(url).unwrap())
+                .map(|u| u.to_string())
+        };
+
+        assert_eq!(
+            intercept("https://api.github.com/user/repos"),
+            Some(String::from("http://10.0.0.1:3128/"))
+        );
+        assert_eq!(
+            intercept("http://example.com"),
+            Some(String::from("http://10.0.0.2:3128/"))
+        );
+        assert_eq!(intercept("http://localhost:8080"), None);
+        assert_eq!(intercept("https://git.internal.example.com"), None);
+        assert_eq!(intercept("https://internal.example.com"), None);
+        assert_eq!(intercept("https://gitea.local"), None);
+        assert_eq!(intercept("http://[::1]:8000"), None);
+        assert!(intercept("https://notinternal.example.com").is_some());
+
+        let config = config::Proxy {
+            url: Some(String::from("http://127.0.0.1:7890")),
+            no_proxy: Some(vec![]),
+        };
+        let settings = ProxySettings::resolve(Some(&config), env).unwrap();
+        assert_eq!(
+            settings
+                .intercept(&Url::parse("http://localhost").unwrap())
+                .map(|u| u.to_string()),
+            Some(String::from("http://127.0.0.1:7890/"))
+        );
+
+        let config = config::Proxy {
+            url: Some(String::from("http://[invalid")),
+            no_proxy: None,
+        };
+        assert!(ProxySettings::resolve(Some(&config), env).is_err());
+    }
+}
@@ -0,0 +1,83 @@
+//! gitv 的库接口，其他 Rust 工具可以直接嵌入 gitv 的仓库分析流程
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! let config = gitv::load_configs(&[String::from("gitv.yaml")])?;
+//! gitv::create(config.create.unwrap_or_default()).await?;
+//!
+//! let executions = vec![gitv::Execution {
+//!     db_name: String::from("db"),
+//!     dir: String::from("./db"),
+//! }];
+//! let batches = gitv::query(executions, "SELECT count(1) FROM 'db.commit'").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod analyze;
+pub mod auth;
+pub mod check;
+pub mod config;
+pub mod dashboard;
+pub mod dedup;
+pub mod delta;
+pub mod demo;
+pub mod devserver;
+pub mod enricher;
+pub mod executor;
+pub mod fetcher;
+pub mod gitimp;
+pub mod gitmoji;
+pub mod http;
+pub mod library;
+pub mod license;
+pub mod logger;
+pub mod model;
+pub mod progress;
+pub mod record;
+pub mod render;
+pub mod selector;
+pub mod shell;
+pub mod skiplist;
+pub mod svg;
+pub mod tempdir;
+pub mod updater;
+pub mod verify;
+
+use anyhow::Result;
+use record::RecordSerializer;
+
+pub use config::{
+    load_configs, Config, CreateAction, Execution, FetchAction, RenderAction, ShellAction,
+};
+pub use datafusion::arrow::record_batch::RecordBatch;
+pub use enricher::{register_enricher, RecordEnricher};
+
+/// 从远程源（github、bitbucket、gitea）拉取仓库元数据，对应 `gitv --fetch`
+///
+/// 需要代理时先调用 `http::configure` 设置
+pub async fn fetch(config: FetchAction) -> Result<()> {
+    fetcher::RepoFetcher::new(config, false).fetch().await
+}
+
+/// 克隆或者更新仓库并生成数据库，对应 `gitv --create`
+pub async fn create(config: CreateAction) -> Result<()> {
+    record::CsvSerializer::serialize(config).await
+}
+
+/// 执行查询并按照 renderMode 输出结果，对应 `gitv --render`
+pub async fn render(config: RenderAction) -> Result<()> {
+    let ctx = executor::Executor::create_context(
+        config.executions.clone(),
+        config.periods.clone(),
+        config.display.timezone.clone(),
+    )
+    .await?;
+    render::create_render(ctx, config).render().await
+}
+
+/// 在给定的数据库上执行 sql 查询，返回查询结果
+pub async fn query(executions: Vec<Execution>, sql: &str) -> Result<Vec<RecordBatch>> {
+    let mut ctx = executor::Executor::create_context(executions, None, None).await?;
+    Ok(ctx.sql(sql).await?.collect().await?)
+}
@@ -0,0 +1,133 @@
+use crate::config::Query;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs};
+
+static CONTENT_QUERIES: &str = include_str!("../static/queries.yaml");
+
+/// 查询库中的命名查询
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NamedQuery {
+    pub description: Option<String>,
+    /// 查询语句，`{db}` 会被替换为 database 名称
+    pub statements: Vec<String>,
+}
+
+/// 命名查询库，由内置查询以及 queryLibrary 文件合并而成，同名时文件中的查询覆盖内置查询
+#[derive(Debug, Clone, Default)]
+pub struct QueryLibrary {
+    queries: BTreeMap<String, NamedQuery>,
+}
+
+impl QueryLibrary {
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        let mut queries: BTreeMap<String, NamedQuery> = serde_yaml::from_str(CONTENT_QUERIES)?;
+        if let Some(path) = path {
+            let content = fs::read_to_string(path)
+                .map_err(|e| anyhow!("Read query library '{}' error: {}", path, e))?;
+            let custom: BTreeMap<String, NamedQuery> = serde_yaml::from_str(&content)
+                .map_err(|e| anyhow!("Parse query library '{}' error: {}", path, e))?;
+            queries.extend(custom);
+        }
+        Ok(Self { queries })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &NamedQuery)> {
+        self.queries.iter()
+    }
+
+    /// 命名查询的语句，`{db}` 替换为给定的 database 名称
+    pub fn statements(&self, name: &str, db: &str) -> Result<Vec<String>> {
+        let query = self
+            .queries
+            .get(name)
+            .ok_or_else(|| anyhow!("Unknown query '{}' in query library", name))?;
+        Ok(query
+            .statements
+            .iter()
+            .map(|stmt| stmt.replace("{db}", db))
+            .collect())
+    }
+
+    /// 将 render 查询中的 `use` 展开为命名查询的语句，未设置 `db` 时使用给定的 database 名称
+    pub fn resolve(&self, queries: &mut [Query], db: &str) -> Result<()> {
+        for (index, query) in queries.iter_mut().enumerate() {
+            let name = match &query.use_query {
+                Some(name) => name,
+                None => continue,
+            };
+            if !query.statements.is_empty() {
+                return Err(anyhow!(
+                    "query #{} sets both 'use' and 'statements'",
+                    index + 1
+                ));
+            }
+            let db = query.db.as_deref().unwrap_or(db);
+            query.statements = self.statements(name, db)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    #[test]
+    fn test_query_library() {
+        let dir = TempDir::new("library").unwrap();
+        let path = dir.join("queries.yaml");
+        fs::write(
+            &path,
+            r#"
+top_authors:
+  statements:
+    - "SELECT author_name FROM '{db}.commit' LIMIT 3;"
+tags:
+  description: "tags"
+  statements:
+    - "SELECT * FROM '{db}.tag';"
+"#,
+        )
+        .unwrap();
+
+        let builtin = QueryLibrary::load(None).unwrap();
+        assert!(builtin
+            .iter()
+            .all(|(_, q)| q.description.is_some() && !q.statements.is_empty()));
+        assert_eq!(
+            builtin.statements("top_languages", "gitv").unwrap(),
+            vec!["SELECT ext, sum(code) AS code FROM 'gitv.snapshot' GROUP BY ext ORDER BY code DESC LIMIT 10;"]
+        );
+
+        let library = QueryLibrary::load(path.to_str()).unwrap();
+        assert_eq!(library.iter().count(), builtin.iter().count() + 1);
+        assert_eq!(
+            library.statements("top_authors", "db").unwrap(),
+            vec!["SELECT author_name FROM 'db.commit' LIMIT 3;"]
+        );
+        assert!(library.statements("unknown", "db").is_err());
+
+        let query = |name: &str, db: Option<&str>| Query {
+            use_query: Some(name.to_string()),
+            db: db.map(|db| db.to_string()),
+            ..Default::default()
+        };
+        let mut queries = vec![
+            query("tags", None),
+            query("tags", Some("other")),
+            Query::default(),
+        ];
+        library.resolve(&mut queries, "db").unwrap();
+        assert_eq!(queries[0].statements, vec!["SELECT * FROM 'db.tag';"]);
+        assert_eq!(queries[1].statements, vec!["SELECT * FROM 'other.tag';"]);
+        assert!(queries[2].statements.is_empty());
+
+        queries[0].use_query = Some(String::from("unknown"));
+        queries[0].statements.clear();
+        assert!(library.resolve(&mut queries, "db").is_err());
+        assert!(library.resolve(&mut queries[1..], "db").is_err());
+        assert!(QueryLibrary::load(dir.join("missing.yaml").to_str()).is_err());
+    }
+}
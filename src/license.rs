@@ -0,0 +1,161 @@
+use std::{fs, path::Path};
+
+/// 无法识别的许可证，与 Github API 的返回值保持一致
+pub static NOASSERTION: &str = "NOASSERTION";
+
+/// 许可证文件名前缀（不区分大小写），如 LICENSE、LICENSE.md、LICENSE-MIT、COPYING
+static LICENSE_FILES: [&str; 4] = ["license", "licence", "copying", "unlicense"];
+
+/// SPDX id 以及许可证正文中的特征语句（小写，空白字符已合并），按顺序匹配，需要包含所有语句
+///
+/// LGPL/AGPL 的正文同样包含 GPL 的特征语句，需要排在 GPL 之前
+static LICENSES: [(&str, &[&str]); 17] = [
+    (
+        "AGPL-3.0",
+        &["gnu affero general public license", "version 3"],
+    ),
+    (
+        "LGPL-3.0",
+        &["gnu lesser general public license", "version 3"],
+    ),
+    (
+        "LGPL-2.1",
+        &["gnu lesser general public license", "version 2.1"],
+    ),
+    ("GPL-3.0", &["gnu general public license", "version 3"]),
+    ("GPL-2.0", &["gnu general public license", "version 2"]),
+    ("Apache-2.0", &["apache license", "version 2.0"]),
+    ("MPL-2.0", &["mozilla public license", "2.0"]),
+    ("EPL-2.0", &["eclipse public license", "v 2.0"]),
+    ("BSL-1.0", &["boost software license", "version 1.0"]),
+    ("MIT", &["permission is hereby granted, free of charge"]),
+    (
+        "ISC",
+        &["permission to use, copy, modify, and/or distribute this software for any purpose"],
+    ),
+    (
+        "BSD-3-Clause",
+        &[
+            "redistribution and use in source and binary forms",
+            "endorse or promote",
+        ],
+    ),
+    (
+        "BSD-2-Clause",
+        &["redistribution and use in source and binary forms"],
+    ),
+    (
+        "Unlicense",
+        &["this is free and unencumbered software released into the public domain"],
+    ),
+    ("CC0-1.0", &["cc0 1.0 universal"]),
+    ("Zlib", &["altered source versions must be plainly marked"]),
+    ("WTFPL", &["do what the fuck you want to public license"]),
+];
+
+/// 根据许可证正文识别 SPDX id，无法识别时返回 NOASSERTION
+fn classify(text: &str) -> &'static str {
+    let text = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    LICENSES
+        .iter()
+        .find(|(_, phrases)| phrases.iter().all(|p| text.contains(p)))
+        .map(|(id, _)| *id)
+        .unwrap_or(NOASSERTION)
+}
+
+/// 检测仓库根目录下的许可证文件并识别为 SPDX id
+///
+/// 存在多个许可证文件时（如 LICENSE-MIT 以及 LICENSE-APACHE）使用 ` OR ` 连接，没有许可证文件时返回 None
+pub fn detect(dir: &str) -> Option<String> {
+    let mut files = fs::read_dir(Path::new(dir))
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            LICENSE_FILES.iter().any(|prefix| name.starts_with(prefix))
+        })
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let mut ids = vec![];
+    for file in files {
+        let text = match fs::read(&file) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
+            Err(_) => continue,
+        };
+        let id = classify(&text);
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    // 能识别出许可证时忽略无法识别的文件（如 LICENSE-THIRD-PARTY）
+    if ids.len() > 1 {
+        ids.retain(|id| *id != NOASSERTION);
+    }
+    match ids.is_empty() {
+        true => None,
+        false => Some(ids.join(" OR ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    #[test]
+    fn test_classify() {
+        let cases = [
+            (
+                "MIT License\n\nPermission is hereby granted, free of charge, to any person\nobtaining a copy",
+                "MIT",
+            ),
+            (
+                "                                 Apache License\n                           Version 2.0, January 2004",
+                "Apache-2.0",
+            ),
+            (
+                "GNU LESSER GENERAL PUBLIC LICENSE\n Version 3, 29 June 2007\n GNU General Public License",
+                "LGPL-3.0",
+            ),
+            ("GNU GENERAL PUBLIC LICENSE\nVersion 2, June 1991", "GPL-2.0"),
+            (
+                "Redistribution and use in source and binary forms, with or without\nmodification... Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote products",
+                "BSD-3-Clause",
+            ),
+            ("Redistribution and use in source and binary forms", "BSD-2-Clause"),
+            ("All rights reserved.", NOASSERTION),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(classify(text), expected);
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        let dir = TempDir::new("license").unwrap();
+        let path = dir.path_str();
+        assert_eq!(detect(path), None);
+
+        fs::write(
+            dir.join("LICENSE-MIT"),
+            "Permission is hereby granted, free of charge",
+        )
+        .unwrap();
+        fs::write(dir.join("LICENSE-APACHE"), "Apache License\nVersion 2.0").unwrap();
+        fs::write(dir.join("LICENSE-THIRD-PARTY"), "Some vendored code").unwrap();
+        fs::write(dir.join("README.md"), "Apache License Version 2.0").unwrap();
+        assert_eq!(detect(path), Some(String::from("Apache-2.0 OR MIT")));
+
+        fs::remove_dir_all(&dir).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("copying.txt"), "Custom terms").unwrap();
+        assert_eq!(detect(path), Some(String::from(NOASSERTION)));
+    }
+}
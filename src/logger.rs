@@ -0,0 +1,35 @@
+use crate::progress;
+use env_logger::{Builder, Env};
+use log::LevelFilter;
+use std::io::Write;
+
+/// 按照 `GITV_LOG` 环境变量设置各个模块的日志级别，如 `GITV_LOG=gitv::fetcher=debug`
+static LOG_ENV: &str = "GITV_LOG";
+
+/// 初始化日志，默认输出 info 级别，`-v` 为 debug，`-vv` 为 trace，`--quiet` 只输出错误并隐藏进度条
+pub fn init(verbose: u8, quiet: bool) {
+    let level = match (quiet, verbose) {
+        (true, _) => LevelFilter::Error,
+        (false, 0) => LevelFilter::Info,
+        (false, 1) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    };
+    if quiet {
+        progress::hide();
+    }
+
+    Builder::new()
+        .filter_module(env!("CARGO_PKG_NAME"), level)
+        .parse_env(Env::new().filter(LOG_ENV))
+        .format(|buf, record| {
+            writeln!(
+                buf,
+                "{} {:<5} [{}] {}",
+                buf.timestamp(),
+                record.level(),
+                record.module_path().unwrap_or_default(),
+                record.args()
+            )
+        })
+        .init();
+}
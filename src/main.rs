@@ -1,19 +1,15 @@
-mod config;
-mod executor;
-mod fetcher;
-mod gitimp;
-mod record;
-mod render;
-mod shell;
-
 use anyhow::Result;
-use clap::{IntoApp, Parser};
-use config::*;
-use executor::*;
-use fetcher::*;
-use gitimp::*;
-use record::*;
-use std::{fs::File, io::Write, process::exit};
+use clap::{IntoApp, Parser, Subcommand};
+use gitv::{
+    analyze, auth, check, config, config::*, dashboard, demo, devserver, executor::*, fetcher::*,
+    gitimp::*, http, logger, record::*, render, shell, updater::*, verify,
+};
+use log::error;
+use std::{
+    fs::{self, File},
+    io::{Read, Write},
+    process::exit,
+};
 
 #[derive(Debug, Parser)]
 #[clap(about = "\nA git repos analyzing and visualizing tool built in Rust.")]
@@ -27,11 +23,11 @@ struct Cli {
     #[clap(short, long)]
     fetch: bool,
 
-    /// Render query result as the given mode (htlm, table)
+    /// Render query result as the given mode (htlm, table, csv, svg)
     #[clap(short, long)]
     render: bool,
 
-    /// Load data and enter into a new spawn shell
+    /// Load data and enter into a new spawn shell (with --render, the chart query results are registered as q_<chart> tables)
     #[clap(short, long)]
     shell: bool,
 
@@ -39,93 +35,518 @@ struct Cli {
     #[clap(short, long)]
     gernerate: bool,
 
+    /// Print the repos that would be fetched without writing database files
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Wait for the database lock instead of failing when another process holds it
+    #[clap(long)]
+    wait: bool,
+
+    /// Serve the rendered charts on localhost and re-render when the config changes
+    #[clap(long)]
+    dev: bool,
+
+    /// Listen address of the dev server
+    #[clap(long, default_value = "127.0.0.1:8000")]
+    dev_addr: String,
+
+    /// Render the charts and serve them with an index page over HTTP
+    #[clap(long)]
+    serve: bool,
+
+    /// Listen host of the HTTP server, use 0.0.0.0 to accept connections from other machines
+    #[clap(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Listen port of the HTTP server
+    #[clap(long, default_value = "8080")]
+    port: u16,
+
+    /// Re-run the render queries whenever a chart page is requested (with --serve)
+    #[clap(long)]
+    refresh: bool,
+
+    /// List the chart variables (${index:column}) of each render query without rendering
+    #[clap(long)]
+    list_vars: bool,
+
+    /// Run fetch, create and render in order (same as -f -c -r), stop at the first failed action
+    #[clap(long)]
+    pipeline: bool,
+
+    /// Validate the config (files, directories, render modes, statements and chart variables) without running any action
+    #[clap(long)]
+    check: bool,
+
+    /// Pick the repos to analyze from each database in an interactive selector (with --create)
+    #[clap(long)]
+    select: bool,
+
+    /// Retry the repos that are skipped for failing repeatedly in previous runs (with --create)
+    #[clap(long)]
+    retry_failed: bool,
+
+    /// Read newline-separated repo paths or URLs from the file ('-' for stdin) instead of the config (with --create)
+    #[clap(long)]
+    repos_from: Option<String>,
+
+    /// Database directory of the repos read by --repos-from, remote repos are cloned into <DB_DIR>/repos
+    #[clap(long, default_value = "./db")]
+    db_dir: String,
+
+    /// Read a token from stdin and store it in the OS keychain as <service>[/<user>]
+    #[clap(long)]
+    store_token: Option<String>,
+
+    /// Increase the log verbosity (-v debug, -vv trace), per-module levels can be set by GITV_LOG
+    #[clap(short, long, parse(from_occurrences))]
+    verbose: u8,
+
+    /// Only print errors and hide the progress bars
+    #[clap(short, long)]
+    quiet: bool,
+
+    /// Additional config files or directories merged into the config, can be repeated
+    #[clap(short = 'p', long = "config")]
+    configs: Vec<String>,
+
     /// config file path (default: gitv.yaml)
     path: Option<String>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Analyze a local repo without any config and enter into a new spawn shell
+    Analyze {
+        /// repo path (default: current directory)
+        path: Option<String>,
+
+        /// Print a summary report instead of entering into the shell
+        #[clap(long)]
+        summary: bool,
+    },
+
+    /// Check github releases and update the binary to the latest version
+    SelfUpdate,
+
+    /// Generate a synthetic demo database and config and render its charts
+    Demo {
+        /// output directory (default: gitv-demo)
+        dir: Option<String>,
+    },
+
+    /// Search commit subjects and authors across the shell databases
+    Grep {
+        /// case-insensitive substring matched against subjects, author names and emails
+        pattern: String,
+
+        /// config file path (default: gitv.yaml)
+        path: Option<String>,
+    },
+
+    /// Show a terminal dashboard (commits per week, top authors, recent activity) of the shell databases
+    Tui {
+        /// config file path (default: gitv.yaml)
+        path: Option<String>,
+    },
+
+    /// Compare the commit counts of the create databases with `git rev-list --count` of each repo
+    Verify {
+        /// config file path (default: gitv.yaml)
+        path: Option<String>,
+    },
+}
+
+impl Command {
+    /// 子命令自身指定的配置文件路径
+    fn config_path(&self) -> Option<&String> {
+        match self {
+            Command::Grep { path, .. } | Command::Tui { path } | Command::Verify { path } => {
+                path.as_ref()
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Cli {
+    /// PATH 以及 `-p` 指定的所有配置文件或者目录，均未指定时为 gitv.yaml
+    fn config_paths(&self) -> Vec<String> {
+        let mut paths = self.path.iter().cloned().collect::<Vec<_>>();
+        paths.extend(self.command.as_ref().and_then(|c| c.config_path()).cloned());
+        paths.extend(self.configs.iter().cloned());
+        if paths.is_empty() {
+            paths.push(String::from("gitv.yaml"));
+        }
+        paths
+    }
 }
 
 static DEFAULT_CONFIG: &str = include_str!("../static/gitv.example.yaml");
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let cli: Cli = Cli::parse();
-    if !cli.create && !cli.fetch && !cli.render && !cli.shell && !cli.gernerate {
+    let mut cli: Cli = Cli::parse();
+    logger::init(cli.verbose, cli.quiet);
+    if cli.pipeline {
+        cli.fetch = true;
+        cli.create = true;
+        cli.render = true;
+    }
+    if !cli.create
+        && !cli.fetch
+        && !cli.render
+        && !cli.shell
+        && !cli.gernerate
+        && !cli.serve
+        && !cli.check
+        && cli.store_token.is_none()
+        && cli.command.is_none()
+    {
         Cli::command().print_help().unwrap();
         exit(0)
     }
 
+    if let Some(Command::Analyze { path, summary }) = &cli.command {
+        let path = path.as_deref().unwrap_or(".");
+        if let Err(e) = analyze::analyze(path, *summary).await {
+            error!("Analyze repo error: {:#}", e);
+            exit(1);
+        }
+        exit(0)
+    }
+
+    if let Some(Command::SelfUpdate) = &cli.command {
+        if let Err(e) = SelfUpdater::update().await {
+            error!("Self update error: {}", e);
+            exit(1)
+        }
+        exit(0)
+    }
+
+    if let Some(entry) = &cli.store_token {
+        let mut token = String::new();
+        if let Err(e) = std::io::stdin().read_line(&mut token) {
+            error!("Read token error: {}", e);
+            exit(1)
+        }
+        if let Err(e) = auth::store_secret(entry, token.trim()) {
+            error!("Store token error: {}", e);
+            exit(1)
+        }
+        println!(
+            "Token stored as 'keyring:{}'",
+            entry.trim_start_matches("keyring:")
+        );
+        exit(0)
+    }
+
     if cli.gernerate {
         let p = &cli.path.unwrap_or_else(|| "gitv.example.yaml".to_string());
         let mut f = match File::create(p) {
             Err(e) => {
-                println!("Create config file error: {}", e);
+                error!("Create config file error: {}", e);
                 exit(1)
             }
             Ok(f) => f,
         };
 
         if let Err(e) = f.write_all(DEFAULT_CONFIG.as_bytes()) {
-            println!("Write config file error: {}", e);
+            error!("Write config file error: {}", e);
             exit(1)
         }
         exit(0)
     }
 
-    let c: Config = match config::load_config(&cli.path.unwrap_or_else(|| "gitv.yaml".to_string()))
-    {
+    if let Some(Command::Demo { dir }) = &cli.command {
+        let dir = dir.as_deref().unwrap_or("gitv-demo");
+        let config_path = match demo::generate(dir) {
+            Err(e) => {
+                error!("Generate demo error: {}", e);
+                exit(1)
+            }
+            Ok(p) => p.to_string_lossy().to_string(),
+        };
+        let render_config = match config::load_configs(&[config_path.clone()]) {
+            Err(e) => {
+                error!("Load config error: {}", e);
+                exit(1)
+            }
+            Ok(c) => c.render.unwrap_or_default(),
+        };
+        let executions = render_config.executions.clone();
+        let periods = render_config.periods.clone();
+        let timezone = render_config.display.timezone.clone();
+        let ctx = match Executor::create_context(executions, periods, timezone).await {
+            Err(e) => {
+                error!("Create executor context error: {}", e);
+                exit(1)
+            }
+            Ok(ctx) => ctx,
+        };
+
+        let mut renderer = render::create_render(ctx, render_config.clone());
+        if let Err(e) = renderer.render().await {
+            error!("Render output error: {:#}", e);
+            exit(1);
+        }
+        println!(
+            "Demo charts are rendered into '{}', edit '{}' and run `gitv -r {}` to render again",
+            render_config.display.destination, config_path, config_path
+        );
+        exit(0)
+    }
+
+    if cli.repos_from.is_some() && !cli.create {
+        error!("--repos-from can only be used with --create");
+        exit(1)
+    }
+
+    if let Some(source) = &cli.repos_from {
+        let clone_dir = format!("{}/repos", cli.db_dir);
+        let mut lines = String::new();
+        let read = match source.as_str() {
+            "-" => std::io::stdin().read_to_string(&mut lines).map(|_| ()),
+            p => fs::read_to_string(p).map(|s| lines = s),
+        };
+        if let Err(e) = read.and_then(|_| fs::create_dir_all(&cli.db_dir)) {
+            error!("Read repos error: {}", e);
+            exit(1)
+        }
+
+        let create_config = CreateAction {
+            wait_lock: Some(cli.wait),
+            select: Some(cli.select),
+            retry_failed: Some(cli.retry_failed),
+            databases: vec![Database::from_lines(&cli.db_dir, &clone_dir, &lines)],
+            ..Default::default()
+        };
+        if let Err(e) = CsvSerializer::serialize(create_config).await {
+            error!("Create database error: {}", e);
+            exit(1);
+        };
+        exit(0)
+    }
+
+    let config_paths = cli.config_paths();
+    let config_path = config_paths.join(", ");
+    let c: Config = match config::load_configs(&config_paths) {
         Err(e) => {
-            println!("Load config error: {}", e);
+            error!("Load config error: {}", e);
             exit(1);
         }
         Ok(c) => c,
     };
+    http::configure(c.proxy.clone());
+
+    if cli.check {
+        let problems = check::check(&c).await;
+        if problems.is_empty() {
+            println!("Config '{}' is valid", config_path);
+            exit(0)
+        }
+        for problem in problems.iter() {
+            println!("[error] {}", problem);
+        }
+        error!(
+            "Check config error: {} problems found in '{}'",
+            problems.len(),
+            config_path
+        );
+        exit(1)
+    }
+
+    // 同时指定多个 action 时按照 fetch -> create -> render 的顺序依次执行
+    if cli.fetch && c.fetch.is_some() {
+        let repo_fetcher = RepoFetcher::new(c.fetch.unwrap(), cli.dry_run);
+        if let Err(e) = repo_fetcher.fetch().await {
+            error!("Fetch repos error: {}", e);
+            exit(1);
+        };
+        if !cli.create && !cli.render {
+            exit(0)
+        }
+    }
 
     if cli.create && c.create.is_some() {
-        if let Err(e) = CsvSerializer::serialize(c.create.unwrap()).await {
-            println!("Create database error: {}", e);
+        let mut create_config = c.create.clone().unwrap();
+        if cli.wait {
+            create_config.wait_lock = Some(true);
+        }
+        if cli.select {
+            create_config.select = Some(true);
+        }
+        if cli.retry_failed {
+            create_config.retry_failed = Some(true);
+        }
+        if let Err(e) = CsvSerializer::serialize(create_config).await {
+            error!("Create database error: {}", e);
             exit(1);
         };
+        if !cli.render {
+            exit(0)
+        }
+    }
+
+    if let Some(Command::Verify { .. }) = &cli.command {
+        if let Err(e) = verify::verify(&c.create.unwrap_or_default()) {
+            error!("Verify databases error: {}", e);
+            exit(1);
+        }
         exit(0)
     }
 
-    if cli.fetch && c.fetch.is_some() {
-        let repo_fetcher = RepoFetcher::new(c.fetch.unwrap());
-        if let Err(e) = repo_fetcher.fetch().await {
-            println!("Fetch repos error: {}", e);
+    if let Some(Command::Grep { pattern, .. }) = &cli.command {
+        let shell_config = c.shell.unwrap_or_default();
+        let executions = shell_config.executions.clone();
+        let ctx = match Executor::create_context(executions, shell_config.periods, None).await {
+            Err(e) => {
+                error!("Create executor context error: {}", e);
+                exit(1)
+            }
+            Ok(ctx) => ctx,
+        };
+
+        if let Err(e) = shell::grep(ctx, &shell_config.executions, pattern).await {
+            error!("Grep commits error: {}", e);
+            exit(1);
+        }
+        exit(0)
+    }
+
+    if let Some(Command::Tui { .. }) = &cli.command {
+        let shell_config = c.shell.unwrap_or_default();
+        let executions = shell_config.executions.clone();
+        let ctx = match Executor::create_context(executions, shell_config.periods, None).await {
+            Err(e) => {
+                error!("Create executor context error: {}", e);
+                exit(1)
+            }
+            Ok(ctx) => ctx,
+        };
+
+        if let Err(e) = dashboard::show(ctx, &shell_config.executions).await {
+            error!("Show dashboard error: {}", e);
+            exit(1);
+        }
+        exit(0)
+    }
+
+    if cli.render && cli.shell && c.render.is_some() {
+        let render_config = c.render.unwrap();
+        let executions = render_config.executions.clone();
+        let periods = render_config.periods.clone();
+        let timezone = render_config.display.timezone.clone();
+        let mut ctx = match Executor::create_context(executions, periods, timezone).await {
+            Err(e) => {
+                error!("Create executor context error: {}", e);
+                exit(1)
+            }
+            Ok(ctx) => ctx,
+        };
+
+        let mut renderer = render::create_render(ctx.clone(), render_config.clone());
+        if let Err(e) = renderer.render().await {
+            error!("Render output error: {:#}", e);
+            exit(1);
+        }
+        match render::register_results(&mut ctx, &render_config).await {
+            Err(e) => {
+                error!("Register query results error: {:#}", e);
+                exit(1)
+            }
+            Ok(tables) => println!("Query results are registered as: {}\n", tables.join(", ")),
+        }
+
+        if let Err(e) = shell::console_loop(
+            ctx,
+            &render_config.executions,
+            render_config.query_library.as_deref(),
+        )
+        .await
+        {
+            error!("Shell console loop error: {}", e);
             exit(1);
         };
         exit(0)
     }
 
     if cli.shell && c.shell.is_some() {
-        let ctx = Executor::create_context(c.shell.unwrap().executions).await;
+        let shell_config = c.shell.unwrap();
+        let ctx =
+            Executor::create_context(shell_config.executions.clone(), shell_config.periods, None)
+                .await;
         let ctx = match ctx {
             Err(e) => {
-                println!("Create executor context error: {}", e);
+                error!("Create executor context error: {}", e);
                 exit(1)
             }
             Ok(ctx) => ctx,
         };
 
-        if let Err(e) = shell::console_loop(ctx).await {
-            println!("Shell console loop error: {}", e);
+        if let Err(e) = shell::console_loop(
+            ctx,
+            &shell_config.executions,
+            shell_config.query_library.as_deref(),
+        )
+        .await
+        {
+            error!("Shell console loop error: {}", e);
             exit(1);
         };
         exit(0)
     }
 
+    if cli.render && cli.dev {
+        if let Err(e) = devserver::DevServer::new(&config_paths, &cli.dev_addr)
+            .serve()
+            .await
+        {
+            error!("Dev server error: {}", e);
+            exit(1);
+        }
+        exit(0)
+    }
+
+    if cli.serve {
+        let addr = format!("{}:{}", cli.host, cli.port);
+        if let Err(e) = devserver::DevServer::serving(&config_paths, &addr, cli.refresh)
+            .serve()
+            .await
+        {
+            error!("Serve error: {:#}", e);
+            exit(1);
+        }
+        exit(0)
+    }
+
     if cli.render && c.render.is_some() {
         let render_config = c.render.unwrap();
         let executions = render_config.executions.clone();
-        let ctx = match Executor::create_context(executions).await {
+        let periods = render_config.periods.clone();
+        let timezone = render_config.display.timezone.clone();
+        let ctx = match Executor::create_context(executions, periods, timezone).await {
             Err(e) => {
-                println!("Create executor context error: {}", e);
+                error!("Create executor context error: {}", e);
                 exit(1)
             }
             Ok(ctx) => ctx,
         };
 
+        if cli.list_vars {
+            if let Err(e) = render::list_vars(ctx, render_config).await {
+                error!("List variables error: {:#}", e);
+                exit(1);
+            }
+            exit(0)
+        }
+
         if let Err(e) = render::create_render(ctx, render_config).render().await {
-            println!("Render output error: {}", e);
+            error!("Render output error: {:#}", e);
             exit(1);
         }
         exit(0)
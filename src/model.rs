@@ -0,0 +1,233 @@
+use crate::{auth::GitCredential, config::NormalizeOptions};
+use anyhow::Result;
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Repository {
+    pub name: String,
+    pub branch: Option<String>,
+    /// 固定检出的 commit/tag，设置后将忽略 branch 的最新提交
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    pub remote: Option<String>,
+    pub path: String,
+    pub forks_count: Option<usize>,
+    pub stargazers_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fork: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub private: Option<bool>,
+    /// 许可证的 SPDX id，未设置时分析时从仓库的 LICENSE 文件中识别
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// 由 database 的 excludePaths 生成，不参与序列化
+    #[serde(skip)]
+    pub exclude_paths: PathFilter,
+    /// 由 create 的 cloneCredentials 生成，只用于 clone 以及 pull，不参与序列化
+    #[serde(skip)]
+    pub credential: Option<GitCredential>,
+}
+
+/// 文件路径过滤规则，由 glob 列表编译而来
+///
+/// - 不包含 `/` 的规则匹配任意层级的文件名，如 `*.lock`
+/// - 包含 `/` 的规则从仓库根目录开始匹配，`**` 匹配任意层级的目录，如 `vendor/**`
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter(Vec<regex::Regex>);
+
+impl PathFilter {
+    pub fn try_new(globs: &[String]) -> Result<Self> {
+        let mut patterns = vec![];
+        for glob in globs {
+            patterns.push(regex::Regex::new(&Self::glob_to_regex(glob))?);
+        }
+        Ok(Self(patterns))
+    }
+
+    pub fn is_excluded(&self, path: &str) -> bool {
+        self.0.iter().any(|re| re.is_match(path))
+    }
+
+    fn glob_to_regex(glob: &str) -> String {
+        let mut re = String::from("^");
+        let glob = match glob.trim_start_matches('/') {
+            g if g.contains('/') => g,
+            g => {
+                re.push_str("(.*/)?");
+                g
+            }
+        };
+
+        let mut chars = glob.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        re.push_str("(.*/)?");
+                    } else {
+                        re.push_str(".*");
+                    }
+                }
+                '*' => re.push_str("[^/]*"),
+                '?' => re.push_str("[^/]"),
+                c => re.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        re.push('$');
+        re
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Author {
+    pub name: String,
+    pub email: String,
+}
+
+impl Author {
+    pub fn domain(&self) -> String {
+        let email = self.email.clone();
+        let fields = email.splitn(2, '@').collect::<Vec<&str>>();
+        fields.last().unwrap_or(&"").to_string()
+    }
+
+    /// 归一化作者信息：按选项去除首尾空白、邮箱转为小写以及去除邮箱的 `+tag` 后缀
+    pub fn normalize(&self, opts: &NormalizeOptions) -> Author {
+        let (mut name, mut email) = (self.name.clone(), self.email.clone());
+        if opts.trim() {
+            name = name.trim().to_string();
+            email = email.trim().to_string();
+        }
+        if opts.lowercase() {
+            email = email.to_lowercase();
+        }
+        if opts.strip_plus() {
+            if let Some((local, domain)) = email.split_once('@') {
+                let local = local.split_once('+').map(|(l, _)| l).unwrap_or(local);
+                email = format!("{}@{}", local, domain);
+            }
+        }
+        Author { name, email }
+    }
+}
+
+/// 提交记录
+#[derive(Debug, Clone, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Commit {
+    /// 仓库名称
+    pub repo: String,
+    /// Commit hash
+    pub hash: String,
+    /// 提交作者
+    pub author: Author,
+    /// 提交日期
+    pub datetime: RfcDateTime,
+    /// 提交标题
+    pub subject: String,
+    /// 是否为 merge 提交
+    pub is_merge: bool,
+    /// `Co-authored-by` trailer 中的共同作者，不包含提交作者
+    pub co_authors: Vec<Author>,
+    /// 变动文件数
+    pub change_files: i64,
+    /// 文件变更记录
+    pub changes: Vec<FileExtChange>,
+}
+
+#[derive(Debug, Clone, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RfcDateTime(pub(crate) String);
+
+impl RfcDateTime {
+    pub fn to_rfc339(&self) -> String {
+        match DateTime::parse_from_rfc2822(&self.0) {
+            Ok(t) => t.to_rfc3339(),
+            Err(_) => String::new(),
+        }
+    }
+
+    pub fn timestamp(&self) -> Option<i64> {
+        DateTime::parse_from_rfc2822(&self.0)
+            .ok()
+            .map(|t| t.timestamp())
+    }
+}
+
+impl Commit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// 文件变更记录
+#[derive(Debug, Clone, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FileExtChange {
+    /// 文件扩展名
+    pub ext: String,
+    /// 文件改动增加行数
+    pub insertion: usize,
+    /// 文件改动删除函数
+    pub deletion: usize,
+}
+
+impl FileExtChange {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Tags 数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Tag {
+    /// 版本号
+    pub tag: String,
+    /// 提交时间
+    pub datetime: RfcDateTime,
+    /// 是否为附注标签（annotated tag）
+    pub annotated: bool,
+    /// 打标签的作者，轻量标签为空
+    pub tagger_name: String,
+    /// 打标签的时间，轻量标签为空
+    pub tag_datetime: RfcDateTime,
+}
+
+/// Github 仓库的 star 记录
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Stargazer {
+    /// star 用户
+    pub user: String,
+    /// star 时间，RFC3339 格式
+    pub starred_at: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// 统计周期，HEAD 快照为空
+    pub period: String,
+    /// 提交时间
+    pub datetime: RfcDateTime,
+    /// 文件统计数据
+    pub stats: Vec<FileExtStat>,
+}
+
+/// 文件统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileExtStat {
+    /// 文件扩展名
+    pub ext: String,
+    /// 文件代码行数
+    pub code: usize,
+    /// 文件注释行数
+    pub comments: usize,
+    /// 文件空格行数
+    pub blanks: usize,
+}
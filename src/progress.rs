@@ -0,0 +1,138 @@
+use anyhow::{anyhow, Result};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+lazy_static! {
+    /// 所有阶段的进度条共享同一个 MultiProgress，多个 database 并发时同时展示
+    static ref MULTI: MultiProgress = MultiProgress::new();
+}
+
+/// 隐藏所有进度条，用于 `--quiet` 模式
+pub fn hide() {
+    MULTI.set_draw_target(ProgressDrawTarget::hidden());
+}
+
+static PROGRESS_TEMPLATE: &str =
+    "{prefix:>16} [{bar:40.cyan/blue}] {pos}/{len} elapsed {elapsed_precise} ETA {eta} {wide_msg}";
+
+/// 某个阶段（如 git clone/pull、git analyze）的进度条，同时记录失败的仓库
+///
+/// 默认每个仓库推进 1 个单位，设置了仓库权重（如提交数）时按权重推进，ETA 也按权重估算
+#[derive(Clone)]
+pub struct Progress {
+    bar: ProgressBar,
+    total: usize,
+    done: Arc<AtomicUsize>,
+    weights: Arc<HashMap<String, u64>>,
+    failed: Arc<Mutex<Vec<(String, String)>>>,
+}
+
+impl Progress {
+    pub fn new(prefix: &str, total: usize) -> Self {
+        Self::build(prefix, total, HashMap::new())
+    }
+
+    /// 按仓库权重推进的进度条，权重为 0 的仓库按 1 计算
+    pub fn weighted(prefix: &str, weights: HashMap<String, u64>) -> Self {
+        Self::build(prefix, weights.len(), weights)
+    }
+
+    fn build(prefix: &str, total: usize, weights: HashMap<String, u64>) -> Self {
+        let len = match weights.is_empty() {
+            true => total as u64,
+            false => weights.values().map(|w| (*w).max(1)).sum(),
+        };
+        let bar = MULTI.add(ProgressBar::new(len));
+        bar.set_style(
+            ProgressStyle::with_template(PROGRESS_TEMPLATE)
+                .unwrap()
+                .progress_chars("=> "),
+        );
+        bar.set_prefix(prefix.to_string());
+        Self {
+            bar,
+            total,
+            done: Arc::new(AtomicUsize::new(0)),
+            weights: Arc::new(weights),
+            failed: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    fn weight(&self, repo: &str) -> u64 {
+        self.weights.get(repo).copied().unwrap_or(1).max(1)
+    }
+
+    /// 推进仓库的进度，返回已处理的仓库数
+    fn advance(&self, repo: &str) -> usize {
+        self.bar.inc(self.weight(repo));
+        self.done.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// 标记仓库开始处理
+    pub fn start(&self, repo: &str) {
+        match self.weights.get(repo) {
+            Some(weight) => self.bar.set_message(format!("{} ({})", repo, weight)),
+            None => self.bar.set_message(repo.to_string()),
+        }
+    }
+
+    /// 仓库处理成功，输出日志时暂时隐藏进度条，日志输出在进度条上方
+    pub fn success(&self, repo: &str, msg: String) {
+        let done = self.advance(repo);
+        self.bar
+            .suspend(|| info!("[{}/{}] {}", done, self.total, msg));
+    }
+
+    /// 仓库无需处理，只推进进度
+    pub fn skip(&self, repo: &str) {
+        self.advance(repo);
+    }
+
+    /// 输出警告信息，不影响进度
+    pub fn warn(&self, msg: String) {
+        self.bar.suspend(|| warn!("{}", msg));
+    }
+
+    pub fn fail(&self, repo: &str, err: anyhow::Error) {
+        self.advance(repo);
+        self.bar
+            .suspend(|| error!("[failed] '{}' => {}", repo, err));
+        self.failed
+            .lock()
+            .unwrap()
+            .push((repo.to_string(), err.to_string()));
+    }
+
+    /// 失败的仓库名称以及失败原因
+    pub fn failed(&self) -> Vec<(String, String)> {
+        self.failed.lock().unwrap().clone()
+    }
+
+    /// 结束进度条，存在失败的仓库时打印汇总信息并返回错误
+    pub fn finish(&self) -> Result<()> {
+        self.bar.finish_and_clear();
+        let failed = self.failed.lock().unwrap();
+        if failed.is_empty() {
+            return Ok(());
+        }
+
+        error!(
+            "{} => {} of {} repos failed:",
+            self.bar.prefix(),
+            failed.len(),
+            self.total
+        );
+        for (repo, err) in failed.iter() {
+            error!("  - {}: {}", repo, err);
+        }
+        Err(anyhow!("{} repos failed", failed.len()))
+    }
+}
@@ -1,14 +1,35 @@
-use crate::{config::Repository, gitimp::*, AuthorMapping, CreateAction, Database, GitImpl};
-use anyhow::Result;
+use crate::{
+    auth,
+    config::{
+        AuthorMapping, AuthorResolver, CloneCredential, CreateAction, CsvOptions, Database,
+        ForkDedup, NormalizeOptions, PatternThresholds, RemoteMismatch, Sampling, SnapshotInterval,
+        Stargazers, StatusThresholds,
+    },
+    dedup::Deduplicator,
+    delta::DeltaReport,
+    enricher, fetcher,
+    gitimp::*,
+    gitmoji, license,
+    model::{Commit, Repository},
+    progress::Progress,
+    selector,
+    skiplist::SkipList,
+};
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, Local};
+use log::{info, warn};
 use serde::Serialize;
+use sha2::{Digest, Sha256};
 use std::{
-    fs::File,
-    path::Path,
-    sync::{Arc, Mutex},
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 use tokio::{
-    sync::{self, mpsc::Sender},
+    sync::{self, mpsc::Sender, Semaphore},
     task::JoinHandle,
     time,
 };
@@ -19,7 +40,18 @@ pub enum RecordType {
     Change(RecordChange),
     Tag(RecordTag),
     Snapshot(RecordSnapshot),
+    SnapshotHistory(RecordSnapshotHistory),
     Active(RecordActive),
+    Star(RecordStar),
+    Repo(RecordRepo),
+    Status(RecordStatus),
+    Retention(RecordRetention),
+    Migration(RecordMigration),
+    WorkingPattern(RecordWorkingPattern),
+    FirstContribution(RecordFirstContribution),
+    Enrichment(RecordEnrichment),
+    /// 按 sampling 采样分析的仓库名称，写入 meta 表
+    Sampled(String),
 }
 
 #[derive(Debug, Default, Serialize, Clone)]
@@ -31,6 +63,12 @@ pub struct RecordCommit {
     pub author_name: String,
     pub author_email: String,
     pub author_domain: String,
+    pub subject: String,
+    pub is_merge: bool,
+    /// 由 `Co-authored-by` trailer 生成的共同作者记录
+    pub is_co_author: bool,
+    /// 提交标题开头的 emoji 或者 gitmoji 代码
+    pub emoji: String,
 }
 
 impl RecordCommit {
@@ -65,6 +103,9 @@ pub struct RecordTag {
     pub branch: String,
     pub datetime: String,
     pub tag: String,
+    pub tagger_name: String,
+    pub tag_datetime: String,
+    pub is_annotated: bool,
 }
 
 impl RecordTag {
@@ -82,6 +123,10 @@ pub struct RecordSnapshot {
     pub code: usize,
     pub comments: usize,
     pub blanks: usize,
+    /// 该语言历史累计的新增行数，由 change 记录汇总得到
+    pub insertions: usize,
+    /// 该语言历史累计的删除行数，由 change 记录汇总得到
+    pub deletions: usize,
 }
 
 impl RecordSnapshot {
@@ -90,6 +135,25 @@ impl RecordSnapshot {
     }
 }
 
+/// 按周期（snapshotInterval）统计的历史代码行数
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct RecordSnapshotHistory {
+    pub repo_name: String,
+    pub branch: String,
+    pub period: String,
+    pub datetime: String,
+    pub ext: String,
+    pub code: usize,
+    pub comments: usize,
+    pub blanks: usize,
+}
+
+impl RecordSnapshotHistory {
+    pub fn name() -> String {
+        String::from("snapshot_history")
+    }
+}
+
 #[derive(Debug, Default, Serialize, Clone)]
 pub struct RecordActive {
     pub repo_name: String,
@@ -103,6 +167,285 @@ impl RecordActive {
     }
 }
 
+/// Github 仓库的 star 时间线，可用于绘制 star 增长趋势
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct RecordStar {
+    pub repo_name: String,
+    pub user: String,
+    pub starred_at: String,
+}
+
+impl RecordStar {
+    pub fn name() -> String {
+        String::from("star")
+    }
+}
+
+/// Github 仓库的 issue，由 fetch 写入
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct RecordIssue {
+    pub repo_name: String,
+    pub number: u64,
+    pub author: String,
+    pub state: String,
+    pub labels: String,
+    pub comments: u64,
+    pub created_at: String,
+    pub closed_at: String,
+}
+
+impl RecordIssue {
+    pub fn name() -> String {
+        String::from("issue")
+    }
+}
+
+/// Github 仓库的 pull request，由 fetch 写入，未合并时 merged_at 为空
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct RecordPullRequest {
+    pub repo_name: String,
+    pub number: u64,
+    pub author: String,
+    pub state: String,
+    pub labels: String,
+    pub comments: u64,
+    pub created_at: String,
+    pub closed_at: String,
+    pub merged_at: String,
+}
+
+impl RecordPullRequest {
+    pub fn name() -> String {
+        String::from("pull_request")
+    }
+}
+
+/// issue 以及 pull request 的首次维护者回复，由 fetch 写入，未回复时 responder 以及 response_hours 为空
+///
+/// is_first_time 表示这是作者在该仓库中的第一个 issue/pull request
+#[derive(Debug, Default, Serialize, Clone, PartialEq)]
+pub struct RecordFirstResponse {
+    pub repo_name: String,
+    pub number: u64,
+    /// issue 或者 pull_request
+    pub kind: String,
+    pub author: String,
+    pub is_first_time: bool,
+    pub created_at: String,
+    pub responder: String,
+    pub responded_at: String,
+    pub response_hours: Option<f64>,
+}
+
+impl RecordFirstResponse {
+    pub fn name() -> String {
+        String::from("first_response")
+    }
+}
+
+/// database 的元数据，记录 gitv 版本、创建时间以及分析范围，用于展示数据的新鲜度以及来源
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct RecordMeta {
+    pub key: String,
+    pub value: String,
+}
+
+impl RecordMeta {
+    pub fn name() -> String {
+        String::from("meta")
+    }
+
+    fn new<V: ToString>(key: &str, value: V) -> Self {
+        Self {
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+}
+
+/// 仓库元数据，来源于 fetch 生成的仓库列表
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct RecordRepo {
+    pub repo_name: String,
+    pub default_branch: String,
+    pub visibility: String,
+    pub private: bool,
+    pub archived: bool,
+    pub fork: bool,
+    pub size: usize,
+    /// 许可证的 SPDX id，无法识别时为 NOASSERTION
+    pub license: String,
+}
+
+impl RecordRepo {
+    pub fn name() -> String {
+        String::from("repo")
+    }
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct RecordStatus {
+    pub repo_name: String,
+    pub branch: String,
+    pub last_commit: String,
+    pub inactive_days: i64,
+    pub recent_authors: usize,
+    pub previous_authors: usize,
+    pub trend: i64,
+    pub status: String,
+}
+
+impl RecordStatus {
+    pub fn name() -> String {
+        String::from("status")
+    }
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct RecordRetention {
+    pub repo_name: String,
+    pub branch: String,
+    pub quarter: String,
+    pub authors: usize,
+    pub new: usize,
+    pub retained: usize,
+    pub churned: usize,
+}
+
+impl RecordRetention {
+    pub fn name() -> String {
+        String::from("retention")
+    }
+}
+
+/// 作者在仓库中的工作模式
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct RecordWorkingPattern {
+    pub repo_name: String,
+    pub branch: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub commits: usize,
+    pub weekend_ratio: f64,
+    pub night_ratio: f64,
+    pub early_ratio: f64,
+    pub pattern: String,
+}
+
+impl RecordWorkingPattern {
+    pub fn name() -> String {
+        String::from("working_pattern")
+    }
+}
+
+/// 相邻历史快照之间的语言迁移
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct RecordMigration {
+    pub repo_name: String,
+    pub branch: String,
+    pub period: String,
+    pub from_language: String,
+    pub to_language: String,
+    pub magnitude: f64,
+}
+
+impl RecordMigration {
+    pub fn name() -> String {
+        String::from("migrations")
+    }
+}
+
+/// 作者在仓库中的首次提交
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct RecordFirstContribution {
+    pub repo_name: String,
+    pub branch: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub hash: String,
+    pub datetime: String,
+}
+
+impl RecordFirstContribution {
+    pub fn name() -> String {
+        String::from("first_contribution")
+    }
+}
+
+/// RecordEnricher 插件生成的额外字段，source 为来源表（commit/change）
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct RecordEnrichment {
+    pub repo_name: String,
+    pub hash: String,
+    pub source: String,
+    pub ext: String,
+    pub key: String,
+    pub value: String,
+}
+
+impl RecordEnrichment {
+    pub fn name() -> String {
+        String::from("enrichment")
+    }
+}
+
+/// 跨仓库重复的提交，kept 表示该行是否仍保留在 commit/change 表中
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct RecordDuplicate {
+    pub repo_name: String,
+    pub hash: String,
+    pub canonical: String,
+    pub kept: bool,
+}
+
+impl RecordDuplicate {
+    pub fn name() -> String {
+        String::from("duplicate")
+    }
+}
+
+/// 单次 create 运行的统计数据，每个 database 一条
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct RunStats {
+    pub datetime: String,
+    pub database: String,
+    pub repos: usize,
+    pub commits: usize,
+    pub rows: usize,
+    pub bytes: u64,
+    pub elapsed_secs: f64,
+    pub rows_per_sec: f64,
+}
+
+impl RunStats {
+    fn print(&self) {
+        info!(
+            "[stats] database '{}': repos {}, commits {}, rows {}, bytes {}, elapsed {:.2}s, rows/sec {:.2}",
+            self.database,
+            self.repos,
+            self.commits,
+            self.rows,
+            self.bytes,
+            self.elapsed_secs,
+            self.rows_per_sec,
+        );
+    }
+
+    /// 追加写入到本地 csv 文件，文件不存在时写入表头
+    fn append(stats: &[RunStats], path: &str) -> Result<()> {
+        let has_headers = fs::metadata(path).map(|m| m.len() == 0).unwrap_or(true);
+        let f = OpenOptions::new().create(true).append(true).open(path)?;
+        let mut wtr = csv::WriterBuilder::new()
+            .has_headers(has_headers)
+            .from_writer(f);
+        for stat in stats {
+            wtr.serialize(stat)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
 /// 定义 Record 序列化接口
 #[async_trait]
 pub trait RecordSerializer {
@@ -111,6 +454,34 @@ pub trait RecordSerializer {
 
 const BUFFER_SIZE: usize = 1000;
 
+/// 单个 database 创建时的选项，由 CreateAction 生成
+#[derive(Debug, Clone, Default)]
+struct CreateOptions {
+    author_mappings: Vec<AuthorMapping>,
+    disable_pull: bool,
+    unshallow: bool,
+    remote_mismatch: RemoteMismatch,
+    cache_dir: Option<String>,
+    clone_credentials: Vec<CloneCredential>,
+    concurrency: usize,
+    wait_lock: bool,
+    delta_report: bool,
+    thresholds: StatusThresholds,
+    churn_months: i64,
+    pattern_thresholds: PatternThresholds,
+    snapshot_interval: Option<SnapshotInterval>,
+    sampling: Option<Sampling>,
+    migration_threshold: f64,
+    stargazers: Option<Stargazers>,
+    fork_dedup: Option<ForkDedup>,
+    normalize_authors: Option<NormalizeOptions>,
+    first_contribution: bool,
+    skip_after_failures: u32,
+    retry_failed: bool,
+    log: LogOptions,
+    csv: CsvOptions,
+}
+
 /// Csv 序列化实现
 #[derive(Debug)]
 pub struct CsvSerializer;
@@ -122,7 +493,7 @@ impl CsvSerializer {
         commits: Vec<Commit>,
     ) -> Result<()> {
         for commit in commits {
-            let record = RecordCommit {
+            let mut record = RecordCommit {
                 repo_name: repo.name.clone(),
                 hash: commit.hash.clone(),
                 branch: repo.branch.clone().unwrap_or_default(),
@@ -130,13 +501,40 @@ impl CsvSerializer {
                 author_name: commit.author.name.clone(),
                 author_email: commit.author.email.clone(),
                 author_domain: commit.author.domain(),
+                subject: commit.subject.clone(),
+                is_merge: commit.is_merge,
+                is_co_author: false,
+                emoji: gitmoji::leading_emoji(&commit.subject).unwrap_or_default(),
             };
+            // 共同作者记录复用提交作者的记录，只替换作者信息
+            let co_authors = commit
+                .co_authors
+                .iter()
+                .map(|author| RecordCommit {
+                    author_name: author.name.clone(),
+                    author_email: author.email.clone(),
+                    author_domain: author.domain(),
+                    is_co_author: true,
+                    ..record.clone()
+                })
+                .collect::<Vec<_>>();
+            let extras = enricher::enrich_commit(&mut record);
             if tx.send(RecordType::Commit(record)).await.is_err() {
                 return Ok(());
             };
+            for co_author in co_authors {
+                if tx.send(RecordType::Commit(co_author)).await.is_err() {
+                    return Ok(());
+                };
+            }
+            for extra in extras {
+                if tx.send(RecordType::Enrichment(extra)).await.is_err() {
+                    return Ok(());
+                };
+            }
 
             for fc in commit.changes {
-                let record = RecordChange {
+                let mut record = RecordChange {
                     repo_name: repo.name.clone(),
                     hash: commit.hash.clone(),
                     branch: repo.branch.clone().unwrap_or_default(),
@@ -148,9 +546,15 @@ impl CsvSerializer {
                     insertion: fc.insertion,
                     deletion: fc.deletion,
                 };
+                let extras = enricher::enrich_change(&mut record);
                 if tx.send(RecordType::Change(record)).await.is_err() {
                     return Ok(());
                 };
+                for extra in extras {
+                    if tx.send(RecordType::Enrichment(extra)).await.is_err() {
+                        return Ok(());
+                    };
+                }
             }
         }
         Ok(())
@@ -159,8 +563,9 @@ impl CsvSerializer {
     async fn serialize_commits_sectional(
         tx: Sender<RecordType>,
         repo: &Repository,
-        author_mappings: Vec<AuthorMapping>,
+        authors: AuthorResolver,
         hashs: Vec<String>,
+        log: LogOptions,
     ) -> Result<()> {
         let concurrency = num_cpus::get();
 
@@ -175,13 +580,13 @@ impl CsvSerializer {
         let mut handles = vec![];
         for _ in 0..concurrency {
             let repo = repo.clone();
-            let mappings = author_mappings.clone();
+            let authors = authors.clone();
             let tx = tx.clone();
             let mut lines_rx = rxs.remove(0);
 
             let handle: JoinHandle<Result<(), anyhow::Error>> = tokio::spawn(async move {
                 while let Some(hash) = lines_rx.recv().await {
-                    let commits = GitImpl::commits(&repo, &mappings, &hash)?;
+                    let commits = GitImpl::commits(&repo, &authors, &hash, log)?;
                     Self::send_commit_records(&tx, &repo, commits).await?;
                 }
                 Ok(())
@@ -206,29 +611,49 @@ impl CsvSerializer {
     async fn serialize_commits(
         tx: Sender<RecordType>,
         repo: &Repository,
-        author_mappings: Vec<AuthorMapping>,
+        authors: AuthorResolver,
+        log: LogOptions,
+        sampling: Option<Sampling>,
     ) -> Result<()> {
         const MAX_COMMITS: usize = 10000;
-        let hashs = GitImpl::commits_hash(repo)?;
+        let mut hashs = GitImpl::commits_hash(repo, log, None)?;
+        if let Some(sampling) = sampling.filter(|s| hashs.len() > s.max_commits) {
+            let total = hashs.len();
+            if let Some(since) = &sampling.since {
+                hashs = GitImpl::commits_hash(repo, log, Some(since))?;
+            }
+            hashs = sampling.sample(hashs);
+            let record = RecordType::Sampled(repo.name.clone());
+            if tx.send(record).await.is_err() {
+                return Ok(());
+            }
+            warn!(
+                "[sampling] '{}' => {} commits exceed maxCommits {}, only {} sampled commits are analyzed and the results are approximate",
+                repo.name,
+                total,
+                sampling.max_commits,
+                hashs.len(),
+            );
+            return Self::serialize_commits_sectional(tx, repo, authors, hashs, log).await;
+        }
         if hashs.len() > MAX_COMMITS {
-            Self::serialize_commits_sectional(tx, repo, author_mappings, hashs).await?
+            Self::serialize_commits_sectional(tx, repo, authors, hashs, log).await?
         } else {
-            let commits = GitImpl::commits(repo, &author_mappings, "")?;
+            let commits = GitImpl::commits(repo, &authors, "", log)?;
             Self::send_commit_records(&tx, repo, commits).await?;
         }
         Ok(())
     }
 
-    async fn serialize_tags(
-        tx: Sender<RecordType>,
-        repo: &Repository,
-        author_mappings: Vec<AuthorMapping>,
-    ) -> Result<()> {
-        for tag in GitImpl::tags(repo, author_mappings)? {
+    async fn serialize_tags(tx: Sender<RecordType>, repo: &Repository) -> Result<()> {
+        for tag in GitImpl::tags(repo)? {
             let record = RecordTag {
                 repo_name: repo.name.clone(),
                 datetime: tag.datetime.to_rfc339(),
                 tag: tag.tag,
+                tagger_name: tag.tagger_name,
+                tag_datetime: tag.tag_datetime.to_rfc339(),
+                is_annotated: tag.annotated,
                 branch: repo.branch.clone().unwrap_or_default(),
             };
             if tx.send(RecordType::Tag(record)).await.is_err() {
@@ -238,7 +663,12 @@ impl CsvSerializer {
         Ok(())
     }
 
-    async fn serialize_snapshot(tx: Sender<RecordType>, repo: &Repository) -> Result<()> {
+    async fn serialize_snapshot(
+        tx: Sender<RecordType>,
+        repo: &Repository,
+        interval: Option<SnapshotInterval>,
+        migration_threshold: f64,
+    ) -> Result<()> {
         let snapshot = GitImpl::snapshot(repo)?;
         for stat in snapshot.stats {
             let record = RecordSnapshot {
@@ -249,11 +679,49 @@ impl CsvSerializer {
                 code: stat.code,
                 comments: stat.comments,
                 blanks: stat.blanks,
+                insertions: 0,
+                deletions: 0,
             };
             if tx.send(RecordType::Snapshot(record)).await.is_err() {
                 return Ok(());
             }
         }
+
+        let interval = match interval {
+            Some(interval) => interval,
+            None => return Ok(()),
+        };
+        let history = GitImpl::snapshot_history(repo, interval)?;
+        for migration in GitImpl::migrations(&history, migration_threshold) {
+            let record = RecordMigration {
+                repo_name: repo.name.clone(),
+                branch: repo.branch.clone().unwrap_or_default(),
+                period: migration.period,
+                from_language: migration.from_language,
+                to_language: migration.to_language,
+                magnitude: migration.magnitude,
+            };
+            if tx.send(RecordType::Migration(record)).await.is_err() {
+                return Ok(());
+            }
+        }
+        for snapshot in history {
+            for stat in snapshot.stats {
+                let record = RecordSnapshotHistory {
+                    repo_name: repo.name.clone(),
+                    branch: repo.branch.clone().unwrap_or_default(),
+                    period: snapshot.period.clone(),
+                    datetime: snapshot.datetime.to_rfc339(),
+                    ext: stat.ext,
+                    code: stat.code,
+                    comments: stat.comments,
+                    blanks: stat.blanks,
+                };
+                if tx.send(RecordType::SnapshotHistory(record)).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
         Ok(())
     }
 
@@ -269,30 +737,156 @@ impl CsvSerializer {
         Ok(())
     }
 
+    async fn serialize_stars(
+        tx: Sender<RecordType>,
+        repo: &Repository,
+        config: Option<Stargazers>,
+    ) -> Result<()> {
+        let (config, full_name) = match (config, GitImpl::github_full_name(repo)) {
+            (Some(config), Some(full_name)) => (config, full_name),
+            _ => return Ok(()),
+        };
+        for stargazer in fetcher::fetch_stargazers(&full_name, &config).await? {
+            let record = RecordStar {
+                repo_name: repo.name.clone(),
+                user: stargazer.user,
+                starred_at: stargazer.starred_at,
+            };
+            if tx.send(RecordType::Star(record)).await.is_err() {
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    async fn serialize_repo(tx: Sender<RecordType>, repo: &Repository) -> Result<()> {
+        let visibility = repo.visibility.clone().unwrap_or_default();
+        let record = RecordRepo {
+            repo_name: repo.name.clone(),
+            default_branch: repo.branch.clone().unwrap_or_default(),
+            private: repo.private.unwrap_or(visibility == "private"),
+            visibility,
+            archived: repo.archived.unwrap_or_default(),
+            fork: repo.fork.unwrap_or_default(),
+            size: repo.size.unwrap_or_default(),
+            license: repo
+                .license
+                .clone()
+                .or_else(|| license::detect(&repo.path))
+                .unwrap_or_default(),
+        };
+        if tx.send(RecordType::Repo(record)).await.is_err() {
+            return Ok(());
+        }
+        Ok(())
+    }
+
+    async fn serialize_activity(
+        tx: Sender<RecordType>,
+        repo: &Repository,
+        authors: AuthorResolver,
+        thresholds: StatusThresholds,
+        churn_months: i64,
+        first_contribution: bool,
+        pattern_thresholds: PatternThresholds,
+    ) -> Result<()> {
+        let commits = GitImpl::commits_info(repo, &authors)?;
+        for pattern in GitImpl::working_patterns(&commits, &pattern_thresholds) {
+            let record = RecordWorkingPattern {
+                repo_name: repo.name.clone(),
+                branch: repo.branch.clone().unwrap_or_default(),
+                author_name: pattern.author.name,
+                author_email: pattern.author.email,
+                commits: pattern.commits,
+                weekend_ratio: pattern.weekend_ratio,
+                night_ratio: pattern.night_ratio,
+                early_ratio: pattern.early_ratio,
+                pattern: pattern.pattern,
+            };
+            if tx.send(RecordType::WorkingPattern(record)).await.is_err() {
+                return Ok(());
+            }
+        }
+        if first_contribution {
+            for commit in GitImpl::first_contributions(&commits) {
+                let record = RecordFirstContribution {
+                    repo_name: repo.name.clone(),
+                    branch: repo.branch.clone().unwrap_or_default(),
+                    author_name: commit.author.name.clone(),
+                    author_email: commit.author.email.clone(),
+                    hash: commit.hash.clone(),
+                    datetime: commit.datetime.to_rfc339(),
+                };
+                if tx
+                    .send(RecordType::FirstContribution(record))
+                    .await
+                    .is_err()
+                {
+                    return Ok(());
+                }
+            }
+        }
+        for retention in GitImpl::retention(&commits, churn_months) {
+            let record = RecordRetention {
+                repo_name: repo.name.clone(),
+                branch: repo.branch.clone().unwrap_or_default(),
+                quarter: retention.quarter,
+                authors: retention.authors,
+                new: retention.new,
+                retained: retention.retained,
+                churned: retention.churned,
+            };
+            if tx.send(RecordType::Retention(record)).await.is_err() {
+                return Ok(());
+            }
+        }
+
+        let activity = GitImpl::activity(&commits, &thresholds);
+        let record = RecordStatus {
+            repo_name: repo.name.clone(),
+            branch: repo.branch.clone().unwrap_or_default(),
+            last_commit: activity.datetime.to_rfc339(),
+            inactive_days: activity.inactive_days,
+            recent_authors: activity.recent_authors,
+            previous_authors: activity.previous_authors,
+            trend: activity.recent_authors as i64 - activity.previous_authors as i64,
+            status: activity.status,
+        };
+        if tx.send(RecordType::Status(record)).await.is_err() {
+            return Ok(());
+        }
+        Ok(())
+    }
+
     async fn analyze_repo(
         tx: Sender<RecordType>,
         repo: &Repository,
-        author_mappings: Vec<AuthorMapping>,
+        opts: CreateOptions,
+        authors: AuthorResolver,
     ) -> Result<()> {
         let mut handles: Vec<JoinHandle<Result<(), anyhow::Error>>> = vec![];
-        for i in 0..4usize {
+        for i in 0..7usize {
             let repo = repo.clone();
             let tx = tx.clone();
-            let mappings = author_mappings.clone();
+            let authors = authors.clone();
             match i {
                 0 => {
+                    let log = opts.log;
+                    let sampling = opts.sampling.clone();
                     handles.push(tokio::spawn(async move {
-                        Self::serialize_commits(tx.clone(), &repo, mappings).await
+                        Self::serialize_commits(tx.clone(), &repo, authors, log, sampling).await
                     }));
                 }
                 1 => {
+                    let interval = opts.snapshot_interval;
+                    let threshold = opts.migration_threshold;
                     handles.push(tokio::spawn(async move {
-                        Self::serialize_snapshot(tx.clone(), &repo).await
+                        Self::serialize_snapshot(tx.clone(), &repo, interval, threshold).await
                     }));
                 }
                 2 => {
                     handles.push(tokio::spawn(async move {
-                        Self::serialize_tags(tx.clone(), &repo, mappings).await
+                        Self::serialize_tags(tx.clone(), &repo).await
                     }));
                 }
                 3 => {
@@ -300,6 +894,35 @@ impl CsvSerializer {
                         Self::serialize_active(tx.clone(), &repo).await
                     }));
                 }
+                4 => {
+                    let thresholds = opts.thresholds.clone();
+                    let churn_months = opts.churn_months;
+                    let first_contribution = opts.first_contribution;
+                    let pattern_thresholds = opts.pattern_thresholds.clone();
+                    handles.push(tokio::spawn(async move {
+                        Self::serialize_activity(
+                            tx.clone(),
+                            &repo,
+                            authors,
+                            thresholds,
+                            churn_months,
+                            first_contribution,
+                            pattern_thresholds,
+                        )
+                        .await
+                    }));
+                }
+                5 => {
+                    handles.push(tokio::spawn(async move {
+                        Self::serialize_repo(tx.clone(), &repo).await
+                    }));
+                }
+                6 => {
+                    let stargazers = opts.stargazers.clone();
+                    handles.push(tokio::spawn(async move {
+                        Self::serialize_stars(tx.clone(), &repo, stargazers).await
+                    }));
+                }
                 _ => unreachable!(),
             }
         }
@@ -309,100 +932,380 @@ impl CsvSerializer {
         Ok(())
     }
 
-    async fn serialize_records(
-        database: Database,
-        author_mappings: Vec<AuthorMapping>,
-        disable_pull: bool,
-    ) -> Result<()> {
-        let repos = database.load()?;
+    async fn serialize_records(database: Database, opts: CreateOptions) -> Result<RunStats> {
+        let start = time::Instant::now();
+        let config_hash = Sha256::digest(format!("{:?}{:?}", database, opts).as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        let lock = DatabaseLock::acquire(&database.dir, opts.wait_lock).await?;
+        let mut skip_list = SkipList::load(&database.dir, opts.skip_after_failures)?;
+        let repos = skip_list.filter(database.load()?, opts.retry_failed);
+        let names = repos.iter().map(|r| r.name.clone()).collect::<Vec<_>>();
         let total = repos.len();
+        let deduplicator = opts
+            .fork_dedup
+            .map(|policy| Deduplicator::new(policy, &repos));
 
         let (tx, mut rx) = sync::mpsc::channel::<RecordType>(BUFFER_SIZE);
-        let mutex = Arc::new(Mutex::new(0));
-        let mut handles: Vec<JoinHandle<Result<(), anyhow::Error>>> = vec![];
+        let mut handles: Vec<JoinHandle<()>> = vec![];
 
-        GitImpl::clone_or_pull(repos.clone(), disable_pull).await?;
+        // 凭证只用于 clone 以及 pull，不写入 remote 地址以及分析记录
+        let mut clone_repos = repos.clone();
+        for repo in clone_repos.iter_mut() {
+            if let Some(remote) = &repo.remote {
+                repo.credential = auth::clone_credential(remote, &opts.clone_credentials)?;
+            }
+        }
+        let sync = Progress::new("git sync", clone_repos.len());
+        GitImpl::clone_or_pull(
+            clone_repos,
+            opts.disable_pull,
+            opts.unshallow,
+            opts.remote_mismatch,
+            opts.cache_dir.clone(),
+            opts.concurrency,
+            &sync,
+        )
+        .await?;
+        let failed = sync.failed();
+        if !failed.is_empty() {
+            skip_list.update(&names, &failed, Local::now());
+            skip_list.save()?;
+        }
+        sync.finish()?;
+        // 按照提交数推进进度，提交较多的仓库在 ETA 中占更大的比重
+        let weights = repos
+            .iter()
+            .map(|repo| {
+                let count = GitImpl::commit_count(repo).unwrap_or_default();
+                (repo.name.clone(), count)
+            })
+            .collect::<HashMap<_, _>>();
+        let progress = Progress::weighted(&format!("analyze {}", database.dir), weights);
+        let authors = AuthorResolver::new(opts.normalize_authors, opts.author_mappings.clone());
+        let semaphore = Arc::new(Semaphore::new(opts.concurrency.max(1)));
         for repo in repos {
             let repo = repo.clone();
-            let mappings = author_mappings.clone();
+            let opts = opts.clone();
+            let authors = authors.clone();
             let tx = tx.clone();
-            let mutex = mutex.clone();
+            let progress = progress.clone();
+            let semaphore = semaphore.clone();
 
             let handle = tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
                 let now = time::Instant::now();
-                GitImpl::checkout(&repo)?;
-                Self::analyze_repo(tx.clone(), &repo, mappings).await?;
-
-                let mut lock = mutex.lock().unwrap();
-                *lock += 1;
-                let n = lock;
-                println!(
-                    "[{}/{}] git analyze '{}' => elapsed {:#?}",
-                    n,
-                    total,
-                    repo.name,
-                    now.elapsed(),
-                );
-                Ok(())
+                progress.start(&repo.name);
+                let result = match GitImpl::checkout(&repo) {
+                    Ok(_) => Self::analyze_repo(tx.clone(), &repo, opts, authors).await,
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Ok(_) => progress.success(
+                        &repo.name,
+                        format!(
+                            "git analyze '{}' => elapsed {:#?}",
+                            repo.name,
+                            now.elapsed(),
+                        ),
+                    ),
+                    Err(e) => progress.fail(&repo.name, e),
+                }
             });
             handles.push(handle)
         }
 
-        let rev: JoinHandle<Result<(), anyhow::Error>> = tokio::spawn(async move {
-            let dir = &database.dir;
-            let mut commit_wtr = CsvWriter::try_new(dir, RecordCommit::name())?;
-            let mut change_wtr = CsvWriter::try_new(dir, RecordChange::name())?;
-            let mut tag_wtr = CsvWriter::try_new(dir, RecordTag::name())?;
-            let mut snapshot_wtr = CsvWriter::try_new(dir, RecordSnapshot::name())?;
-            let mut active_wtr = CsvWriter::try_new(dir, RecordActive::name())?;
-
-            while let Some(record) = rx.recv().await {
-                match record {
-                    RecordType::Commit(commit) => commit_wtr.write(commit)?,
-                    RecordType::Change(change) => change_wtr.write(change)?,
-                    RecordType::Tag(tag) => tag_wtr.write(tag)?,
-                    RecordType::Snapshot(snapshot) => snapshot_wtr.write(snapshot)?,
-                    RecordType::Active(active) => active_wtr.write(active)?,
+        let dir = database.dir.clone();
+        let csv = opts.csv.clone();
+        let rev: JoinHandle<Result<(RunStats, Vec<CsvWriter>, CommitRange, Vec<String>)>> =
+            tokio::spawn(async move {
+                let dir = &database.dir;
+                let mut commit_wtr = CsvWriter::try_new(dir, RecordCommit::name(), &csv)?;
+                let mut change_wtr = CsvWriter::try_new(dir, RecordChange::name(), &csv)?;
+                let mut tag_wtr = CsvWriter::try_new(dir, RecordTag::name(), &csv)?;
+                let mut snapshot_wtr = CsvWriter::try_new(dir, RecordSnapshot::name(), &csv)?;
+                let mut snapshot_history_wtr =
+                    CsvWriter::try_new(dir, RecordSnapshotHistory::name(), &csv)?;
+                let mut active_wtr = CsvWriter::try_new(dir, RecordActive::name(), &csv)?;
+                let mut star_wtr = CsvWriter::try_new(dir, RecordStar::name(), &csv)?;
+                let mut repo_wtr = CsvWriter::try_new(dir, RecordRepo::name(), &csv)?;
+                let mut status_wtr = CsvWriter::try_new(dir, RecordStatus::name(), &csv)?;
+                let mut retention_wtr = CsvWriter::try_new(dir, RecordRetention::name(), &csv)?;
+                let mut migration_wtr = CsvWriter::try_new(dir, RecordMigration::name(), &csv)?;
+                let mut working_pattern_wtr =
+                    CsvWriter::try_new(dir, RecordWorkingPattern::name(), &csv)?;
+                let mut first_contribution_wtr =
+                    CsvWriter::try_new(dir, RecordFirstContribution::name(), &csv)?;
+                let mut enrichment_wtr = CsvWriter::try_new(dir, RecordEnrichment::name(), &csv)?;
+
+                // snapshot 记录需要等所有 change 记录汇总完成后再写入
+                let mut churn: HashMap<(String, String), (usize, usize)> = HashMap::new();
+                let mut snapshots = vec![];
+                let mut range = CommitRange::default();
+                let mut sampled = vec![];
+                while let Some(record) = rx.recv().await {
+                    match record {
+                        RecordType::Commit(commit) => {
+                            range.update(&commit.datetime);
+                            commit_wtr.write(commit)?
+                        }
+                        RecordType::Change(change) => {
+                            let key =
+                                (change.repo_name.clone(), GitImpl::ext_language(&change.ext));
+                            let total = churn.entry(key).or_default();
+                            total.0 += change.insertion;
+                            total.1 += change.deletion;
+                            change_wtr.write(change)?
+                        }
+                        RecordType::Tag(tag) => tag_wtr.write(tag)?,
+                        RecordType::Snapshot(snapshot) => snapshots.push(snapshot),
+                        RecordType::SnapshotHistory(snapshot) => {
+                            snapshot_history_wtr.write(snapshot)?
+                        }
+                        RecordType::Active(active) => active_wtr.write(active)?,
+                        RecordType::Star(star) => star_wtr.write(star)?,
+                        RecordType::Repo(repo) => repo_wtr.write(repo)?,
+                        RecordType::Status(status) => status_wtr.write(status)?,
+                        RecordType::Retention(retention) => retention_wtr.write(retention)?,
+                        RecordType::Migration(migration) => migration_wtr.write(migration)?,
+                        RecordType::WorkingPattern(pattern) => {
+                            working_pattern_wtr.write(pattern)?
+                        }
+                        RecordType::FirstContribution(first) => {
+                            first_contribution_wtr.write(first)?
+                        }
+                        RecordType::Enrichment(enrichment) => enrichment_wtr.write(enrichment)?,
+                        RecordType::Sampled(repo_name) => sampled.push(repo_name),
+                    }
+                }
+
+                for mut snapshot in snapshots {
+                    let key = (snapshot.repo_name.clone(), snapshot.ext.clone());
+                    if let Some((insertions, deletions)) = churn.get(&key) {
+                        snapshot.insertions = *insertions;
+                        snapshot.deletions = *deletions;
+                    }
+                    snapshot_wtr.write(snapshot)?;
                 }
-            }
 
-            commit_wtr.flush()?;
-            change_wtr.flush()?;
-            tag_wtr.flush()?;
-            snapshot_wtr.flush()?;
-            active_wtr.flush()?;
-            Ok(())
-        });
+                let mut stats = RunStats {
+                    commits: commit_wtr.rows,
+                    ..Default::default()
+                };
+                let mut writers = vec![
+                    commit_wtr,
+                    change_wtr,
+                    tag_wtr,
+                    snapshot_wtr,
+                    snapshot_history_wtr,
+                    active_wtr,
+                    star_wtr,
+                    repo_wtr,
+                    status_wtr,
+                    retention_wtr,
+                    migration_wtr,
+                    working_pattern_wtr,
+                    first_contribution_wtr,
+                    enrichment_wtr,
+                ];
+                for wtr in writers.iter_mut() {
+                    wtr.flush()?;
+                    stats.rows += wtr.rows;
+                    stats.bytes += wtr.bytes();
+                }
+                sampled.sort();
+                Ok((stats, writers, range, sampled))
+            });
 
         for handle in handles {
-            handle.await??;
+            handle.await?;
         }
         drop(tx);
+        skip_list.update(&names, &progress.failed(), Local::now());
+        skip_list.save()?;
+        progress.finish()?;
 
-        rev.await??;
-        Ok(())
+        let identities = authors.stats();
+        if identities.raw > identities.resolved {
+            info!(
+                "[authors] '{}' => {} identities merged into {} ({} by normalization, {} by mappings)",
+                dir,
+                identities.raw,
+                identities.resolved,
+                identities.raw - identities.normalized,
+                identities.normalized - identities.resolved,
+            );
+        }
+
+        // 所有仓库都分析成功后才将临时文件替换为正式的数据文件
+        let (mut stats, mut writers, range, sampled) = rev.await??;
+        let mut duplicate_wtr = CsvWriter::try_new(&dir, RecordDuplicate::name(), &opts.csv)?;
+        if let Some(deduplicator) = deduplicator {
+            let (records, removed) = deduplicator.apply(&dir, &opts.csv)?;
+            for record in records {
+                duplicate_wtr.write(record)?;
+            }
+            stats.commits -= removed.commits;
+            stats.rows -= removed.commits + removed.changes + removed.enrichments;
+        }
+        duplicate_wtr.flush()?;
+        stats.rows += duplicate_wtr.rows;
+        writers.push(duplicate_wtr);
+
+        let mut meta_wtr = CsvWriter::try_new(&dir, RecordMeta::name(), &opts.csv)?;
+        let meta = vec![
+            RecordMeta::new("gitv_version", env!("CARGO_PKG_VERSION")),
+            RecordMeta::new("created_at", Local::now().to_rfc3339()),
+            RecordMeta::new("config_hash", config_hash),
+            RecordMeta::new("repo_count", total),
+            RecordMeta::new("commit_count", stats.commits),
+            RecordMeta::new("range_start", range.start()),
+            RecordMeta::new("range_end", range.end()),
+            RecordMeta::new("ignore_whitespace", opts.log.ignore_whitespace),
+            RecordMeta::new("sampled", sampled.join(",")),
+        ];
+        for record in meta {
+            meta_wtr.write(record)?;
+        }
+        meta_wtr.flush()?;
+        stats.rows += meta_wtr.rows;
+        writers.push(meta_wtr);
+
+        if opts.delta_report {
+            match DeltaReport::try_new(&dir)? {
+                Some(report) => report.write(&dir)?,
+                None => info!("[delta] no previous data in '{}', skip delta report", dir),
+            }
+        }
+        for wtr in writers {
+            wtr.persist()?;
+        }
+
+        let elapsed = start.elapsed().as_secs_f64();
+        stats.datetime = Local::now().to_rfc3339();
+        stats.database = dir;
+        stats.repos = total;
+        stats.elapsed_secs = elapsed;
+        if elapsed > 0.0 {
+            stats.rows_per_sec = stats.rows as f64 / elapsed;
+        }
+        drop(lock);
+        Ok(stats)
+    }
+}
+
+/// 所有提交的时间范围，按照时间戳比较，保留提交原始的时区
+#[derive(Debug, Default)]
+struct CommitRange {
+    start: Option<DateTime<FixedOffset>>,
+    end: Option<DateTime<FixedOffset>>,
+}
+
+impl CommitRange {
+    fn update(&mut self, datetime: &str) {
+        let t = match DateTime::parse_from_rfc3339(datetime) {
+            Ok(t) => t,
+            Err(_) => return,
+        };
+        if self.start.map_or(true, |start| t < start) {
+            self.start = Some(t);
+        }
+        if self.end.map_or(true, |end| t > end) {
+            self.end = Some(t);
+        }
+    }
+
+    fn start(&self) -> String {
+        self.start.map(|t| t.to_rfc3339()).unwrap_or_default()
+    }
+
+    fn end(&self) -> String {
+        self.end.map(|t| t.to_rfc3339()).unwrap_or_default()
+    }
+}
+
+/// database 目录的文件锁，避免多个 gitv 进程同时写入同一个目录
+///
+/// 锁文件为 `${dir}/gitv.lock`，内容为持有锁的进程 pid，Drop 时删除
+struct DatabaseLock {
+    path: PathBuf,
+}
+
+const LOCK_FILE: &str = "gitv.lock";
+
+impl DatabaseLock {
+    async fn acquire(dir: &str, wait: bool) -> Result<DatabaseLock> {
+        let path = Path::new(dir).join(LOCK_FILE);
+        let mut waiting = false;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut f) => {
+                    f.write_all(std::process::id().to_string().as_bytes())?;
+                    return Ok(Self { path });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let pid = fs::read_to_string(&path).unwrap_or_default();
+                    if !wait {
+                        return Err(anyhow!(
+                            "Database dir '{}' is locked by another gitv process (pid: {}), \
+                            use --wait to wait for it or remove '{}' if the process is gone",
+                            dir,
+                            pid.trim(),
+                            path.display(),
+                        ));
+                    }
+                    if !waiting {
+                        info!(
+                            "database dir '{}' is locked by pid {}, waiting...",
+                            dir,
+                            pid.trim()
+                        );
+                        waiting = true;
+                    }
+                    time::sleep(time::Duration::from_secs(1)).await;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Drop for DatabaseLock {
+    fn drop(&mut self) {
+        fs::remove_file(&self.path).unwrap_or(());
     }
 }
 
+/// 数据先写入 `${name}.csv.tmp` 临时文件，全部完成后再通过 rename 原子替换 `${name}.csv`
 struct CsvWriter {
     wtr: csv::Writer<File>,
+    tmp: PathBuf,
+    path: PathBuf,
     size: usize,
     curr: usize,
+    rows: usize,
 }
 
 const FLUSH_SIZE: usize = 500;
 
 impl CsvWriter {
-    fn try_new(dir: &str, name: String) -> Result<CsvWriter> {
+    fn try_new(dir: &str, name: String, csv: &CsvOptions) -> Result<CsvWriter> {
+        let path = Path::new(dir).join(format!("{}.csv", name));
+        let tmp = Path::new(dir).join(format!("{}.csv.tmp", name));
         Ok(Self {
-            wtr: csv::Writer::from_path(Path::new(dir).join(format!("{}.csv", name)))?,
+            wtr: csv.writer(File::create(&tmp)?)?,
+            tmp,
+            path,
             size: FLUSH_SIZE,
             curr: 0,
+            rows: 0,
         })
     }
 
     fn write<T: Serialize>(&mut self, record: T) -> Result<()> {
         self.curr += 1;
+        self.rows += 1;
         self.wtr.serialize(record)?;
         if self.curr >= self.size {
             self.flush()?;
@@ -415,26 +1318,234 @@ impl CsvWriter {
         self.wtr.flush()?;
         Ok(())
     }
+
+    fn bytes(&self) -> u64 {
+        fs::metadata(&self.tmp).map(|m| m.len()).unwrap_or_default()
+    }
+
+    /// 将临时文件替换为正式的数据文件，没有数据时写入空文件（不包含 BOM）以便注册为空表
+    fn persist(mut self) -> Result<()> {
+        self.flush()?;
+        if self.rows == 0 {
+            File::create(&self.tmp)?;
+        }
+        fs::rename(&self.tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// 根据表头识别数据文件的分隔符，默认为 `,`
+pub fn csv_delimiter(path: &Path) -> Result<u8> {
+    let mut header = String::new();
+    BufReader::new(File::open(path)?).read_line(&mut header)?;
+    let mut delimiter = (b',', header.matches(',').count());
+    for c in [';', '\t', '|'] {
+        let count = header.matches(c).count();
+        if count > delimiter.1 {
+            delimiter = (c as u8, count);
+        }
+    }
+    Ok(delimiter.0)
+}
+
+/// 打开数据文件并自动识别分隔符，UTF-8 BOM 由 csv 库自动忽略
+pub fn csv_reader(path: &Path) -> Result<csv::Reader<File>> {
+    let delimiter = csv_delimiter(path)?;
+    Ok(csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_path(path)?)
 }
 
 #[async_trait]
 impl RecordSerializer for CsvSerializer {
     async fn serialize(config: CreateAction) -> Result<()> {
         let mut handles = vec![];
-        let disable_pull = config.disable_pull.unwrap_or(false);
-        for database in config.databases {
-            let database = database.clone();
-            let author_mappings = config.author_mappings.clone().unwrap_or_default();
+        GitImpl::configure(
+            config.git_binary.clone(),
+            config.git_global_args.clone().unwrap_or_default(),
+        );
+        let opts = CreateOptions {
+            author_mappings: config.author_mappings()?,
+            disable_pull: config.disable_pull.unwrap_or(false),
+            unshallow: config.unshallow.unwrap_or(false),
+            remote_mismatch: config.remote_mismatch.unwrap_or_default(),
+            cache_dir: config.cache_dir.clone(),
+            clone_credentials: config.clone_credentials.clone().unwrap_or_default(),
+            concurrency: config.concurrency.unwrap_or(8),
+            wait_lock: config.wait_lock.unwrap_or(false),
+            delta_report: config.delta_report.unwrap_or(false),
+            thresholds: config.status_thresholds.clone().unwrap_or_default(),
+            churn_months: config.churn_months.unwrap_or(6),
+            pattern_thresholds: config.pattern_thresholds.clone().unwrap_or_default(),
+            snapshot_interval: config.snapshot_interval,
+            sampling: config.sampling.clone(),
+            migration_threshold: config.migration_threshold.unwrap_or(0.05),
+            stargazers: config.stargazers.clone(),
+            fork_dedup: config.fork_dedup,
+            normalize_authors: config.normalize_authors.and_then(|n| n.options()),
+            first_contribution: config.first_contribution.unwrap_or(false),
+            skip_after_failures: config.skip_after_failures.unwrap_or(3),
+            retry_failed: config.retry_failed.unwrap_or(false),
+            log: LogOptions {
+                merges: config.include_merges.unwrap_or(false),
+                co_authors: config.co_authors.unwrap_or(false),
+                ignore_whitespace: config.ignore_whitespace.unwrap_or(false),
+            },
+            csv: config.csv.clone().unwrap_or_default(),
+        };
+        let mut databases = config.databases;
+        // 交互式选择需要在所有 database 开始分析之前完成，避免进度条与选择界面冲突
+        if config.select.unwrap_or(false) {
+            for database in databases.iter_mut() {
+                let repos = selector::select(&database.dir, database.load()?)?;
+                database.repos = Some(repos);
+                database.files = None;
+            }
+        }
+        for database in databases {
+            let opts = opts.clone();
 
-            let handle = tokio::spawn(async move {
-                Self::serialize_records(database, author_mappings, disable_pull).await
-            });
+            let handle = tokio::spawn(async move { Self::serialize_records(database, opts).await });
             handles.push(handle);
         }
 
+        let mut stats = vec![];
         for handle in handles {
-            handle.await??;
+            let stat = handle.await??;
+            stat.print();
+            stats.push(stat);
+        }
+
+        if let Some(runs_log) = config.runs_log {
+            RunStats::append(&stats, &runs_log)?;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+    use std::process::Command;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let out = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(
+            out.status.success(),
+            "{}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+    }
+
+    fn count_rows(dir: &str, name: String) -> usize {
+        let path = Path::new(dir).join(format!("{}.csv", name));
+        csv_reader(&path).unwrap().records().count()
+    }
+
+    #[tokio::test]
+    async fn test_serialize_commits_sampling() {
+        let dir = TempDir::new("sampling").unwrap();
+        git(&dir, &["init", "-q"]);
+        git(&dir, &["config", "user.name", "gitv"]);
+        git(&dir, &["config", "user.email", "gitv@example.com"]);
+        for i in 0..4 {
+            fs::write(dir.join("main.rs"), format!("// {}\n", i)).unwrap();
+            git(&dir, &["add", "."]);
+            git(&dir, &["commit", "-q", "-m", &format!("commit {}", i)]);
+        }
+        let repo = Repository {
+            name: String::from("sampling"),
+            path: dir.path_str().to_string(),
+            ..Default::default()
+        };
+
+        let serialize = |max_commits: usize, every: Option<usize>, since: Option<&str>| {
+            let repo = repo.clone();
+            let sampling = Sampling {
+                max_commits,
+                every,
+                since: since.map(|s| s.to_string()),
+            };
+            async move {
+                let (tx, mut rx) = sync::mpsc::channel::<RecordType>(BUFFER_SIZE);
+                let authors = AuthorResolver::new(None, vec![]);
+                let log = LogOptions::default();
+                CsvSerializer::serialize_commits(tx, &repo, authors, log, Some(sampling))
+                    .await
+                    .unwrap();
+                let (mut commits, mut sampled) = (0, false);
+                while let Some(record) = rx.recv().await {
+                    match record {
+                        RecordType::Commit(_) => commits += 1,
+                        RecordType::Sampled(name) => sampled = name == "sampling",
+                        _ => {}
+                    }
+                }
+                (commits, sampled)
+            }
+        };
+        // 提交数不超过 maxCommits 时 since 以及 every 均不生效
+        assert_eq!(serialize(4, Some(2), Some("2100-01-01")).await, (4, false));
+        assert_eq!(serialize(3, Some(2), None).await, (2, true));
+        assert_eq!(serialize(3, Some(0), None).await, (4, true));
+        assert_eq!(serialize(3, None, Some("2100-01-01")).await, (0, true));
+    }
+
+    #[tokio::test]
+    async fn test_serialize_ignore_whitespace() {
+        let root = TempDir::new("whitespace").unwrap();
+        let repo = root.join("repo");
+        fs::create_dir_all(&repo).unwrap();
+        git(&repo, &["init", "-q"]);
+        git(&repo, &["config", "user.name", "gitv"]);
+        git(&repo, &["config", "user.email", "gitv@example.com"]);
+        fs::write(
+            repo.join("main.rs"),
+            "fn main() {\n    println!(\"gitv\");\n}\n",
+        )
+        .unwrap();
+        git(&repo, &["add", "."]);
+        git(&repo, &["commit", "-q", "-m", "init"]);
+        // 只调整缩进以及增加空行
+        fs::write(
+            repo.join("main.rs"),
+            "fn main() {\n\n        println!(\"gitv\");\n}\n",
+        )
+        .unwrap();
+        git(&repo, &["commit", "-q", "-a", "-m", "reformat"]);
+
+        let mut changes = vec![];
+        for ignore_whitespace in [false, true] {
+            let dir = root.join(format!("db-{}", ignore_whitespace));
+            let dir = dir.to_str().unwrap();
+            fs::create_dir_all(dir).unwrap();
+            let config = CreateAction {
+                databases: vec![Database::from_lines(dir, dir, repo.to_str().unwrap())],
+                ignore_whitespace: Some(ignore_whitespace),
+                ..Default::default()
+            };
+            CsvSerializer::serialize(config).await.unwrap();
+
+            let meta = csv_reader(&Path::new(dir).join(format!("{}.csv", RecordMeta::name())))
+                .unwrap()
+                .records()
+                .map(|r| {
+                    let r = r.unwrap();
+                    (r[0].to_string(), r[1].to_string())
+                })
+                .collect::<HashMap<_, _>>();
+            assert_eq!(meta["ignore_whitespace"], ignore_whitespace.to_string());
+            assert_eq!(meta["sampled"], "");
+            assert_eq!(count_rows(dir, RecordCommit::name()), 2);
+            changes.push(count_rows(dir, RecordChange::name()));
+        }
+        // 忽略空白字符后 reformat 提交没有任何行变更
+        assert_eq!(changes, vec![2, 1]);
+    }
+}
@@ -1,35 +1,217 @@
-use crate::config;
-use anyhow::{anyhow, Result};
+use crate::{config, http, svg::SvgChart};
+use anyhow::{anyhow, Context as _, Result};
 use async_trait::async_trait;
 use datafusion::{
-    arrow::{array, datatypes::DataType},
+    arrow::{
+        array::{self, Array, ArrayRef},
+        csv,
+        datatypes::{DataType, Schema},
+        record_batch::RecordBatch,
+    },
+    datasource::MemTable,
     prelude::ExecutionContext,
+    scalar::ScalarValue,
 };
+use log::{debug, info};
 use rand::prelude::*;
 use serde::Serialize;
 use serde_yaml::{Mapping, Number, Value};
-use std::{collections::HashMap, fmt::Debug, fs::File, io::Write, path::Path};
+use std::{
+    collections::HashMap, fmt::Debug, fs::File, io::Write, path::Path, process::Command, sync::Arc,
+};
 use tera::{Context, Tera};
 use tokio::time;
 
+/// 按类型存储的列数据，模板替换时才转换为 yaml Value，避免大结果集占用过多内存
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum Column {
+    Str(Vec<Option<String>>),
+    Float(Vec<Option<f64>>),
+    Int(Vec<Option<i64>>),
+    /// 列表类型的字段（如 histogram 的结果）
+    List(Vec<Vec<Value>>),
+}
+
+impl Column {
+    /// 合并多个 RecordBatch 中的同名列，类型不一致时忽略
+    fn extend(&mut self, other: Column) {
+        match (self, other) {
+            (Column::Str(a), Column::Str(b)) => a.extend(b),
+            (Column::Float(a), Column::Float(b)) => a.extend(b),
+            (Column::Int(a), Column::Int(b)) => a.extend(b),
+            (Column::List(a), Column::List(b)) => a.extend(b),
+            _ => (),
+        }
+    }
+
+    /// 列表类型的字段会被展开，可以直接用于图表数据，空值转换为 null
+    fn values(&self) -> Vec<Value> {
+        match self {
+            Column::Str(v) => v
+                .iter()
+                .map(|x| x.clone().map_or(Value::Null, Value::String))
+                .collect(),
+            Column::Float(v) => v
+                .iter()
+                .map(|x| x.map_or(Value::Null, |x| Value::Number(Number::from(x))))
+                .collect(),
+            Column::Int(v) => v
+                .iter()
+                .map(|x| x.map_or(Value::Null, |x| Value::Number(Number::from(x))))
+                .collect(),
+            Column::List(v) => v.iter().flatten().cloned().collect(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct ColumnMap {
-    store: HashMap<String, Vec<Value>>,
+    store: HashMap<String, Column>,
+    /// 查询结果的 csv 内容，用于图表页面的数据下载
+    #[serde(skip)]
+    csv: String,
 }
 
 impl ColumnMap {
     fn new() -> Self {
         Self {
             store: HashMap::new(),
+            csv: String::new(),
+        }
+    }
+
+    fn insert(&mut self, name: String, column: Column) {
+        match self.store.get_mut(&name) {
+            Some(c) => c.extend(column),
+            None => {
+                self.store.insert(name, column);
+            }
         }
     }
 
     fn get(&self, k: &str) -> Option<Vec<Value>> {
-        let mut values = vec![];
-        for v in self.store.get(k)? {
-            values.push(v.clone())
+        Some(self.store.get(k)?.values())
+    }
+}
+
+/// 除 RFC 3986 unreserved 字符外均进行百分号编码
+pub(crate) fn percent_encode(content: &str) -> String {
+    let mut encoded = String::new();
+    for b in content.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(b as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+/// 将 csv 内容编码为 data uri
+fn csv_data_uri(content: &str) -> String {
+    format!("data:text/csv;charset=utf-8,{}", percent_encode(content))
+}
+
+/// 图表索引页中的图表
+#[derive(Debug, Serialize)]
+struct GalleryItem {
+    name: String,
+    href: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Download {
+    name: String,
+    href: String,
+}
+
+/// 单个 chartjs 图表页面的模板数据，bundle 模式下多个图表共用一个页面
+#[derive(Debug, Serialize)]
+struct ChartPage {
+    chart_id: String,
+    title: String,
+    width: String,
+    height: String,
+    config: String,
+    aria_label: String,
+    downloads: Vec<Download>,
+    tables: Vec<DataTable>,
+}
+
+/// 图表下方的数据表格
+#[derive(Debug, Serialize)]
+struct DataTable {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl DataTable {
+    fn try_new(content: &str) -> Result<Self> {
+        let mut rdr = ::csv::Reader::from_reader(content.as_bytes());
+        let headers = rdr.headers()?.iter().map(|h| h.to_string()).collect();
+        let mut rows = vec![];
+        for row in rdr.records() {
+            rows.push(row?.iter().map(|f| f.to_string()).collect());
         }
-        Some(values)
+        Ok(Self { headers, rows })
+    }
+}
+
+static HIGH_CONTRAST_COLORS: &str = "HighContrast";
+
+fn round_float(v: f64, precision: u32) -> f64 {
+    let factor = 10f64.powi(precision as i32);
+    (v * factor).round() / factor
+}
+
+/// 按照 precision 保留浮点列的小数位数，避免图表标签以及 csv 中出现 33.333333333333336 之类的值
+fn round_batches(batches: Vec<RecordBatch>, precision: Option<u32>) -> Result<Vec<RecordBatch>> {
+    let precision = match precision {
+        Some(p) => p,
+        None => return Ok(batches),
+    };
+
+    let mut rounded = vec![];
+    for batch in batches {
+        let mut columns = vec![];
+        for column in batch.columns() {
+            let data = column.as_any();
+            let column: ArrayRef = match column.data_type() {
+                DataType::Float64 => Arc::new(
+                    data.downcast_ref::<array::Float64Array>()
+                        .unwrap()
+                        .iter()
+                        .map(|x| x.map(|v| round_float(v, precision)))
+                        .collect::<array::Float64Array>(),
+                ),
+                DataType::Float32 => Arc::new(
+                    data.downcast_ref::<array::Float32Array>()
+                        .unwrap()
+                        .iter()
+                        .map(|x| x.map(|v| round_float(v as f64, precision) as f32))
+                        .collect::<array::Float32Array>(),
+                ),
+                _ => column.clone(),
+            };
+            columns.push(column);
+        }
+        rounded.push(RecordBatch::try_new(batch.schema(), columns)?);
+    }
+    Ok(rounded)
+}
+
+fn scalar_to_value(scalar: ScalarValue) -> Value {
+    match scalar {
+        ScalarValue::Utf8(Some(v)) | ScalarValue::LargeUtf8(Some(v)) => Value::String(v),
+        ScalarValue::Float64(Some(v)) => Value::Number(Number::from(v)),
+        ScalarValue::Float32(Some(v)) => Value::Number(Number::from(v as f64)),
+        ScalarValue::Int64(Some(v)) => Value::Number(Number::from(v)),
+        ScalarValue::Int32(Some(v)) => Value::Number(Number::from(v as i64)),
+        ScalarValue::UInt64(Some(v)) => Value::Number(Number::from(v)),
+        ScalarValue::UInt32(Some(v)) => Value::Number(Number::from(v as u64)),
+        _ => Value::Null,
     }
 }
 
@@ -42,12 +224,23 @@ impl Engine {
         Self { ctx }
     }
 
-    async fn select(&mut self, sql: &str) -> Result<ColumnMap> {
+    /// 执行查询并按列收集结果，precision 不为空时保留对应的小数位数
+    async fn select(&mut self, sql: &str, precision: Option<u32>) -> Result<ColumnMap> {
         let mut cm = ColumnMap::new();
         let ctx = &mut self.ctx;
         let df = ctx.sql(sql).await?;
+        let batches = round_batches(df.collect().await?, precision)?;
 
-        for val in df.collect().await? {
+        let mut buf = vec![];
+        {
+            let mut wtr = csv::Writer::new(&mut buf);
+            for batch in batches.iter() {
+                wtr.write(batch)?;
+            }
+        }
+        cm.csv = String::from_utf8_lossy(&buf).to_string();
+
+        for val in batches {
             if val.num_rows() == 0 {
                 continue;
             }
@@ -61,129 +254,101 @@ impl Engine {
                 let data = column.as_any();
                 let field = &fields[idx];
                 let name = field.name().to_string();
-                match field.data_type() {
-                    DataType::Utf8 => {
-                        let downcast = data
-                            .downcast_ref::<array::StringArray>()
+                let column = match field.data_type() {
+                    DataType::Utf8 => Column::Str(
+                        data.downcast_ref::<array::StringArray>()
                             .unwrap()
                             .iter()
-                            .map(|x| Value::String(x.unwrap().to_string()))
-                            .collect::<Vec<Value>>();
-                        let v = cm.store.entry(name).or_insert(vec![]);
-                        v.extend(downcast)
-                    }
-
-                    DataType::Float64 => {
-                        let downcast = data
-                            .downcast_ref::<array::Float64Array>()
+                            .map(|x| x.map(|x| x.to_string()))
+                            .collect(),
+                    ),
+                    DataType::Float64 => Column::Float(
+                        data.downcast_ref::<array::Float64Array>()
                             .unwrap()
                             .iter()
-                            .map(|x| Value::Number(Number::from(x.unwrap() as f64)))
-                            .collect::<Vec<Value>>();
-                        let v = cm.store.entry(name).or_insert(vec![]);
-                        v.extend(downcast)
-                    }
-
-                    DataType::Float32 => {
-                        let downcast = data
-                            .downcast_ref::<array::Float32Array>()
+                            .collect(),
+                    ),
+                    DataType::Float32 => Column::Float(
+                        data.downcast_ref::<array::Float32Array>()
                             .unwrap()
                             .iter()
-                            .map(|x| Value::Number(Number::from(x.unwrap() as f64)))
-                            .collect::<Vec<Value>>();
-                        let v = cm.store.entry(name).or_insert(vec![]);
-                        v.extend(downcast)
-                    }
-
-                    DataType::UInt64 => {
-                        let downcast = data
-                            .downcast_ref::<array::UInt64Array>()
+                            .map(|x| x.map(f64::from))
+                            .collect(),
+                    ),
+                    DataType::UInt64 => Column::Int(
+                        data.downcast_ref::<array::UInt64Array>()
                             .unwrap()
                             .iter()
-                            .map(|x| Value::Number(Number::from(x.unwrap() as u64)))
-                            .collect::<Vec<Value>>();
-                        let v = cm.store.entry(name).or_insert(vec![]);
-                        v.extend(downcast)
-                    }
-
-                    DataType::Int64 => {
-                        let downcast = data
-                            .downcast_ref::<array::Int64Array>()
+                            .map(|x| x.map(i64::try_from).transpose())
+                            .collect::<Result<_, _>>()
+                            .map_err(|_| {
+                                anyhow!("column '{}' has values larger than {}", name, i64::MAX)
+                            })?,
+                    ),
+                    DataType::Int64 => Column::Int(
+                        data.downcast_ref::<array::Int64Array>()
                             .unwrap()
                             .iter()
-                            .map(|x| Value::Number(Number::from(x.unwrap() as i64)))
-                            .collect::<Vec<Value>>();
-                        let v = cm.store.entry(name).or_insert(vec![]);
-                        v.extend(downcast)
-                    }
-
-                    DataType::UInt32 => {
-                        let downcast = data
-                            .downcast_ref::<array::UInt32Array>()
+                            .collect(),
+                    ),
+                    DataType::UInt32 => Column::Int(
+                        data.downcast_ref::<array::UInt32Array>()
                             .unwrap()
                             .iter()
-                            .map(|x| Value::Number(Number::from(x.unwrap() as u64)))
-                            .collect::<Vec<Value>>();
-                        let v = cm.store.entry(name).or_insert(vec![]);
-                        v.extend(downcast)
-                    }
-
-                    DataType::Int32 => {
-                        let downcast = data
-                            .downcast_ref::<array::Int32Array>()
+                            .map(|x| x.map(i64::from))
+                            .collect(),
+                    ),
+                    DataType::Int32 => Column::Int(
+                        data.downcast_ref::<array::Int32Array>()
                             .unwrap()
                             .iter()
-                            .map(|x| Value::Number(Number::from(x.unwrap() as i64)))
-                            .collect::<Vec<Value>>();
-                        let v = cm.store.entry(name).or_insert(vec![]);
-                        v.extend(downcast)
-                    }
-
-                    DataType::UInt16 => {
-                        let downcast = data
-                            .downcast_ref::<array::UInt16Array>()
+                            .map(|x| x.map(i64::from))
+                            .collect(),
+                    ),
+                    DataType::UInt16 => Column::Int(
+                        data.downcast_ref::<array::UInt16Array>()
                             .unwrap()
                             .iter()
-                            .map(|x| Value::Number(Number::from(x.unwrap() as u64)))
-                            .collect::<Vec<Value>>();
-                        let v = cm.store.entry(name).or_insert(vec![]);
-                        v.extend(downcast)
-                    }
-
-                    DataType::Int16 => {
-                        let downcast = data
-                            .downcast_ref::<array::Int16Array>()
+                            .map(|x| x.map(i64::from))
+                            .collect(),
+                    ),
+                    DataType::Int16 => Column::Int(
+                        data.downcast_ref::<array::Int16Array>()
                             .unwrap()
                             .iter()
-                            .map(|x| Value::Number(Number::from(x.unwrap() as i64)))
-                            .collect::<Vec<Value>>();
-                        let v = cm.store.entry(name).or_insert(vec![]);
-                        v.extend(downcast)
-                    }
-
-                    DataType::UInt8 => {
-                        let downcast = data
-                            .downcast_ref::<array::UInt8Array>()
+                            .map(|x| x.map(i64::from))
+                            .collect(),
+                    ),
+                    DataType::UInt8 => Column::Int(
+                        data.downcast_ref::<array::UInt8Array>()
                             .unwrap()
                             .iter()
-                            .map(|x| Value::Number(Number::from(x.unwrap() as u64)))
-                            .collect::<Vec<Value>>();
-                        let v = cm.store.entry(name).or_insert(vec![]);
-                        v.extend(downcast)
-                    }
-
-                    DataType::Int8 => {
-                        let downcast = data
-                            .downcast_ref::<array::Int8Array>()
+                            .map(|x| x.map(i64::from))
+                            .collect(),
+                    ),
+                    DataType::Int8 => Column::Int(
+                        data.downcast_ref::<array::Int8Array>()
                             .unwrap()
                             .iter()
-                            .map(|x| Value::Number(Number::from(x.unwrap() as i64)))
-                            .collect::<Vec<Value>>();
-                        let v = cm.store.entry(name).or_insert(vec![]);
-                        v.extend(downcast)
+                            .map(|x| x.map(i64::from))
+                            .collect(),
+                    ),
+                    DataType::List(_) => {
+                        let list = data.downcast_ref::<array::ListArray>().unwrap();
+                        let mut rows = vec![];
+                        for i in 0..list.len() {
+                            let items = list.value(i);
+                            let mut seq = vec![];
+                            for j in 0..items.len() {
+                                seq.push(scalar_to_value(ScalarValue::try_from_array(&items, j)?));
+                            }
+                            rows.push(seq);
+                        }
+                        Column::List(rows)
                     }
-                    _ => (),
-                }
+                    _ => continue,
+                };
+                cm.insert(name, column);
             }
         }
 
@@ -194,6 +359,8 @@ impl Engine {
 enum RenderMode {
     Table,
     Html,
+    Csv,
+    Svg,
     Unsupported,
 }
 
@@ -202,6 +369,8 @@ impl From<&str> for RenderMode {
         match s {
             "table" => RenderMode::Table,
             "html" => RenderMode::Html,
+            "csv" => RenderMode::Csv,
+            "svg" => RenderMode::Svg,
             _ => RenderMode::Unsupported,
         }
     }
@@ -212,9 +381,14 @@ pub trait ResultRender {
     async fn render(&mut self) -> Result<()>;
 }
 
-pub fn create_render(ctx: ExecutionContext, config: config::RenderAction) -> Box<dyn ResultRender> {
+pub fn create_render(
+    ctx: ExecutionContext,
+    config: config::RenderAction,
+) -> Box<dyn ResultRender + Send> {
     match RenderMode::from(config.display.render_mode.as_str()) {
         RenderMode::Html => Box::new(ChartRender::new(ctx, config)),
+        RenderMode::Csv => Box::new(CsvRender::new(ctx, config)),
+        RenderMode::Svg => Box::new(ChartRender::new(ctx, config).svg()),
         RenderMode::Table | RenderMode::Unsupported => Box::new(TableRender::new(ctx, config)),
     }
 }
@@ -234,13 +408,152 @@ impl TableRender {
 impl ResultRender for TableRender {
     async fn render(&mut self) -> Result<()> {
         let queries = self.config.display.queries.clone();
-        for query in queries {
-            for sql in query.statements {
+        for (index, query) in queries.into_iter().enumerate() {
+            for (idx, sql) in query.sqls().iter().enumerate() {
+                let now = time::Instant::now();
+                info!("[render] SQL: {}", sql);
+                let df = self
+                    .ctx
+                    .sql(sql)
+                    .await
+                    .with_context(|| query.location(index, idx))?;
+                df.show()
+                    .await
+                    .with_context(|| query.location(index, idx))?;
+                debug!("[render] Query OK, elapsed: {:#?}", now.elapsed())
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 打印图表中可用的 `${index:column}` 变量
+pub async fn list_vars(ctx: ExecutionContext, config: config::RenderAction) -> Result<()> {
+    ChartRender::new(ctx, config).list_vars().await
+}
+
+/// 检查渲染配置，返回发现的问题：不支持的 renderMode、无法解析的语句以及图表中无效的变量
+pub async fn check(ctx: ExecutionContext, config: config::RenderAction) -> Vec<String> {
+    ChartRender::new(ctx, config).check().await
+}
+
+/// 将每个查询的结果注册为内存表 `q_${name}`，name 为图表名称，未配置图表时为查询序号，返回注册的表名
+///
+/// 图表名称中字母数字以外的字符替换为 `_`，同一个查询包含多条语句时，表名为 `q_${name}_${statement_index}`
+pub async fn register_results(
+    ctx: &mut ExecutionContext,
+    config: &config::RenderAction,
+) -> Result<Vec<String>> {
+    let mut tables = vec![];
+    for (index, query) in config.display.queries.iter().enumerate() {
+        let name = match &query.chart {
+            Some(chart) => result_table_name(&chart.name),
+            None => (index + 1).to_string(),
+        };
+        let precision = query
+            .chart
+            .as_ref()
+            .and_then(|chart| chart.precision)
+            .or(config.display.precision);
+        let sqls = query.sqls();
+        for (idx, sql) in sqls.iter().enumerate() {
+            let df = ctx
+                .sql(sql)
+                .await
+                .with_context(|| query.location(index, idx))?;
+            let batches = df
+                .collect()
+                .await
+                .with_context(|| query.location(index, idx))?;
+            let batches = round_batches(batches, precision)?;
+            let schema = match batches.first() {
+                Some(batch) => batch.schema(),
+                None => Arc::new(Schema::from(df.schema().clone())),
+            };
+
+            let mut table = format!("q_{}", name);
+            if sqls.len() > 1 {
+                table = format!("{}_{}", table, idx);
+            }
+            let provider = MemTable::try_new(schema, vec![batches])?;
+            ctx.register_table(table.as_str(), Arc::new(provider))?;
+            tables.push(table);
+        }
+    }
+    Ok(tables)
+}
+
+/// 将图表名称转换为表名，字母数字以外的字符替换为 `_`
+fn result_table_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_lowercase(),
+            false => '_',
+        })
+        .collect()
+}
+
+/// 将每个查询的结果写入 `${destination}/${name}.csv`，name 为图表名称，未配置图表时为 `query-${index}`
+///
+/// 同一个查询包含多条语句时，文件名为 `${name}-${statement_index}.csv`
+struct CsvRender {
+    config: config::RenderAction,
+    ctx: ExecutionContext,
+}
+
+impl CsvRender {
+    fn new(ctx: ExecutionContext, config: config::RenderAction) -> Self {
+        Self { ctx, config }
+    }
+}
+
+#[async_trait]
+impl ResultRender for CsvRender {
+    async fn render(&mut self) -> Result<()> {
+        let destination = self.config.display.destination.clone();
+        std::fs::create_dir_all(&destination)?;
+
+        let queries = self.config.display.queries.clone();
+        let total = queries.len();
+        for (index, query) in queries.into_iter().enumerate() {
+            let name = match &query.chart {
+                Some(chart) => chart.name.clone(),
+                None => format!("query-{}", index + 1),
+            };
+            let multiple = query.statements.len() > 1;
+            for (idx, sql) in query.sqls().iter().enumerate() {
                 let now = time::Instant::now();
-                println!("SQL: {}", sql);
-                let df = self.ctx.sql(&sql).await?;
-                df.show().await?;
-                println!("Query OK, elapsed: {:#?}\n", now.elapsed())
+                let batches = self
+                    .ctx
+                    .sql(sql)
+                    .await
+                    .with_context(|| query.location(index, idx))?
+                    .collect()
+                    .await
+                    .with_context(|| query.location(index, idx))?;
+                let precision = query
+                    .chart
+                    .as_ref()
+                    .and_then(|chart| chart.precision)
+                    .or(self.config.display.precision);
+                let batches = round_batches(batches, precision)?;
+
+                let mut dest = Path::new(&destination).join(&name);
+                if multiple {
+                    dest = Path::new(&destination).join(format!("{}-{}", name, idx));
+                }
+                dest.set_extension("csv");
+                let mut wtr = csv::Writer::new(File::create(&dest)?);
+                for batch in batches.iter() {
+                    wtr.write(batch)?;
+                }
+                info!(
+                    "[{}/{}] render file '{}' => elapsed {:#?}",
+                    index + 1,
+                    total,
+                    dest.to_str().unwrap_or_default(),
+                    now.elapsed(),
+                )
             }
         }
         Ok(())
@@ -248,6 +561,10 @@ impl ResultRender for TableRender {
 }
 
 static TEMPLATE_CHART: &str = include_str!("../static/chart.tpl");
+static TEMPLATE_GALLERY: &str = include_str!("../static/gallery.tpl");
+static TEMPLATE_BUNDLE: &str = include_str!("../static/bundle.tpl");
+static BUNDLE_FILE: &str = "gitv.html";
+const GALLERY_WIDTH: usize = 320;
 static CONTENT_COLORS: &str = include_str!("../static/colors.yaml");
 static CONTENT_FUNCTIONS: &str = include_str!("../static/functions.yaml");
 
@@ -282,6 +599,8 @@ struct ChartRender {
     engine: Engine,
     colors: HashMap<String, Vec<Value>>,
     functions: HashMap<String, Value>,
+    /// 输出不依赖浏览器的 svg 文件而不是 chartjs html 页面
+    svg: bool,
 }
 
 impl ChartRender {
@@ -301,8 +620,14 @@ impl ChartRender {
             engine: Engine::new(ctx),
             colors,
             functions,
+            svg: false,
         }
     }
+
+    fn svg(mut self) -> Self {
+        self.svg = true;
+        self
+    }
 }
 
 #[async_trait]
@@ -310,22 +635,63 @@ impl ResultRender for ChartRender {
     async fn render(&mut self) -> Result<()> {
         let queries = self.config.display.queries.clone();
         let total = queries.len();
+        let mut gallery = vec![];
+        let bundle = !self.svg && self.config.display.bundle.unwrap_or(false);
+        let mut pages = vec![];
         for (index, query) in queries.into_iter().enumerate() {
             let mut cms = vec![];
             let now = time::Instant::now();
-            for sql in query.statements {
-                cms.push(self.engine.select(&sql).await?)
+            let precision = query
+                .chart
+                .as_ref()
+                .and_then(|chart| chart.precision)
+                .or(self.config.display.precision);
+            for (idx, sql) in query.sqls().iter().enumerate() {
+                let cm = self
+                    .engine
+                    .select(sql, precision)
+                    .await
+                    .with_context(|| query.location(index, idx))?;
+                cms.push(cm)
             }
 
             if query.chart.is_none() {
                 continue;
             }
             let chart_config = query.chart.unwrap();
+            if bundle {
+                let chart_name = chart_config.name.clone();
+                let page = self
+                    .chart_page(chart_config, &cms)
+                    .with_context(|| format!("query #{} (chart '{}')", index + 1, chart_name))?;
+                pages.extend(page);
+                info!(
+                    "[{}/{}] render chart '{}' => elapsed {:#?}",
+                    index + 1,
+                    total,
+                    chart_name,
+                    now.elapsed(),
+                );
+                continue;
+            }
+
             let mut dest =
                 Path::new(&self.config.display.destination).join(chart_config.name.clone());
-            dest.set_extension("html");
-            self.render_chart(chart_config, &cms, &dest).await?;
-            println!(
+            dest.set_extension(if self.svg { "svg" } else { "html" });
+            gallery.push(GalleryItem {
+                name: chart_config.name.clone(),
+                href: dest
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+            });
+            let chart_name = chart_config.name.clone();
+            self.render_chart(chart_config, &cms, &dest)
+                .await
+                .and_then(|_| self.post_process(&dest))
+                .with_context(|| format!("query #{} (chart '{}')", index + 1, chart_name))?;
+            info!(
                 "[{}/{}] render file '{}' => elapsed {:#?}",
                 index + 1,
                 total,
@@ -333,6 +699,13 @@ impl ResultRender for ChartRender {
                 now.elapsed(),
             )
         }
+
+        if self.svg && self.config.display.gallery.unwrap_or(false) {
+            self.render_gallery(&gallery)?;
+        }
+        if bundle {
+            self.render_bundle(&pages).await?;
+        }
         Ok(())
     }
 }
@@ -371,6 +744,15 @@ impl KeyType {
     }
 }
 
+/// 图表配置中引用的变量
+#[derive(Debug, Default)]
+struct References {
+    /// 语句序号以及字段名称
+    columns: Vec<(usize, String)>,
+    colors: Vec<String>,
+    functions: Vec<String>,
+}
+
 impl ChartRender {
     fn parse_variable<S: Into<String>>(&self, s: S) -> Option<(usize, String)> {
         let s = s.into();
@@ -390,6 +772,254 @@ impl ChartRender {
         None
     }
 
+    /// 收集图表配置中引用的变量，backgroundColor 中的为颜色变量，formatter 中的为函数变量，其余为字段变量
+    fn collect_references(&self, key: &str, val: &Value, refs: &mut References) {
+        match val {
+            Value::String(s) => {
+                if let Some((idx, name)) = self.parse_variable(s.as_str()) {
+                    if key == KeyType::Colors.as_str() {
+                        refs.colors.push(name);
+                    } else if key == KeyType::Formatter.as_str() {
+                        refs.functions.push(name);
+                    } else {
+                        refs.columns.push((idx, name));
+                    }
+                }
+            }
+            Value::Sequence(seq) => seq
+                .iter()
+                .for_each(|v| self.collect_references(key, v, refs)),
+            Value::Mapping(mappings) => mappings.iter().for_each(|(k, v)| {
+                self.collect_references(k.as_str().unwrap_or_default(), v, refs)
+            }),
+            _ => {}
+        }
+    }
+
+    /// 与 list_vars 一样只解析语句的逻辑计划，检查图表引用的语句序号、字段、颜色以及函数是否存在
+    async fn check(&mut self) -> Vec<String> {
+        let mut problems = vec![];
+        let mode = self.config.display.render_mode.clone();
+        if let RenderMode::Unsupported = RenderMode::from(mode.as_str()) {
+            problems.push(format!(
+                "unknown renderMode '{}', expected one of html, table, csv, svg",
+                mode
+            ));
+        }
+
+        let queries = self.config.display.queries.clone();
+        for (index, query) in queries.into_iter().enumerate() {
+            if query.statements.is_empty() {
+                problems.push(format!("query #{} has no statements", index + 1));
+            }
+
+            // 解析失败的语句为 None，不再检查引用其字段的变量
+            let mut fields = vec![];
+            for (idx, sql) in query.sqls().iter().enumerate() {
+                match self.engine.ctx.sql(sql).await {
+                    Ok(df) => fields.push(Some(
+                        df.schema()
+                            .fields()
+                            .iter()
+                            .map(|f| f.name().to_string())
+                            .collect::<Vec<_>>(),
+                    )),
+                    Err(e) => {
+                        problems.push(format!("{}: {}", query.location(index, idx), e));
+                        fields.push(None);
+                    }
+                }
+            }
+
+            let chart = match &query.chart {
+                Some(chart) => chart,
+                None => continue,
+            };
+            let location = format!("query #{} (chart '{}')", index + 1, chart.name);
+            let mut refs = References::default();
+            self.collect_references("", &chart.data, &mut refs);
+            let mut options = References::default();
+            if let Some(val) = &chart.options {
+                self.collect_references("", val, &mut options);
+            }
+
+            for (idx, column) in refs.columns {
+                match fields.get(idx) {
+                    None => problems.push(format!(
+                        "{}: variable '${{{}:{}}}' refers to statement #{}, but the query has {} statements",
+                        location,
+                        idx,
+                        column,
+                        idx + 1,
+                        fields.len()
+                    )),
+                    Some(Some(names)) if !names.contains(&column) => problems.push(format!(
+                        "{}: unknown variable '${{{}:{}}}', available columns: {}",
+                        location,
+                        idx,
+                        column,
+                        names.join(", ")
+                    )),
+                    _ => {}
+                }
+            }
+            for color in refs.colors {
+                if color != KeyType::Random.as_str() && !self.colors.contains_key(&color) {
+                    problems.push(format!("{}: unknown colors '${{{}}}'", location, color));
+                }
+            }
+            for function in options.functions {
+                if !self.functions.contains_key(&function) {
+                    problems.push(format!(
+                        "{}: unknown function '${{{}}}'",
+                        location, function
+                    ));
+                }
+            }
+        }
+        problems
+    }
+
+    /// 只解析每个查询语句的逻辑计划获取结果字段，不实际执行查询
+    async fn list_vars(&mut self) -> Result<()> {
+        let queries = self.config.display.queries.clone();
+        for (index, query) in queries.into_iter().enumerate() {
+            let name = match &query.chart {
+                Some(chart) => chart.name.clone(),
+                None => String::from("<no chart>"),
+            };
+            println!("[{}] {}", index + 1, name);
+
+            let mut vars = vec![];
+            for (idx, sql) in query.sqls().iter().enumerate() {
+                let df = self
+                    .engine
+                    .ctx
+                    .sql(sql)
+                    .await
+                    .with_context(|| query.location(index, idx))?;
+                for field in df.schema().fields() {
+                    let var = format!("${{{}:{}}}", idx, field.name());
+                    println!("  {:<40} {:?}", var, field.data_type());
+                    vars.push((idx, field.name().to_string()));
+                }
+            }
+
+            if let Some(chart) = query.chart {
+                for warning in self.unknown_variables(&chart.data, &vars) {
+                    println!("  [warn] {}", warning);
+                }
+            }
+            println!();
+        }
+        Ok(())
+    }
+
+    /// 图表数据中引用的未知字段以及颜色变量，backgroundColor 中的颜色变量不作为字段检查
+    fn unknown_variables(&self, data: &Value, vars: &[(usize, String)]) -> Vec<String> {
+        let mut refs = References::default();
+        self.collect_references("", data, &mut refs);
+
+        let mut unknown = refs
+            .columns
+            .into_iter()
+            .filter(|var| !vars.contains(var))
+            .map(|(idx, column)| format!("unknown variable '${{{}:{}}}'", idx, column))
+            .collect::<Vec<_>>();
+        for color in refs.colors {
+            if color != KeyType::Random.as_str() && !self.colors.contains_key(&color) {
+                unknown.push(format!("unknown colors '${{{}}}'", color));
+            }
+        }
+        unknown
+    }
+
+    /// 生成引用所有 svg 图表的索引页，图表按缩略尺寸展示并延迟加载
+    ///
+    /// gitv 没有栅格化 svg 的能力，索引页直接引用原始的 svg 文件，不生成 png 缩略图
+    fn render_gallery(&self, items: &[GalleryItem]) -> Result<()> {
+        let mut ctx = Context::new();
+        ctx.insert("items", items);
+        ctx.insert("width", &GALLERY_WIDTH);
+
+        let dest = Path::new(&self.config.display.destination).join("index.html");
+        let mut f = File::create(&dest)?;
+        f.write_all(
+            Tera::default()
+                .render_str(TEMPLATE_GALLERY, &ctx)?
+                .as_bytes(),
+        )?;
+        info!("render gallery '{}'", dest.to_str().unwrap_or_default());
+        self.post_process(&dest)
+    }
+
+    /// 将所有图表以及内联的 js 依赖写入单个 html 文件，便于通过邮件或者聊天工具分享
+    async fn render_bundle(&self, pages: &[ChartPage]) -> Result<()> {
+        let deps = self.config.display.dependency.clone().unwrap_or_default();
+        let mut scripts = vec![];
+        for dep in deps.list() {
+            let script = Self::load_script(&dep)
+                .await
+                .with_context(|| format!("load dependency '{}'", dep))?;
+            // 避免脚本内容提前闭合 script 标签
+            scripts.push(script.replace("</script", "<\\/script"));
+        }
+
+        let mut ctx = Context::new();
+        ctx.insert("charts", pages);
+        ctx.insert("scripts", &scripts);
+        ctx.insert("register", &deps.register());
+
+        let dest = Path::new(&self.config.display.destination).join(BUNDLE_FILE);
+        let mut f = File::create(&dest)?;
+        let content = Tera::default().render_str(TEMPLATE_BUNDLE, &ctx)?;
+        f.write_all(self.cleanup_content(content).as_bytes())?;
+        info!("render bundle '{}'", dest.to_str().unwrap_or_default());
+        self.post_process(&dest)
+    }
+
+    /// 对生成的文件依次执行 postProcessors 中扩展名匹配的命令，命令以非 0 状态码退出时返回错误
+    fn post_process(&self, dest: &Path) -> Result<()> {
+        // 查询结果为空时不会生成图表文件
+        if !dest.exists() {
+            return Ok(());
+        }
+        let processors = self.config.display.post_processors.clone();
+        let file = dest.to_str().unwrap_or_default();
+        for processor in processors.iter().flatten().filter(|p| p.matches(dest)) {
+            let now = time::Instant::now();
+            let output = Command::new(&processor.command)
+                .args(processor.args(file))
+                .output()
+                .with_context(|| format!("run post processor '{}'", processor.command))?;
+            if !output.status.success() {
+                return Err(anyhow!(
+                    "Post processor '{}' on '{}' exited with {}: {}",
+                    processor.command,
+                    file,
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
+            }
+            debug!(
+                "[post-process]: '{}' on '{}' => elapsed {:#?}",
+                processor.command,
+                file,
+                now.elapsed()
+            );
+        }
+        Ok(())
+    }
+
+    /// 读取 js 依赖的内容，http(s) 地址从远程下载，否则作为本地文件读取
+    async fn load_script(dep: &str) -> Result<String> {
+        if dep.starts_with("http://") || dep.starts_with("https://") {
+            let response = http::client()?.get(dep).send().await?.error_for_status()?;
+            return Ok(response.text().await?);
+        }
+        Ok(std::fs::read_to_string(dep)?)
+    }
+
     fn cleanup_content(&self, s: String) -> String {
         s.replace(r#""{{%"#, "").replace(r#"%}}""#, "")
     }
@@ -404,12 +1034,56 @@ impl ChartRender {
             return Ok(());
         }
 
-        let mut data_section = chart_config.data.clone();
-        let mappings = data_section.as_mapping_mut();
-        if mappings.is_none() {
-            return Err(anyhow!("Mismatched: data section should be mappings type"));
+        if self.svg {
+            let mut data_section = chart_config.data.clone();
+            let mappings = data_section.as_mapping_mut();
+            if mappings.is_none() {
+                return Err(anyhow!("Mismatched: data section should be mappings type"));
+            }
+            self.hanlde_data_section(mappings.unwrap(), cms);
+
+            let chart = SvgChart::try_new(
+                &chart_config.chart_type,
+                &chart_config.name,
+                &chart_config.width,
+                &chart_config.height,
+                &data_section,
+            )?;
+            let mut f = File::create(dest)?;
+            f.write_all(chart.render()?.as_bytes())?;
+            return Ok(());
+        }
+
+        let page = match self.chart_page(chart_config, cms)? {
+            Some(page) => page,
+            None => return Ok(()),
+        };
+        let mut ctx = Context::from_serialize(&page)?;
+        let deps = self.config.display.dependency.clone().unwrap_or_default();
+        ctx.insert("dependencies", &deps.list());
+        ctx.insert("register", &deps.register());
+
+        let mut f = File::create(dest)?;
+        let content = Tera::default().render_str(TEMPLATE_CHART, &ctx)?;
+        f.write_all(self.cleanup_content(content).as_bytes())?;
+        Ok(())
+    }
+
+    /// 生成 chartjs 图表的模板数据，查询结果为空时返回 None
+    fn chart_page(
+        &mut self,
+        chart_config: config::ChartConfig,
+        cms: &[ColumnMap],
+    ) -> Result<Option<ChartPage>> {
+        if cms.is_empty() {
+            return Ok(None);
         }
-        self.hanlde_data_section(mappings.unwrap(), cms);
+
+        let mut data_section = chart_config.data.clone();
+        let mappings = data_section
+            .as_mapping_mut()
+            .ok_or_else(|| anyhow!("Mismatched: data section should be mappings type"))?;
+        self.hanlde_data_section(mappings, cms);
 
         let options_section = chart_config.options.clone();
         let mut options_section = options_section.unwrap_or_default();
@@ -419,27 +1093,56 @@ impl ChartRender {
         }
 
         let content = serde_json::to_string(&Chart {
-            chart_type: chart_config.chart_type,
+            chart_type: chart_config.chart_type.clone(),
             data: data_section,
             options: options_section,
         })
         .unwrap_or_default();
 
-        let mut ctx = Context::new();
-        ctx.insert("width", &chart_config.width);
-        ctx.insert("height", &chart_config.height);
-        ctx.insert("title", &chart_config.name);
-        ctx.insert("config", &content);
-        ctx.insert("chart_id", &chart_config.name);
-
-        let deps = self.config.display.dependency.clone().unwrap_or_default();
-        ctx.insert("dependencies", &deps.list());
-        ctx.insert("register", &deps.register());
+        let downloads = cms
+            .iter()
+            .enumerate()
+            .map(|(idx, cm)| Download {
+                name: if cms.len() > 1 {
+                    format!("{}-{}.csv", chart_config.name, idx)
+                } else {
+                    format!("{}.csv", chart_config.name)
+                },
+                href: csv_data_uri(&cm.csv),
+            })
+            .collect::<Vec<_>>();
+
+        let accessibility = self
+            .config
+            .display
+            .accessibility
+            .clone()
+            .unwrap_or_default();
+        let aria_label = match accessibility.aria_label {
+            Some(true) => chart_config.description.clone().unwrap_or(format!(
+                "{} chart: {}",
+                chart_config.chart_type, chart_config.name
+            )),
+            _ => String::new(),
+        };
+
+        let mut tables = vec![];
+        if accessibility.data_table.unwrap_or(false) {
+            for cm in cms.iter().filter(|cm| !cm.csv.is_empty()) {
+                tables.push(DataTable::try_new(&cm.csv)?);
+            }
+        }
 
-        let mut f = File::create(dest)?;
-        let content = Tera::default().render_str(TEMPLATE_CHART, &ctx)?;
-        f.write_all(self.cleanup_content(content).as_bytes())?;
-        Ok(())
+        Ok(Some(ChartPage {
+            chart_id: chart_config.name.clone(),
+            title: chart_config.name,
+            width: chart_config.width,
+            height: chart_config.height,
+            config: content,
+            aria_label,
+            downloads,
+            tables,
+        }))
     }
 
     fn hanlde_options_section(&mut self, mappings: &mut Mapping) {
@@ -560,11 +1263,20 @@ impl ChartRender {
 
     fn handle_colors_field(&mut self, val: &mut Value) -> Option<&[Value]> {
         let var = self.parse_variable(val.as_str().unwrap_or_default())?;
+        let accessibility = self
+            .config
+            .display
+            .accessibility
+            .clone()
+            .unwrap_or_default();
+        if accessibility.high_contrast.unwrap_or(false) {
+            return Some(self.colors.get(HIGH_CONTRAST_COLORS)?);
+        }
         if var.1 == KeyType::Random.as_str() {
             let mut rng = rand::thread_rng();
             let n: usize = rng.gen();
             let k = self.colors.keys().nth(n % self.colors.len())?;
-            println!("[render]: random colors select '{}'", k);
+            debug!("[render]: random colors select '{}'", k);
             return Some(self.colors.get(k)?);
         }
         Some(self.colors.get(&var.1)?)
@@ -592,6 +1304,216 @@ mod tests {
         assert_eq!(render.parse_variable("}${"), None);
     }
 
+    #[test]
+    fn test_unknown_variables() {
+        let render = ChartRender::new(ExecutionContext::new(), config::RenderAction::default());
+        let data: Value = serde_yaml::from_str(
+            r#"
+labels:
+  - "${0:name}"
+datasets:
+  - data:
+      - "${0:n}"
+      - "${0:count}"
+    backgroundColor: "${0:Blues}"
+  - data:
+      - "${n}"
+    backgroundColor: "${NoSuchColors}"
+"#,
+        )
+        .unwrap();
+        let vars = vec![(0, String::from("n")), (0, String::from("name"))];
+        assert_eq!(
+            render.unknown_variables(&data, &vars),
+            vec![
+                "unknown variable '${0:count}'",
+                "unknown colors '${NoSuchColors}'"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_query_location() {
+        let content = r#"
+render:
+  executions: []
+  display:
+    destination: "./gitstats"
+    renderMode: "html"
+    queries:
+      - statements:
+          - "SELECT 1;"
+          - "SELECT 2;"
+      - statements:
+          - "SELECT 1;"
+        chart:
+          name: "chart"
+          type: "bar"
+          width: "680px"
+          height: "460px"
+          data: {}
+      - statements:
+          - "SELECT 1;"
+          - "SELECT 1;"
+          - "SELECT ${index};"
+"#;
+        let mut config: config::Config = serde_yaml::from_str(content).unwrap();
+        let queries = &mut config.render.as_mut().unwrap().display.queries;
+        config::locate_statements(content, queries);
+        assert_eq!(
+            queries[0].location(0, 1),
+            "query #1 statement #2 at line 10"
+        );
+        assert_eq!(
+            queries[1].location(1, 0),
+            "query #2 (chart 'chart') statement #1 at line 12"
+        );
+        // 首行相同的语句依次对应各自的行号
+        assert_eq!(queries[2].lines, vec![Some(20), Some(21), Some(22)]);
+
+        // 展开环境变量之后的语句在原始文本中找不到
+        queries[2].statements[2] = String::from("SELECT 3;");
+        config::locate_statements(content, queries);
+        assert_eq!(queries[2].lines[2], None);
+        assert_eq!(queries[2].location(2, 2), "query #3 statement #3");
+    }
+
+    #[tokio::test]
+    async fn test_check() {
+        let content = r#"
+executions: []
+display:
+  destination: "./gitstats"
+  renderMode: "chart"
+  queries:
+    - statements: []
+    - statements:
+        - "SELECT 1 AS n, 'a' AS name;"
+        - "SELECT * FROM missing;"
+      chart:
+        name: "chart"
+        type: "bar"
+        width: "680px"
+        height: "460px"
+        options:
+          plugins:
+            datalabels:
+              formatter: "${unknown_function}"
+        data:
+          labels:
+            - "${name}"
+          datasets:
+            - data:
+                - "${0:count}"
+                - "${1:n}"
+                - "${2:n}"
+              backgroundColor: "${NoSuchColors}"
+            - data:
+                - "${n}"
+              backgroundColor: "${random}"
+"#;
+        let config: config::RenderAction = serde_yaml::from_str(content).unwrap();
+        let problems = check(ExecutionContext::new(), config).await;
+        let expected = [
+            "unknown renderMode 'chart'",
+            "query #1 has no statements",
+            "query #2 (chart 'chart') statement #2",
+            "unknown variable '${0:count}', available columns: n, name",
+            "variable '${2:n}' refers to statement #3, but the query has 2 statements",
+            "unknown colors '${NoSuchColors}'",
+            "unknown function '${unknown_function}'",
+        ];
+        assert_eq!(problems.len(), expected.len(), "{:?}", problems);
+        for (problem, expected) in problems.iter().zip(expected.iter()) {
+            assert!(problem.contains(expected), "{}", problem);
+        }
+    }
+
+    #[test]
+    fn test_column_map() {
+        let mut cm = ColumnMap::new();
+        cm.insert("n".to_string(), Column::Int(vec![Some(1), None]));
+        cm.insert("n".to_string(), Column::Int(vec![Some(3)]));
+        cm.insert(
+            "h".to_string(),
+            Column::List(vec![vec![Value::from(1)], vec![Value::from(2)]]),
+        );
+        assert_eq!(
+            cm.get("n").unwrap(),
+            vec![Value::from(1), Value::Null, Value::from(3)]
+        );
+        assert_eq!(cm.get("h").unwrap(), vec![Value::from(1), Value::from(2)]);
+        assert!(cm.get("unknown").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_select_uint64() {
+        use datafusion::arrow::datatypes::{Field, Schema};
+
+        let mut ctx = ExecutionContext::new();
+        for (name, values) in [("t", vec![Some(1), None]), ("big", vec![Some(u64::MAX)])] {
+            let schema = Arc::new(Schema::new(vec![Field::new("n", DataType::UInt64, true)]));
+            let column: ArrayRef = Arc::new(array::UInt64Array::from(values));
+            let batch = RecordBatch::try_new(schema.clone(), vec![column]).unwrap();
+            let table = MemTable::try_new(schema, vec![vec![batch]]).unwrap();
+            ctx.register_table(name, Arc::new(table)).unwrap();
+        }
+
+        let mut engine = Engine::new(ctx);
+        let cm = engine.select("SELECT n FROM t", None).await.unwrap();
+        assert_eq!(cm.get("n").unwrap(), vec![Value::from(1), Value::Null]);
+        let e = engine.select("SELECT n FROM big", None).await.unwrap_err();
+        assert!(
+            e.to_string().contains("column 'n' has values larger than"),
+            "{}",
+            e
+        );
+    }
+
+    #[test]
+    fn test_round_batches() {
+        use datafusion::arrow::datatypes::{Field, Schema};
+
+        let schema = Arc::new(Schema::new(vec![Field::new("v", DataType::Float64, true)]));
+        let column: ArrayRef = Arc::new(array::Float64Array::from(vec![
+            Some(100.0 / 3.0),
+            None,
+            Some(0.125),
+        ]));
+        let batch = RecordBatch::try_new(schema, vec![column]).unwrap();
+
+        let batches = round_batches(vec![batch], Some(2)).unwrap();
+        let rounded = batches[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<array::Float64Array>()
+            .unwrap()
+            .iter()
+            .collect::<Vec<_>>();
+        assert_eq!(rounded, vec![Some(33.33), None, Some(0.13)]);
+    }
+
+    #[test]
+    fn test_result_table_name() {
+        assert_eq!(result_table_name("Commits-By Year"), "commits_by_year");
+        assert_eq!(result_table_name("作者"), "__");
+    }
+
+    #[test]
+    fn test_csv_data_uri() {
+        assert_eq!(
+            csv_data_uri("a,b\n1,x y\n"),
+            "data:text/csv;charset=utf-8,a%2Cb%0A1%2Cx%20y%0A"
+        );
+    }
+
+    #[test]
+    fn test_data_table() {
+        let table = DataTable::try_new("name,count\na,1\nb,2\n").unwrap();
+        assert_eq!(table.headers, vec!["name", "count"]);
+        assert_eq!(table.rows, vec![vec!["a", "1"], vec!["b", "2"]]);
+    }
+
     #[test]
     fn test_cleanup_content() {
         let render = ChartRender::new(ExecutionContext::new(), config::RenderAction::default());
@@ -0,0 +1,247 @@
+use crate::model::Repository;
+use anyhow::{anyhow, Result};
+
+/// 交互式选择需要分析的仓库，返回选中的仓库，取消选择时返回错误
+///
+/// 需要使用 `tui` feature 编译
+#[cfg(not(feature = "tui"))]
+pub fn select(dir: &str, _repos: Vec<Repository>) -> Result<Vec<Repository>> {
+    Err(anyhow!(
+        "Failed to select repos of '{}': gitv is built without the `tui` feature",
+        dir
+    ))
+}
+
+#[cfg(feature = "tui")]
+pub use tui::select;
+
+#[cfg(feature = "tui")]
+mod tui {
+    use super::*;
+    use crossterm::{
+        event::{self, Event, KeyCode, KeyEventKind},
+        execute,
+        terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    };
+    use ratatui::{
+        backend::{Backend, CrosstermBackend},
+        layout::{Constraint, Direction, Layout},
+        style::{Modifier, Style},
+        widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+        Terminal,
+    };
+    use std::io;
+
+    /// 仓库的选中状态以及过滤条件，默认选中所有仓库
+    pub(super) struct Selection {
+        repos: Vec<Repository>,
+        checked: Vec<bool>,
+        filter: String,
+        cursor: usize,
+    }
+
+    impl Selection {
+        pub(super) fn new(repos: Vec<Repository>) -> Self {
+            let checked = vec![true; repos.len()];
+            Self {
+                repos,
+                checked,
+                filter: String::new(),
+                cursor: 0,
+            }
+        }
+
+        /// 名称中包含过滤条件（不区分大小写）的仓库序号
+        pub(super) fn visible(&self) -> Vec<usize> {
+            let filter = self.filter.to_lowercase();
+            (0..self.repos.len())
+                .filter(|idx| self.repos[*idx].name.to_lowercase().contains(&filter))
+                .collect()
+        }
+
+        pub(super) fn push_filter(&mut self, c: char) {
+            self.filter.push(c);
+            self.cursor = 0;
+        }
+
+        pub(super) fn pop_filter(&mut self) {
+            self.filter.pop();
+            self.cursor = 0;
+        }
+
+        pub(super) fn move_cursor(&mut self, offset: isize) {
+            let len = self.visible().len();
+            if len == 0 {
+                return;
+            }
+            self.cursor = (self.cursor as isize + offset).clamp(0, len as isize - 1) as usize;
+        }
+
+        /// 切换光标所在仓库的选中状态
+        pub(super) fn toggle(&mut self) {
+            if let Some(idx) = self.visible().get(self.cursor) {
+                self.checked[*idx] = !self.checked[*idx];
+            }
+        }
+
+        /// 可见的仓库全部选中时取消选中，否则全部选中
+        pub(super) fn toggle_all(&mut self) {
+            let visible = self.visible();
+            let checked = !visible.iter().all(|idx| self.checked[*idx]);
+            for idx in visible {
+                self.checked[idx] = checked;
+            }
+        }
+
+        pub(super) fn selected(self) -> Vec<Repository> {
+            self.repos
+                .into_iter()
+                .zip(self.checked)
+                .filter(|(_, checked)| *checked)
+                .map(|(repo, _)| repo)
+                .collect()
+        }
+    }
+
+    pub fn select(dir: &str, repos: Vec<Repository>) -> Result<Vec<Repository>> {
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+        let result = run(&mut terminal, dir, Selection::new(repos));
+
+        disable_raw_mode()?;
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+        terminal.show_cursor()?;
+        result
+    }
+
+    fn run<B: Backend>(
+        terminal: &mut Terminal<B>,
+        dir: &str,
+        mut selection: Selection,
+    ) -> Result<Vec<Repository>> {
+        let mut filtering = false;
+        loop {
+            terminal.draw(|f| {
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(3), Constraint::Min(1)])
+                    .split(f.size());
+
+                let help = match filtering {
+                    true => "type to filter, Enter/Esc: done",
+                    false => "Space: toggle, a: toggle all, /: filter, Enter: confirm, q: cancel",
+                };
+                let filter = Paragraph::new(format!("/{}", selection.filter)).block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(format!("{} ({})", dir, help)),
+                );
+                f.render_widget(filter, chunks[0]);
+
+                let visible = selection.visible();
+                let items = visible
+                    .iter()
+                    .map(|idx| {
+                        let mark = if selection.checked[*idx] {
+                            "[x]"
+                        } else {
+                            "[ ]"
+                        };
+                        ListItem::new(format!("{} {}", mark, selection.repos[*idx].name))
+                    })
+                    .collect::<Vec<_>>();
+                let total = selection.checked.iter().filter(|c| **c).count();
+                let list = List::new(items)
+                    .block(Block::default().borders(Borders::ALL).title(format!(
+                        "selected {}/{}",
+                        total,
+                        selection.repos.len()
+                    )))
+                    .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+                let mut state = ListState::default();
+                state.select(match visible.is_empty() {
+                    true => None,
+                    false => Some(selection.cursor),
+                });
+                f.render_stateful_widget(list, chunks[1], &mut state);
+            })?;
+
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            if filtering {
+                match key.code {
+                    KeyCode::Enter | KeyCode::Esc => filtering = false,
+                    KeyCode::Backspace => selection.pop_filter(),
+                    KeyCode::Char(c) => selection.push_filter(c),
+                    _ => {}
+                }
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') => selection.move_cursor(-1),
+                KeyCode::Down | KeyCode::Char('j') => selection.move_cursor(1),
+                KeyCode::PageUp => selection.move_cursor(-10),
+                KeyCode::PageDown => selection.move_cursor(10),
+                KeyCode::Char(' ') => selection.toggle(),
+                KeyCode::Char('a') => selection.toggle_all(),
+                KeyCode::Char('/') => filtering = true,
+                KeyCode::Enter => return Ok(selection.selected()),
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    return Err(anyhow!("Repos selection of '{}' is cancelled", dir))
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tui"))]
+mod tests {
+    use super::tui::Selection;
+    use super::*;
+
+    #[test]
+    fn test_selection() {
+        let repos = [
+            "chenjiandongx/gitv",
+            "chenjiandongx/ginprom",
+            "rust-lang/rust",
+        ]
+        .iter()
+        .map(|name| Repository {
+            name: name.to_string(),
+            ..Default::default()
+        })
+        .collect();
+        let mut selection = Selection::new(repos);
+
+        for c in "GIN".chars() {
+            selection.push_filter(c);
+        }
+        assert_eq!(selection.visible(), vec![1]);
+        selection.toggle();
+
+        selection.pop_filter();
+        selection.pop_filter();
+        selection.pop_filter();
+        selection.push_filter('/');
+        selection.toggle_all();
+        selection.toggle_all();
+        selection.move_cursor(5);
+        selection.move_cursor(-2);
+        selection.toggle();
+
+        let names = selection
+            .selected()
+            .into_iter()
+            .map(|repo| repo.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["chenjiandongx/gitv"]);
+    }
+}
@@ -1,7 +1,18 @@
+use crate::{config::Execution, executor, library::QueryLibrary};
 use anyhow::{anyhow, Context, Result};
-use datafusion::{arrow::util::pretty, prelude::ExecutionContext};
-use rustyline::{error::ReadlineError, Editor};
-use std::path::PathBuf;
+use datafusion::{
+    arrow::{csv, json, record_batch::RecordBatch, util::pretty},
+    prelude::ExecutionContext,
+};
+use rustyline::{
+    completion::{Completer, Pair},
+    error::ReadlineError,
+    highlight::Highlighter,
+    hint::Hinter,
+    validate::Validator,
+    Editor, Helper,
+};
+use std::{fs::File, io::Write, path::PathBuf};
 use tokio::time;
 
 /// 记录 gitx shell 的语句执行历史，默认路径为 ~/.gitx
@@ -12,44 +23,310 @@ fn history_path() -> Result<PathBuf> {
     Ok(home)
 }
 
+static PROMPT: &str = "gitx(sql)> ";
+static CONTINUATION_PROMPT: &str = "        -> ";
+
+/// 将输入行追加到语句缓冲区，以 `;` 结尾时返回完整的语句并清空缓冲区
+fn push_line(buffer: &mut String, line: &str) -> Option<String> {
+    let line = line.trim_end();
+    if line.is_empty() {
+        return None;
+    }
+    if !buffer.is_empty() {
+        buffer.push('\n');
+    }
+    buffer.push_str(line);
+
+    if !buffer.ends_with(';') {
+        return None;
+    }
+    let statement = buffer.trim().to_string();
+    buffer.clear();
+    Some(statement)
+}
+
+static SQL_KEYWORDS: &[&str] = &[
+    "SELECT", "FROM", "WHERE", "GROUP", "BY", "ORDER", "HAVING", "LIMIT", "OFFSET", "AS", "AND",
+    "OR", "NOT", "IN", "IS", "NULL", "LIKE", "BETWEEN", "DISTINCT", "ASC", "DESC", "JOIN", "INNER",
+    "LEFT", "RIGHT", "FULL", "OUTER", "ON", "UNION", "ALL", "WITH", "CASE", "WHEN", "THEN", "ELSE",
+    "END", "CAST", "EXPLAIN", "COUNT", "SUM", "AVG", "MIN", "MAX",
+];
+
+/// shell 的 Tab 补全，候选项为 sql 关键字、table 名称、字段名称以及 udf 名称
+struct SqlHelper {
+    candidates: Vec<String>,
+}
+
+impl SqlHelper {
+    /// 从已注册的 table 中收集 table 名称以及字段名称，table 名称包含 `.`，补全为带引号的 `'db.commit'`
+    fn new(ctx: &ExecutionContext, executions: &[Execution]) -> Self {
+        let mut candidates = executor::function_names();
+        for execution in executions {
+            for name in executor::table_names() {
+                let table = format!("{}.{}", execution.db_name, name);
+                if let Ok(df) = ctx.table(table.as_str()) {
+                    candidates.extend(df.schema().fields().iter().map(|f| f.name().clone()));
+                    candidates.push(format!("'{}'", table));
+                }
+            }
+        }
+        Self::with_candidates(candidates)
+    }
+
+    fn with_candidates(mut candidates: Vec<String>) -> Self {
+        candidates.sort();
+        candidates.dedup();
+        Self { candidates }
+    }
+
+    /// 返回匹配前缀的候选项，关键字忽略大小写并保持与输入一致的大小写，未输入引号时同样补全带引号的 table 名称
+    fn complete_word(&self, word: &str) -> Vec<Pair> {
+        if word.is_empty() {
+            return vec![];
+        }
+        let lower = word.to_lowercase();
+        let lowercase = word == lower;
+        let keywords = SQL_KEYWORDS
+            .iter()
+            .filter(|k| k.to_lowercase().starts_with(&lower))
+            .map(|k| match lowercase {
+                true => k.to_lowercase(),
+                false => k.to_string(),
+            });
+        let names = self
+            .candidates
+            .iter()
+            .filter(|c| c.starts_with(word) || c.trim_start_matches('\'').starts_with(word))
+            .cloned();
+
+        let mut words = keywords.chain(names).collect::<Vec<_>>();
+        words.dedup();
+        words
+            .into_iter()
+            .map(|w| Pair {
+                display: w.clone(),
+                replacement: w,
+            })
+            .collect()
+    }
+}
+
+/// 光标前的单词的起始位置，`.` 和 `_` 视为单词的一部分，单词前的 `'` 一并替换
+fn word_start(line: &str, pos: usize) -> usize {
+    let start = line[..pos]
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+        .map(|idx| idx + 1)
+        .unwrap_or(0);
+    match line[..start].ends_with('\'') {
+        true => start - 1,
+        false => start,
+    }
+}
+
+impl Completer for SqlHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = word_start(line, pos);
+        Ok((start, self.complete_word(&line[start..pos])))
+    }
+}
+
+impl Hinter for SqlHelper {
+    type Hint = String;
+}
+
+impl Highlighter for SqlHelper {}
+
+impl Validator for SqlHelper {}
+
+impl Helper for SqlHelper {}
+
+/// 解析 `\o [file]` 命令，返回新的输出文件，未指定文件时恢复输出到终端
+fn parse_output_command(line: &str) -> Option<Option<PathBuf>> {
+    let line = line.trim();
+    let rest = line.strip_prefix("\\o")?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    match rest.trim().trim_end_matches(';') {
+        "" => Some(None),
+        file => Some(Some(PathBuf::from(file))),
+    }
+}
+
+/// 解析 `\use [name] [db]` 命令，返回命名查询以及 database 名称，未指定名称时列出所有命名查询
+fn parse_use_command(line: &str) -> Option<(Option<String>, Option<String>)> {
+    let line = line.trim().trim_end_matches(';');
+    let rest = line.strip_prefix("\\use")?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let mut args = rest.split_whitespace().map(|s| s.to_string());
+    Some((args.next(), args.next()))
+}
+
+fn print_library(library: &QueryLibrary) {
+    for (name, query) in library.iter() {
+        println!(
+            "  {:<24} {}",
+            name,
+            query.description.clone().unwrap_or_default()
+        );
+    }
+    println!();
+}
+
+/// `\o <file>` 打开的输出文件，之后每条语句的查询结果依次追加写入
+struct Output {
+    path: PathBuf,
+    file: File,
+}
+
+impl Output {
+    /// 创建（或者清空）输出文件
+    fn create(path: PathBuf) -> Result<Self> {
+        let file = File::create(&path)
+            .with_context(|| format!("Failed to create '{}'", path.display()))?;
+        Ok(Self { path, file })
+    }
+
+    /// 追加写入查询结果，`.json` 后缀每条语句输出为一行 json 数组，其余输出为带表头的 csv
+    fn write_batches(&mut self, batches: &[RecordBatch]) -> Result<()> {
+        match self.path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => {
+                let mut wtr = json::ArrayWriter::new(&mut self.file);
+                wtr.write_batches(batches)?;
+                wtr.finish()?;
+                self.file.write_all(b"\n")?;
+            }
+            _ => {
+                let mut wtr = csv::Writer::new(&mut self.file);
+                for batch in batches {
+                    wtr.write(batch)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn execute(
+    ctx: &mut ExecutionContext,
+    statement: &str,
+    output: &mut Option<Output>,
+) -> Result<()> {
+    let now = time::Instant::now();
+    match ctx.sql(statement).await {
+        Ok(batches) => match batches.collect().await {
+            Ok(batches) => {
+                match output {
+                    Some(output) => match output.write_batches(&batches) {
+                        Ok(_) => println!(
+                            "{} rows written to '{}'",
+                            batches.iter().map(|b| b.num_rows()).sum::<usize>(),
+                            output.path.display()
+                        ),
+                        Err(e) => println!("Error: {}", e),
+                    },
+                    None => pretty::print_batches(&batches)?,
+                }
+                println!("Query OK, elapsed: {:#?}\n", now.elapsed())
+            }
+            Err(e) => println!("Error: {}", e),
+        },
+        Err(e) => {
+            println!("Error: {}", e);
+        }
+    }
+    Ok(())
+}
+
 /// 持续循环读取并执行 sql 语句，监听 `Ctrl+C`、`q`、`Q` 作为退出信号
-pub async fn console_loop(mut ctx: ExecutionContext) -> anyhow::Result<()> {
+///
+/// 语句以 `;` 结尾，未结束时使用续行提示符继续读取，`Ctrl+C` 可放弃当前未完成的语句。
+/// `\o <file>` 将之后的查询结果依次追加写入文件（csv/json），`\o` 恢复输出到终端，`Tab` 补全关键字、表名、字段名以及函数名。
+/// `\use <name> [db]` 执行查询库中的命名查询，`\use` 列出所有命名查询
+pub async fn console_loop(
+    mut ctx: ExecutionContext,
+    executions: &[Execution],
+    query_library: Option<&str>,
+) -> anyhow::Result<()> {
+    let library = QueryLibrary::load(query_library)?;
     let history = history_path();
-    let mut readline = Editor::<()>::new();
+    let mut readline = Editor::<SqlHelper>::new();
+    readline.set_helper(Some(SqlHelper::new(&ctx, executions)));
     if let Ok(ref history) = history {
         readline.load_history(&history).unwrap_or(());
     }
 
+    let mut buffer = String::new();
+    let mut output: Option<Output> = None;
     loop {
-        match readline.readline("gitx(sql)> ") {
+        let prompt = if buffer.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+        match readline.readline(prompt) {
             Ok(line) => {
-                readline.add_history_entry(line.as_str());
-                match line.as_ref() {
-                    "exit" | "quit" | "q" => {
+                if buffer.is_empty() {
+                    if let "exit" | "quit" | "q" = line.trim() {
                         println!("Good bye!");
                         break;
                     }
-                    s => {
-                        if s.is_empty() {
-                            println!("gitx(sql)> ");
-                            continue;
+                    if let Some(path) = parse_output_command(&line) {
+                        readline.add_history_entry(line.trim());
+                        match path.map(Output::create).transpose() {
+                            Ok(Some(o)) => {
+                                println!("Output redirected to '{}'\n", o.path.display());
+                                output = Some(o);
+                            }
+                            Ok(None) => {
+                                println!("Output redirected to stdout\n");
+                                output = None;
+                            }
+                            Err(e) => println!("Error: {:#}\n", e),
                         }
-
-                        let now = time::Instant::now();
-                        match ctx.sql(s).await {
-                            Ok(batches) => match batches.collect().await {
-                                Ok(batches) => {
-                                    pretty::print_batches(&batches)?;
-                                    println!("Query OK, elapsed: {:#?}\n", now.elapsed())
+                        continue;
+                    }
+                    if let Some((name, db)) = parse_use_command(&line) {
+                        readline.add_history_entry(line.trim());
+                        let name = match name {
+                            Some(name) => name,
+                            None => {
+                                print_library(&library);
+                                continue;
+                            }
+                        };
+                        let db = db
+                            .or_else(|| executions.first().map(|e| e.db_name.clone()))
+                            .unwrap_or_default();
+                        match library.statements(&name, &db) {
+                            Ok(statements) => {
+                                for statement in statements {
+                                    println!("{}", statement);
+                                    execute(&mut ctx, &statement, &mut output).await?;
                                 }
-                                Err(e) => println!("Error: {}", e),
-                            },
-                            Err(e) => {
-                                println!("Error: {}", e);
                             }
+                            Err(e) => println!("Error: {}\n", e),
                         }
+                        continue;
                     }
                 }
+
+                if let Some(statement) = push_line(&mut buffer, &line) {
+                    readline.add_history_entry(statement.as_str());
+                    execute(&mut ctx, &statement, &mut output).await?;
+                }
+            }
+            Err(ReadlineError::Interrupted) if !buffer.is_empty() => {
+                buffer.clear();
             }
             Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
                 println!("Good bye!");
@@ -69,3 +346,234 @@ pub async fn console_loop(mut ctx: ExecutionContext) -> anyhow::Result<()> {
     }
     Ok(())
 }
+
+/// 拼接跨 database 的提交搜索语句，匹配提交标题、作者名称以及作者邮箱（忽略大小写）
+///
+/// 使用 `strpos` 按字面子串匹配，pattern 中的 `%` 以及 `_` 不作为 LIKE 通配符
+fn grep_sql(executions: &[Execution], pattern: &str) -> String {
+    let pattern = pattern.to_lowercase().replace('\'', "''");
+    let selects = executions
+        .iter()
+        .map(|e| {
+            format!(
+                "SELECT repo_name, datetime, hash, author_name, subject FROM '{}.commit' \
+                WHERE strpos(lower(subject), '{p}') > 0 OR strpos(lower(author_name), '{p}') > 0 \
+                OR strpos(lower(author_email), '{p}') > 0",
+                e.db_name,
+                p = pattern
+            )
+        })
+        .collect::<Vec<_>>();
+    format!(
+        "SELECT * FROM ({}) AS t ORDER BY datetime DESC",
+        selects.join(" UNION ALL ")
+    )
+}
+
+/// `gitv grep` 跨仓库搜索提交记录
+pub async fn grep(
+    mut ctx: ExecutionContext,
+    executions: &[Execution],
+    pattern: &str,
+) -> Result<()> {
+    if executions.is_empty() {
+        return Err(anyhow!("No executions configured"));
+    }
+
+    let now = time::Instant::now();
+    let batches = ctx
+        .sql(&grep_sql(executions, pattern))
+        .await?
+        .collect()
+        .await?;
+    let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+    pretty::print_batches(&batches)?;
+    println!("{} commits matched, elapsed: {:#?}", rows, now.elapsed());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+    use datafusion::arrow::array::StringArray;
+    use std::fs;
+
+    #[test]
+    fn test_push_line() {
+        let mut buffer = String::new();
+        assert_eq!(push_line(&mut buffer, "select repo_name"), None);
+        assert_eq!(push_line(&mut buffer, ""), None);
+        assert_eq!(
+            push_line(&mut buffer, "from 'db.active';  "),
+            Some(String::from("select repo_name\nfrom 'db.active';"))
+        );
+        assert!(buffer.is_empty());
+        assert_eq!(
+            push_line(&mut buffer, "select 1;"),
+            Some(String::from("select 1;"))
+        );
+    }
+
+    #[test]
+    fn test_complete_word() {
+        let helper = SqlHelper::with_candidates(vec![
+            String::from("'db.commit'"),
+            String::from("'db.change'"),
+            String::from("author_name"),
+            String::from("year"),
+        ]);
+        let words = |word: &str| {
+            helper
+                .complete_word(word)
+                .into_iter()
+                .map(|p| p.replacement)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(words("db.c"), vec!["'db.change'", "'db.commit'"]);
+        assert_eq!(words("'db.co"), vec!["'db.commit'"]);
+        assert_eq!(words("sel"), vec!["select"]);
+        assert_eq!(words("SEL"), vec!["SELECT"]);
+        assert_eq!(words("auth"), vec!["author_name"]);
+        assert!(words("").is_empty());
+
+        let line = "select count(1) from 'db.co";
+        assert_eq!(word_start(line, line.len()), line.len() - 6);
+        let line = "select count(1) from db.co";
+        assert_eq!(word_start(line, line.len()), line.len() - 5);
+        assert_eq!(word_start("yea", 3), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sql_helper_candidates() {
+        let dir = TempDir::new("candidates").unwrap();
+        fs::write(dir.join("commit.csv"), "repo_name,hash\na/x,h1\n").unwrap();
+        let executions = vec![Execution {
+            db_name: String::from("db"),
+            dir: dir.path_str().to_string(),
+        }];
+        let ctx = executor::Executor::create_context(executions.clone(), None, None)
+            .await
+            .unwrap();
+
+        let helper = SqlHelper::new(&ctx, &executions);
+        let has = |candidate: &str| helper.candidates.iter().any(|c| c == candidate);
+        assert!(has("'db.commit'") && has("repo_name") && has("hash"));
+        assert!(!has("db.commit"));
+        assert!(!helper.candidates.iter().any(|c| c.contains("db.change")));
+    }
+
+    #[test]
+    fn test_parse_output_command() {
+        assert_eq!(
+            parse_output_command("\\o result.csv"),
+            Some(Some(PathBuf::from("result.csv")))
+        );
+        assert_eq!(parse_output_command(" \\o "), Some(None));
+        assert_eq!(parse_output_command("\\output.csv"), None);
+        assert_eq!(parse_output_command("select 1;"), None);
+    }
+
+    #[tokio::test]
+    async fn test_execute_output() {
+        let dir = TempDir::new("output").unwrap();
+        let mut ctx = ExecutionContext::new();
+        for name in ["result.csv", "result.json"] {
+            let mut output = Some(Output::create(dir.join(name)).unwrap());
+            execute(&mut ctx, "SELECT 1 AS a;", &mut output)
+                .await
+                .unwrap();
+            execute(&mut ctx, "SELECT 'x' AS b;", &mut output)
+                .await
+                .unwrap();
+        }
+
+        let csv = fs::read_to_string(dir.join("result.csv")).unwrap();
+        assert_eq!(csv, "a\n1\nb\nx\n");
+        let json = fs::read_to_string(dir.join("result.json")).unwrap();
+        assert_eq!(json, "[{\"a\":1}]\n[{\"b\":\"x\"}]\n");
+    }
+
+    #[test]
+    fn test_parse_use_command() {
+        assert_eq!(parse_use_command("\\use"), Some((None, None)));
+        assert_eq!(
+            parse_use_command("\\use top_authors;"),
+            Some((Some(String::from("top_authors")), None))
+        );
+        assert_eq!(
+            parse_use_command(" \\use top_authors other "),
+            Some((
+                Some(String::from("top_authors")),
+                Some(String::from("other"))
+            ))
+        );
+        assert_eq!(parse_use_command("\\user"), None);
+        assert_eq!(parse_use_command("select 1;"), None);
+    }
+
+    #[tokio::test]
+    async fn test_grep_sql() {
+        let dir = TempDir::new("grep").unwrap();
+        let executions = ["a", "b"]
+            .iter()
+            .map(|db| {
+                let path = dir.join(db);
+                fs::create_dir_all(&path).unwrap();
+                Execution {
+                    db_name: db.to_string(),
+                    dir: path.to_string_lossy().to_string(),
+                }
+            })
+            .collect::<Vec<_>>();
+        let header = "repo_name,datetime,hash,author_name,author_email,subject\n";
+        fs::write(
+            dir.join("a").join("commit.csv"),
+            format!(
+                "{}a/x,2022-03-01T10:00:00+08:00,h1,alice,alice@x.com,Fix 'Bug' in parser\n\
+                a/x,2022-03-02T10:00:00+08:00,h2,bob,bob@x.com,add 100% coverage\n\
+                a/x,2022-03-03T10:00:00+08:00,h3,carol,carol@x.com,add 100 tests\n",
+                header
+            ),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("b").join("commit.csv"),
+            format!(
+                "{}b/y,2022-03-04T10:00:00+08:00,h4,dave,fix_bug@y.com,refactor\n",
+                header
+            ),
+        )
+        .unwrap();
+
+        let mut ctx = executor::Executor::create_context(executions.clone(), None, None)
+            .await
+            .unwrap();
+        let mut hashes = vec![];
+        for pattern in ["fix", "FIX 'bug'", "100%", "fix_", "missing"] {
+            let batches = ctx
+                .sql(&grep_sql(&executions, pattern))
+                .await
+                .unwrap()
+                .collect()
+                .await
+                .unwrap();
+            let mut matched = vec![];
+            for batch in batches.iter() {
+                let column = batch
+                    .column(2)
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .unwrap();
+                matched.extend((0..column.len()).map(|i| column.value(i).to_string()));
+            }
+            hashes.push(matched);
+        }
+        // `%` 以及 `_` 按字面匹配，不会匹配到 "add 100 tests" 以及 "Fix 'Bug'"
+        assert_eq!(hashes[0], vec!["h4", "h1"]);
+        assert_eq!(hashes[1], vec!["h1"]);
+        assert_eq!(hashes[2], vec!["h2"]);
+        assert_eq!(hashes[3], vec!["h4"]);
+        assert!(hashes[4].is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::model::Repository;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+static SKIP_LIST: &str = "skip_list.yaml";
+
+/// 连续失败的仓库记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkipEntry {
+    /// 最近一次失败的原因
+    pub reason: String,
+    /// 连续失败的次数
+    pub failures: u32,
+    /// 首次失败的时间（rfc3339）
+    pub first_failed: String,
+    /// 最近一次失败的时间（rfc3339）
+    pub last_failed: String,
+}
+
+/// database 目录下连续失败（如仓库已删除、无访问权限）的仓库列表，保存在 `${dir}/skip_list.yaml`
+///
+/// 连续失败次数达到阈值的仓库在后续运行中会被跳过，使用 `--retry-failed` 时重新尝试，成功后从列表中移除
+pub struct SkipList {
+    path: PathBuf,
+    threshold: u32,
+    entries: BTreeMap<String, SkipEntry>,
+}
+
+impl SkipList {
+    /// 读取 database 目录下的列表，threshold 为 0 时不跳过任何仓库
+    pub fn load(dir: &str, threshold: u32) -> Result<Self> {
+        let path = Path::new(dir).join(SKIP_LIST);
+        let entries = match path.exists() {
+            true => serde_yaml::from_str(&fs::read_to_string(&path)?)?,
+            false => BTreeMap::new(),
+        };
+        Ok(Self {
+            path,
+            threshold,
+            entries,
+        })
+    }
+
+    fn skipped(&self, name: &str) -> Option<&SkipEntry> {
+        self.entries
+            .get(name)
+            .filter(|entry| self.threshold > 0 && entry.failures >= self.threshold)
+    }
+
+    /// 移除需要跳过的仓库，retry 为 true 时保留所有仓库
+    pub fn filter(&self, repos: Vec<Repository>, retry: bool) -> Vec<Repository> {
+        if retry {
+            return repos;
+        }
+
+        let now = Local::now();
+        repos
+            .into_iter()
+            .filter(|repo| match self.skipped(&repo.name) {
+                None => true,
+                Some(entry) => {
+                    warn!(
+                        "[skip] '{}' => failed {} times in a row since {} ({} days ago): {}, use --retry-failed to retry",
+                        repo.name,
+                        entry.failures,
+                        entry.first_failed,
+                        age_days(&entry.first_failed, now),
+                        entry.reason,
+                    );
+                    false
+                }
+            })
+            .collect()
+    }
+
+    /// 记录一次运行的结果，失败的仓库累加失败次数，其余尝试过的仓库从列表中移除
+    pub fn update(
+        &mut self,
+        attempted: &[String],
+        failed: &[(String, String)],
+        now: DateTime<Local>,
+    ) {
+        let now = now.to_rfc3339();
+        for name in attempted {
+            match failed.iter().find(|(repo, _)| repo == name) {
+                None => {
+                    self.entries.remove(name);
+                }
+                Some((_, reason)) => {
+                    let entry = self.entries.entry(name.clone()).or_insert(SkipEntry {
+                        reason: String::new(),
+                        failures: 0,
+                        first_failed: now.clone(),
+                        last_failed: String::new(),
+                    });
+                    entry.reason = reason.clone();
+                    entry.failures += 1;
+                    entry.last_failed = now.clone();
+                }
+            }
+        }
+    }
+
+    /// 保存列表，列表为空时删除文件
+    pub fn save(&self) -> Result<()> {
+        if self.entries.is_empty() {
+            if self.path.exists() {
+                fs::remove_file(&self.path)?;
+            }
+            return Ok(());
+        }
+        fs::write(&self.path, serde_yaml::to_string(&self.entries)?)?;
+        Ok(())
+    }
+}
+
+/// 距离给定时间的天数，时间格式错误时为 0
+fn age_days(datetime: &str, now: DateTime<Local>) -> i64 {
+    DateTime::parse_from_rfc3339(datetime)
+        .map(|t| (now.timestamp() - t.timestamp()) / 86400)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn test_skip_list() {
+        let dir = TempDir::new("skip").unwrap();
+        let dir = dir.path_str();
+
+        let repos = ["a/gone", "a/ok", "a/flaky"]
+            .iter()
+            .map(|name| Repository {
+                name: name.to_string(),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let names = repos.iter().map(|r| r.name.clone()).collect::<Vec<_>>();
+        let now = Local.ymd(2022, 3, 1).and_hms(12, 0, 0);
+
+        let mut skip_list = SkipList::load(dir, 2).unwrap();
+        let failed = vec![
+            ("a/gone".to_string(), "repository not found".to_string()),
+            ("a/flaky".to_string(), "timeout".to_string()),
+        ];
+        skip_list.update(&names, &failed, now);
+        assert_eq!(skip_list.filter(repos.clone(), false).len(), 3);
+
+        let failed = vec![("a/gone".to_string(), "repository not found".to_string())];
+        skip_list.update(&names, &failed, now + Duration::days(1));
+        skip_list.save().unwrap();
+
+        let mut skip_list = SkipList::load(dir, 2).unwrap();
+        let entry = skip_list.entries.get("a/gone").unwrap();
+        assert_eq!(entry.failures, 2);
+        assert_eq!(age_days(&entry.first_failed, now + Duration::days(3)), 3);
+        assert!(!skip_list.entries.contains_key("a/flaky"));
+
+        let kept = skip_list
+            .filter(repos.clone(), false)
+            .into_iter()
+            .map(|r| r.name)
+            .collect::<Vec<_>>();
+        assert_eq!(kept, vec!["a/ok", "a/flaky"]);
+        assert_eq!(skip_list.filter(repos, true).len(), 3);
+
+        skip_list.update(&names, &[], now);
+        skip_list.save().unwrap();
+        assert!(!Path::new(dir).join(SKIP_LIST).exists());
+    }
+}
@@ -0,0 +1,445 @@
+use anyhow::{anyhow, Result};
+use serde_yaml::Value;
+use std::{f64::consts::PI, fmt::Write};
+
+/// 未配置 backgroundColor 时使用的默认颜色
+static DEFAULT_COLORS: [&str; 10] = [
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#ff9da7",
+    "#9c755f", "#bab0ac",
+];
+
+const MARGIN_LEFT: f64 = 60.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_TOP: f64 = 60.0;
+const MARGIN_BOTTOM: f64 = 60.0;
+const GRID_LINES: usize = 5;
+
+struct DataSet {
+    label: String,
+    data: Vec<f64>,
+    colors: Vec<String>,
+}
+
+impl DataSet {
+    fn color(&self, idx: usize) -> String {
+        if self.colors.is_empty() {
+            return DEFAULT_COLORS[idx % DEFAULT_COLORS.len()].to_string();
+        }
+        self.colors[idx % self.colors.len()].clone()
+    }
+}
+
+/// 不依赖浏览器的 svg 图表，支持 bar/line/pie/doughnut 类型
+///
+/// 数据来自变量替换后的 chartjs data 配置，即 `labels` 以及 `datasets[].{label, data, backgroundColor}`
+pub struct SvgChart {
+    chart_type: String,
+    title: String,
+    width: f64,
+    height: f64,
+    labels: Vec<String>,
+    datasets: Vec<DataSet>,
+}
+
+/// 转义 xml/html 中的特殊字符
+pub(crate) fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 解析 "680px" 之类的尺寸，解析失败时使用默认值
+fn parse_size(s: &str, default: f64) -> f64 {
+    s.trim_end_matches("px").trim().parse().unwrap_or(default)
+}
+
+fn value_to_string(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        _ => String::new(),
+    }
+}
+
+fn format_number(v: f64) -> String {
+    if v.fract() == 0.0 {
+        format!("{}", v as i64)
+    } else {
+        format!("{:.2}", v)
+    }
+}
+
+impl SvgChart {
+    pub fn try_new(
+        chart_type: &str,
+        title: &str,
+        width: &str,
+        height: &str,
+        data: &Value,
+    ) -> Result<Self> {
+        let labels = data
+            .get("labels")
+            .and_then(|v| v.as_sequence())
+            .map(|seq| seq.iter().map(value_to_string).collect())
+            .unwrap_or_default();
+
+        let mut datasets = vec![];
+        for ds in data
+            .get("datasets")
+            .and_then(|v| v.as_sequence())
+            .ok_or_else(|| anyhow!("Mismatched: datasets field should be sequence type"))?
+        {
+            let colors = match ds.get("backgroundColor") {
+                Some(Value::Sequence(seq)) => seq.iter().map(value_to_string).collect(),
+                Some(Value::String(s)) => vec![s.clone()],
+                _ => vec![],
+            };
+            datasets.push(DataSet {
+                label: ds.get("label").map(value_to_string).unwrap_or_default(),
+                data: ds
+                    .get("data")
+                    .and_then(|v| v.as_sequence())
+                    .map(|seq| seq.iter().map(|v| v.as_f64().unwrap_or_default()).collect())
+                    .unwrap_or_default(),
+                colors,
+            });
+        }
+
+        Ok(Self {
+            chart_type: chart_type.to_string(),
+            title: title.to_string(),
+            width: parse_size(width, 800.0),
+            height: parse_size(height, 400.0),
+            labels,
+            datasets,
+        })
+    }
+
+    pub fn render(&self) -> Result<String> {
+        let mut body = String::new();
+        match self.chart_type.as_str() {
+            "bar" => self.render_bar(&mut body)?,
+            "line" => self.render_line(&mut body)?,
+            "pie" => self.render_pie(&mut body, 0.0)?,
+            "doughnut" => self.render_pie(&mut body, 0.5)?,
+            t => return Err(anyhow!("Unsupported chart type '{}' for svg render", t)),
+        }
+
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="12">"#,
+            w = self.width,
+            h = self.height
+        )?;
+        writeln!(svg, "<title>{}</title>", escape(&self.title))?;
+        writeln!(
+            svg,
+            r#"<rect width="{}" height="{}" fill="white"/>"#,
+            self.width, self.height
+        )?;
+        writeln!(
+            svg,
+            r#"<text x="{}" y="22" text-anchor="middle" font-size="16">{}</text>"#,
+            self.width / 2.0,
+            escape(&self.title)
+        )?;
+        svg.push_str(&body);
+        svg.push_str("</svg>\n");
+        Ok(svg)
+    }
+
+    fn max_value(&self) -> f64 {
+        let max = self
+            .datasets
+            .iter()
+            .flat_map(|ds| ds.data.iter().cloned())
+            .fold(0.0, f64::max);
+        if max <= 0.0 {
+            1.0
+        } else {
+            max
+        }
+    }
+
+    fn plot_area(&self) -> (f64, f64, f64, f64) {
+        (
+            MARGIN_LEFT,
+            MARGIN_TOP,
+            self.width - MARGIN_LEFT - MARGIN_RIGHT,
+            self.height - MARGIN_TOP - MARGIN_BOTTOM,
+        )
+    }
+
+    /// 坐标轴、网格线以及 x 轴标签
+    fn render_axes(&self, out: &mut String, max: f64) -> Result<()> {
+        let (x, y, w, h) = self.plot_area();
+        for i in 0..=GRID_LINES {
+            let v = max * i as f64 / GRID_LINES as f64;
+            let gy = y + h - h * i as f64 / GRID_LINES as f64;
+            writeln!(
+                out,
+                r##"<line x1="{x}" y1="{gy:.1}" x2="{x2}" y2="{gy:.1}" stroke="#e0e0e0"/>"##,
+                x = x,
+                x2 = x + w,
+                gy = gy
+            )?;
+            writeln!(
+                out,
+                r#"<text x="{}" y="{:.1}" text-anchor="end" dominant-baseline="middle">{}</text>"#,
+                x - 6.0,
+                gy,
+                format_number(v)
+            )?;
+        }
+
+        let n = self.labels.len().max(1) as f64;
+        for (idx, label) in self.labels.iter().enumerate() {
+            let lx = x + w * (idx as f64 + 0.5) / n;
+            writeln!(
+                out,
+                r#"<text x="{lx:.1}" y="{ly}" text-anchor="end" transform="rotate(-30 {lx:.1} {ly})">{}</text>"#,
+                escape(label),
+                lx = lx,
+                ly = y + h + 14.0
+            )?;
+        }
+        Ok(())
+    }
+
+    /// 多个数据集时在标题下方输出图例
+    fn render_legend(&self, out: &mut String, items: &[(String, String)]) -> Result<()> {
+        let mut lx = MARGIN_LEFT;
+        for (label, color) in items {
+            writeln!(
+                out,
+                r#"<rect x="{:.1}" y="34" width="12" height="12" fill="{}"/>"#,
+                lx,
+                escape(color)
+            )?;
+            writeln!(
+                out,
+                r#"<text x="{:.1}" y="44">{}</text>"#,
+                lx + 16.0,
+                escape(label)
+            )?;
+            lx += 16.0 + 7.0 * label.chars().count() as f64 + 16.0;
+        }
+        Ok(())
+    }
+
+    fn render_bar(&self, out: &mut String) -> Result<()> {
+        let max = self.max_value();
+        self.render_axes(out, max)?;
+
+        let (x, y, w, h) = self.plot_area();
+        let n = self.labels.len().max(1) as f64;
+        let m = self.datasets.len().max(1) as f64;
+        let group = w / n;
+        let bar = group * 0.8 / m;
+        for (di, ds) in self.datasets.iter().enumerate() {
+            for (idx, v) in ds.data.iter().enumerate() {
+                // 单个数据集时与 chartjs 一致，每个柱子使用不同的颜色
+                let color = if self.datasets.len() == 1 {
+                    ds.color(idx)
+                } else {
+                    ds.color(0)
+                };
+                let bh = h * v / max;
+                writeln!(
+                    out,
+                    r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"><title>{}: {}</title></rect>"#,
+                    x + group * idx as f64 + group * 0.1 + bar * di as f64,
+                    y + h - bh,
+                    bar,
+                    bh,
+                    escape(&color),
+                    escape(self.labels.get(idx).map(|s| s.as_str()).unwrap_or_default()),
+                    format_number(*v)
+                )?;
+            }
+        }
+
+        if self.datasets.len() > 1 {
+            let items = self
+                .datasets
+                .iter()
+                .map(|ds| (ds.label.clone(), ds.color(0)))
+                .collect::<Vec<_>>();
+            self.render_legend(out, &items)?;
+        }
+        Ok(())
+    }
+
+    fn render_line(&self, out: &mut String) -> Result<()> {
+        let max = self.max_value();
+        self.render_axes(out, max)?;
+
+        let (x, y, w, h) = self.plot_area();
+        let n = self.labels.len().max(1) as f64;
+        for ds in self.datasets.iter() {
+            let color = ds.color(0);
+            let points = ds
+                .data
+                .iter()
+                .enumerate()
+                .map(|(idx, v)| (x + w * (idx as f64 + 0.5) / n, y + h - h * v / max))
+                .collect::<Vec<_>>();
+            let path = points
+                .iter()
+                .map(|(px, py)| format!("{:.1},{:.1}", px, py))
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(
+                out,
+                r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2"/>"#,
+                path,
+                escape(&color)
+            )?;
+            for (px, py) in points {
+                writeln!(
+                    out,
+                    r#"<circle cx="{:.1}" cy="{:.1}" r="3" fill="{}"/>"#,
+                    px,
+                    py,
+                    escape(&color)
+                )?;
+            }
+        }
+
+        let items = self
+            .datasets
+            .iter()
+            .map(|ds| (ds.label.clone(), ds.color(0)))
+            .collect::<Vec<_>>();
+        self.render_legend(out, &items)
+    }
+
+    /// 只绘制第一个数据集，hole 为内圆半径占比（doughnut）
+    fn render_pie(&self, out: &mut String, hole: f64) -> Result<()> {
+        let ds = match self.datasets.first() {
+            Some(ds) => ds,
+            None => return Ok(()),
+        };
+        let total: f64 = ds.data.iter().filter(|v| **v > 0.0).sum();
+        if total <= 0.0 {
+            return Ok(());
+        }
+
+        let (_, y, _, h) = self.plot_area();
+        let r = (h / 2.0).min((self.width - 200.0) / 2.0).max(10.0);
+        let (cx, cy) = (MARGIN_LEFT + r, y + h / 2.0);
+        let mut angle = -PI / 2.0;
+        for (idx, v) in ds.data.iter().enumerate().filter(|(_, v)| **v > 0.0) {
+            let sweep = 2.0 * PI * v / total;
+            let (x1, y1) = (cx + r * angle.cos(), cy + r * angle.sin());
+            let (x2, y2) = (
+                cx + r * (angle + sweep).cos(),
+                cy + r * (angle + sweep).sin(),
+            );
+            let large = if sweep > PI { 1 } else { 0 };
+            let label = self.labels.get(idx).cloned().unwrap_or_default();
+
+            // 只有一个扇区时 arc 起止点重合无法绘制，直接画圆
+            let shape = if (sweep - 2.0 * PI).abs() < 1e-9 {
+                format!(r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}""#, cx, cy, r)
+            } else {
+                format!(
+                    r#"<path d="M {cx:.1} {cy:.1} L {x1:.1} {y1:.1} A {r:.1} {r:.1} 0 {large} 1 {x2:.1} {y2:.1} Z""#,
+                    cx = cx,
+                    cy = cy,
+                    x1 = x1,
+                    y1 = y1,
+                    r = r,
+                    large = large,
+                    x2 = x2,
+                    y2 = y2
+                )
+            };
+            writeln!(
+                out,
+                r#"{} fill="{}" stroke="white"><title>{}: {}</title>{}"#,
+                shape,
+                escape(&ds.color(idx)),
+                escape(&label),
+                format_number(*v),
+                if shape.starts_with("<circle") {
+                    "</circle>"
+                } else {
+                    "</path>"
+                }
+            )?;
+            angle += sweep;
+        }
+        if hole > 0.0 {
+            writeln!(
+                out,
+                r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" fill="white"/>"#,
+                cx,
+                cy,
+                r * hole
+            )?;
+        }
+
+        // 图例
+        let lx = cx + r + 30.0;
+        for (idx, label) in self.labels.iter().enumerate() {
+            let ly = y + 16.0 * idx as f64;
+            writeln!(
+                out,
+                r#"<rect x="{:.1}" y="{:.1}" width="12" height="12" fill="{}"/>"#,
+                lx,
+                ly,
+                escape(&ds.color(idx))
+            )?;
+            writeln!(
+                out,
+                r#"<text x="{:.1}" y="{:.1}">{} ({})</text>"#,
+                lx + 16.0,
+                ly + 10.0,
+                escape(label),
+                format_number(ds.data.get(idx).cloned().unwrap_or_default())
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> Value {
+        serde_yaml::from_str(
+            r##"
+labels: ["a", "b<c"]
+datasets:
+  - label: "commits"
+    data: [3, 1]
+    backgroundColor: ["#000000", "#ffffff"]
+"##,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_svg_render() {
+        for t in ["bar", "line", "pie", "doughnut"] {
+            let chart = SvgChart::try_new(t, "title", "680px", "460px", &data()).unwrap();
+            let svg = chart.render().unwrap();
+            assert!(svg.starts_with("<svg"));
+            assert!(svg.contains("b&lt;c"));
+        }
+
+        let chart = SvgChart::try_new("radar", "title", "680px", "460px", &data()).unwrap();
+        assert!(chart.render().is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("680px", 800.0), 680.0);
+        assert_eq!(parse_size("auto", 800.0), 800.0);
+    }
+}
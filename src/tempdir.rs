@@ -0,0 +1,69 @@
+use std::{
+    env, fs, io,
+    ops::Deref,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// 同一进程内临时目录的序号，与 pid 一起保证目录唯一
+static SEQ: AtomicUsize = AtomicUsize::new(0);
+
+/// 系统临时目录下的 `gitv-<name>-<pid>-<seq>` 目录，Drop 时（包括出错提前返回或者测试断言失败）删除
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> io::Result<Self> {
+        let path = env::temp_dir().join(format!(
+            "gitv-{}-{}-{}",
+            name,
+            process::id(),
+            SEQ.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&path)?;
+        Ok(Self(path))
+    }
+
+    /// 目录路径的字符串形式，数据目录等配置项均为字符串
+    pub fn path_str(&self) -> &str {
+        self.0.to_str().unwrap_or_default()
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.0).unwrap_or(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temp_dir() {
+        let a = TempDir::new("tempdir").unwrap();
+        let b = TempDir::new("tempdir").unwrap();
+        assert_ne!(a.path_str(), b.path_str());
+        fs::write(a.join("file"), "gitv").unwrap();
+
+        let path = a.to_path_buf();
+        drop(a);
+        assert!(!path.exists());
+        assert!(b.is_dir());
+    }
+}
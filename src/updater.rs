@@ -0,0 +1,150 @@
+use crate::http;
+use anyhow::{anyhow, Result};
+use log::info;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{env, fs, path::Path};
+
+static RELEASES_LATEST_API: &str =
+    "https://api.github.com/repos/chenjiandongx/gitv/releases/latest";
+
+#[derive(Debug, Deserialize, Clone)]
+struct ReleaseResponse {
+    tag_name: String,
+    assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// 从 Github Releases 检查并更新当前的二进制文件
+///
+/// Release 资源命名格式为 `gitv_${os_arch}`，校验文件为 `gitv_${os_arch}.sha256`
+pub struct SelfUpdater;
+
+impl SelfUpdater {
+    /// 当前平台对应的 release 资源名称
+    fn asset_name() -> Result<String> {
+        let os = match env::consts::OS {
+            "linux" => "linux",
+            "macos" => "darwin",
+            "windows" => "windows",
+            s => return Err(anyhow!("Unsupported os: {}", s)),
+        };
+        let arch = match env::consts::ARCH {
+            "x86_64" => "amd64",
+            "aarch64" => "arm64",
+            s => return Err(anyhow!("Unsupported arch: {}", s)),
+        };
+
+        let mut name = format!("gitv_{}_{}", os, arch);
+        if os == "windows" {
+            name.push_str(".exe");
+        }
+        Ok(name)
+    }
+
+    async fn get(url: &str) -> Result<reqwest::Response> {
+        let response = http::client()?.get(url).send().await?.error_for_status()?;
+        Ok(response)
+    }
+
+    /// 校验下载内容的 sha256，校验文件格式兼容 `sha256sum` 的输出
+    fn verify_checksum(content: &[u8], checksum: &str) -> Result<()> {
+        let expected = checksum
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        let actual = Sha256::digest(content)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+
+        if expected != actual {
+            return Err(anyhow!(
+                "Checksum mismatched: expected '{}', got '{}'",
+                expected,
+                actual
+            ));
+        }
+        Ok(())
+    }
+
+    /// 先写入同目录下的临时文件，再通过 rename 替换当前二进制文件
+    fn replace_binary(content: &[u8]) -> Result<()> {
+        let exe = env::current_exe()?;
+        let tmp = exe.with_extension("tmp");
+        fs::write(&tmp, content)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tmp, fs::Permissions::from_mode(0o755))?;
+        }
+
+        if let Err(e) = fs::rename(&tmp, &exe) {
+            fs::remove_file(Path::new(&tmp)).unwrap_or(());
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    pub async fn update() -> Result<()> {
+        let current = env!("CARGO_PKG_VERSION");
+        let release = Self::get(RELEASES_LATEST_API)
+            .await?
+            .json::<ReleaseResponse>()
+            .await?;
+
+        let latest = release.tag_name.trim_start_matches('v');
+        if latest == current {
+            info!("gitv is already up to date (version: {})", current);
+            return Ok(());
+        }
+
+        let name = Self::asset_name()?;
+        let checksum_name = format!("{}.sha256", name);
+        let find_asset = |n: &str| {
+            release
+                .assets
+                .iter()
+                .find(|asset| asset.name == n)
+                .cloned()
+                .ok_or_else(|| anyhow!("Release asset '{}' not found", n))
+        };
+        let asset = find_asset(&name)?;
+        let checksum_asset = find_asset(&checksum_name)?;
+
+        info!("downloading '{}' (version: {})...", asset.name, latest);
+        let content = Self::get(&asset.browser_download_url)
+            .await?
+            .bytes()
+            .await?;
+        let checksum = Self::get(&checksum_asset.browser_download_url)
+            .await?
+            .text()
+            .await?;
+
+        Self::verify_checksum(&content, &checksum)?;
+        Self::replace_binary(&content)?;
+        info!("gitv has been updated: {} => {}", current, latest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_checksum() {
+        let checksum = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  gitv";
+        assert!(SelfUpdater::verify_checksum(b"hello", checksum).is_ok());
+        assert!(SelfUpdater::verify_checksum(b"world", checksum).is_err());
+    }
+}
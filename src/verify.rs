@@ -0,0 +1,163 @@
+use crate::{
+    config::CreateAction,
+    gitimp::GitImpl,
+    record::{self, RecordCommit, RecordMeta},
+};
+use anyhow::{anyhow, Result};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+/// 统计 commit 表中每个仓库的提交数
+///
+/// 不包含 merge 提交以及共同作者记录，与 `git rev-list --count --no-merges` 的口径保持一致
+fn recorded_commits(path: &Path) -> Result<HashMap<String, u64>> {
+    if !path.exists() || fs::metadata(path)?.len() == 0 {
+        return Ok(HashMap::new());
+    }
+
+    let mut rdr = record::csv_reader(path)?;
+    let headers = rdr.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let (repo_idx, hash_idx) = match (column("repo_name"), column("hash")) {
+        (Some(repo_idx), Some(hash_idx)) => (repo_idx, hash_idx),
+        _ => return Err(anyhow!("Invalid commit table '{}'", path.display())),
+    };
+    let flags = [column("is_merge"), column("is_co_author")];
+
+    let mut hashs: HashMap<String, HashSet<String>> = HashMap::new();
+    for row in rdr.records() {
+        let row = row?;
+        if flags.iter().flatten().any(|idx| &row[*idx] == "true") {
+            continue;
+        }
+        hashs
+            .entry(row[repo_idx].to_string())
+            .or_default()
+            .insert(row[hash_idx].to_string());
+    }
+    Ok(hashs
+        .into_iter()
+        .map(|(repo, hashs)| (repo, hashs.len() as u64))
+        .collect())
+}
+
+/// meta 表中记录的按 sampling 采样分析的仓库，采样仓库的提交数与 git 不一致是预期的结果
+fn sampled_repos(path: &Path) -> Result<HashSet<String>> {
+    if !path.exists() || fs::metadata(path)?.len() == 0 {
+        return Ok(HashSet::new());
+    }
+
+    for row in record::csv_reader(path)?.records() {
+        let row = row?;
+        if row.get(0) == Some("sampled") {
+            let repos = row.get(1).unwrap_or_default();
+            return Ok(repos
+                .split(',')
+                .filter(|r| !r.is_empty())
+                .map(|r| r.to_string())
+                .collect());
+        }
+    }
+    Ok(HashSet::new())
+}
+
+/// 对比每个 database 的 commit 表与仓库 `git rev-list --count` 的提交数，存在差异时返回错误
+///
+/// 差异为 git 的提交数减去 database 的提交数，正数表示 database 已过期或者创建不完整，
+/// 按 sampling 采样分析的仓库标记为 sampled，不计入差异
+pub fn verify(config: &CreateAction) -> Result<()> {
+    GitImpl::configure(
+        config.git_binary.clone(),
+        config.git_global_args.clone().unwrap_or_default(),
+    );
+    let mut drifted = 0;
+    for database in config.databases.iter() {
+        let path = Path::new(&database.dir).join(format!("{}.csv", RecordCommit::name()));
+        let recorded = recorded_commits(&path)?;
+        let sampled =
+            sampled_repos(&Path::new(&database.dir).join(format!("{}.csv", RecordMeta::name())))?;
+
+        println!("Database '{}'", database.dir);
+        println!(
+            "  {:<48} {:>10} {:>10} {:>10}",
+            "repo", "database", "git", "drift"
+        );
+        for repo in database.load()? {
+            let db = recorded.get(&repo.name).copied().unwrap_or_default();
+            let (git, drift) = match GitImpl::commit_count(&repo) {
+                Ok(n) if sampled.contains(&repo.name) => (n.to_string(), String::from("sampled")),
+                Ok(n) if n == db => (n.to_string(), String::from("ok")),
+                Ok(n) => (n.to_string(), format!("{:+}", n as i64 - db as i64)),
+                Err(_) => (String::from("-"), String::from("missing")),
+            };
+            if drift != "ok" && drift != "sampled" {
+                drifted += 1;
+            }
+            println!("  {:<48} {:>10} {:>10} {:>10}", repo.name, db, git, drift);
+        }
+        println!();
+    }
+
+    if drifted > 0 {
+        return Err(anyhow!("{} repos drifted from git", drifted));
+    }
+    println!("All repos are consistent with git");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    #[test]
+    fn test_recorded_commits() {
+        let dir = TempDir::new("verify").unwrap();
+        let path = dir.join("commit.csv");
+
+        let mut wtr = csv::Writer::from_path(&path).unwrap();
+        let commit =
+            |repo_name: &str, hash: &str, is_merge: bool, is_co_author: bool| RecordCommit {
+                repo_name: repo_name.to_string(),
+                hash: hash.to_string(),
+                is_merge,
+                is_co_author,
+                ..Default::default()
+            };
+        for record in [
+            commit("a", "h1", false, false),
+            commit("a", "h1", false, true),
+            commit("a", "h2", false, false),
+            commit("a", "h3", true, false),
+            commit("b", "h1", false, false),
+        ] {
+            wtr.serialize(record).unwrap();
+        }
+        wtr.flush().unwrap();
+
+        let counts = recorded_commits(&path).unwrap();
+        assert_eq!(counts.get("a"), Some(&2));
+        assert_eq!(counts.get("b"), Some(&1));
+        assert!(recorded_commits(&dir.join("missing.csv"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_sampled_repos() {
+        let dir = TempDir::new("verify-sampled").unwrap();
+        let path = dir.join("meta.csv");
+        assert!(sampled_repos(&path).unwrap().is_empty());
+
+        fs::write(&path, "key,value\nrepo_count,3\nsampled,\n").unwrap();
+        assert!(sampled_repos(&path).unwrap().is_empty());
+
+        fs::write(&path, "key,value\nrepo_count,3\nsampled,\"a/x,b/y\"\n").unwrap();
+        let sampled = sampled_repos(&path).unwrap();
+        assert_eq!(sampled.len(), 2);
+        assert!(sampled.contains("a/x") && sampled.contains("b/y"));
+    }
+}
@@ -0,0 +1,56 @@
+<!DOCTYPE html>
+<html lang="en">
+
+<head>
+    <meta charset="UTF-8">
+    <title>gitv charts</title>
+    {%- for script in scripts %}
+    <script>{{ script | safe }}</script>
+    {%- endfor %}
+</head>
+
+<body>
+    {%- for chart in charts %}
+    <section>
+        <h2>{{ chart.title | escape }}</h2>
+        <div>
+            {%- if chart.aria_label %}
+            <canvas id="{{ chart.chart_id }}" width="{{ chart.width }}" height="{{ chart.height }}" role="img" aria-label="{{ chart.aria_label | escape }}"></canvas>
+            {%- else %}
+            <canvas id="{{ chart.chart_id }}" width="{{ chart.width }}" height="{{ chart.height }}"></canvas>
+            {%- endif %}
+        </div>
+        {%- for table in chart.tables %}
+        <table>
+            <caption>{{ chart.title | escape }}</caption>
+            <thead>
+                <tr>{% for h in table.headers %}<th scope="col">{{ h | escape }}</th>{% endfor %}</tr>
+            </thead>
+            <tbody>
+                {%- for row in table.rows %}
+                <tr>{% for v in row %}<td>{{ v | escape }}</td>{% endfor %}</tr>
+                {%- endfor %}
+            </tbody>
+        </table>
+        {%- endfor %}
+        <div>
+            {%- for d in chart.downloads %}
+            <a download="{{ d.name }}" href="{{ d.href }}">download data ({{ d.name }})</a>
+            {%- endfor %}
+        </div>
+    </section>
+    {%- endfor %}
+    <script>
+        {%- for reg in register %}
+        {{ reg | safe }}
+        {%- endfor %}
+        {%- for chart in charts %}
+        new Chart(
+            document.getElementById('{{ chart.chart_id }}'),
+            {{ chart.config }}
+        );
+        {%- endfor %}
+    </script>
+</body>
+
+</html>
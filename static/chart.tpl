@@ -10,7 +10,31 @@
 </head>
 
 <body>
-    <div><canvas id="{{ chart_id }}" width="{{ width }}" height="{{ height }}"></canvas></div>
+    <div>
+        {%- if aria_label %}
+        <canvas id="{{ chart_id }}" width="{{ width }}" height="{{ height }}" role="img" aria-label="{{ aria_label | escape }}"></canvas>
+        {%- else %}
+        <canvas id="{{ chart_id }}" width="{{ width }}" height="{{ height }}"></canvas>
+        {%- endif %}
+    </div>
+    {%- for table in tables %}
+    <table>
+        <caption>{{ title | escape }}</caption>
+        <thead>
+            <tr>{% for h in table.headers %}<th scope="col">{{ h | escape }}</th>{% endfor %}</tr>
+        </thead>
+        <tbody>
+            {%- for row in table.rows %}
+            <tr>{% for v in row %}<td>{{ v | escape }}</td>{% endfor %}</tr>
+            {%- endfor %}
+        </tbody>
+    </table>
+    {%- endfor %}
+    <div>
+        {%- for d in downloads %}
+        <a download="{{ d.name }}" href="{{ d.href }}">download data ({{ d.name }})</a>
+        {%- endfor %}
+    </div>
     <script>
         {%- for reg in register %}
         {{ reg | safe }}
@@ -0,0 +1,25 @@
+<!DOCTYPE html>
+<html lang="en">
+
+<head>
+    <meta charset="UTF-8">
+    <title>gitv charts</title>
+    <style>
+        .gallery { display: flex; flex-wrap: wrap; gap: 16px; }
+        .gallery figure { margin: 0; }
+        .gallery img { width: {{ width }}px; height: auto; border: 1px solid #ddd; }
+    </style>
+</head>
+
+<body>
+    <div class="gallery">
+        {%- for item in items %}
+        <figure>
+            <a href="{{ item.href | escape }}"><img src="{{ item.href | escape }}" alt="{{ item.name | escape }}" loading="lazy"></a>
+            <figcaption>{{ item.name | escape }}</figcaption>
+        </figure>
+        {%- endfor %}
+    </div>
+</body>
+
+</html>